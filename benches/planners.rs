@@ -0,0 +1,118 @@
+/*
+   Copyright 2017 Takashi Ogura
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+// Run with `cargo bench`, or `cargo bench --features alloc-count` to also
+// count heap allocations.
+
+use rand::distributions::{Distribution, Uniform};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "alloc-count")]
+mod counting {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    pub static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+    pub static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+    pub struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+            System.alloc(layout)
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    pub fn snapshot() -> (usize, usize) {
+        (
+            ALLOCATIONS.load(Ordering::Relaxed),
+            ALLOCATED_BYTES.load(Ordering::Relaxed),
+        )
+    }
+}
+
+const NUM_RUNS: u32 = 20;
+
+fn is_free(p: &[f64]) -> bool {
+    !(p[0].abs() < 1.0 && p[1].abs() < 1.0)
+}
+
+fn random_sample() -> Vec<f64> {
+    let between = Uniform::new(-2.0, 2.0);
+    let mut rng = rand::thread_rng();
+    vec![between.sample(&mut rng), between.sample(&mut rng)]
+}
+
+fn bench(name: &str, mut f: impl FnMut()) {
+    #[cfg(feature = "alloc-count")]
+    let before = counting::snapshot();
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..NUM_RUNS {
+        let now = Instant::now();
+        f();
+        elapsed += now.elapsed();
+    }
+    print!("{name:<24} {:>12.3?}/iter", elapsed / NUM_RUNS);
+    #[cfg(feature = "alloc-count")]
+    {
+        let after = counting::snapshot();
+        print!(
+            " {:>10} allocs/iter {:>12} bytes/iter",
+            (after.0 - before.0) / NUM_RUNS as usize,
+            (after.1 - before.1) / NUM_RUNS as usize
+        );
+    }
+    println!();
+}
+
+fn main() {
+    bench("dual_rrt_connect", || {
+        rrt::dual_rrt_connect(&[-1.2, 0.0], &[1.2, 0.0], is_free, random_sample, 0.2, 1000)
+            .unwrap();
+    });
+    bench("dual_rrt_connect+smooth", || {
+        let mut path =
+            rrt::dual_rrt_connect(&[-1.2, 0.0], &[1.2, 0.0], is_free, random_sample, 0.2, 1000)
+                .unwrap();
+        rrt::smooth_path(&mut path, is_free, 0.2, 100);
+    });
+    bench("rrtstar", || {
+        rrt::rrtstar::rrtstar(
+            &[-1.2, 0.0],
+            &[1.2, 0.0],
+            is_free,
+            random_sample,
+            0.2,
+            1000,
+            0.4,
+            false,
+        )
+        .unwrap();
+    });
+}
@@ -0,0 +1,28 @@
+/*
+   Copyright 2017 Takashi Ogura
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at
+
+       http://www.apache.org/licenses/LICENSE-2.0
+
+   Unless required by applicable law or agreed to in writing, software
+   distributed under the License is distributed on an "AS IS" BASIS,
+   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+   See the License for the specific language governing permissions and
+   limitations under the License.
+*/
+
+// Try it with:
+//
+// curl -X POST localhost:8080/plan -d '{"start": [-1.2, 0.0], "goal": [1.2, 0.0],
+//   "lower": [-2.0, -2.0], "upper": [2.0, 2.0], "extend_length": 0.2, "num_max_try": 1000,
+//   "scene": {"obstacles": [{"box": {"min": [-1.0, -1.0], "max": [1.0, 1.0]}}]}}'
+fn main() -> std::io::Result<()> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    println!("listening on {addr}");
+    rrt::service::serve(addr)
+}
@@ -0,0 +1,425 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Interactive planning demos with [Bevy](https://bevyengine.org).
+//!
+//! [`RrtPlugin`] grows a [`DualRrtConnect`] a few tries every frame from the
+//! [`Start`] entity to the [`Goal`] entity around the [`Obstacle`] entities,
+//! and draws the trees and the path with gizmos. The entities can be dragged
+//! with the mouse on the `z = 0` plane. Moving the start or the goal plans
+//! again from scratch, while moving, adding or removing an obstacle only
+//! removes the blocked parts of the trees (see
+//! [`DualRrtConnect::revalidate`]).
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use rrt::bevy_plugin::{Goal, Obstacle, Planning, RrtPlugin, Start};
+//!
+//! App::new()
+//!     .add_plugins((DefaultPlugins, RrtPlugin))
+//!     .insert_resource(Planning::new_2d(Vec2::splat(-300.0), Vec2::splat(300.0), 20.0))
+//!     .add_systems(Startup, |mut commands: Commands| {
+//!         commands.spawn(Camera2dBundle::default());
+//!         commands.spawn((Start, Transform::from_xyz(-250.0, 0.0, 0.0)));
+//!         commands.spawn((Goal, Transform::from_xyz(250.0, 0.0, 0.0)));
+//!         commands.spawn((
+//!             Obstacle::new(Vec3::new(50.0, 150.0, 0.0)),
+//!             Transform::default(),
+//!         ));
+//!     })
+//!     .run();
+//! ```
+
+use crate::rrt::DualRrtConnect;
+use bevy::ecs::system::SystemParam;
+use bevy::math::primitives::InfinitePlane3d;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Root of the start tree, at the translation of its [`Transform`]
+#[derive(Debug, Clone, Copy, Default, Component)]
+pub struct Start;
+
+/// Root of the goal tree, at the translation of its [`Transform`]
+#[derive(Debug, Clone, Copy, Default, Component)]
+pub struct Goal;
+
+/// Axis aligned box obstacle, centered at the translation of its
+/// [`Transform`]
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+pub struct Obstacle {
+    /// Half of the size of the box (`z` is not used by the 2D planning)
+    pub half_size: Vec3,
+}
+
+impl Obstacle {
+    /// Box of the half size
+    pub fn new(half_size: Vec3) -> Self {
+        Self { half_size }
+    }
+
+    fn contains(&self, center: Vec3, p: Vec3, dim: usize) -> bool {
+        let d = (p - center).abs();
+        d.x <= self.half_size.x && d.y <= self.half_size.y && (dim == 2 || d.z <= self.half_size.z)
+    }
+}
+
+/// Options and state of the planning, which must be inserted before the
+/// systems of [`RrtPlugin`] run
+#[derive(Debug, Resource)]
+pub struct Planning {
+    /// Lower corner of the sampled region
+    pub min: Vec3,
+    /// Upper corner of the sampled region
+    pub max: Vec3,
+    /// Length of each extension of the trees
+    pub extend_length: f32,
+    /// Interval of the collision checks along the edges
+    pub validity_resolution: f32,
+    /// Number of tries of each frame
+    pub tries_per_frame: usize,
+    /// Distance from the start or the goal within which they are grabbed by
+    /// the mouse
+    pub grab_radius: f32,
+    dim: usize,
+    planner: Option<DualRrtConnect<f32>>,
+    rng: StdRng,
+    num_replans: usize,
+    num_removed: usize,
+}
+
+impl Planning {
+    /// Plan on the `z = 0` plane in the rectangle from `min` to `max`
+    pub fn new_2d(min: Vec2, max: Vec2, extend_length: f32) -> Self {
+        Self::new(min.extend(0.0), max.extend(0.0), extend_length, 2)
+    }
+
+    /// Plan in the box from `min` to `max`
+    pub fn new_3d(min: Vec3, max: Vec3, extend_length: f32) -> Self {
+        Self::new(min, max, extend_length, 3)
+    }
+
+    fn new(min: Vec3, max: Vec3, extend_length: f32, dim: usize) -> Self {
+        assert!(extend_length > 0.0);
+        Self {
+            min,
+            max,
+            extend_length,
+            validity_resolution: 0.1 * extend_length,
+            tries_per_frame: 10,
+            grab_radius: extend_length,
+            dim,
+            planner: None,
+            rng: StdRng::from_entropy(),
+            num_replans: 0,
+            num_removed: 0,
+        }
+    }
+
+    /// Draw the samples from `rng` instead of the entropy of the system
+    pub fn rng(mut self, rng: StdRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Current planner, `None` while the start, the goal or both are missing
+    /// or blocked
+    pub fn planner(&self) -> Option<&DualRrtConnect<f32>> {
+        self.planner.as_ref()
+    }
+
+    /// Path from the start to the goal once the trees are connected
+    pub fn path(&self) -> Option<Vec<Vec3>> {
+        let path = self.planner.as_ref()?.path()?;
+        Some(path.iter().map(|q| self.point(q)).collect())
+    }
+
+    /// Number of times the planning started over, because the start or the
+    /// goal moved
+    pub fn num_replans(&self) -> usize {
+        self.num_replans
+    }
+
+    /// Number of the tree vertices removed because the obstacles changed
+    pub fn num_removed(&self) -> usize {
+        self.num_removed
+    }
+
+    fn point(&self, q: &[f32]) -> Vec3 {
+        Vec3::new(q[0], q[1], if self.dim == 3 { q[2] } else { 0.0 })
+    }
+
+    fn configuration(&self, p: Vec3) -> Vec<f32> {
+        p.to_array()[..self.dim].to_vec()
+    }
+}
+
+/// Plugin which plans, draws and drags the entities of this module.
+///
+/// The [`Planning`] resource must be inserted by the application.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RrtPlugin;
+
+impl Plugin for RrtPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (drag, plan, draw).chain());
+    }
+}
+
+/// Entities which are planned around
+#[derive(SystemParam)]
+pub struct Environment<'w, 's> {
+    start: Query<'w, 's, Ref<'static, Transform>, With<Start>>,
+    goal: Query<'w, 's, Ref<'static, Transform>, With<Goal>>,
+    obstacles: Query<'w, 's, (Ref<'static, Transform>, Ref<'static, Obstacle>)>,
+    removed: RemovedComponents<'w, 's, Obstacle>,
+}
+
+/// Start over when the start or the goal moved, remove the blocked parts of
+/// the trees when the obstacles changed, and grow the trees
+pub fn plan(mut planning: ResMut<Planning>, mut environment: Environment) {
+    let planning = &mut *planning;
+    let (Ok(start), Ok(goal)) = (
+        environment.start.get_single(),
+        environment.goal.get_single(),
+    ) else {
+        planning.planner = None;
+        return;
+    };
+    let obstacles = environment
+        .obstacles
+        .iter()
+        .map(|(transform, obstacle)| (transform.translation, *obstacle))
+        .collect::<Vec<_>>();
+    let obstacles_changed = environment.removed.read().count() > 0
+        || environment
+            .obstacles
+            .iter()
+            .any(|(transform, obstacle)| transform.is_changed() || obstacle.is_changed());
+    let (min, max, dim) = (planning.min, planning.max, planning.dim);
+    let mut is_free = |q: &[f32]| {
+        let p = Vec3::new(q[0], q[1], if dim == 3 { q[2] } else { 0.0 });
+        p.cmpge(min).all()
+            && p.cmple(max).all()
+            && obstacles
+                .iter()
+                .all(|(center, obstacle)| !obstacle.contains(*center, p, dim))
+    };
+    let (q_start, q_goal) = (
+        planning.configuration(start.translation),
+        planning.configuration(goal.translation),
+    );
+    if !is_free(&q_start) || !is_free(&q_goal) {
+        planning.planner = None;
+        return;
+    }
+    match &mut planning.planner {
+        Some(planner) if !start.is_changed() && !goal.is_changed() => {
+            if obstacles_changed {
+                planning.num_removed +=
+                    planner.revalidate(planning.validity_resolution, &mut is_free);
+            }
+        }
+        _ => {
+            planning.planner = Some(DualRrtConnect::new(
+                &q_start,
+                &q_goal,
+                planning.extend_length,
+            ));
+            planning.num_replans += 1;
+        }
+    }
+    let planner = planning.planner.as_mut().unwrap();
+    for _ in 0..planning.tries_per_frame {
+        let q_rand = (0..dim)
+            .map(|i| planning.rng.gen_range(min[i]..=max[i]))
+            .collect::<Vec<_>>();
+        if planner.step(&q_rand, planning.validity_resolution, &mut is_free) {
+            break;
+        }
+    }
+}
+
+/// Draw the trees, the path, the obstacles, the start and the goal
+pub fn draw(
+    mut gizmos: Gizmos,
+    planning: Res<Planning>,
+    start: Query<&Transform, With<Start>>,
+    goal: Query<&Transform, With<Goal>>,
+    obstacles: Query<(&Transform, &Obstacle)>,
+) {
+    let radius = 0.5 * planning.grab_radius;
+    for transform in &start {
+        gizmos.sphere(transform.translation, Quat::IDENTITY, radius, Color::WHITE);
+    }
+    for transform in &goal {
+        gizmos.sphere(transform.translation, Quat::IDENTITY, radius, Color::WHITE);
+    }
+    for (transform, obstacle) in &obstacles {
+        let size = 2.0 * obstacle.half_size;
+        gizmos.cuboid(
+            Transform::from_translation(transform.translation).with_scale(size),
+            Color::srgb(1.0, 0.3, 0.3),
+        );
+    }
+    let Some(planner) = planning.planner() else {
+        return;
+    };
+    for (tree, color) in [
+        (planner.start_tree(), Color::srgb(0.3, 0.6, 1.0)),
+        (planner.goal_tree(), Color::srgb(0.3, 1.0, 0.6)),
+    ] {
+        for (parent, child) in tree.edges() {
+            gizmos.line(
+                planning.point(tree.vertex(parent)),
+                planning.point(tree.vertex(child)),
+                color,
+            );
+        }
+    }
+    if let Some(path) = planning.path() {
+        gizmos.linestrip(path, Color::srgb(1.0, 1.0, 0.0));
+    }
+}
+
+type Draggable = Or<(With<Start>, With<Goal>, With<Obstacle>)>;
+
+/// Move the start, the goal or an obstacle with the left mouse button
+pub fn drag(
+    buttons: Res<ButtonInput<MouseButton>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    planning: Res<Planning>,
+    mut draggable: Query<(Entity, &mut Transform, Option<&Obstacle>), Draggable>,
+    mut grabbed: Local<Option<(Entity, Vec3)>>,
+) {
+    if !buttons.pressed(MouseButton::Left) {
+        *grabbed = None;
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) = (window.get_single(), camera.get_single())
+    else {
+        return;
+    };
+    let Some(point) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
+        .and_then(|ray| {
+            let distance = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Z))?;
+            Some(ray.get_point(distance))
+        })
+    else {
+        return;
+    };
+    if buttons.just_pressed(MouseButton::Left) {
+        // the start and the goal are grabbed before the obstacles around them
+        *grabbed = draggable
+            .iter()
+            .filter(|(_, transform, obstacle)| match obstacle {
+                Some(obstacle) => obstacle.contains(transform.translation, point, 2),
+                None => transform.translation.distance(point) <= planning.grab_radius,
+            })
+            .min_by_key(|(_, _, obstacle)| obstacle.is_some())
+            .map(|(entity, transform, _)| (entity, transform.translation - point));
+    }
+    if let Some((entity, offset)) = *grabbed {
+        if let Ok((_, mut transform, _)) = draggable.get_mut(entity) {
+            let translation = (point + offset).with_z(transform.translation.z);
+            // only a move changes the transform, which makes the planner
+            // start over or check the trees again
+            if transform.translation != translation {
+                transform.translation = translation;
+            }
+        }
+    }
+}
+
+#[test]
+fn it_works() {
+    let mut app = App::new();
+    app.insert_resource(
+        Planning::new_2d(Vec2::splat(-2.0), Vec2::splat(2.0), 0.2).rng(StdRng::seed_from_u64(0)),
+    )
+    .add_systems(Update, plan);
+    app.world_mut()
+        .spawn((Start, Transform::from_xyz(-1.5, 0.0, 0.0)));
+    let goal = app
+        .world_mut()
+        .spawn((Goal, Transform::from_xyz(1.5, 0.0, 0.0)))
+        .id();
+    let obstacle = app
+        .world_mut()
+        .spawn((
+            Obstacle::new(Vec3::new(0.2, 1.0, 0.0)),
+            Transform::from_xyz(0.0, 1.5, 0.0),
+        ))
+        .id();
+    fn planning(app: &App) -> &Planning {
+        app.world().resource::<Planning>()
+    }
+    let mut num_frames = 0;
+    while planning(&app).path().is_none() {
+        app.update();
+        num_frames += 1;
+        assert!(num_frames < 1000);
+    }
+    assert_eq!(planning(&app).num_replans(), 1);
+    assert_eq!(planning(&app).num_removed(), 0);
+
+    // dragging the obstacle over the trees only removes the blocked parts
+    let num_vertices = |app: &App| {
+        let planner = planning(app).planner().unwrap();
+        planner.start_tree().len() + planner.goal_tree().len()
+    };
+    let before = num_vertices(&app);
+    app.world_mut()
+        .get_mut::<Transform>(obstacle)
+        .unwrap()
+        .translation = Vec3::ZERO;
+    app.update();
+    assert_eq!(planning(&app).num_replans(), 1);
+    assert!(planning(&app).num_removed() > 0);
+    assert!(planning(&app).num_removed() < before);
+    while planning(&app).path().is_none() {
+        app.update();
+        num_frames += 1;
+        assert!(num_frames < 1000);
+    }
+    let path = planning(&app).path().unwrap();
+    assert_eq!(path[0], Vec3::new(-1.5, 0.0, 0.0));
+    assert_eq!(path.last().unwrap(), &Vec3::new(1.5, 0.0, 0.0));
+    let blocked = Obstacle::new(Vec3::new(0.2, 1.0, 0.0));
+    assert!(path.iter().all(|p| !blocked.contains(Vec3::ZERO, *p, 2)));
+
+    // moving the goal starts over, and a blocked goal stops the planning
+    app.world_mut()
+        .get_mut::<Transform>(goal)
+        .unwrap()
+        .translation = Vec3::new(1.5, 1.0, 0.0);
+    app.update();
+    assert_eq!(planning(&app).num_replans(), 2);
+    app.world_mut()
+        .get_mut::<Transform>(goal)
+        .unwrap()
+        .translation = Vec3::new(0.0, 0.5, 0.0);
+    app.update();
+    assert!(planning(&app).planner().is_none());
+    app.world_mut().entity_mut(obstacle).despawn();
+    app.update();
+    assert_eq!(planning(&app).num_replans(), 3);
+}
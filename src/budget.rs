@@ -0,0 +1,394 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Iteration scheduling within a time budget, and termination conditions of
+//! the planners.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Splits the iterations of a planner into batches which fit in a time budget.
+///
+/// The controller measures the time per iteration of each batch, and sizes
+/// the next batch to use at most half of the remaining time, so the clock is
+/// read rarely when the iterations are cheap, and the deadline is still kept
+/// when the cost of the collision checks changes a lot during planning.
+#[derive(Debug, Clone)]
+pub struct IterationController {
+    deadline: Instant,
+    last_batch: Option<(Instant, usize)>,
+    per_iteration: Option<Duration>,
+    max_batch: usize,
+    num_iterations: usize,
+}
+
+impl IterationController {
+    /// Create a controller which stops after `budget` from now
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            deadline: Instant::now() + budget,
+            last_batch: None,
+            per_iteration: None,
+            max_batch: 1024,
+            num_iterations: 0,
+        }
+    }
+
+    /// Number of iterations of the finished batches
+    pub fn num_iterations(&self) -> usize {
+        self.num_iterations
+    }
+
+    /// Estimated time of an iteration, `None` before the first batch
+    pub fn per_iteration(&self) -> Option<Duration> {
+        self.per_iteration
+    }
+
+    /// Size of the next batch, called after the previous batch has been
+    /// finished. Returns zero if there is not enough time for an iteration.
+    pub fn next_batch(&mut self) -> usize {
+        let now = Instant::now();
+        if let Some((started, size)) = self.last_batch.take() {
+            self.num_iterations += size;
+            let measured = (now - started) / size as u32;
+            // react immediately to slower iterations, slowly to faster ones
+            self.per_iteration = Some(match self.per_iteration {
+                Some(estimate) if measured < estimate => (estimate + measured) / 2,
+                _ => measured,
+            });
+        }
+        let remaining = self.deadline.saturating_duration_since(now);
+        let size = match self.per_iteration {
+            None => 1,
+            Some(estimate) if estimate >= remaining => 0,
+            Some(estimate) if estimate.is_zero() => self.max_batch,
+            Some(estimate) => ((remaining.as_secs_f64() / estimate.as_secs_f64() / 2.0) as usize)
+                .clamp(1, self.max_batch),
+        };
+        if size > 0 && !remaining.is_zero() {
+            self.last_batch = Some((now, size));
+            size
+        } else {
+            0
+        }
+    }
+}
+
+/// Termination of an anytime planner: stop as soon as the solution costs
+/// less than the target, or when the budget has passed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoftDeadline {
+    /// Time after which the best solution so far is returned
+    pub budget: Duration,
+    /// Cost below which a solution is good enough
+    pub target_cost: f64,
+}
+
+impl SoftDeadline {
+    /// Use the whole budget, as no solution is good enough
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            target_cost: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Stop as soon as a solution costs less than `target_cost`
+    pub fn with_target_cost(mut self, target_cost: f64) -> Self {
+        self.target_cost = target_cost;
+        self
+    }
+
+    /// If a solution of `cost` allows to stop before the deadline
+    pub fn is_good_enough(&self, cost: f64) -> bool {
+        cost < self.target_cost
+    }
+}
+
+/// Flag to stop a planner from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token which is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the planners watching this token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// If [`CancellationToken::cancel`] has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl From<Arc<AtomicBool>> for CancellationToken {
+    /// Use an existing flag, which cancels when it is set to true
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        Self(flag)
+    }
+}
+
+/// State of a running planner, checked by a [`TerminationCondition`]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PlannerStatus {
+    /// Number of iterations so far
+    pub num_iterations: usize,
+    /// Number of vertices of the trees
+    pub tree_size: usize,
+    /// Cost of the best solution, `None` if there is none yet
+    pub cost: Option<f64>,
+    /// Time since the planner has started
+    pub elapsed: Duration,
+}
+
+/// Why a [`TerminationCondition`] stopped the planner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The timeout has passed
+    Timeout,
+    /// The maximum number of iterations has been done
+    MaxIterations,
+    /// The trees have reached the maximum size
+    MaxTreeSize,
+    /// A solution has been found
+    FirstSolution,
+    /// A solution costs less than the threshold
+    CostThreshold,
+    /// The [`CancellationToken`] has been cancelled
+    Cancelled,
+}
+
+/// When a planner stops, combining any number of criteria: the planner
+/// stops as soon as one of them is met.
+///
+/// The condition is checked once per iteration. Only the timeout, the
+/// maximum number of iterations and the cancellation stop a planner which
+/// makes no progress, so the planners reject a condition without any of
+/// them, see [`TerminationCondition::is_bounded`].
+#[derive(Debug, Clone, Default)]
+pub struct TerminationCondition {
+    timeout: Option<Duration>,
+    max_iterations: Option<usize>,
+    max_tree_size: Option<usize>,
+    first_solution: bool,
+    cost_threshold: Option<f64>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl TerminationCondition {
+    /// Create a condition without any criterion
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop when `timeout` has passed since the start of the planner
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Stop after `max_iterations` iterations
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = Some(max_iterations);
+        self
+    }
+
+    /// Stop when the trees have `max_tree_size` vertices
+    pub fn max_tree_size(mut self, max_tree_size: usize) -> Self {
+        self.max_tree_size = Some(max_tree_size);
+        self
+    }
+
+    /// Stop as soon as a solution is found
+    pub fn first_solution(mut self) -> Self {
+        self.first_solution = true;
+        self
+    }
+
+    /// Stop as soon as a solution costs less than `cost_threshold`
+    pub fn cost_threshold(mut self, cost_threshold: f64) -> Self {
+        self.cost_threshold = Some(cost_threshold);
+        self
+    }
+
+    /// Stop when `token` is cancelled
+    pub fn cancelled_by(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// If the condition stops the planner even if it makes no progress: it
+    /// has a timeout, a maximum number of iterations or a cancellation token
+    pub fn is_bounded(&self) -> bool {
+        self.timeout.is_some() || self.max_iterations.is_some() || self.cancellation.is_some()
+    }
+
+    /// Returns the reason to stop the planner in the state `status`, or
+    /// `None` to continue
+    pub fn check(&self, status: &PlannerStatus) -> Option<StopReason> {
+        let cancelled = self
+            .cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled);
+        if cancelled {
+            Some(StopReason::Cancelled)
+        } else if self.first_solution && status.cost.is_some() {
+            Some(StopReason::FirstSolution)
+        } else if (self.cost_threshold.zip(status.cost)).is_some_and(|(max, cost)| cost < max) {
+            Some(StopReason::CostThreshold)
+        } else if self
+            .max_iterations
+            .is_some_and(|max| status.num_iterations >= max)
+        {
+            Some(StopReason::MaxIterations)
+        } else if self
+            .max_tree_size
+            .is_some_and(|max| status.tree_size >= max)
+        {
+            Some(StopReason::MaxTreeSize)
+        } else if self
+            .timeout
+            .is_some_and(|timeout| status.elapsed >= timeout)
+        {
+            Some(StopReason::Timeout)
+        } else {
+            None
+        }
+    }
+}
+
+/// Checks a [`TerminationCondition`] once per iteration of a planner, e.g.
+/// of a loop of [`DualRrtConnect::step`](crate::rrt::DualRrtConnect::step),
+/// counting the iterations and the time since it was created.
+#[derive(Debug, Clone)]
+pub struct ConditionMonitor<'c> {
+    condition: &'c TerminationCondition,
+    started: Instant,
+    num_iterations: usize,
+}
+
+impl<'c> ConditionMonitor<'c> {
+    /// Start monitoring the planner now
+    pub fn new(condition: &'c TerminationCondition) -> Self {
+        Self {
+            condition,
+            started: Instant::now(),
+            num_iterations: 0,
+        }
+    }
+
+    /// Number of the iterations so far
+    pub fn num_iterations(&self) -> usize {
+        self.num_iterations
+    }
+
+    /// Called before each iteration with the size of the trees and the cost
+    /// of the best solution. Returns the reason to stop, or `None` to do the
+    /// iteration.
+    pub fn check(&mut self, tree_size: usize, cost: Option<f64>) -> Option<StopReason> {
+        let status = PlannerStatus {
+            num_iterations: self.num_iterations,
+            tree_size,
+            cost,
+            elapsed: self.started.elapsed(),
+        };
+        let reason = self.condition.check(&status);
+        if reason.is_none() {
+            self.num_iterations += 1;
+        }
+        reason
+    }
+}
+
+#[test]
+fn it_works() {
+    let budget = Duration::from_millis(50);
+    let started = Instant::now();
+    let mut controller = IterationController::new(budget);
+    let mut batches = 0;
+    loop {
+        let size = controller.next_batch();
+        if size == 0 {
+            break;
+        }
+        batches += 1;
+        for _ in 0..size {
+            std::thread::sleep(Duration::from_micros(500));
+        }
+    }
+    let elapsed = started.elapsed();
+    // the last batch fits in the remaining time, the margin is for the
+    // sleeps which oversleep on a loaded machine
+    assert!(elapsed < budget * 2, "{elapsed:?}");
+    assert!(elapsed > budget / 2, "{elapsed:?}");
+    assert!(controller.num_iterations() > batches);
+    assert!(controller.per_iteration().unwrap() >= Duration::from_micros(500));
+
+    let deadline = SoftDeadline::new(budget);
+    assert!(!deadline.is_good_enough(0.0));
+    let deadline = deadline.with_target_cost(2.0);
+    assert!(deadline.is_good_enough(1.9));
+    assert!(!deadline.is_good_enough(2.0));
+
+    let token = CancellationToken::new();
+    let condition = TerminationCondition::new()
+        .max_iterations(100)
+        .max_tree_size(50)
+        .timeout(budget)
+        .cost_threshold(2.0)
+        .cancelled_by(token.clone());
+    let mut status = PlannerStatus::default();
+    assert_eq!(condition.check(&status), None);
+    status.cost = Some(3.0);
+    assert_eq!(condition.check(&status), None);
+    status.cost = Some(1.5);
+    assert_eq!(condition.check(&status), Some(StopReason::CostThreshold));
+    status.cost = None;
+    status.elapsed = budget;
+    assert_eq!(condition.check(&status), Some(StopReason::Timeout));
+    status.tree_size = 50;
+    assert_eq!(condition.check(&status), Some(StopReason::MaxTreeSize));
+    status.num_iterations = 100;
+    assert_eq!(condition.check(&status), Some(StopReason::MaxIterations));
+    token.cancel();
+    assert_eq!(condition.check(&status), Some(StopReason::Cancelled));
+
+    let flag = Arc::new(AtomicBool::new(false));
+    let condition = TerminationCondition::new()
+        .first_solution()
+        .cancelled_by(flag.clone().into());
+    let mut status = PlannerStatus::default();
+    assert_eq!(condition.check(&status), None);
+    status.cost = Some(10.0);
+    assert_eq!(condition.check(&status), Some(StopReason::FirstSolution));
+    flag.store(true, Ordering::Relaxed);
+    assert_eq!(condition.check(&status), Some(StopReason::Cancelled));
+    assert_eq!(TerminationCondition::new().check(&status), None);
+    assert!(!TerminationCondition::new().first_solution().is_bounded());
+    assert!(condition.is_bounded());
+
+    let condition = TerminationCondition::new().max_iterations(3);
+    let mut monitor = ConditionMonitor::new(&condition);
+    assert!((0..3).all(|_| monitor.check(1, None).is_none()));
+    assert_eq!(monitor.check(1, None), Some(StopReason::MaxIterations));
+    assert_eq!(monitor.num_iterations(), 3);
+}
@@ -0,0 +1,239 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Cache of the paths of repeated queries.
+//!
+//! Pick and place cells repeat similar motions, so [`PathCache`] keeps the
+//! found paths keyed by the cells of the start and the goal (quantized by
+//! `cell_size`) and the version of the scene (e.g.
+//! [`crate::roadmap::scene_hash`]). A cached path is reused with its first and
+//! last waypoints replaced by the new start and goal, and only after all of
+//! its edges are checked again, so the things which are not in the version
+//! (e.g. moving obstacles) are still respected.
+
+use crate::rrt::is_edge_free;
+use num_traits::float::Float;
+use std::collections::HashMap;
+
+type Key = (Vec<i64>, Vec<i64>, u64);
+
+/// Counts of the outcomes of the lookups
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Lookups which returned a cached path
+    pub hits: usize,
+    /// Lookups without a cached path
+    pub misses: usize,
+    /// Cached paths which were not free anymore (and were removed)
+    pub invalidated: usize,
+}
+
+/// Paths keyed by the quantized start and goal and the scene version
+#[derive(Debug, Clone)]
+pub struct PathCache<N> {
+    cell_size: N,
+    validity_resolution: N,
+    entries: HashMap<Key, Vec<Vec<N>>>,
+    stats: CacheStats,
+}
+
+impl<N: Float> PathCache<N> {
+    /// Create an empty cache whose cells are `cell_size` wide. The reused
+    /// paths are checked every `validity_resolution`.
+    pub fn new(cell_size: N, validity_resolution: N) -> Self {
+        assert!(cell_size > N::zero() && validity_resolution > N::zero());
+        Self {
+            cell_size,
+            validity_resolution,
+            entries: HashMap::new(),
+            stats: CacheStats::default(),
+        }
+    }
+
+    fn key(&self, start: &[N], goal: &[N], version: u64) -> Key {
+        let cell = |q: &[N]| {
+            q.iter()
+                .map(|v| (*v / self.cell_size).floor().to_i64().unwrap_or(i64::MAX))
+                .collect()
+        };
+        (cell(start), cell(goal), version)
+    }
+
+    /// Number of the cached paths
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there is no cached path
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all the cached paths
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Counts of the lookups so far
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Store `path` from `start` to `goal` in the scene of `version`
+    pub fn insert(&mut self, start: &[N], goal: &[N], version: u64, path: Vec<Vec<N>>) {
+        let key = self.key(start, goal, version);
+        self.entries.insert(key, path);
+    }
+
+    /// The cached path of the cells of `start` and `goal`, from `start` to
+    /// `goal`, if it is still free. A path which is not free is removed.
+    pub fn get<FF>(
+        &mut self,
+        start: &[N],
+        goal: &[N],
+        version: u64,
+        mut is_free: FF,
+    ) -> Option<Vec<Vec<N>>>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let key = self.key(start, goal, version);
+        let Some(cached) = self.entries.get(&key) else {
+            self.stats.misses += 1;
+            return None;
+        };
+        let mut path = Vec::with_capacity(cached.len().max(2));
+        path.push(start.to_vec());
+        if cached.len() > 2 {
+            path.extend_from_slice(&cached[1..cached.len() - 1]);
+        }
+        path.push(goal.to_vec());
+        let is_path_free = is_free(start)
+            && path
+                .windows(2)
+                .all(|w| is_edge_free(&w[0], &w[1], self.validity_resolution, &mut is_free));
+        if !is_path_free {
+            self.entries.remove(&key);
+            self.stats.invalidated += 1;
+            self.stats.misses += 1;
+            return None;
+        }
+        self.stats.hits += 1;
+        Some(path)
+    }
+
+    /// Return the cached path, or plan with `planner` (which gets the start,
+    /// the goal and `is_free`) and cache its path
+    pub fn plan<FF, FP, E>(
+        &mut self,
+        start: &[N],
+        goal: &[N],
+        version: u64,
+        mut is_free: FF,
+        planner: FP,
+    ) -> Result<Vec<Vec<N>>, E>
+    where
+        FF: FnMut(&[N]) -> bool,
+        FP: FnOnce(&[N], &[N], &mut dyn FnMut(&[N]) -> bool) -> Result<Vec<Vec<N>>, E>,
+    {
+        if let Some(path) = self.get(start, goal, version, &mut is_free) {
+            return Ok(path);
+        }
+        let path = planner(start, goal, &mut is_free)?;
+        self.insert(start, goal, version, path.clone());
+        Ok(path)
+    }
+}
+
+#[test]
+fn it_works() {
+    use crate::collision::{Obstacle, Scene, Sphere};
+    use crate::roadmap::scene_hash;
+    use rand::Rng;
+    let mut scene = Scene {
+        obstacles: vec![Obstacle::Sphere(Sphere {
+            center: vec![0.0, 0.0],
+            radius: 0.5,
+        })],
+    };
+    let random_sample = || {
+        let mut rng = rand::thread_rng();
+        vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    let mut num_plans = 0;
+    let mut planner = |start: &[f64], goal: &[f64], is_free: &mut dyn FnMut(&[f64]) -> bool| {
+        num_plans += 1;
+        crate::RrtPlanner::new()
+            .start(start)
+            .goal(goal)
+            .extend_length(0.2)
+            .validity_resolution(0.01)
+            .sampler(random_sample)
+            .plan(is_free)
+    };
+    let mut cache = PathCache::new(0.1, 0.01);
+    let version = scene_hash(&scene);
+    let path = cache
+        .plan(
+            &[-0.95, 0.02],
+            &[1.02, 0.02],
+            version,
+            |q: &[f64]| scene.is_free(q),
+            &mut planner,
+        )
+        .unwrap();
+    assert_eq!(cache.len(), 1);
+    // a nearby query in the same cells reuses the path
+    let reused = cache
+        .plan(
+            &[-0.97, 0.05],
+            &[1.05, 0.01],
+            version,
+            |q: &[f64]| scene.is_free(q),
+            &mut planner,
+        )
+        .unwrap();
+    assert_eq!(reused[0], [-0.97, 0.05]);
+    assert_eq!(reused[reused.len() - 1], [1.05, 0.01]);
+    assert_eq!(reused[1..reused.len() - 1], path[1..path.len() - 1]);
+    assert_eq!(
+        cache.stats(),
+        CacheStats {
+            hits: 1,
+            misses: 1,
+            invalidated: 0,
+        }
+    );
+    // an obstacle which is not in the version blocks the path
+    let blocked = path[path.len() / 2].clone();
+    assert!(cache
+        .get(&[-0.95, 0.02], &[1.02, 0.02], version, |q: &[f64]| {
+            scene.is_free(q) && q.iter().zip(&blocked).any(|(a, b)| (a - b).abs() > 0.05)
+        })
+        .is_none());
+    assert_eq!(cache.stats().invalidated, 1);
+    assert!(cache.is_empty());
+    // the other versions of the scene do not share the paths
+    cache.insert(&[-0.95, 0.02], &[1.02, 0.02], version, path);
+    scene.obstacles.clear();
+    let version = scene_hash(&scene);
+    assert!(cache
+        .get(&[-0.95, 0.02], &[1.02, 0.02], version, |q: &[f64]| scene
+            .is_free(q))
+        .is_none());
+    drop(cache);
+    assert_eq!(num_plans, 1);
+}
@@ -0,0 +1,228 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Simple N-dimensional obstacles and scenes built from them.
+
+use kdtree::distance::squared_euclidean;
+use num_traits::float::Float;
+use std::fmt::Debug;
+
+/// Hypersphere obstacle
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sphere<N> {
+    /// Center of the sphere
+    pub center: Vec<N>,
+    /// Radius of the sphere
+    pub radius: N,
+}
+
+/// Axis aligned hyper-rectangle obstacle
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AxisAlignedBox<N> {
+    /// Lower corner
+    pub min: Vec<N>,
+    /// Upper corner
+    pub max: Vec<N>,
+}
+
+/// Obstacle which can be placed in a [`Scene`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Obstacle<N> {
+    /// Hypersphere
+    Sphere(Sphere<N>),
+    /// Axis aligned hyper-rectangle
+    Box(AxisAlignedBox<N>),
+}
+
+impl<N> Obstacle<N>
+where
+    N: Float + Debug,
+{
+    /// Returns true if `q` is inside of the obstacle
+    pub fn contains(&self, q: &[N]) -> bool {
+        match self {
+            Obstacle::Sphere(s) => squared_euclidean(q, &s.center) <= s.radius.powi(2),
+            Obstacle::Box(b) => q
+                .iter()
+                .zip(b.min.iter().zip(b.max.iter()))
+                .all(|(v, (min, max))| min <= v && v <= max),
+        }
+    }
+
+    /// Signed distance from `q` to the surface of the obstacle (negative inside)
+    pub fn distance(&self, q: &[N]) -> N {
+        match self {
+            Obstacle::Sphere(s) => squared_euclidean(q, &s.center).sqrt() - s.radius,
+            Obstacle::Box(b) => {
+                if self.contains(q) {
+                    -q.iter()
+                        .zip(b.min.iter().zip(b.max.iter()))
+                        .map(|(v, (min, max))| (*v - *min).min(*max - *v))
+                        .fold(N::infinity(), N::min)
+                } else {
+                    q.iter()
+                        .zip(b.min.iter().zip(b.max.iter()))
+                        .map(|(v, (min, max))| {
+                            let d = (*min - *v).max(*v - *max).max(N::zero());
+                            d * d
+                        })
+                        .fold(N::zero(), |sum, d| sum + d)
+                        .sqrt()
+                }
+            }
+        }
+    }
+
+    /// Returns true if the segment from `from` to `to` touches the obstacle.
+    ///
+    /// The test is exact, so unlike sampled edge checks it never misses thin corners.
+    pub fn intersects_segment(&self, from: &[N], to: &[N]) -> bool {
+        match self {
+            Obstacle::Sphere(s) => {
+                // closest point of the segment to the center
+                let (dd, dc) = from.iter().zip(to.iter()).zip(s.center.iter()).fold(
+                    (N::zero(), N::zero()),
+                    |(dd, dc), ((f, t), c)| {
+                        (dd + (*t - *f) * (*t - *f), dc + (*t - *f) * (*c - *f))
+                    },
+                );
+                let t = if dd > N::zero() {
+                    (dc / dd).max(N::zero()).min(N::one())
+                } else {
+                    N::zero()
+                };
+                let closest = from
+                    .iter()
+                    .zip(to.iter())
+                    .map(|(f, g)| *f + (*g - *f) * t)
+                    .collect::<Vec<_>>();
+                squared_euclidean(&closest, &s.center) <= s.radius.powi(2)
+            }
+            Obstacle::Box(b) => {
+                // slab method: clip the parameter range [0, 1] against each axis
+                let mut t_min = N::zero();
+                let mut t_max = N::one();
+                for ((f, t), (min, max)) in from
+                    .iter()
+                    .zip(to.iter())
+                    .zip(b.min.iter().zip(b.max.iter()))
+                {
+                    let d = *t - *f;
+                    if d == N::zero() {
+                        if *f < *min || *f > *max {
+                            return false;
+                        }
+                    } else {
+                        let t0 = (*min - *f) / d;
+                        let t1 = (*max - *f) / d;
+                        t_min = t_min.max(t0.min(t1));
+                        t_max = t_max.min(t0.max(t1));
+                        if t_min > t_max {
+                            return false;
+                        }
+                    }
+                }
+                true
+            }
+        }
+    }
+}
+
+/// Set of obstacles
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scene<N> {
+    /// Obstacles in the scene
+    pub obstacles: Vec<Obstacle<N>>,
+}
+
+impl<N> Scene<N>
+where
+    N: Float + Debug,
+{
+    /// Returns true if `q` is not inside of any obstacle.
+    /// This can be used as `is_free` function of the planners.
+    pub fn is_free(&self, q: &[N]) -> bool {
+        !self.obstacles.iter().any(|o| o.contains(q))
+    }
+
+    /// Returns true if the segment from `from` to `to` does not touch any obstacle.
+    pub fn is_edge_free(&self, from: &[N], to: &[N]) -> bool {
+        !self
+            .obstacles
+            .iter()
+            .any(|o| o.intersects_segment(from, to))
+    }
+
+    /// Signed distance from `q` to the nearest obstacle, infinity if there are no obstacles
+    pub fn distance(&self, q: &[N]) -> N {
+        self.obstacles
+            .iter()
+            .map(|o| o.distance(q))
+            .fold(N::infinity(), N::min)
+    }
+}
+
+#[test]
+fn it_works() {
+    let scene = Scene {
+        obstacles: vec![
+            Obstacle::Sphere(Sphere {
+                center: vec![0.0, 0.0],
+                radius: 1.0,
+            }),
+            Obstacle::Box(AxisAlignedBox {
+                min: vec![2.0, -1.0],
+                max: vec![3.0, 1.0],
+            }),
+        ],
+    };
+    assert!(!scene.is_free(&[0.5, 0.5]));
+    assert!(!scene.is_free(&[2.5, 0.0]));
+    assert!(scene.is_free(&[1.5, 0.0]));
+    assert!(scene.is_free(&[2.5, 1.5]));
+    assert!((scene.distance(&[1.5, 0.0]) - 0.5).abs() < 1e-9);
+    assert!((scene.distance(&[2.5, 0.0]) + 0.5).abs() < 1e-9);
+
+    // both endpoints are free but the segment crosses the obstacles
+    assert!(!scene.is_edge_free(&[-1.5, 0.0], &[1.5, 0.0]));
+    assert!(!scene.is_edge_free(&[1.5, 0.0], &[3.5, 0.0]));
+    // the segment cuts a corner of the box, which sampling at thirds would miss
+    assert!(!scene.is_edge_free(&[1.5, 0.45], &[2.5, 1.45]));
+    assert!(scene.is_edge_free(&[1.5, 0.0], &[1.5, 3.0]));
+    assert!(scene.is_edge_free(&[-1.5, 1.01], &[1.5, 1.01]));
+    assert!(!scene.is_edge_free(&[-1.5, 0.99], &[1.5, 0.99]));
+    assert!(scene.is_edge_free(&[1.5, 1.5], &[3.5, 1.5]));
+    assert!(!scene.is_edge_free(&[2.5, 0.0], &[2.5, 0.0]));
+
+    let obstacle = Obstacle::Box(AxisAlignedBox {
+        min: vec![0.0, 0.0, 0.0, 0.0],
+        max: vec![1.0, 1.0, 1.0, 1.0],
+    });
+    assert!(obstacle.intersects_segment(&[-1.0, -1.0, -1.0, -1.0], &[2.0, 2.0, 2.0, 2.0]));
+    assert!(!obstacle.intersects_segment(&[-1.0, 0.5, 0.5, 2.0], &[2.0, 0.5, 0.5, 2.0]));
+    let obstacle = Obstacle::Sphere(Sphere {
+        center: vec![0.0, 0.0, 0.0, 0.0],
+        radius: 1.0,
+    });
+    assert!(obstacle.intersects_segment(&[-2.0, 0.5, 0.5, 0.5], &[2.0, 0.5, 0.5, 0.5]));
+    assert!(!obstacle.intersects_segment(&[-2.0, 0.6, 0.6, 0.6], &[2.0, 0.6, 0.6, 0.6]));
+    assert!(!obstacle.intersects_segment(&[2.0, 0.0, 0.0, 0.0], &[3.0, 0.0, 0.0, 0.0]));
+}
@@ -0,0 +1,379 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Resolution complete planning, which always terminates.
+//!
+//! The samples are the points of a lattice of the resolution, from coarse to
+//! fine (every other point of the previous level is skipped), so the
+//! dispersion of the samples shrinks deterministically. Once all the points
+//! are sampled, the dispersion is at most the resolution, and if the trees
+//! are not connected by then, the lattice graph is searched exhaustively, so
+//! the answer is either a path or [`Error::NoPathAtResolution`]: there is no
+//! path through the free lattice points which are connected by free edges
+//! (to the axis neighbours, and from the start and the goal to the corners
+//! of their cells). The lattice has `(extent / resolution + 1)^dim` points,
+//! so this is for the low dimensions or coarse resolutions.
+
+use crate::rrt::{is_edge_free, DualRrtConnect, Error};
+use num_traits::float::Float;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+/// Points of the lattice between the bounds, whose spacing is at most the
+/// resolution, in the order of the coarse to fine levels
+#[derive(Debug, Clone)]
+pub struct LatticeSampler<N> {
+    lower: Vec<N>,
+    step: Vec<N>,
+    counts: Vec<usize>,
+    level: usize,
+    max_level: usize,
+    digits: Vec<usize>,
+    done: bool,
+}
+
+impl<N: Float> LatticeSampler<N> {
+    /// Lattice between `lower` and `upper`, whose spacing is at most
+    /// `resolution` in each dimension
+    pub fn new(lower: &[N], upper: &[N], resolution: N) -> Self {
+        assert_eq!(lower.len(), upper.len());
+        assert!(resolution > N::zero());
+        let counts = lower
+            .iter()
+            .zip(upper)
+            .map(|(lower, upper)| {
+                assert!(lower <= upper);
+                ((*upper - *lower) / resolution).ceil().to_usize().unwrap() + 1
+            })
+            .collect::<Vec<_>>();
+        let step = lower
+            .iter()
+            .zip(upper)
+            .zip(&counts)
+            .map(|((lower, upper), &count)| {
+                if count > 1 {
+                    (*upper - *lower) / N::from(count - 1).unwrap()
+                } else {
+                    N::zero()
+                }
+            })
+            .collect();
+        let largest = counts.iter().max().copied().unwrap_or(1) - 1;
+        let mut max_level = 0;
+        while (1 << max_level) < largest {
+            max_level += 1;
+        }
+        Self {
+            lower: lower.to_vec(),
+            step,
+            digits: vec![0; counts.len()],
+            counts,
+            level: max_level,
+            max_level,
+            done: false,
+        }
+    }
+
+    /// Number of the points of the lattice
+    pub fn len(&self) -> usize {
+        self.counts.iter().product()
+    }
+
+    /// Always false, the lattice has at least a point
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of the points along each dimension
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// Configuration of the point at the lattice `indices`
+    pub fn point(&self, indices: &[usize]) -> Vec<N> {
+        indices
+            .iter()
+            .enumerate()
+            .map(|(i, &index)| self.lower[i] + self.step[i] * N::from(index).unwrap())
+            .collect()
+    }
+
+    // indices of the lattice points around `q` (the corners of its cell)
+    fn cell_corners(&self, q: &[N]) -> Vec<Vec<usize>> {
+        let mut corners = vec![Vec::new()];
+        for (i, v) in q.iter().enumerate() {
+            let last = self.counts[i] - 1;
+            let low = if self.step[i] > N::zero() {
+                ((*v - self.lower[i]) / self.step[i])
+                    .floor()
+                    .max(N::zero())
+                    .to_usize()
+                    .unwrap()
+                    .min(last)
+            } else {
+                0
+            };
+            let high = (low + 1).min(last);
+            corners = corners
+                .into_iter()
+                .flat_map(|c| {
+                    let mut candidates = vec![low];
+                    if high != low {
+                        candidates.push(high);
+                    }
+                    candidates.into_iter().map(move |index| {
+                        let mut c = c.clone();
+                        c.push(index);
+                        c
+                    })
+                })
+                .collect();
+        }
+        corners
+    }
+}
+
+impl<N: Float> Iterator for LatticeSampler<N> {
+    type Item = Vec<N>;
+
+    fn next(&mut self) -> Option<Vec<N>> {
+        while !self.done {
+            let scale = 1 << self.level;
+            let indices = self.digits.iter().map(|d| d * scale).collect::<Vec<_>>();
+            // advance to the next point of this level
+            let mut dim = 0;
+            loop {
+                if dim == self.digits.len() {
+                    if self.level == 0 {
+                        self.done = true;
+                    } else {
+                        self.level -= 1;
+                    }
+                    break;
+                }
+                self.digits[dim] += 1;
+                if self.digits[dim] * scale < self.counts[dim] {
+                    break;
+                }
+                self.digits[dim] = 0;
+                dim += 1;
+            }
+            // the points of the coarser levels are already sampled
+            let is_coarser =
+                scale < (1 << self.max_level) && indices.iter().all(|i| i % (2 * scale) == 0);
+            if !is_coarser {
+                return Some(self.point(&indices));
+            }
+        }
+        None
+    }
+}
+
+/// Breadth first search from `start` to `goal` in the lattice graph
+fn lattice_search<FF, N>(
+    lattice: &LatticeSampler<N>,
+    start: &[N],
+    goal: &[N],
+    validity_resolution: N,
+    is_free: &mut FF,
+) -> Option<Vec<Vec<N>>>
+where
+    FF: FnMut(&[N]) -> bool,
+    N: Float,
+{
+    let flat = |indices: &[usize]| {
+        indices
+            .iter()
+            .zip(&lattice.counts)
+            .rev()
+            .fold(0, |sum, (index, count)| sum * count + index)
+    };
+    let mut parents = vec![None; lattice.len()];
+    let mut queue = VecDeque::new();
+    for corner in lattice.cell_corners(start) {
+        let index = flat(&corner);
+        if parents[index].is_none()
+            && is_edge_free(start, &lattice.point(&corner), validity_resolution, is_free)
+        {
+            // the start is marked by pointing to itself
+            parents[index] = Some(index);
+            queue.push_back(corner);
+        }
+    }
+    while let Some(indices) = queue.pop_front() {
+        let q = lattice.point(&indices);
+        if is_edge_free(&q, goal, validity_resolution, is_free) {
+            let mut path = vec![goal.to_vec()];
+            let mut index = flat(&indices);
+            path.push(q);
+            let mut current = indices;
+            while parents[index] != Some(index) {
+                let parent = parents[index].unwrap();
+                // decode the flat index of the parent
+                let mut rest = parent;
+                for (i, count) in lattice.counts.iter().enumerate() {
+                    current[i] = rest % count;
+                    rest /= count;
+                }
+                path.push(lattice.point(&current));
+                index = parent;
+            }
+            path.push(start.to_vec());
+            path.reverse();
+            return Some(path);
+        }
+        for dim in 0..indices.len() {
+            for forward in [false, true] {
+                let mut next = indices.clone();
+                if forward {
+                    if next[dim] + 1 == lattice.counts[dim] {
+                        continue;
+                    }
+                    next[dim] += 1;
+                } else {
+                    if next[dim] == 0 {
+                        continue;
+                    }
+                    next[dim] -= 1;
+                }
+                let index = flat(&next);
+                if parents[index].is_none()
+                    && is_edge_free(&q, &lattice.point(&next), validity_resolution, is_free)
+                {
+                    parents[index] = Some(flat(&indices));
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Dual RRT Connect with the samples of the [`LatticeSampler`] of
+/// `resolution` between `lower` and `upper`, followed by the exhaustive
+/// search of the lattice if the trees are not connected, so it returns
+/// either a path or [`Error::NoPathAtResolution`] in a bounded time.
+#[allow(clippy::too_many_arguments)]
+pub fn resolution_complete<FF, N>(
+    start: &[N],
+    goal: &[N],
+    mut is_free: FF,
+    lower: &[N],
+    upper: &[N],
+    extend_length: N,
+    resolution: N,
+    validity_resolution: N,
+) -> Result<Vec<Vec<N>>, Error<N>>
+where
+    FF: FnMut(&[N]) -> bool,
+    N: Float + Debug,
+{
+    if start.len() != goal.len() {
+        return Err(Error::DimensionMismatch {
+            start: start.len(),
+            goal: goal.len(),
+        });
+    }
+    if lower.len() != start.len() || upper.len() != start.len() {
+        return Err(Error::InvalidConfiguration(
+            "the bounds and the start have different dimensions",
+        ));
+    }
+    if !is_free(start) {
+        return Err(Error::StartInCollision);
+    }
+    if !is_free(goal) {
+        return Err(Error::GoalInCollision);
+    }
+    let lattice = LatticeSampler::new(lower, upper, resolution);
+    let mut planner = DualRrtConnect::new(start, goal, extend_length);
+    for q in lattice.clone() {
+        if planner.step(&q, validity_resolution, &mut is_free) {
+            return Ok(planner.path().unwrap().to_vec());
+        }
+    }
+    lattice_search(&lattice, start, goal, validity_resolution, &mut is_free)
+        .ok_or(Error::NoPathAtResolution { resolution })
+}
+
+#[test]
+fn it_works() {
+    let lattice = LatticeSampler::new(&[0.0, 0.0], &[1.0, 0.5], 0.1);
+    assert_eq!(lattice.counts(), [11, 6]);
+    let points = lattice.clone().collect::<Vec<_>>();
+    assert_eq!(points.len(), lattice.len());
+    assert_eq!(points[0], [0.0, 0.0]);
+    let keys_of = |points: &[Vec<f64>]| {
+        points
+            .iter()
+            .map(|p| ((p[0] * 10.0).round() as i32, (p[1] * 10.0).round() as i32))
+            .collect::<Vec<_>>()
+    };
+    let mut keys = keys_of(&points);
+    keys.sort();
+    keys.dedup();
+    assert_eq!(keys.len(), lattice.len());
+    // the coarse points come first
+    assert_eq!(points[1], [0.8, 0.0]);
+    assert!(keys_of(&points[..6])
+        .iter()
+        .all(|(x, y)| x % 4 == 0 && y % 4 == 0));
+
+    let (lower, upper) = ([-2.0, -2.0], [2.0, 2.0]);
+    // wall at x = 0 with a narrow gap
+    let is_free = |q: &[f64]| q[0].abs() > 0.05 || (0.5..0.8).contains(&q[1]);
+    let path = resolution_complete(
+        &[-1.0, 0.0],
+        &[1.0, 0.0],
+        is_free,
+        &lower,
+        &upper,
+        0.2,
+        0.1,
+        0.01,
+    )
+    .unwrap();
+    assert_eq!(path[0], [-1.0, 0.0]);
+    assert_eq!(path[path.len() - 1], [1.0, 0.0]);
+    for w in path.windows(2) {
+        assert!(is_edge_free(&w[0], &w[1], 0.01, &mut { is_free }));
+    }
+    // the gap is narrower than the lattice
+    let path = lattice_search(
+        &LatticeSampler::new(&lower, &upper, 0.1),
+        &[-1.0, 0.0],
+        &[1.0, 0.0],
+        0.01,
+        &mut { is_free },
+    )
+    .unwrap();
+    assert!(path.iter().any(|q| (0.5..0.8).contains(&q[1])));
+
+    let is_free = |q: &[f64]| q[0].abs() > 0.05;
+    assert_eq!(
+        resolution_complete(
+            &[-1.0, 0.0],
+            &[1.0, 0.0],
+            is_free,
+            &lower,
+            &upper,
+            0.2,
+            0.1,
+            0.01
+        ),
+        Err(Error::NoPathAtResolution { resolution: 0.1 })
+    );
+}
@@ -0,0 +1,284 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Reproducible problem instances for bug reports and regression tests.
+//!
+//! A [`Case`] records a problem, the planner parameters and the seed of the
+//! sampler, so running it again gives exactly the same result. Cases are
+//! stored in a compact line based text format:
+//!
+//! ```text
+//! case seed=42 extend_length=0.2 num_max_try=1000
+//! start -1.8 -1.8
+//! goal 1.8 1.8
+//! lower -2 -2
+//! upper 2 2
+//! sphere 0.5 0.5 0.3
+//! box -0.2 -1 0.2 0
+//! end
+//! ```
+
+use crate::collision::{AxisAlignedBox, Obstacle, Scene, Sphere};
+use crate::problems::Problem;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::cell::RefCell;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Corpus error
+#[derive(Debug, derive_more::Error, derive_more::Display)]
+pub enum CorpusError {
+    /// Failed to parse the corpus
+    #[display(fmt = "line {line}: {message}")]
+    Parse {
+        /// Line number (1-origin)
+        line: usize,
+        /// Description of the error
+        #[error(not(source))]
+        message: String,
+    },
+    /// Failed to read or write the corpus
+    #[display(fmt = "{_0}")]
+    Io(std::io::Error),
+}
+
+/// Recorded problem instance
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    /// Seed of the sampler
+    pub seed: u64,
+    /// Unit length of extend
+    pub extend_length: f64,
+    /// Max repeat num
+    pub num_max_try: usize,
+    /// The problem
+    pub problem: Problem,
+}
+
+impl Case {
+    /// Run `dual_rrt_connect` on this case with a sampler seeded by `seed`
+    pub fn run(&self) -> Result<Vec<Vec<f64>>, crate::rrt::Error<f64>> {
+        let rng = RefCell::new(StdRng::seed_from_u64(self.seed));
+        crate::rrt::dual_rrt_connect(
+            &self.problem.start,
+            &self.problem.goal,
+            |q: &[f64]| self.problem.is_free(q),
+            || self.problem.random_sample(&mut *rng.borrow_mut()),
+            self.extend_length,
+            self.num_max_try,
+        )
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn line(f: &mut fmt::Formatter<'_>, tag: &str, values: &[f64]) -> fmt::Result {
+            write!(f, "{tag}")?;
+            for v in values {
+                write!(f, " {v}")?;
+            }
+            writeln!(f)
+        }
+        writeln!(
+            f,
+            "case seed={} extend_length={} num_max_try={}",
+            self.seed, self.extend_length, self.num_max_try
+        )?;
+        line(f, "start", &self.problem.start)?;
+        line(f, "goal", &self.problem.goal)?;
+        line(f, "lower", &self.problem.lower)?;
+        line(f, "upper", &self.problem.upper)?;
+        for obstacle in &self.problem.scene.obstacles {
+            match obstacle {
+                Obstacle::Sphere(s) => {
+                    let mut values = s.center.clone();
+                    values.push(s.radius);
+                    line(f, "sphere", &values)?;
+                }
+                Obstacle::Box(b) => {
+                    let mut values = b.min.clone();
+                    values.extend(&b.max);
+                    line(f, "box", &values)?;
+                }
+            }
+        }
+        for point in &self.problem.corridor {
+            line(f, "corridor", point)?;
+        }
+        writeln!(f, "end")
+    }
+}
+
+/// Set of recorded cases
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Corpus {
+    /// Recorded cases
+    pub cases: Vec<Case>,
+}
+
+impl Corpus {
+    /// Load a corpus from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CorpusError> {
+        std::fs::read_to_string(path)
+            .map_err(CorpusError::Io)?
+            .parse()
+    }
+
+    /// Save the corpus to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), CorpusError> {
+        std::fs::write(path, self.to_string()).map_err(CorpusError::Io)
+    }
+
+    /// Run all cases and return the indices and errors of the failed ones
+    pub fn run(&self) -> Vec<(usize, crate::rrt::Error<f64>)> {
+        self.cases
+            .iter()
+            .enumerate()
+            .filter_map(|(i, case)| case.run().err().map(|e| (i, e)))
+            .collect()
+    }
+}
+
+impl fmt::Display for Corpus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for case in &self.cases {
+            write!(f, "{case}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Corpus {
+    type Err = CorpusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cases = Vec::new();
+        let mut current: Option<Case> = None;
+        for (i, text) in s.lines().enumerate() {
+            let error = |message: &str| CorpusError::Parse {
+                line: i + 1,
+                message: message.to_string(),
+            };
+            let mut tokens = text.split_whitespace();
+            let Some(tag) = tokens.next() else {
+                continue;
+            };
+            if tag == "case" {
+                if current.is_some() {
+                    return Err(error("missing `end`"));
+                }
+                let mut case = Case {
+                    seed: 0,
+                    extend_length: 0.0,
+                    num_max_try: 0,
+                    problem: Problem {
+                        start: vec![],
+                        goal: vec![],
+                        lower: vec![],
+                        upper: vec![],
+                        scene: Scene::default(),
+                        corridor: vec![],
+                    },
+                };
+                for token in tokens {
+                    let (key, value) = token.split_once('=').ok_or_else(|| error(token))?;
+                    match key {
+                        "seed" => case.seed = value.parse().map_err(|_| error(value))?,
+                        "extend_length" => {
+                            case.extend_length = value.parse().map_err(|_| error(value))?
+                        }
+                        "num_max_try" => {
+                            case.num_max_try = value.parse().map_err(|_| error(value))?
+                        }
+                        _ => return Err(error(key)),
+                    }
+                }
+                current = Some(case);
+                continue;
+            }
+            let case = current.as_mut().ok_or_else(|| error("missing `case`"))?;
+            let values = tokens
+                .map(|t| t.parse::<f64>().map_err(|_| error(t)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let dim = case.problem.start.len();
+            match tag {
+                "start" => case.problem.start = values,
+                "goal" => case.problem.goal = values,
+                "lower" => case.problem.lower = values,
+                "upper" => case.problem.upper = values,
+                "corridor" => case.problem.corridor.push(values),
+                "sphere" if values.len() == dim + 1 => {
+                    case.problem.scene.obstacles.push(Obstacle::Sphere(Sphere {
+                        center: values[..dim].to_vec(),
+                        radius: values[dim],
+                    }))
+                }
+                "box" if values.len() == dim * 2 => {
+                    case.problem
+                        .scene
+                        .obstacles
+                        .push(Obstacle::Box(AxisAlignedBox {
+                            min: values[..dim].to_vec(),
+                            max: values[dim..].to_vec(),
+                        }))
+                }
+                "sphere" | "box" => return Err(error("dimension mismatch")),
+                "end" => cases.push(current.take().unwrap()),
+                _ => return Err(error(tag)),
+            }
+        }
+        if current.is_some() {
+            return Err(CorpusError::Parse {
+                line: s.lines().count(),
+                message: "missing `end`".to_string(),
+            });
+        }
+        Ok(Self { cases })
+    }
+}
+
+/// Draw the seed of a new [`Case`] from `rng`, e.g. a seeded one so the
+/// generated corpus is reproducible too
+pub fn random_seed(rng: &mut dyn RngCore) -> u64 {
+    rng.gen()
+}
+
+#[test]
+fn it_works() {
+    use crate::problems::{generate, ProblemConfig};
+    let mut rng = StdRng::seed_from_u64(0);
+    let corpus = Corpus {
+        cases: (0..3)
+            .map(|_| Case {
+                seed: random_seed(&mut rng),
+                extend_length: 0.2,
+                num_max_try: 1000,
+                problem: generate(&ProblemConfig::default(), &mut rng),
+            })
+            .collect(),
+    };
+    let parsed: Corpus = corpus.to_string().parse().unwrap();
+    assert_eq!(parsed, corpus);
+    for case in &parsed.cases {
+        assert_eq!(case.run(), case.run());
+    }
+    assert!(matches!(
+        "start 0 0".parse::<Corpus>(),
+        Err(CorpusError::Parse { line: 1, .. })
+    ));
+}
@@ -0,0 +1,318 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Layered costmaps like the ones of navigation stacks.
+//!
+//! Each layer (static map, dynamic obstacles, inflation, user layers) is
+//! kept and updated independently, and [`LayeredCostmap::update`] combines
+//! the enabled layers in order into the master [`Costmap`], whose
+//! [`Costmap::is_free`] and [`Costmap::path_cost`] are used by the planners.
+
+use crate::grid::OccupancyGrid;
+use crate::path::subdivide;
+use std::any::Any;
+
+/// Cost of the cells which must not be entered
+pub const LETHAL: u8 = 254;
+
+/// 2D grid of costs from 0 (free) to [`LETHAL`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Costmap {
+    width: usize,
+    height: usize,
+    resolution: f64,
+    origin: [f64; 2],
+    costs: Vec<u8>,
+}
+
+impl Costmap {
+    /// Create a free costmap of `width` x `height` cells of size
+    /// `resolution`, whose lower left corner is at `origin`
+    pub fn new(width: usize, height: usize, resolution: f64, origin: [f64; 2]) -> Self {
+        assert!(resolution > 0.0);
+        Self {
+            width,
+            height,
+            resolution,
+            origin,
+            costs: vec![0; width * height],
+        }
+    }
+
+    /// Number of cells in x
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of cells in y
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Size of a cell
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    /// Cost of the cell
+    pub fn cell_cost(&self, x: usize, y: usize) -> u8 {
+        self.costs[y * self.width + x]
+    }
+
+    /// Raise the cost of the cell to `cost` (layers are combined by maximum)
+    pub fn raise(&mut self, x: usize, y: usize, cost: u8) {
+        assert!(x < self.width && y < self.height);
+        let c = &mut self.costs[y * self.width + x];
+        *c = (*c).max(cost);
+    }
+
+    /// Cell which contains `p`, `None` if `p` is outside of the map
+    pub fn cell(&self, p: &[f64]) -> Option<(usize, usize)> {
+        let x = ((p[0] - self.origin[0]) / self.resolution).floor();
+        let y = ((p[1] - self.origin[1]) / self.resolution).floor();
+        if x < 0.0 || y < 0.0 || x >= self.width as f64 || y >= self.height as f64 {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    /// Center position of the cell
+    pub fn cell_center(&self, x: usize, y: usize) -> [f64; 2] {
+        [
+            self.origin[0] + (x as f64 + 0.5) * self.resolution,
+            self.origin[1] + (y as f64 + 0.5) * self.resolution,
+        ]
+    }
+
+    /// Cost at `p` scaled to `[0, 1]`, one outside of the map
+    pub fn cost(&self, p: &[f64]) -> f64 {
+        self.cell(p)
+            .map_or(1.0, |(x, y)| self.cell_cost(x, y) as f64 / LETHAL as f64)
+    }
+
+    /// Returns true if `p` is inside of the map and its cell is not lethal.
+    /// This can be used as `is_free` function of the planners.
+    pub fn is_free(&self, p: &[f64]) -> bool {
+        self.cell(p)
+            .is_some_and(|(x, y)| self.cell_cost(x, y) < LETHAL)
+    }
+
+    /// Integral of the cost along `path`, sampled every `resolution`.
+    /// This can be used as (a term of) the cost of the path optimizers.
+    pub fn path_cost(&self, path: &[Vec<f64>], resolution: f64) -> f64 {
+        subdivide(path, resolution)
+            .iter()
+            .map(|(q, step)| self.cost(q) * step)
+            .sum()
+    }
+
+    fn clear(&mut self) {
+        self.costs.fill(0);
+    }
+}
+
+/// Layer of a [`LayeredCostmap`]
+pub trait CostmapLayer: Any {
+    /// Raise the costs of `master`, which has the costs of the previous layers
+    fn update_costs(&self, master: &mut Costmap);
+}
+
+/// Occupied cells of a static map are lethal
+#[derive(Debug, Clone)]
+pub struct StaticLayer {
+    /// The map, which can have another resolution than the costmap
+    pub map: OccupancyGrid,
+}
+
+impl CostmapLayer for StaticLayer {
+    fn update_costs(&self, master: &mut Costmap) {
+        for y in 0..master.height {
+            for x in 0..master.width {
+                let center = master.cell_center(x, y);
+                if let Some((mx, my)) = self.map.cell(&center) {
+                    if self.map.is_occupied(mx, my) {
+                        master.raise(x, y, LETHAL);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Cells of the observed (dynamic) obstacles are lethal
+#[derive(Debug, Clone, Default)]
+pub struct ObstacleLayer {
+    /// Observed obstacle points
+    pub points: Vec<[f64; 2]>,
+}
+
+impl CostmapLayer for ObstacleLayer {
+    fn update_costs(&self, master: &mut Costmap) {
+        for p in &self.points {
+            if let Some((x, y)) = master.cell(p) {
+                master.raise(x, y, LETHAL);
+            }
+        }
+    }
+}
+
+/// Cells near the lethal cells of the previous layers get costs which
+/// decrease exponentially with the distance, so the paths keep clearance.
+#[derive(Debug, Clone)]
+pub struct InflationLayer {
+    /// Distance to which the costs are inflated
+    pub inflation_radius: f64,
+    /// Decay rate of the cost with the distance
+    pub cost_scaling_factor: f64,
+}
+
+impl CostmapLayer for InflationLayer {
+    fn update_costs(&self, master: &mut Costmap) {
+        let lethal = (0..master.height)
+            .flat_map(|y| (0..master.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| master.cell_cost(x, y) == LETHAL)
+            .collect::<Vec<_>>();
+        let r = (self.inflation_radius / master.resolution).ceil() as isize;
+        for (x, y) in lethal {
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    let (Some(nx), Some(ny)) = (x.checked_add_signed(dx), y.checked_add_signed(dy))
+                    else {
+                        continue;
+                    };
+                    if nx >= master.width || ny >= master.height {
+                        continue;
+                    }
+                    let d = ((dx * dx + dy * dy) as f64).sqrt() * master.resolution;
+                    if d <= self.inflation_radius {
+                        let cost = (LETHAL - 1) as f64 * (-self.cost_scaling_factor * d).exp();
+                        master.raise(nx, ny, cost as u8);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Ordered layers combined into a master [`Costmap`]
+pub struct LayeredCostmap {
+    master: Costmap,
+    layers: Vec<(String, bool, Box<dyn CostmapLayer>)>,
+}
+
+impl LayeredCostmap {
+    /// Create a costmap without layers (see [`Costmap::new`])
+    pub fn new(width: usize, height: usize, resolution: f64, origin: [f64; 2]) -> Self {
+        Self {
+            master: Costmap::new(width, height, resolution, origin),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Add an enabled layer after the existing ones
+    pub fn with<L: CostmapLayer>(mut self, name: &str, layer: L) -> Self {
+        self.layers.push((name.to_owned(), true, Box::new(layer)));
+        self
+    }
+
+    /// Enable or disable the layer, returns false if there is no such layer
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.layers.iter_mut().find(|(n, _, _)| n == name) {
+            Some((_, e, _)) => {
+                *e = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The layer to be updated, `None` if there is no such layer of type `L`
+    pub fn layer_mut<L: CostmapLayer>(&mut self, name: &str) -> Option<&mut L> {
+        self.layers
+            .iter_mut()
+            .find(|(n, _, _)| n == name)
+            .and_then(|(_, _, layer)| (layer.as_mut() as &mut dyn Any).downcast_mut())
+    }
+
+    /// Combine the enabled layers in order into the master costmap
+    pub fn update(&mut self) -> &Costmap {
+        self.master.clear();
+        for (_, enabled, layer) in &self.layers {
+            if *enabled {
+                layer.update_costs(&mut self.master);
+            }
+        }
+        &self.master
+    }
+
+    /// The master costmap of the last update
+    pub fn costmap(&self) -> &Costmap {
+        &self.master
+    }
+}
+
+#[test]
+fn it_works() {
+    // user layer which keeps the robot away from the top row
+    struct KeepOut;
+    impl CostmapLayer for KeepOut {
+        fn update_costs(&self, master: &mut Costmap) {
+            for x in 0..master.width() {
+                master.raise(x, master.height() - 1, 100);
+            }
+        }
+    }
+
+    let mut map = OccupancyGrid::new(10, 10, 1.0, [0.0, 0.0]);
+    map.set_occupied(5, 5, true);
+    let mut costmap = LayeredCostmap::new(20, 20, 0.5, [0.0, 0.0])
+        .with("static", StaticLayer { map })
+        .with("obstacles", ObstacleLayer::default())
+        .with(
+            "inflation",
+            InflationLayer {
+                inflation_radius: 1.0,
+                cost_scaling_factor: 2.0,
+            },
+        )
+        .with("keep_out", KeepOut);
+    let master = costmap.update();
+    assert!(!master.is_free(&[5.5, 5.5]));
+    assert!(master.is_free(&[4.9, 5.5]));
+    assert!(master.cost(&[4.9, 5.5]) > 0.0);
+    assert_eq!(master.cost(&[1.0, 1.0]), 0.0);
+    assert!(master.cost(&[1.0, 9.9]) > 0.3);
+    assert!(!master.is_free(&[-1.0, 0.0]));
+
+    // dynamic obstacle
+    costmap
+        .layer_mut::<ObstacleLayer>("obstacles")
+        .unwrap()
+        .points
+        .push([1.2, 1.2]);
+    assert!(costmap.layer_mut::<StaticLayer>("obstacles").is_none());
+    assert!(!costmap.update().is_free(&[1.2, 1.2]));
+    assert!(costmap.set_enabled("obstacles", false));
+    assert!(!costmap.set_enabled("unknown", false));
+    let master = costmap.update();
+    assert!(master.is_free(&[1.2, 1.2]));
+
+    // a path close to the obstacle costs more
+    let near = vec![vec![0.0, 4.9], vec![9.9, 4.9]];
+    let far = vec![vec![0.0, 2.0], vec![9.9, 2.0]];
+    assert!(master.path_cost(&near, 0.1) > master.path_cost(&far, 0.1));
+    assert_eq!(costmap.costmap().path_cost(&far, 0.1), 0.0);
+}
@@ -0,0 +1,159 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Coverage path planning for cleaning or inspection robots.
+
+use crate::grid::OccupancyGrid;
+
+/// Boustrophedon path which visits all the free cells of `grid` reachable
+/// from the first free cell.
+///
+/// The rows are swept in alternating directions. Consecutive runs of free
+/// cells are connected along the shortest 4-connected path of free cells,
+/// so every edge of the returned path (of cell centers) is collision free.
+/// Runs which can not be reached from the first one are skipped.
+pub fn grid_coverage(grid: &OccupancyGrid) -> Vec<Vec<f64>> {
+    let mut path: Vec<Vec<f64>> = Vec::new();
+    let mut current: Option<(usize, usize)> = None;
+    for y in 0..grid.height() {
+        let mut runs = Vec::new();
+        let mut x = 0;
+        while x < grid.width() {
+            if grid.is_occupied(x, y) {
+                x += 1;
+                continue;
+            }
+            let begin = x;
+            while x < grid.width() && !grid.is_occupied(x, y) {
+                x += 1;
+            }
+            runs.push((begin, x - 1));
+        }
+        if y % 2 == 1 {
+            runs.reverse();
+            for run in &mut runs {
+                *run = (run.1, run.0);
+            }
+        }
+        for (first, last) in runs {
+            let center = |x| grid.cell_center(x, y).to_vec();
+            match current {
+                None => path.push(center(first)),
+                Some(from) => match connection(grid, from, (first, y)) {
+                    Some(cells) => path.extend(
+                        cells
+                            .into_iter()
+                            .map(|(x, y)| grid.cell_center(x, y).to_vec()),
+                    ),
+                    None => continue,
+                },
+            }
+            if last != first {
+                path.push(center(last));
+            }
+            current = Some((last, y));
+        }
+    }
+    path
+}
+
+/// Coverage of the region between `lower` and `upper` with cells of size
+/// `cell_size`, using `is_free` at the cell centers (see [`grid_coverage`])
+pub fn coverage<F>(
+    mut is_free: F,
+    lower: [f64; 2],
+    upper: [f64; 2],
+    cell_size: f64,
+) -> Vec<Vec<f64>>
+where
+    F: FnMut(&[f64]) -> bool,
+{
+    let width = ((upper[0] - lower[0]) / cell_size).floor() as usize;
+    let height = ((upper[1] - lower[1]) / cell_size).floor() as usize;
+    let mut grid = OccupancyGrid::new(width, height, cell_size, lower);
+    for y in 0..height {
+        for x in 0..width {
+            let center = grid.cell_center(x, y);
+            grid.set_occupied(x, y, !is_free(&center));
+        }
+    }
+    grid_coverage(&grid)
+}
+
+/// Cells after `from` up to `to` by descending the wavefront of `to`
+fn connection(
+    grid: &OccupancyGrid,
+    from: (usize, usize),
+    to: (usize, usize),
+) -> Option<Vec<(usize, usize)>> {
+    let distances = grid.wavefront(&grid.cell_center(to.0, to.1));
+    let distance = |(x, y): (usize, usize)| distances[y * grid.width() + x];
+    let mut d = distance(from)?;
+    let mut cell = from;
+    let mut cells = Vec::with_capacity(d);
+    while d > 0 {
+        cell = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .filter_map(|(dx, dy): (isize, isize)| {
+                let x = cell.0.checked_add_signed(dx)?;
+                let y = cell.1.checked_add_signed(dy)?;
+                (x < grid.width() && y < grid.height()).then_some((x, y))
+            })
+            .find(|&c| distance(c) == Some(d - 1))?;
+        cells.push(cell);
+        d -= 1;
+    }
+    Some(cells)
+}
+
+#[test]
+fn it_works() {
+    use crate::collision::{AxisAlignedBox, Obstacle, Scene};
+    use crate::path::subdivide;
+
+    let scene = Scene {
+        obstacles: vec![Obstacle::Box(AxisAlignedBox {
+            min: vec![1.0, 1.0],
+            max: vec![3.0, 3.0],
+        })],
+    };
+    let path = coverage(|p: &[f64]| scene.is_free(p), [0.0, 0.0], [5.0, 4.0], 0.5);
+    assert!(subdivide(&path, 0.05).iter().all(|(q, _)| scene.is_free(q)));
+    // every free cell is visited
+    let grid = OccupancyGrid::new(10, 8, 0.5, [0.0, 0.0]);
+    let visited = subdivide(&path, 0.05)
+        .iter()
+        .filter_map(|(q, _)| grid.cell(q))
+        .collect::<std::collections::HashSet<_>>();
+    for y in 0..8 {
+        for x in 0..10 {
+            assert_eq!(
+                visited.contains(&(x, y)),
+                scene.is_free(&grid.cell_center(x, y))
+            );
+        }
+    }
+
+    // unreachable cells are skipped
+    let mut grid = OccupancyGrid::new(3, 3, 1.0, [0.0, 0.0]);
+    grid.set_occupied(1, 0, true);
+    grid.set_occupied(1, 1, true);
+    grid.set_occupied(1, 2, true);
+    let path = grid_coverage(&grid);
+    assert!(path.iter().all(|p| p[0] < 1.0));
+    assert_eq!(path.first().unwrap(), &vec![0.5, 0.5]);
+    assert_eq!(path.last().unwrap(), &vec![0.5, 2.5]);
+}
@@ -0,0 +1,141 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Planning around a demonstrated path (teach and repeat).
+
+use crate::path::{distance_to_path, path_length, subdivide};
+use kdtree::distance::squared_euclidean;
+use num_traits::float::Float;
+use rand::Rng;
+
+/// Sampler which draws points inside of a tube around the demonstrated path
+#[derive(Debug, Clone)]
+pub struct TubeSampler<N> {
+    demonstration: Vec<Vec<N>>,
+    cumulative_lengths: Vec<N>,
+    radius: N,
+}
+
+impl<N: Float> TubeSampler<N> {
+    /// Create a sampler around `demonstration` within `radius`
+    pub fn new(demonstration: Vec<Vec<N>>, radius: N) -> Self {
+        assert!(!demonstration.is_empty());
+        let mut length = N::zero();
+        let mut cumulative_lengths = vec![N::zero()];
+        for w in demonstration.windows(2) {
+            length = length + squared_euclidean(&w[0], &w[1]).sqrt();
+            cumulative_lengths.push(length);
+        }
+        Self {
+            demonstration,
+            cumulative_lengths,
+            radius,
+        }
+    }
+
+    /// Draw a sample. The position along the path is uniform in arc length,
+    /// and the offset is uniform in the ball of `radius`.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<N> {
+        let total = *self.cumulative_lengths.last().unwrap();
+        let s = total * N::from(rng.gen::<f64>()).unwrap();
+        let segment = self
+            .cumulative_lengths
+            .windows(2)
+            .position(|w| s <= w[1])
+            .unwrap_or(0);
+        let base = if self.demonstration.len() == 1 {
+            self.demonstration[0].clone()
+        } else {
+            let a = &self.demonstration[segment];
+            let b = &self.demonstration[segment + 1];
+            let length = self.cumulative_lengths[segment + 1] - self.cumulative_lengths[segment];
+            let t = if length > N::zero() {
+                (s - self.cumulative_lengths[segment]) / length
+            } else {
+                N::zero()
+            };
+            a.iter().zip(b).map(|(a, b)| *a + (*b - *a) * t).collect()
+        };
+
+        // Gaussian direction by Box-Muller, then radius for the uniform ball
+        let dim = base.len();
+        let direction = (0..dim)
+            .map(|_| {
+                let u1 = 1.0 - rng.gen::<f64>();
+                let u2 = rng.gen::<f64>();
+                (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+            })
+            .collect::<Vec<_>>();
+        let norm = direction.iter().map(|d| d * d).sum::<f64>().sqrt();
+        let r = rng.gen::<f64>().powf(1.0 / dim as f64);
+        base.iter()
+            .zip(direction)
+            .map(|(b, d)| {
+                let offset = if norm > 0.0 { d / norm * r } else { 0.0 };
+                *b + self.radius * N::from(offset).unwrap()
+            })
+            .collect()
+    }
+}
+
+/// Maximum distance from `path` to `demonstration`, checked every `resolution` along `path`
+pub fn max_deviation<N: Float>(path: &[Vec<N>], demonstration: &[Vec<N>], resolution: N) -> N {
+    subdivide(path, resolution)
+        .iter()
+        .map(|(q, _)| distance_to_path(q, demonstration))
+        .fold(N::zero(), N::max)
+}
+
+/// Length of `path` plus `weight` times the integral of the distance to
+/// `demonstration` along `path`, checked every `resolution`.
+///
+/// This can be used to rank candidate paths by how closely they repeat the
+/// demonstration.
+pub fn deviation_cost<N: Float>(
+    path: &[Vec<N>],
+    demonstration: &[Vec<N>],
+    weight: N,
+    resolution: N,
+) -> N {
+    let deviation = subdivide(path, resolution)
+        .iter()
+        .map(|(q, step)| distance_to_path(q, demonstration) * *step)
+        .fold(N::zero(), |sum, d| sum + d);
+    path_length(path) + weight * deviation
+}
+
+#[test]
+fn it_works() {
+    let demonstration = vec![vec![-1.5, -1.5], vec![-1.5, 1.5], vec![1.5, 1.5]];
+    let sampler = TubeSampler::new(demonstration.clone(), 0.3);
+    let mut rng = rand::thread_rng();
+    for _ in 0..100 {
+        let q = sampler.sample(&mut rng);
+        assert!(distance_to_path(&q, &demonstration) <= 0.3 + 1e-9);
+    }
+
+    let path = vec![
+        vec![-1.5, -1.5],
+        vec![-1.0, 0.0],
+        vec![-1.5, 1.5],
+        vec![1.5, 1.5],
+    ];
+    assert!((max_deviation(&path, &demonstration, 0.01) - 0.5).abs() < 1e-9);
+    assert!(
+        deviation_cost(&path, &demonstration, 1.0, 0.01)
+            > deviation_cost(&demonstration, &demonstration, 1.0, 0.01)
+    );
+}
@@ -0,0 +1,364 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Human-readable explanations of planning failures.
+
+use crate::rrt::{dual_rrt_connect_impl, tries, Bounds, Search};
+use num_traits::float::Float;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+
+/// A likely reason of a planning failure
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailureCause<N> {
+    /// The start configuration is not free
+    StartInCollision,
+    /// The goal configuration is not free
+    GoalInCollision,
+    /// The start configuration is outside of the bounds
+    StartOutOfBounds,
+    /// The goal configuration is outside of the bounds
+    GoalOutOfBounds,
+    /// Most of the samples are outside of the bounds
+    SamplesOutOfBounds {
+        /// Ratio of the samples outside of the bounds
+        ratio: f64,
+    },
+    /// The tree of the start hardly grows, the start region seems to be
+    /// disconnected from the rest of the free space
+    StartEnclosed {
+        /// Ratio of the successful extensions of the start tree
+        success_rate: f64,
+    },
+    /// The tree of the goal hardly grows, the goal region seems to be
+    /// disconnected from the rest of the free space
+    GoalEnclosed {
+        /// Ratio of the successful extensions of the goal tree
+        success_rate: f64,
+    },
+    /// Most of the collisions happened at the same place
+    Bottleneck {
+        /// Mean of the colliding configurations there
+        location: Vec<N>,
+        /// Ratio of the collisions there
+        ratio: f64,
+    },
+    /// Nothing specific was found, more tries might find a path
+    Exhausted,
+}
+
+impl<N: Debug> fmt::Display for FailureCause<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |ratio: f64| (ratio * 100.0).round();
+        match self {
+            Self::StartInCollision => write!(f, "the start is in collision"),
+            Self::GoalInCollision => write!(f, "the goal is in collision"),
+            Self::StartOutOfBounds => write!(f, "the start is outside of the bounds"),
+            Self::GoalOutOfBounds => write!(f, "the goal is outside of the bounds"),
+            Self::SamplesOutOfBounds { ratio } => write!(
+                f,
+                "{}% of the samples are outside of the bounds, check the sampler",
+                percent(*ratio)
+            ),
+            Self::StartEnclosed { success_rate } => write!(
+                f,
+                "only {}% of the extensions from the start succeeded, \
+                 the start region seems to be disconnected",
+                percent(*success_rate)
+            ),
+            Self::GoalEnclosed { success_rate } => write!(
+                f,
+                "only {}% of the extensions from the goal succeeded, \
+                 the goal region seems to be disconnected",
+                percent(*success_rate)
+            ),
+            Self::Bottleneck { location, ratio } => write!(
+                f,
+                "{}% of the collisions are near {location:?}",
+                percent(*ratio)
+            ),
+            Self::Exhausted => write!(
+                f,
+                "no specific cause was found, try more iterations or another sampler"
+            ),
+        }
+    }
+}
+
+/// Statistics and likely causes of a planning failure
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnosis<N> {
+    /// Likely causes, the most fundamental first
+    pub causes: Vec<FailureCause<N>>,
+    /// Number of samples
+    pub num_tries: usize,
+    /// Ratio of the successful extensions of the start tree
+    pub start_success_rate: f64,
+    /// Ratio of the successful extensions of the goal tree
+    pub goal_success_rate: f64,
+    /// Number of configurations found to be in collision
+    pub num_collisions: usize,
+}
+
+impl<N: Debug> fmt::Display for Diagnosis<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "planning failed after {} tries", self.num_tries)?;
+        for cause in &self.causes {
+            write!(f, "; {cause}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<N: Debug> std::error::Error for Diagnosis<N> {}
+
+// success rates below this are suspicious
+const LOW_SUCCESS_RATE: f64 = 0.25;
+// a tree is enclosed if its success rate is below this ratio of the other's
+const ENCLOSED_RATIO: f64 = 0.25;
+// ratio of the collisions or the samples which is considered dominant
+const DOMINANT_RATIO: f64 = 0.5;
+// minimum number of collisions to find a bottleneck
+const MIN_COLLISIONS: usize = 10;
+
+/// Same as [`dual_rrt_connect`](crate::rrt::dual_rrt_connect) (with the
+/// optional `bounds`), but explains the failure from the statistics
+/// collected while planning.
+pub fn dual_rrt_connect_with_diagnosis<FF, FR, N>(
+    start: &[N],
+    goal: &[N],
+    mut is_free: FF,
+    random_sample: FR,
+    extend_length: N,
+    num_max_try: usize,
+    bounds: Option<Bounds<N>>,
+) -> Result<Vec<Vec<N>>, Diagnosis<N>>
+where
+    FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    N: Float + Debug,
+{
+    let mut diagnosis = Diagnosis {
+        causes: Vec::new(),
+        num_tries: 0,
+        start_success_rate: 0.0,
+        goal_success_rate: 0.0,
+        num_collisions: 0,
+    };
+    if !is_free(start) {
+        diagnosis.causes.push(FailureCause::StartInCollision);
+    }
+    if !is_free(goal) {
+        diagnosis.causes.push(FailureCause::GoalInCollision);
+    }
+    if let Some(bounds) = &bounds {
+        if !bounds.contains(start) {
+            diagnosis.causes.push(FailureCause::StartOutOfBounds);
+        }
+        if !bounds.contains(goal) {
+            diagnosis.causes.push(FailureCause::GoalOutOfBounds);
+        }
+    }
+    if !diagnosis.causes.is_empty() {
+        return Err(diagnosis);
+    }
+
+    let mut collisions = Vec::new();
+    let num_samples_out_of_bounds = Cell::new(0);
+    // the trees alternate, the start tree is extended first
+    let mut extensions = [(0, 0); 2];
+    let sample_bounds = bounds.clone();
+    let mut search = Search::new(extend_length, tries(num_max_try));
+    search.trees.bounds = bounds;
+    search.on_extend = Box::new(|succeeded, _, _, _| {
+        let tree = (extensions[0].0 + extensions[1].0) % 2;
+        extensions[tree].0 += 1;
+        if succeeded {
+            extensions[tree].1 += 1;
+        }
+    });
+    let result = dual_rrt_connect_impl(
+        start,
+        goal,
+        |q: &[N]| {
+            let free = is_free(q);
+            if !free {
+                collisions.push(q.to_vec());
+            }
+            free
+        },
+        || {
+            let q = random_sample();
+            if sample_bounds.as_ref().is_some_and(|b| !b.contains(&q)) {
+                num_samples_out_of_bounds.set(num_samples_out_of_bounds.get() + 1);
+            }
+            q
+        },
+        search,
+    );
+    let Err(_) = result else {
+        return result.map(|(path, _)| path).map_err(|_| unreachable!());
+    };
+
+    let rate = |(num, num_successes): (usize, usize)| num_successes as f64 / num.max(1) as f64;
+    diagnosis.num_tries = extensions[0].0 + extensions[1].0;
+    diagnosis.start_success_rate = rate(extensions[0]);
+    diagnosis.goal_success_rate = rate(extensions[1]);
+    diagnosis.num_collisions = collisions.len();
+    let num_samples = diagnosis.num_tries.max(1) as f64;
+    let ratio = num_samples_out_of_bounds.get() as f64 / num_samples;
+    if ratio > DOMINANT_RATIO {
+        diagnosis
+            .causes
+            .push(FailureCause::SamplesOutOfBounds { ratio });
+    }
+    let (start_rate, goal_rate) = (diagnosis.start_success_rate, diagnosis.goal_success_rate);
+    if start_rate < LOW_SUCCESS_RATE && start_rate < goal_rate * ENCLOSED_RATIO {
+        diagnosis.causes.push(FailureCause::StartEnclosed {
+            success_rate: start_rate,
+        });
+    }
+    if goal_rate < LOW_SUCCESS_RATE && goal_rate < start_rate * ENCLOSED_RATIO {
+        diagnosis.causes.push(FailureCause::GoalEnclosed {
+            success_rate: goal_rate,
+        });
+    }
+    if collisions.len() >= MIN_COLLISIONS {
+        let mut cells = HashMap::<Vec<i64>, Vec<usize>>::new();
+        for (i, q) in collisions.iter().enumerate() {
+            let cell = q
+                .iter()
+                .map(|v| (*v / extend_length).floor().to_i64().unwrap_or(i64::MAX))
+                .collect();
+            cells.entry(cell).or_default().push(i);
+        }
+        let members = cells.values().max_by_key(|members| members.len()).unwrap();
+        let ratio = members.len() as f64 / collisions.len() as f64;
+        if ratio > DOMINANT_RATIO {
+            let scale = N::from(members.len()).unwrap();
+            let location = (0..start.len())
+                .map(|d| {
+                    members
+                        .iter()
+                        .fold(N::zero(), |sum, &i| sum + collisions[i][d])
+                        / scale
+                })
+                .collect();
+            diagnosis
+                .causes
+                .push(FailureCause::Bottleneck { location, ratio });
+        }
+    }
+    if diagnosis.causes.is_empty() {
+        diagnosis.causes.push(FailureCause::Exhausted);
+    }
+    Err(diagnosis)
+}
+
+#[test]
+fn it_works() {
+    use crate::rrt::OutOfBounds;
+    use rand::distributions::{Distribution, Uniform};
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let box_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let path = dual_rrt_connect_with_diagnosis(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        box_free,
+        random_sample,
+        0.2,
+        1000,
+        None,
+    )
+    .unwrap();
+    assert!(path.len() >= 4);
+
+    let diagnosis = dual_rrt_connect_with_diagnosis(
+        &[-1.2, 0.0],
+        &[0.0, 0.0],
+        box_free,
+        random_sample,
+        0.2,
+        1000,
+        Some(Bounds {
+            lower: vec![-1.0, -1.0],
+            upper: vec![1.0, 1.0],
+            policy: OutOfBounds::Reject,
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        diagnosis.causes,
+        [
+            FailureCause::GoalInCollision,
+            FailureCause::StartOutOfBounds
+        ]
+    );
+    assert_eq!(
+        diagnosis.to_string(),
+        "planning failed after 0 tries; the goal is in collision; \
+         the start is outside of the bounds"
+    );
+
+    // the start is in a closed room
+    let room_free = |p: &[f64]| {
+        let d = p[0].abs().max(p[1].abs());
+        !(0.5..0.9).contains(&d)
+    };
+    let diagnosis = dual_rrt_connect_with_diagnosis(
+        &[0.0, 0.0],
+        &[1.5, 1.5],
+        room_free,
+        random_sample,
+        0.2,
+        500,
+        None,
+    )
+    .unwrap_err();
+    assert!(matches!(
+        diagnosis.causes[0],
+        FailureCause::StartEnclosed { .. }
+    ));
+    assert!(diagnosis.start_success_rate < diagnosis.goal_success_rate);
+
+    // a sampler which only draws the goal hits the wall at the same place
+    let wall_free = |p: &[f64]| p[0].abs() > 0.1;
+    let diagnosis = dual_rrt_connect_with_diagnosis(
+        &[-1.0, 0.0],
+        &[1.0, 0.0],
+        wall_free,
+        || vec![1.0, 0.0],
+        0.2,
+        100,
+        None,
+    )
+    .unwrap_err();
+    let Some(FailureCause::Bottleneck { location, ratio }) = diagnosis
+        .causes
+        .iter()
+        .find(|cause| matches!(cause, FailureCause::Bottleneck { .. }))
+    else {
+        panic!("{diagnosis}");
+    };
+    assert!(location[0].abs() < 0.2 && location[1].abs() < 1e-9);
+    assert!(*ratio > 0.9);
+    assert!(diagnosis.to_string().contains("of the collisions are near"));
+}
@@ -0,0 +1,189 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Helpers for executing paths.
+
+use crate::rrt::is_edge_free;
+use num_traits::float::Float;
+use rand::{Rng, RngCore};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+#[derive(Debug)]
+struct Shared<N> {
+    path: Vec<Vec<N>>,
+    // index of the first waypoint which can be changed
+    progress: usize,
+    num_improvements: usize,
+}
+
+/// Shortcuts the not yet executed part of a path in a background thread
+/// while the robot moves along it.
+///
+/// The executor reports its progress by [`OnlineShortcutter::set_progress`]
+/// and reads the current path by [`OnlineShortcutter::path`]. A shortcut is
+/// validated before it is swapped in, and it is dropped if the robot has
+/// passed its start in the meantime, so the executed part and the waypoint
+/// the robot is moving toward never change.
+#[derive(Debug)]
+pub struct OnlineShortcutter<N> {
+    shared: Arc<Mutex<Shared<N>>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<N> OnlineShortcutter<N>
+where
+    N: Float + Send + 'static,
+{
+    /// Start shortcutting `path`, checking the shortcuts every
+    /// `validity_resolution`. The waypoints are chosen by `rng`, which is
+    /// moved to the thread.
+    pub fn spawn<FF, R>(
+        path: Vec<Vec<N>>,
+        mut is_free: FF,
+        validity_resolution: N,
+        mut rng: R,
+    ) -> Self
+    where
+        FF: FnMut(&[N]) -> bool + Send + 'static,
+        R: RngCore + Send + 'static,
+    {
+        let shared = Arc::new(Mutex::new(Shared {
+            path,
+            progress: 0,
+            num_improvements: 0,
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let shared = shared.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let mut num_failures = 0;
+                while !stop.load(Ordering::Relaxed) {
+                    if num_failures >= MAX_FAILURES {
+                        // nothing to improve for now, wait for the progress
+                        thread::sleep(Duration::from_millis(1));
+                        num_failures = 0;
+                    }
+                    let (from, to, a, b) = {
+                        let shared = shared.lock().unwrap();
+                        let len = shared.path.len();
+                        if shared.progress + 2 >= len {
+                            drop(shared);
+                            num_failures = MAX_FAILURES;
+                            continue;
+                        }
+                        let from = rng.gen_range(shared.progress..len - 2);
+                        let to = rng.gen_range(from + 2..len);
+                        (from, to, shared.path[from].clone(), shared.path[to].clone())
+                    };
+                    // validate without blocking the executor
+                    if !is_edge_free(&a, &b, validity_resolution, &mut is_free) {
+                        num_failures += 1;
+                        continue;
+                    }
+                    let mut shared = shared.lock().unwrap();
+                    // the path can only have been changed by this thread, so
+                    // the indices are still valid unless the robot passed `from`
+                    if shared.progress <= from {
+                        shared.path.drain(from + 1..to);
+                        shared.num_improvements += 1;
+                        num_failures = 0;
+                    }
+                }
+            })
+        };
+        Self {
+            shared,
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Report that the robot is moving toward the waypoint at `index` of
+    /// the current path, so it and the previous ones are not changed anymore
+    pub fn set_progress(&self, index: usize) {
+        self.shared.lock().unwrap().progress = index;
+    }
+
+    /// Current path, whose waypoints before the progress are the same as before
+    pub fn path(&self) -> Vec<Vec<N>> {
+        self.shared.lock().unwrap().path.clone()
+    }
+
+    /// Number of the swapped in shortcuts
+    pub fn num_improvements(&self) -> usize {
+        self.shared.lock().unwrap().num_improvements
+    }
+
+    /// Stop the background thread and return the final path
+    pub fn stop(mut self) -> Vec<Vec<N>> {
+        self.stop_thread();
+        self.path()
+    }
+}
+
+impl<N> OnlineShortcutter<N> {
+    fn stop_thread(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.join().unwrap();
+        }
+    }
+}
+
+impl<N> Drop for OnlineShortcutter<N> {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
+
+// consecutive failed shortcuts before the thread waits
+const MAX_FAILURES: usize = 1000;
+
+#[test]
+fn it_works() {
+    use rand::SeedableRng;
+    use std::time::Instant;
+    // zigzag around a wall at x = 0.5
+    let is_free = |p: &[f64]| !((p[0] - 0.5).abs() < 0.05 && p[1] < 0.5);
+    let path = (0..11)
+        .map(|i| vec![i as f64 * 0.1, if i % 2 == 0 { 0.0 } else { 1.0 }])
+        .collect::<Vec<_>>();
+    let rng = rand::rngs::StdRng::seed_from_u64(0);
+    let shortcutter = OnlineShortcutter::spawn(path.clone(), is_free, 0.01, rng);
+    shortcutter.set_progress(3);
+    let started = Instant::now();
+    while shortcutter.num_improvements() == 0 && started.elapsed() < Duration::from_secs(5) {
+        thread::sleep(Duration::from_millis(1));
+    }
+    let current = shortcutter.path();
+    assert!(current.len() < path.len());
+    assert_eq!(current[..=3], path[..=3]);
+    shortcutter.set_progress(4);
+    let current = shortcutter.path();
+    thread::sleep(Duration::from_millis(20));
+    let result = shortcutter.stop();
+    assert_eq!(result[..=4], current[..=4]);
+    assert_eq!(result[result.len() - 1], path[path.len() - 1]);
+    let mut is_free = is_free;
+    for w in result.windows(2) {
+        assert!(is_edge_free(&w[0], &w[1], 0.01, &mut is_free));
+    }
+}
@@ -0,0 +1,284 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Exploration of unknown environments.
+//!
+//! [`ExplorationMap`] has three states per cell. The free cells next to
+//! unknown cells are the frontiers, which [`FrontierSampler`] prefers and
+//! [`next_best_path`] plans to.
+
+use crate::RrtPlanner;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+use std::collections::VecDeque;
+
+/// State of a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Occupancy {
+    /// Observed to be free
+    Free,
+    /// Observed to be occupied
+    Occupied,
+    /// Not observed yet
+    #[default]
+    Unknown,
+}
+
+/// 2D grid of [`Occupancy`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplorationMap {
+    width: usize,
+    height: usize,
+    resolution: f64,
+    origin: [f64; 2],
+    cells: Vec<Occupancy>,
+}
+
+impl ExplorationMap {
+    /// Create an unknown map of `width` x `height` cells of size
+    /// `resolution`, whose lower left corner is at `origin`
+    pub fn new(width: usize, height: usize, resolution: f64, origin: [f64; 2]) -> Self {
+        assert!(resolution > 0.0);
+        Self {
+            width,
+            height,
+            resolution,
+            origin,
+            cells: vec![Occupancy::Unknown; width * height],
+        }
+    }
+
+    /// Set the state of the cell
+    pub fn set(&mut self, x: usize, y: usize, occupancy: Occupancy) {
+        assert!(x < self.width && y < self.height);
+        self.cells[y * self.width + x] = occupancy;
+    }
+
+    /// State of the cell
+    pub fn get(&self, x: usize, y: usize) -> Occupancy {
+        self.cells[y * self.width + x]
+    }
+
+    /// Cell which contains `p`, `None` if `p` is outside of the map
+    pub fn cell(&self, p: &[f64]) -> Option<(usize, usize)> {
+        let x = ((p[0] - self.origin[0]) / self.resolution).floor();
+        let y = ((p[1] - self.origin[1]) / self.resolution).floor();
+        if x < 0.0 || y < 0.0 || x >= self.width as f64 || y >= self.height as f64 {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    /// Center position of the cell
+    pub fn cell_center(&self, x: usize, y: usize) -> [f64; 2] {
+        [
+            self.origin[0] + (x as f64 + 0.5) * self.resolution,
+            self.origin[1] + (y as f64 + 0.5) * self.resolution,
+        ]
+    }
+
+    /// State at `p`, unknown outside of the map
+    pub fn occupancy(&self, p: &[f64]) -> Occupancy {
+        self.cell(p)
+            .map_or(Occupancy::Unknown, |(x, y)| self.get(x, y))
+    }
+
+    /// Returns true if `p` is known to be free. This can be used as
+    /// `is_free` function of the planners.
+    pub fn is_free(&self, p: &[f64]) -> bool {
+        self.occupancy(p) == Occupancy::Free
+    }
+
+    fn neighbours(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        [(-1, 0), (1, 0), (0, -1), (0, 1)].into_iter().filter_map(
+            move |(dx, dy): (isize, isize)| {
+                let nx = x.checked_add_signed(dx)?;
+                let ny = y.checked_add_signed(dy)?;
+                (nx < self.width && ny < self.height).then_some((nx, ny))
+            },
+        )
+    }
+
+    /// Returns true if the cell is free and 4-connected to an unknown cell
+    pub fn is_frontier(&self, x: usize, y: usize) -> bool {
+        self.get(x, y) == Occupancy::Free
+            && self
+                .neighbours(x, y)
+                .any(|(nx, ny)| self.get(nx, ny) == Occupancy::Unknown)
+    }
+
+    /// All the frontier cells
+    pub fn frontiers(&self) -> Vec<(usize, usize)> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.is_frontier(x, y))
+            .collect()
+    }
+
+    /// Frontier cells reachable from `p` through the free cells, nearest
+    /// (in number of 4-connected steps) first
+    pub fn reachable_frontiers(&self, p: &[f64]) -> Vec<(usize, usize)> {
+        let mut frontiers = Vec::new();
+        let Some((sx, sy)) = self.cell(p) else {
+            return frontiers;
+        };
+        if self.get(sx, sy) != Occupancy::Free {
+            return frontiers;
+        }
+        let mut visited = vec![false; self.cells.len()];
+        visited[sy * self.width + sx] = true;
+        let mut queue = VecDeque::from([(sx, sy)]);
+        while let Some((x, y)) = queue.pop_front() {
+            if self.is_frontier(x, y) {
+                frontiers.push((x, y));
+            }
+            for (nx, ny) in self.neighbours(x, y) {
+                let index = ny * self.width + nx;
+                if !visited[index] && self.cells[index] == Occupancy::Free {
+                    visited[index] = true;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        frontiers
+    }
+}
+
+/// Sampler of the free cells which draws a frontier cell with the
+/// probability `bias`, then a point uniformly inside of the cell.
+#[derive(Debug, Clone)]
+pub struct FrontierSampler {
+    frontiers: Vec<(usize, usize)>,
+    free: Vec<(usize, usize)>,
+    bias: f64,
+    resolution: f64,
+    origin: [f64; 2],
+}
+
+impl FrontierSampler {
+    /// Create a sampler of the current state of `map`.
+    ///
+    /// Returns `None` if there is no frontier (the exploration is finished).
+    pub fn new(map: &ExplorationMap, bias: f64) -> Option<Self> {
+        assert!((0.0..=1.0).contains(&bias));
+        let frontiers = map.frontiers();
+        if frontiers.is_empty() {
+            return None;
+        }
+        let free = (0..map.height)
+            .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| map.get(x, y) == Occupancy::Free)
+            .collect();
+        Some(Self {
+            frontiers,
+            free,
+            bias,
+            resolution: map.resolution,
+            origin: map.origin,
+        })
+    }
+
+    /// Draw a sample
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<f64> {
+        let cells = if rng.gen_bool(self.bias) {
+            &self.frontiers
+        } else {
+            &self.free
+        };
+        let &(x, y) = cells.choose(rng).unwrap();
+        vec![
+            self.origin[0] + (x as f64 + rng.gen::<f64>()) * self.resolution,
+            self.origin[1] + (y as f64 + rng.gen::<f64>()) * self.resolution,
+        ]
+    }
+}
+
+/// Plan a path through the known free space from `start` to the nearest
+/// reachable frontier, using [`RrtPlanner`] with a
+/// [`FrontierSampler`]. The edges are checked every tenth of a cell.
+///
+/// The next frontier is tried if the planning fails. The planner of each
+/// frontier is seeded from `rng`. Returns an error if there is no reachable
+/// frontier.
+pub fn next_best_path(
+    map: &ExplorationMap,
+    start: &[f64],
+    bias: f64,
+    extend_length: f64,
+    num_max_try: usize,
+    rng: &mut dyn RngCore,
+) -> Result<Vec<Vec<f64>>, String> {
+    let sampler = FrontierSampler::new(map, bias).ok_or("no frontier")?;
+    for (x, y) in map.reachable_frontiers(start) {
+        let planner = RrtPlanner::new()
+            .start(start)
+            .goal(&map.cell_center(x, y))
+            .extend_length(extend_length)
+            .validity_resolution(map.resolution / 10.0)
+            .max_iterations(num_max_try)
+            .seed(rng.gen())
+            .sampler_with_rng(|rng| sampler.sample(rng));
+        if let Ok(path) = planner.plan(|p: &[f64]| map.is_free(p)) {
+            return Ok(path);
+        }
+    }
+    Err("no reachable frontier".to_owned())
+}
+
+#[test]
+fn it_works() {
+    use crate::path::subdivide;
+
+    // the left half is observed, with a wall at x = 2 which has a gap at the top
+    let mut map = ExplorationMap::new(10, 5, 0.5, [0.0, 0.0]);
+    for y in 0..5 {
+        for x in 0..5 {
+            map.set(x, y, Occupancy::Free);
+        }
+    }
+    for y in 0..4 {
+        map.set(2, y, Occupancy::Occupied);
+    }
+    assert_eq!(map.occupancy(&[1.2, 0.2]), Occupancy::Occupied);
+    assert_eq!(map.occupancy(&[3.0, 0.2]), Occupancy::Unknown);
+    assert_eq!(map.occupancy(&[-1.0, 0.2]), Occupancy::Unknown);
+    assert_eq!(map.frontiers(), [(4, 0), (4, 1), (4, 2), (4, 3), (4, 4)]);
+    let start = map.cell_center(0, 0);
+    // around the wall, the top one is the nearest
+    assert_eq!(map.reachable_frontiers(&start)[0], (4, 4));
+
+    let sampler = FrontierSampler::new(&map, 1.0).unwrap();
+    let mut rng = rand::thread_rng();
+    for _ in 0..100 {
+        let p = sampler.sample(&mut rng);
+        assert_eq!(map.cell(&p).unwrap().0, 4);
+    }
+
+    let path = next_best_path(&map, &start, 0.3, 0.2, 1000, &mut rng).unwrap();
+    assert_eq!(path[0], start);
+    let (x, y) = map.cell(path.last().unwrap()).unwrap();
+    assert!(map.is_frontier(x, y));
+    assert!(subdivide(&path, 0.05).iter().all(|(q, _)| map.is_free(q)));
+
+    // fully explored
+    for y in 0..5 {
+        for x in 5..10 {
+            map.set(x, y, Occupancy::Free);
+        }
+    }
+    assert!(FrontierSampler::new(&map, 0.5).is_none());
+    assert!(next_best_path(&map, &start, 0.3, 0.2, 1000, &mut rng).is_err());
+}
@@ -0,0 +1,186 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Chain of planners which are tried in order until one succeeds.
+//!
+//! ```
+//! use rand::distributions::{Distribution, Uniform};
+//! let mut planner = rrt::fallback::FallbackPlanner::new()
+//!     .then("quick", |start, goal, is_free, random_sample| {
+//!         rrt::dual_rrt_connect(start, goal, is_free, random_sample, 0.2, 10)
+//!     })
+//!     .then("thorough", |start, goal, is_free, random_sample| {
+//!         rrt::dual_rrt_connect(start, goal, is_free, random_sample, 0.1, 10000)
+//!     });
+//! let (result, stats) = planner.plan(
+//!     &[-1.2, 0.0],
+//!     &[1.2, 0.0],
+//!     |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0),
+//!     || {
+//!         let between = Uniform::new(-2.0, 2.0);
+//!         let mut rng = rand::thread_rng();
+//!         vec![between.sample(&mut rng), between.sample(&mut rng)]
+//!     },
+//! );
+//! assert!(result.is_ok());
+//! assert_eq!(stats.succeeded_stage(), Some(stats.stages.len() - 1));
+//! ```
+
+use crate::rrt::{Error, RRTResult};
+use std::time::{Duration, Instant};
+
+type Stage<'a, N> = Box<
+    dyn FnMut(&[N], &[N], &mut dyn FnMut(&[N]) -> bool, &dyn Fn() -> Vec<N>) -> RRTResult<N> + 'a,
+>;
+
+/// Statistics of a stage of [`FallbackPlanner`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageStats {
+    /// Name of the stage
+    pub name: String,
+    /// True if the stage found a path
+    pub succeeded: bool,
+    /// Message of the error of the stage if it failed
+    pub error: Option<String>,
+    /// Number of calls of `is_free`
+    pub num_checks: usize,
+    /// Time spent in the stage
+    pub elapsed: Duration,
+}
+
+/// Statistics of the attempted stages of [`FallbackPlanner`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FallbackStats {
+    /// Attempted stages in order
+    pub stages: Vec<StageStats>,
+}
+
+impl FallbackStats {
+    /// Index of the stage which found the path
+    pub fn succeeded_stage(&self) -> Option<usize> {
+        self.stages.iter().position(|s| s.succeeded)
+    }
+
+    /// Total number of calls of `is_free`
+    pub fn num_checks(&self) -> usize {
+        self.stages.iter().map(|s| s.num_checks).sum()
+    }
+
+    /// Total time of all of the stages
+    pub fn elapsed(&self) -> Duration {
+        self.stages.iter().map(|s| s.elapsed).sum()
+    }
+}
+
+/// Planner which tries its stages in order (e.g. a fast planner with a small
+/// budget first, then more powerful ones) with the same `is_free` and
+/// `random_sample`, and returns the first found path.
+#[derive(Default)]
+pub struct FallbackPlanner<'a, N> {
+    stages: Vec<(String, Stage<'a, N>)>,
+}
+
+impl<'a, N> FallbackPlanner<'a, N> {
+    /// Create a planner without stages
+    pub fn new() -> Self {
+        Self { stages: Vec::new() }
+    }
+
+    /// Add a stage which is tried if the previous ones have failed.
+    ///
+    /// `plan(start, goal, is_free, random_sample)` returns the path.
+    pub fn then<F>(mut self, name: &str, plan: F) -> Self
+    where
+        F: FnMut(&[N], &[N], &mut dyn FnMut(&[N]) -> bool, &dyn Fn() -> Vec<N>) -> RRTResult<N>
+            + 'a,
+    {
+        self.stages.push((name.to_owned(), Box::new(plan)));
+        self
+    }
+
+    /// Try the stages in order, and return the first found path (or the
+    /// error of the last stage) and the statistics of the attempted stages.
+    pub fn plan<FF, FR>(
+        &mut self,
+        start: &[N],
+        goal: &[N],
+        mut is_free: FF,
+        random_sample: FR,
+    ) -> (RRTResult<N>, FallbackStats)
+    where
+        FF: FnMut(&[N]) -> bool,
+        FR: Fn() -> Vec<N>,
+    {
+        let mut stats = FallbackStats::default();
+        let mut error = Error::InvalidConfiguration("no stages");
+        for (name, plan) in &mut self.stages {
+            let mut num_checks = 0;
+            let mut counting_is_free = |q: &[N]| {
+                num_checks += 1;
+                is_free(q)
+            };
+            let started = Instant::now();
+            let result = plan(start, goal, &mut counting_is_free, &random_sample);
+            stats.stages.push(StageStats {
+                name: name.clone(),
+                succeeded: result.is_ok(),
+                error: result.as_ref().err().map(|e| e.to_string()),
+                num_checks,
+                elapsed: started.elapsed(),
+            });
+            match result {
+                Ok(path) => return (Ok(path), stats),
+                Err(e) => error = e,
+            }
+        }
+        (Err(error), stats)
+    }
+}
+
+#[test]
+fn it_works() {
+    let mut planner = FallbackPlanner::new()
+        .then("fail", |_, _, _, _| {
+            Err(Error::MaxIterationsReached { best_partial: None })
+        })
+        .then("straight", |start, goal, is_free, _| {
+            if is_free(start) && is_free(goal) {
+                Ok(vec![start.to_vec(), goal.to_vec()])
+            } else {
+                Err(Error::StartInCollision)
+            }
+        })
+        .then("never", |_, _, _, _| unreachable!());
+    let (result, stats) = planner.plan(&[0.0], &[1.0], |_: &[f64]| true, || vec![0.5]);
+    assert_eq!(result.unwrap(), [[0.0], [1.0]]);
+    assert_eq!(stats.stages.len(), 2);
+    assert_eq!(stats.succeeded_stage(), Some(1));
+    assert_eq!(stats.num_checks(), 2);
+
+    let mut planner = FallbackPlanner::new().then("fail", |_, _, _, _| {
+        Err(Error::MaxIterationsReached { best_partial: None })
+    });
+    let (result, stats) = planner.plan(&[0.0], &[1.0], |_: &[f64]| true, || vec![0.5]);
+    assert_eq!(
+        result.unwrap_err(),
+        Error::MaxIterationsReached { best_partial: None }
+    );
+    assert_eq!(stats.succeeded_stage(), None);
+    assert_eq!(
+        stats.stages[0].error.as_deref(),
+        Some("failed to find a path within the maximum number of iterations")
+    );
+}
@@ -0,0 +1,168 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Configurations stored in a single flat buffer.
+
+use std::ops::Index;
+
+/// Sequence of configurations of the same dimension, stored contiguously in
+/// a single buffer with the dimension as stride.
+///
+/// This is the storage of the vertices of [`crate::rrt::Tree`], and can hold
+/// paths. The buffer can be given to SIMD or GPU code as is.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlatPoints<N> {
+    data: Vec<N>,
+    dim: usize,
+}
+
+impl<N: Copy> FlatPoints<N> {
+    /// Create an empty buffer of configurations with `dim` dimensions
+    pub fn new(dim: usize) -> Self {
+        Self::with_capacity(dim, 0)
+    }
+
+    /// Create an empty buffer with room for `capacity` configurations
+    pub fn with_capacity(dim: usize, capacity: usize) -> Self {
+        assert!(dim > 0, "configurations need at least one dimension");
+        Self {
+            data: Vec::with_capacity(dim * capacity),
+            dim,
+        }
+    }
+
+    /// Use `data` as consecutive configurations of `dim` dimensions.
+    /// Returns `None` if the length of `data` is not a multiple of `dim`.
+    pub fn from_raw(data: Vec<N>, dim: usize) -> Option<Self> {
+        (dim > 0 && data.len().is_multiple_of(dim)).then_some(Self { data, dim })
+    }
+
+    /// Number of dimensions of each configuration, the stride of the buffer
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Number of configurations
+    pub fn len(&self) -> usize {
+        self.data.len() / self.dim
+    }
+
+    /// Returns true if there is no configuration
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Number of configurations which can be stored without reallocation
+    pub fn capacity(&self) -> usize {
+        self.data.capacity() / self.dim
+    }
+
+    /// Append `q`, which must have [`FlatPoints::dim`] dimensions
+    pub fn push(&mut self, q: &[N]) {
+        assert_eq!(q.len(), self.dim);
+        self.data.extend_from_slice(q);
+    }
+
+    /// Configuration at `index`, `None` if it is out of range
+    pub fn get(&self, index: usize) -> Option<&[N]> {
+        self.data.get(index * self.dim..(index + 1) * self.dim)
+    }
+
+    /// Configurations in order
+    pub fn iter(&self) -> std::slice::ChunksExact<'_, N> {
+        self.data.chunks_exact(self.dim)
+    }
+
+    /// Reverse the order of the configurations
+    pub fn reverse(&mut self) {
+        let len = self.len();
+        for i in 0..len / 2 {
+            for d in 0..self.dim {
+                self.data
+                    .swap(i * self.dim + d, (len - 1 - i) * self.dim + d);
+            }
+        }
+    }
+
+    /// The whole buffer, configuration after configuration
+    pub fn as_slice(&self) -> &[N] {
+        &self.data
+    }
+
+    /// Take the buffer, configuration after configuration
+    pub fn into_raw(self) -> Vec<N> {
+        self.data
+    }
+
+    /// Copy the configurations into separate vectors, as returned by the planners
+    pub fn to_vecs(&self) -> Vec<Vec<N>> {
+        self.iter().map(<[N]>::to_vec).collect()
+    }
+}
+
+impl<N: Copy> Index<usize> for FlatPoints<N> {
+    type Output = [N];
+
+    fn index(&self, index: usize) -> &[N] {
+        &self.data[index * self.dim..(index + 1) * self.dim]
+    }
+}
+
+impl<'a, N: Copy> IntoIterator for &'a FlatPoints<N> {
+    type Item = &'a [N];
+    type IntoIter = std::slice::ChunksExact<'a, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<N: Copy> From<&[Vec<N>]> for FlatPoints<N> {
+    /// Copy a path, whose configurations must have the same dimension
+    fn from(path: &[Vec<N>]) -> Self {
+        let mut points = Self::with_capacity(path.first().map_or(1, Vec::len), path.len());
+        for q in path {
+            points.push(q);
+        }
+        points
+    }
+}
+
+#[test]
+fn it_works() {
+    let path = vec![vec![0.0, 1.0], vec![2.0, 3.0], vec![4.0, 5.0]];
+    let mut points = FlatPoints::from(path.as_slice());
+    assert_eq!(points.dim(), 2);
+    assert_eq!(points.len(), 3);
+    assert_eq!(&points[1], &[2.0, 3.0]);
+    assert_eq!(points.get(2), Some(&[4.0, 5.0][..]));
+    assert_eq!(points.get(3), None);
+    assert_eq!(points.as_slice(), &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    assert_eq!(points.to_vecs(), path);
+    points.reverse();
+    assert_eq!(points.iter().next(), Some(&[4.0, 5.0][..]));
+    assert_eq!(&points[2], &[0.0, 1.0]);
+    points.push(&[6.0, 7.0]);
+    assert_eq!((&points).into_iter().count(), 4);
+
+    let raw = points.clone().into_raw();
+    assert_eq!(FlatPoints::from_raw(raw.clone(), 2), Some(points));
+    assert_eq!(FlatPoints::from_raw(raw, 3), None);
+    let points = FlatPoints::<f64>::with_capacity(3, 10);
+    assert!(points.is_empty());
+    assert!(points.capacity() >= 10);
+}
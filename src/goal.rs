@@ -0,0 +1,395 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Goal regions with separate position and orientation tolerances.
+//!
+//! Configurations are `[x, y, theta]` in SE(2) and `[x, y, z, w, qx, qy, qz]`
+//! (position and unit quaternion) in SE(3). Any [`GoalRegion`] (including
+//! a point with a tolerance, inequality constraints and a predicate
+//! closure) can be given to
+//! [`RrtPlanner::plan_to_region`](crate::RrtPlanner::plan_to_region) and
+//! [`crate::rrtstar::rrtstar_to_region`].
+
+use crate::so3::{self, Quaternion};
+use num_traits::float::Float;
+
+/// Difference from `b` to `a` wrapped into `[-pi, pi]`
+pub fn angle_difference<N: Float>(a: N, b: N) -> N {
+    let pi = N::from(std::f64::consts::PI).unwrap();
+    let two_pi = pi + pi;
+    let d = (a - b) % two_pi;
+    if d > pi {
+        d - two_pi
+    } else if d < -pi {
+        d + two_pi
+    } else {
+        d
+    }
+}
+
+fn distance<N: Float>(a: &[N], b: &[N]) -> N {
+    a.iter()
+        .zip(b)
+        .fold(N::zero(), |sum, (a, b)| sum + (*a - *b).powi(2))
+        .sqrt()
+}
+
+/// Set of the configurations which satisfy the goal
+pub trait GoalRegion<N> {
+    /// Returns true if `q` satisfies the goal
+    fn contains(&self, q: &[N]) -> bool;
+
+    /// A configuration in the region which the planners can steer toward,
+    /// `None` if there is no such configuration (e.g. for a predicate)
+    fn sample(&self) -> Option<Vec<N>> {
+        None
+    }
+}
+
+impl<N, F: Fn(&[N]) -> bool> GoalRegion<N> for F {
+    fn contains(&self, q: &[N]) -> bool {
+        self(q)
+    }
+}
+
+/// Goal point with a tolerance of the Euclidean distance
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointGoal<N> {
+    /// Goal configuration
+    pub point: Vec<N>,
+    /// Maximum distance from the goal configuration
+    pub tolerance: N,
+}
+
+impl<N: Float> PointGoal<N> {
+    /// Goal within `tolerance` of `point`
+    pub fn new(point: &[N], tolerance: N) -> Self {
+        Self {
+            point: point.to_vec(),
+            tolerance,
+        }
+    }
+}
+
+impl<N: Float> GoalRegion<N> for PointGoal<N> {
+    fn contains(&self, q: &[N]) -> bool {
+        distance(q, &self.point) <= self.tolerance
+    }
+
+    fn sample(&self) -> Option<Vec<N>> {
+        Some(self.point.clone())
+    }
+}
+
+/// Planar pose goal
+#[derive(Debug, Clone, PartialEq)]
+pub struct Se2Goal<N> {
+    /// Goal position
+    pub position: [N; 2],
+    /// Goal heading (radians)
+    pub heading: N,
+    /// Maximum distance from the goal position
+    pub position_tolerance: N,
+    /// Maximum absolute heading difference (radians)
+    pub angle_tolerance: N,
+}
+
+impl<N: Float> Se2Goal<N> {
+    /// Distance from the goal position and the absolute heading difference of `q`
+    pub fn errors(&self, q: &[N]) -> (N, N) {
+        (
+            distance(&q[..2], &self.position),
+            angle_difference(q[2], self.heading).abs(),
+        )
+    }
+
+    /// Returns true if `q` is within both of the tolerances.
+    /// This can be used to test the vertices of the planners.
+    pub fn is_satisfied(&self, q: &[N]) -> bool {
+        let (position, angle) = self.errors(q);
+        position <= self.position_tolerance && angle <= self.angle_tolerance
+    }
+}
+
+impl<N: Float> GoalRegion<N> for Se2Goal<N> {
+    fn contains(&self, q: &[N]) -> bool {
+        self.is_satisfied(q)
+    }
+
+    fn sample(&self) -> Option<Vec<N>> {
+        Some(vec![self.position[0], self.position[1], self.heading])
+    }
+}
+
+/// Spatial pose goal
+#[derive(Debug, Clone, PartialEq)]
+pub struct Se3Goal<N> {
+    /// Goal position
+    pub position: [N; 3],
+    /// Goal orientation
+    pub orientation: Quaternion<N>,
+    /// Maximum distance from the goal position
+    pub position_tolerance: N,
+    /// Maximum angle of the rotation to the goal orientation (radians)
+    pub angle_tolerance: N,
+}
+
+impl<N: Float> Se3Goal<N> {
+    /// Distance from the goal position and the angle of the rotation to the
+    /// goal orientation of `q` (`q` and `-q` are the same orientation)
+    pub fn errors(&self, q: &[N]) -> (N, N) {
+        let orientation = so3::normalize(&[q[3], q[4], q[5], q[6]]);
+        (
+            distance(&q[..3], &self.position),
+            so3::distance(&orientation, &self.orientation),
+        )
+    }
+
+    /// Returns true if `q` is within both of the tolerances
+    pub fn is_satisfied(&self, q: &[N]) -> bool {
+        let (position, angle) = self.errors(q);
+        position <= self.position_tolerance && angle <= self.angle_tolerance
+    }
+}
+
+impl<N: Float> GoalRegion<N> for Se3Goal<N> {
+    fn contains(&self, q: &[N]) -> bool {
+        self.is_satisfied(q)
+    }
+
+    fn sample(&self) -> Option<Vec<N>> {
+        let mut q = self.position.to_vec();
+        q.extend_from_slice(&self.orientation);
+        Some(q)
+    }
+}
+
+type Constraint<'a, N> = Box<dyn Fn(&[N]) -> N + 'a>;
+
+enum ConstraintSampling<'a, N> {
+    None,
+    Rejection {
+        sample: Box<dyn Fn() -> Vec<N> + 'a>,
+        max_tries: usize,
+    },
+    Projection {
+        sample: Box<dyn Fn() -> Vec<N> + 'a>,
+        max_iterations: usize,
+    },
+}
+
+/// Set of the configurations which satisfy the inequality constraints
+/// `g_i(q) <= tolerance`, as in the optimization based goal specifications.
+///
+/// The planners steer toward the configuration of [`GoalRegion::sample`],
+/// found either by rejection (draw until one satisfies all of the
+/// constraints) or by projection (move a drawn configuration onto the
+/// constraints by Newton steps with the numerical gradients). Without a
+/// sampler only the vertices which happen to be in the set are found.
+pub struct ConstraintGoal<'a, N> {
+    constraints: Vec<Constraint<'a, N>>,
+    tolerance: N,
+    sampling: ConstraintSampling<'a, N>,
+}
+
+impl<'a, N: Float + 'a> ConstraintGoal<'a, N> {
+    /// Goal without constraints, which are satisfied within `tolerance`
+    pub fn new(tolerance: N) -> Self {
+        Self {
+            constraints: Vec::new(),
+            tolerance,
+            sampling: ConstraintSampling::None,
+        }
+    }
+
+    /// Add the constraint `g(q) <= tolerance`
+    pub fn constraint<F>(mut self, g: F) -> Self
+    where
+        F: Fn(&[N]) -> N + 'a,
+    {
+        self.constraints.push(Box::new(g));
+        self
+    }
+
+    /// Sample the goal by drawing from `sample` up to `max_tries` times
+    pub fn rejection_sampler<F>(mut self, sample: F, max_tries: usize) -> Self
+    where
+        F: Fn() -> Vec<N> + 'a,
+    {
+        self.sampling = ConstraintSampling::Rejection {
+            sample: Box::new(sample),
+            max_tries,
+        };
+        self
+    }
+
+    /// Sample the goal by projecting a configuration drawn from `sample`
+    /// onto the constraints, in up to `max_iterations` passes over them
+    pub fn projection_sampler<F>(mut self, sample: F, max_iterations: usize) -> Self
+    where
+        F: Fn() -> Vec<N> + 'a,
+    {
+        self.sampling = ConstraintSampling::Projection {
+            sample: Box::new(sample),
+            max_iterations,
+        };
+        self
+    }
+
+    /// Violation `max(g_i(q), 0)` of each constraint, e.g. to report how well
+    /// the end of a path satisfies them
+    pub fn residuals(&self, q: &[N]) -> Vec<N> {
+        self.constraints
+            .iter()
+            .map(|g| g(q).max(N::zero()))
+            .collect()
+    }
+
+    fn project(&self, mut q: Vec<N>, max_iterations: usize) -> Option<Vec<N>> {
+        let epsilon = N::epsilon().sqrt();
+        for _ in 0..max_iterations {
+            if self.contains(&q) {
+                return Some(q);
+            }
+            for g in &self.constraints {
+                let value = g(&q);
+                if value <= self.tolerance {
+                    continue;
+                }
+                // central differences
+                let gradient = (0..q.len())
+                    .map(|i| {
+                        let h = epsilon * q[i].abs().max(N::one());
+                        let mut forward = q.clone();
+                        let mut backward = q.clone();
+                        forward[i] = forward[i] + h;
+                        backward[i] = backward[i] - h;
+                        (g(&forward) - g(&backward)) / (h + h)
+                    })
+                    .collect::<Vec<_>>();
+                let norm = gradient.iter().fold(N::zero(), |sum, d| sum + *d * *d);
+                if norm <= N::zero() {
+                    return None;
+                }
+                // aim a little inside, so the rounding does not leave it
+                // just outside of the boundary
+                let step = (value + epsilon) / norm;
+                for (v, d) in q.iter_mut().zip(&gradient) {
+                    *v = *v - *d * step;
+                }
+            }
+        }
+        self.contains(&q).then_some(q)
+    }
+}
+
+impl<'a, N: Float + 'a> GoalRegion<N> for ConstraintGoal<'a, N> {
+    fn contains(&self, q: &[N]) -> bool {
+        self.constraints.iter().all(|g| g(q) <= self.tolerance)
+    }
+
+    fn sample(&self) -> Option<Vec<N>> {
+        match &self.sampling {
+            ConstraintSampling::None => None,
+            ConstraintSampling::Rejection { sample, max_tries } => {
+                (0..*max_tries).map(|_| sample()).find(|q| self.contains(q))
+            }
+            ConstraintSampling::Projection {
+                sample,
+                max_iterations,
+            } => self.project(sample(), *max_iterations),
+        }
+    }
+}
+
+#[test]
+fn it_works() {
+    use std::f64::consts::PI;
+    assert!((angle_difference(0.1, -0.1) - 0.2).abs() < 1e-12);
+    // across the wrap around
+    assert!((angle_difference(PI - 0.1, -PI + 0.1) + 0.2).abs() < 1e-12);
+    assert!((angle_difference(5.0 * PI, 0.0).abs() - PI).abs() < 1e-9);
+
+    let goal = Se2Goal {
+        position: [1.0, 2.0],
+        heading: PI,
+        position_tolerance: 0.1,
+        angle_tolerance: 0.2,
+    };
+    assert!(goal.is_satisfied(&[1.05, 2.0, -PI + 0.1]));
+    assert!(!goal.is_satisfied(&[1.05, 2.0, 0.0]));
+    assert!(!goal.is_satisfied(&[1.2, 2.0, PI]));
+    let (position, angle) = goal.errors(&[1.0, 2.0, 3.0 * PI - 0.1]);
+    assert!(position == 0.0 && (angle - 0.1).abs() < 1e-9);
+
+    let half = std::f64::consts::FRAC_1_SQRT_2;
+    let goal = Se3Goal {
+        position: [0.0, 0.0, 1.0],
+        // 90 degrees around z
+        orientation: [half, 0.0, 0.0, half],
+        position_tolerance: 0.05,
+        angle_tolerance: 0.1,
+    };
+    assert!(goal.is_satisfied(&[0.0, 0.0, 1.0, half, 0.0, 0.0, half]));
+    // the same orientation with the opposite sign
+    assert!(goal.is_satisfied(&[0.0, 0.03, 1.0, -half, 0.0, 0.0, -half]));
+    // identity is 90 degrees away, though Euclidean close in the position
+    assert!(!goal.is_satisfied(&[0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0]));
+    let (_, angle) = goal.errors(&[0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0]);
+    assert!((angle - PI / 2.0).abs() < 1e-9);
+
+    let point = PointGoal::new(&[1.0, 0.0], 0.1);
+    assert!(point.contains(&[1.05, 0.05]));
+    assert!(!point.contains(&[1.1, 0.1]));
+    assert_eq!(point.sample(), Some(vec![1.0, 0.0]));
+    let predicate = |q: &[f64]| q[0] > 1.0;
+    assert!(predicate.contains(&[1.5, 0.0]));
+    assert_eq!(GoalRegion::<f64>::sample(&predicate), None);
+    assert!(goal.contains(&goal.sample().unwrap()));
+
+    // within 0.3 of (1.5, 0) and above the x axis
+    let sample = || {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    let constraints = || {
+        ConstraintGoal::new(0.0)
+            .constraint(|q: &[f64]| (q[0] - 1.5).powi(2) + q[1].powi(2) - 0.09)
+            .constraint(|q: &[f64]| -q[1])
+    };
+    let goal = constraints();
+    assert!(goal.contains(&[1.6, 0.1]));
+    assert!(!goal.contains(&[1.6, -0.1]));
+    assert_eq!(goal.residuals(&[1.5, -0.1]), [0.0, 0.1]);
+    assert_eq!(goal.sample(), None);
+    for goal in [
+        constraints().rejection_sampler(sample, 10000),
+        constraints().projection_sampler(sample, 100),
+    ] {
+        let q = goal.sample().unwrap();
+        assert!(goal.residuals(&q).iter().all(|r| *r == 0.0));
+        let is_free = |q: &[f64]| !(q[0].abs() < 1.0 && q[1].abs() < 1.0);
+        let path = crate::RrtPlanner::new()
+            .start(&[-1.5, 0.0])
+            .extend_length(0.2)
+            .sampler(sample)
+            .plan_to_region(&goal, is_free)
+            .unwrap();
+        let end = path.last().unwrap();
+        assert_eq!(goal.residuals(end), [0.0, 0.0]);
+    }
+}
@@ -0,0 +1,325 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! 2D occupancy grid maps.
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use std::collections::VecDeque;
+
+/// 2D occupancy grid
+#[derive(Debug, Clone, PartialEq)]
+pub struct OccupancyGrid {
+    width: usize,
+    height: usize,
+    resolution: f64,
+    origin: [f64; 2],
+    occupied: Vec<bool>,
+}
+
+impl OccupancyGrid {
+    /// Create a free grid of `width` x `height` cells of size `resolution`,
+    /// whose lower left corner is at `origin`
+    pub fn new(width: usize, height: usize, resolution: f64, origin: [f64; 2]) -> Self {
+        assert!(resolution > 0.0);
+        Self {
+            width,
+            height,
+            resolution,
+            origin,
+            occupied: vec![false; width * height],
+        }
+    }
+
+    /// Number of cells in x
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of cells in y
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Size of a cell
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    /// Position of the lower left corner
+    pub fn origin(&self) -> [f64; 2] {
+        self.origin
+    }
+
+    /// Mark the cell as occupied or free
+    pub fn set_occupied(&mut self, x: usize, y: usize, occupied: bool) {
+        assert!(x < self.width && y < self.height);
+        self.occupied[y * self.width + x] = occupied;
+    }
+
+    /// Returns true if the cell is occupied
+    pub fn is_occupied(&self, x: usize, y: usize) -> bool {
+        self.occupied[y * self.width + x]
+    }
+
+    /// Cell which contains `p`, `None` if `p` is outside of the grid
+    pub fn cell(&self, p: &[f64]) -> Option<(usize, usize)> {
+        let x = ((p[0] - self.origin[0]) / self.resolution).floor();
+        let y = ((p[1] - self.origin[1]) / self.resolution).floor();
+        if x < 0.0 || y < 0.0 || x >= self.width as f64 || y >= self.height as f64 {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    /// Center position of the cell
+    pub fn cell_center(&self, x: usize, y: usize) -> [f64; 2] {
+        [
+            self.origin[0] + (x as f64 + 0.5) * self.resolution,
+            self.origin[1] + (y as f64 + 0.5) * self.resolution,
+        ]
+    }
+
+    /// Returns true if `p` is inside of the grid and its cell is free.
+    /// This can be used as `is_free` function of the planners.
+    pub fn is_free(&self, p: &[f64]) -> bool {
+        self.cell(p)
+            .map(|(x, y)| !self.is_occupied(x, y))
+            .unwrap_or(false)
+    }
+
+    fn neighbours(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        [(-1, 0), (1, 0), (0, -1), (0, 1)].into_iter().filter_map(
+            move |(dx, dy): (isize, isize)| {
+                let nx = x.checked_add_signed(dx)?;
+                let ny = y.checked_add_signed(dy)?;
+                (nx < self.width && ny < self.height).then_some((nx, ny))
+            },
+        )
+    }
+
+    /// Number of 4-connected steps from the cell of `goal` to each cell,
+    /// `None` for occupied or unreachable cells. Indexed by `y * width + x`.
+    pub fn wavefront(&self, goal: &[f64]) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.width * self.height];
+        let Some((gx, gy)) = self.cell(goal) else {
+            return distances;
+        };
+        if self.is_occupied(gx, gy) {
+            return distances;
+        }
+        distances[gy * self.width + gx] = Some(0);
+        let mut queue = VecDeque::from([(gx, gy, 0)]);
+        while let Some((x, y, d)) = queue.pop_front() {
+            for (nx, ny) in self.neighbours(x, y) {
+                let index = ny * self.width + nx;
+                if !self.occupied[index] && distances[index].is_none() {
+                    distances[index] = Some(d + 1);
+                    queue.push_back((nx, ny, d + 1));
+                }
+            }
+        }
+        distances
+    }
+
+    /// Distance transform of the grid by brushfire (see [`DistanceField`])
+    pub fn distance_field(&self) -> DistanceField {
+        let index = |x: usize, y: usize| y * self.width + x;
+        // distance and nearest occupied cell
+        let mut nearest = vec![(f64::INFINITY, None); self.width * self.height];
+        let mut queue = VecDeque::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.is_occupied(x, y) {
+                    nearest[index(x, y)] = (0.0, Some((x, y)));
+                    queue.push_back((x, y));
+                }
+            }
+        }
+        while let Some((x, y)) = queue.pop_front() {
+            let (ox, oy) = nearest[index(x, y)].1.unwrap();
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (Some(nx), Some(ny)) = (x.checked_add_signed(dx), y.checked_add_signed(dy))
+                    else {
+                        continue;
+                    };
+                    if nx >= self.width || ny >= self.height {
+                        continue;
+                    }
+                    let ex = nx as f64 - ox as f64;
+                    let ey = ny as f64 - oy as f64;
+                    let d = (ex * ex + ey * ey).sqrt() * self.resolution;
+                    if d < nearest[index(nx, ny)].0 {
+                        nearest[index(nx, ny)] = (d, Some((ox, oy)));
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+        }
+        // outside of the grid is not free
+        let distances = nearest
+            .iter()
+            .enumerate()
+            .map(|(i, (d, _))| {
+                let (x, y) = (i % self.width, i / self.width);
+                let border = (x.min(self.width - 1 - x).min(y).min(self.height - 1 - y) as f64
+                    + 0.5)
+                    * self.resolution;
+                d.min(border)
+            })
+            .collect();
+        DistanceField {
+            width: self.width,
+            height: self.height,
+            resolution: self.resolution,
+            origin: self.origin,
+            distances,
+        }
+    }
+}
+
+/// Distance from each cell center of an [`OccupancyGrid`] to the nearest
+/// occupied cell center or the border of the grid.
+///
+/// The queries interpolate the cells bilinearly, so the clearance and its
+/// gradient can be used for clearance costs or adaptive step sizes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceField {
+    width: usize,
+    height: usize,
+    resolution: f64,
+    origin: [f64; 2],
+    distances: Vec<f64>,
+}
+
+impl DistanceField {
+    /// Distance of the cell
+    pub fn distance(&self, x: usize, y: usize) -> f64 {
+        self.distances[y * self.width + x]
+    }
+
+    /// Interpolated clearance at `p`, zero outside of the grid
+    pub fn clearance(&self, p: &[f64]) -> f64 {
+        let fx = (p[0] - self.origin[0]) / self.resolution - 0.5;
+        let fy = (p[1] - self.origin[1]) / self.resolution - 0.5;
+        if fx < -0.5 || fy < -0.5 || fx > self.width as f64 - 0.5 || fy > self.height as f64 - 0.5 {
+            return 0.0;
+        }
+        let clamp = |v: f64, n: usize| v.max(0.0).min((n - 1) as f64);
+        let (fx, fy) = (clamp(fx, self.width), clamp(fy, self.height));
+        let (x0, y0) = (fx.floor() as usize, fy.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = (fx - x0 as f64, fy - y0 as f64);
+        let bottom = self.distance(x0, y0) * (1.0 - tx) + self.distance(x1, y0) * tx;
+        let top = self.distance(x0, y1) * (1.0 - tx) + self.distance(x1, y1) * tx;
+        bottom * (1.0 - ty) + top * ty
+    }
+
+    /// Gradient of the clearance at `p` by central differences of half a cell
+    pub fn gradient(&self, p: &[f64]) -> [f64; 2] {
+        let h = self.resolution * 0.5;
+        [
+            (self.clearance(&[p[0] + h, p[1]]) - self.clearance(&[p[0] - h, p[1]])) / (2.0 * h),
+            (self.clearance(&[p[0], p[1] + h]) - self.clearance(&[p[0], p[1] - h])) / (2.0 * h),
+        ]
+    }
+}
+
+/// Sampler which prefers cells close to the goal along the free space.
+///
+/// Cells are drawn with weight `exp(-distance / falloff)` where `distance`
+/// is the [`OccupancyGrid::wavefront`] value, then a point is drawn uniformly
+/// inside of the cell. Unreachable cells are never sampled.
+#[derive(Debug, Clone)]
+pub struct WavefrontSampler {
+    cells: Vec<(usize, usize)>,
+    weights: WeightedIndex<f64>,
+    resolution: f64,
+    origin: [f64; 2],
+}
+
+impl WavefrontSampler {
+    /// Create a sampler toward `goal`. `falloff` is in number of cells.
+    ///
+    /// Returns `None` if the goal is not in a free cell of the grid.
+    pub fn new(grid: &OccupancyGrid, goal: &[f64], falloff: f64) -> Option<Self> {
+        assert!(falloff > 0.0);
+        let (cells, weights): (Vec<_>, Vec<_>) = grid
+            .wavefront(goal)
+            .iter()
+            .enumerate()
+            .filter_map(|(i, d)| {
+                d.map(|d| {
+                    (
+                        (i % grid.width, i / grid.width),
+                        (-(d as f64) / falloff).exp(),
+                    )
+                })
+            })
+            .unzip();
+        Some(Self {
+            cells,
+            weights: WeightedIndex::new(weights).ok()?,
+            resolution: grid.resolution,
+            origin: grid.origin,
+        })
+    }
+
+    /// Draw a sample
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<f64> {
+        let (x, y) = self.cells[self.weights.sample(rng)];
+        vec![
+            self.origin[0] + (x as f64 + rng.gen::<f64>()) * self.resolution,
+            self.origin[1] + (y as f64 + rng.gen::<f64>()) * self.resolution,
+        ]
+    }
+}
+
+#[test]
+fn it_works() {
+    // wall at x = 2 with a gap at the top
+    let mut grid = OccupancyGrid::new(5, 5, 0.5, [0.0, 0.0]);
+    for y in 0..4 {
+        grid.set_occupied(2, y, true);
+    }
+    let goal = grid.cell_center(4, 0);
+    let distances = grid.wavefront(&goal);
+    assert_eq!(distances[4], Some(0));
+    assert_eq!(distances[2], None);
+    // around the wall through (2, 4)
+    assert_eq!(distances[0], Some(12));
+
+    let sampler = WavefrontSampler::new(&grid, &goal, 2.0).unwrap();
+    let mut rng = rand::thread_rng();
+    for _ in 0..100 {
+        assert!(grid.is_free(&sampler.sample(&mut rng)));
+    }
+    assert!(WavefrontSampler::new(&grid, &grid.cell_center(2, 0), 2.0).is_none());
+
+    let mut grid = OccupancyGrid::new(40, 40, 0.1, [0.0, 0.0]);
+    grid.set_occupied(20, 20, true);
+    let field = grid.distance_field();
+    assert_eq!(field.distance(20, 20), 0.0);
+    assert!((field.distance(23, 24) - 0.5).abs() < 1e-9);
+    // closer to the border than to the obstacle
+    assert!((field.distance(0, 20) - 0.05).abs() < 1e-9);
+    let p = grid.cell_center(20, 24);
+    assert!((field.clearance(&p) - 0.4).abs() < 1e-9);
+    let g = field.gradient(&p);
+    assert!(g[0].abs() < 1e-9 && (g[1] - 1.0).abs() < 1e-9, "{g:?}");
+    assert_eq!(field.clearance(&[-1.0, 0.5]), 0.0);
+}
@@ -0,0 +1,237 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Planning for composite robots (e.g. two arms on a torso) whose groups of
+//! dimensions have their own step sizes and weights, and can be frozen for
+//! a query.
+
+use crate::rrt::Error;
+use num_traits::float::Float;
+use std::fmt::Debug;
+use std::ops::Range;
+
+/// Dimensions of a part of the robot
+#[derive(Debug, Clone, PartialEq)]
+pub struct JointGroup<N> {
+    /// Name to freeze the group by
+    pub name: String,
+    /// Dimensions of the group in the configuration
+    pub dims: Range<usize>,
+    /// Largest motion of the group between the waypoints
+    pub step: N,
+    /// Weight of the group in the distances, e.g. larger for the heavy joints
+    pub weight: N,
+}
+
+impl<N: Float> JointGroup<N> {
+    /// Create a group of weight one
+    pub fn new(name: &str, dims: Range<usize>, step: N) -> Self {
+        Self {
+            name: name.to_owned(),
+            dims,
+            step,
+            weight: N::one(),
+        }
+    }
+
+    /// Set the weight of the group in the distances
+    pub fn weight(mut self, weight: N) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+/// Query for [`crate::dual_rrt_connect`] over the groups of a composite robot.
+///
+/// The planner works in the space where each group is scaled by its weight,
+/// with the extension length which keeps every group within its step. The
+/// frozen groups, and the dimensions which are not in any group, are held
+/// at the start configuration.
+#[derive(Debug, Clone)]
+pub struct GroupedQuery<N> {
+    groups: Vec<JointGroup<N>>,
+    frozen: Vec<String>,
+}
+
+impl<N> Default for GroupedQuery<N> {
+    fn default() -> Self {
+        Self {
+            groups: Vec::new(),
+            frozen: Vec::new(),
+        }
+    }
+}
+
+impl<N> GroupedQuery<N>
+where
+    N: Float + Debug,
+{
+    /// Create a query without groups
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a group, which must not overlap the others
+    pub fn group(mut self, group: JointGroup<N>) -> Self {
+        assert!(
+            self.groups
+                .iter()
+                .all(|other| other.dims.end <= group.dims.start
+                    || group.dims.end <= other.dims.start),
+            "the group {} overlaps another group",
+            group.name
+        );
+        self.groups.push(group);
+        self
+    }
+
+    /// Hold the group named `name` at the start configuration
+    pub fn freeze(mut self, name: &str) -> Self {
+        assert!(
+            self.groups.iter().any(|group| group.name == name),
+            "there is no group {name}"
+        );
+        self.frozen.push(name.to_owned());
+        self
+    }
+
+    /// Search the path from `start` to `goal`, which must not move the
+    /// frozen groups. `random_sample` draws full configurations, whose
+    /// frozen dimensions are ignored.
+    pub fn plan<FF, FR>(
+        &self,
+        start: &[N],
+        goal: &[N],
+        mut is_free: FF,
+        random_sample: FR,
+        num_max_try: usize,
+    ) -> Result<Vec<Vec<N>>, Error<N>>
+    where
+        FF: FnMut(&[N]) -> bool,
+        FR: Fn() -> Vec<N>,
+    {
+        if start.len() != goal.len() {
+            return Err(Error::DimensionMismatch {
+                start: start.len(),
+                goal: goal.len(),
+            });
+        }
+        let active = self
+            .groups
+            .iter()
+            .filter(|group| !self.frozen.contains(&group.name))
+            .collect::<Vec<_>>();
+        let Some(extend_length) = active
+            .iter()
+            .map(|group| group.weight * group.step)
+            .reduce(N::min)
+        else {
+            return Err(Error::InvalidConfiguration("all the groups are frozen"));
+        };
+        // active dimensions and their weights
+        let scales = active
+            .iter()
+            .flat_map(|group| group.dims.clone().map(|i| (i, group.weight)))
+            .collect::<Vec<_>>();
+        let is_active = |i: usize| scales.iter().any(|&(active, _)| active == i);
+        if (0..start.len()).any(|i| !is_active(i) && start[i] != goal[i]) {
+            return Err(Error::InvalidConfiguration(
+                "the goal moves a frozen dimension",
+            ));
+        }
+        let to_scaled = |q: &[N]| scales.iter().map(|&(i, w)| q[i] * w).collect::<Vec<_>>();
+        let from_scaled = |x: &[N]| {
+            let mut q = start.to_vec();
+            for (v, &(i, w)) in x.iter().zip(&scales) {
+                q[i] = *v / w;
+            }
+            q
+        };
+        let lift = |path: Vec<Vec<N>>| path.iter().map(|x| from_scaled(x)).collect::<Vec<_>>();
+        let mut path = crate::rrt::dual_rrt_connect(
+            &to_scaled(start),
+            &to_scaled(goal),
+            |x: &[N]| is_free(&from_scaled(x)),
+            || to_scaled(&random_sample()),
+            extend_length,
+            num_max_try,
+        )
+        .map(lift)
+        .map_err(|e| match e {
+            Error::MaxIterationsReached { best_partial } => Error::MaxIterationsReached {
+                best_partial: best_partial.map(lift),
+            },
+            e => e,
+        })?;
+        let last = path.len() - 1;
+        path[0].copy_from_slice(start);
+        path[last].copy_from_slice(goal);
+        Ok(path)
+    }
+}
+
+#[test]
+fn it_works() {
+    use rand::Rng;
+    // two planar arms, the second one moves in smaller steps
+    let query = GroupedQuery::new()
+        .group(JointGroup::new("arm_a", 0..2, 0.2))
+        .group(JointGroup::new("arm_b", 2..4, 0.1).weight(2.0));
+    let is_free = |q: &[f64]| !(q[0].abs() < 1.0 && q[1].abs() < 1.0) && q[2] + q[3] < 1.5;
+    let random_sample = || {
+        let mut rng = rand::thread_rng();
+        (0..4).map(|_| rng.gen_range(-2.0..2.0)).collect::<Vec<_>>()
+    };
+    let start = [-1.2, 0.0, 0.0, 0.0];
+    let goal = [1.2, 0.0, 0.5, 0.5];
+    let path = query
+        .plan(&start, &goal, is_free, random_sample, 10000)
+        .unwrap();
+    assert_eq!(path[0], start);
+    assert_eq!(path[path.len() - 1], goal);
+    let norm = |a: &[f64], b: &[f64]| {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    };
+    for w in path.windows(2) {
+        assert!(norm(&w[0][0..2], &w[1][0..2]) <= 0.2 + 1e-9);
+        assert!(norm(&w[0][2..4], &w[1][2..4]) <= 0.1 + 1e-9);
+    }
+
+    // only the first arm moves
+    let query = query.freeze("arm_b");
+    let goal = [1.2, 0.0, 0.0, 0.0];
+    let path = query
+        .plan(&start, &goal, is_free, random_sample, 1000)
+        .unwrap();
+    assert!(path.iter().all(|q| q[2] == 0.0 && q[3] == 0.0));
+    assert!(path.iter().all(|q| is_free(q)));
+    assert_eq!(
+        query.plan(&start, &[1.2, 0.0, 0.5, 0.5], is_free, random_sample, 1000),
+        Err(Error::InvalidConfiguration(
+            "the goal moves a frozen dimension"
+        ))
+    );
+    assert_eq!(
+        query
+            .freeze("arm_a")
+            .plan(&start, &start, is_free, random_sample, 1000),
+        Err(Error::InvalidConfiguration("all the groups are frozen"))
+    );
+}
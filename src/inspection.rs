@@ -0,0 +1,166 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Inspection planning: visit a set of viewpoints in one collision-free tour.
+
+use crate::path::path_length;
+use crate::rrt::{dual_rrt_connect, smooth_path};
+use num_traits::float::Float;
+use std::fmt::Debug;
+
+/// Plan a tour which starts at `viewpoints[0]` and visits all the viewpoints.
+///
+/// The viewpoints are connected pairwise by [`dual_rrt_connect`] and
+/// [`smooth_path`], then the order is solved as an open traveling salesman
+/// problem over the lengths of the connections (nearest neighbour followed
+/// by 2-opt). Returns the order of the viewpoints and the combined path.
+pub fn inspection_tour<FF, FR, N>(
+    viewpoints: &[Vec<N>],
+    mut is_free: FF,
+    random_sample: FR,
+    extend_length: N,
+    num_max_try: usize,
+) -> Result<(Vec<usize>, Vec<Vec<N>>), String>
+where
+    FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    N: Float + Debug,
+{
+    let n = viewpoints.len();
+    if n == 0 {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let mut connections = vec![vec![None; n]; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            if let Ok(mut path) = dual_rrt_connect(
+                &viewpoints[i],
+                &viewpoints[j],
+                &mut is_free,
+                &random_sample,
+                extend_length,
+                num_max_try,
+            ) {
+                smooth_path(&mut path, &mut is_free, extend_length, num_max_try);
+                connections[i][j] = Some(path);
+            }
+        }
+    }
+    let cost = |i: usize, j: usize| {
+        connections[i.min(j)][i.max(j)]
+            .as_ref()
+            .map_or(N::infinity(), |path| path_length(path))
+    };
+
+    // nearest neighbour
+    let mut order = vec![0];
+    let mut remaining = (1..n).collect::<Vec<_>>();
+    while !remaining.is_empty() {
+        let last = order[order.len() - 1];
+        let (k, c) = remaining
+            .iter()
+            .enumerate()
+            .map(|(k, &j)| (k, cost(last, j)))
+            .fold((0, N::infinity()), |a, b| if b.1 < a.1 { b } else { a });
+        if c == N::infinity() {
+            return Err(format!("viewpoint {} is unreachable", remaining[0]));
+        }
+        order.push(remaining.swap_remove(k));
+    }
+
+    // 2-opt, keeping the first viewpoint
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 1..n {
+            for j in i + 1..n {
+                let mut delta = cost(order[i - 1], order[j]) - cost(order[i - 1], order[i]);
+                if j + 1 < n {
+                    delta = delta + cost(order[i], order[j + 1]) - cost(order[j], order[j + 1]);
+                }
+                if delta < -N::epsilon() {
+                    order[i..=j].reverse();
+                    improved = true;
+                }
+            }
+        }
+    }
+
+    let mut tour = vec![viewpoints[order[0]].clone()];
+    for w in order.windows(2) {
+        let mut path = connections[w[0].min(w[1])][w[0].max(w[1])].clone().unwrap();
+        if w[0] > w[1] {
+            path.reverse();
+        }
+        tour.extend(path.into_iter().skip(1));
+    }
+    Ok((order, tour))
+}
+
+#[test]
+fn it_works() {
+    use crate::collision::{AxisAlignedBox, Obstacle, Scene};
+    use crate::path::subdivide;
+    use rand::distributions::{Distribution, Uniform};
+
+    let scene = Scene {
+        obstacles: vec![Obstacle::Box(AxisAlignedBox {
+            min: vec![-1.0, -1.0],
+            max: vec![1.0, 1.0],
+        })],
+    };
+    let viewpoints = vec![
+        vec![-2.0, -2.0],
+        vec![2.0, 2.0],
+        vec![-2.0, 0.0],
+        vec![2.0, -2.0],
+        vec![-2.0, 2.0],
+    ];
+    let (order, tour) = inspection_tour(
+        &viewpoints,
+        |p: &[f64]| scene.is_free(p),
+        || {
+            let between = Uniform::new(-3.0, 3.0);
+            let mut rng = rand::thread_rng();
+            vec![between.sample(&mut rng), between.sample(&mut rng)]
+        },
+        0.2,
+        1000,
+    )
+    .unwrap();
+    let mut sorted = order.clone();
+    sorted.sort();
+    assert_eq!(sorted, vec![0, 1, 2, 3, 4]);
+    assert_eq!(order[0], 0);
+    // going around the box without crossing it
+    assert!(order == vec![0, 2, 4, 1, 3] || order == vec![0, 3, 1, 4, 2]);
+    assert_eq!(tour[0], viewpoints[0]);
+    assert_eq!(tour.last().unwrap(), &viewpoints[*order.last().unwrap()]);
+    for v in &viewpoints {
+        assert!(tour.contains(v));
+    }
+    assert!(subdivide(&tour, 0.01).iter().all(|(q, _)| scene.is_free(q)));
+
+    // unreachable viewpoint
+    assert!(inspection_tour(
+        &[vec![-2.0, -2.0], vec![0.0, 0.0]],
+        |p: &[f64]| scene.is_free(p),
+        || vec![0.0, 0.0],
+        0.2,
+        10,
+    )
+    .is_err());
+}
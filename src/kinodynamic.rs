@@ -0,0 +1,241 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Kinodynamic RRT.
+//!
+//! The straight lines of [`crate::rrt`] are not feasible motions for
+//! nonholonomic robots (a differential drive can not move sideways), so the
+//! tree is grown by a steering function of the user instead. It drives the
+//! robot from a vertex toward the sample and returns the states which it
+//! passes through (one per time step), the control which it applied and the
+//! direction of the motion. The controls are kept on the edges of the tree,
+//! and the plan is the sequence of the controls from the start to the goal.
+
+use crate::rrt::Error;
+use crate::se2::Direction;
+use kdtree::distance::squared_euclidean;
+use num_traits::float::Float;
+use std::fmt::Debug;
+use tracing::debug;
+
+/// Edge of a kinodynamic plan
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControlStep<N, C> {
+    /// State which is reached from the state of the previous edge (or the
+    /// start)
+    pub state: Vec<N>,
+    /// Control (the steering primitive) which is applied
+    pub control: C,
+    /// Direction of the motion
+    pub direction: Direction,
+    /// Duration for which the control is applied
+    pub duration: N,
+}
+
+struct Vertex<N, C> {
+    state: Vec<N>,
+    // parent and the control from it, which is applied for the duration
+    edge: Option<(usize, C, Direction, N)>,
+}
+
+/// Search a trajectory from `start` to a state where `is_goal` holds.
+///
+/// `steer(from, to)` returns the states from `from` toward `to`, which are
+/// `dt` apart in time, the control which is applied and the direction of the
+/// motion (or `None` if `to` can not be approached). Every state of the steering is checked by
+/// `is_free`, and the edge ends at the first state in the goal. The nearest
+/// vertex to the sample is searched by the euclidean distance of the states.
+pub fn kinodynamic_rrt<N, C, FF, FS, FR, FG>(
+    start: &[N],
+    mut is_goal: FG,
+    mut is_free: FF,
+    mut steer: FS,
+    mut random_sample: FR,
+    dt: N,
+    max_iters: usize,
+) -> Result<Vec<ControlStep<N, C>>, Error<N>>
+where
+    N: Float + Debug,
+    C: Clone,
+    FG: FnMut(&[N]) -> bool,
+    FF: FnMut(&[N]) -> bool,
+    FS: FnMut(&[N], &[N]) -> Option<(Vec<Vec<N>>, C, Direction)>,
+    FR: FnMut() -> Vec<N>,
+{
+    if !is_free(start) {
+        return Err(Error::StartInCollision);
+    }
+    if dt <= N::zero() {
+        return Err(Error::InvalidConfiguration("dt must be positive"));
+    }
+    let mut kdtree = kdtree::KdTree::new(start.len());
+    kdtree.add(start.to_vec(), 0).unwrap();
+    let mut vertices = vec![Vertex {
+        state: start.to_vec(),
+        edge: None::<(usize, C, Direction, N)>,
+    }];
+    let mut goal_index = is_goal(start).then_some(0);
+    for _ in 0..max_iters {
+        if goal_index.is_some() {
+            break;
+        }
+        let q_rand = random_sample();
+        let nearest = *kdtree.nearest(&q_rand, 1, &squared_euclidean).unwrap()[0].1;
+        let Some((states, control, direction)) = steer(&vertices[nearest].state, &q_rand) else {
+            continue;
+        };
+        let mut num_steps = 0;
+        let mut reached_goal = false;
+        for q in &states {
+            if !is_free(q) {
+                break;
+            }
+            num_steps += 1;
+            if is_goal(q) {
+                reached_goal = true;
+                break;
+            }
+        }
+        // the control is only valid for the whole duration, so a partially
+        // free motion is not added (except when it stops in the goal)
+        if num_steps == 0 || (num_steps < states.len() && !reached_goal) {
+            continue;
+        }
+        let state = states[num_steps - 1].clone();
+        let duration = dt * N::from(num_steps).unwrap();
+        let index = vertices.len();
+        kdtree.add(state.clone(), index).unwrap();
+        vertices.push(Vertex {
+            state,
+            edge: Some((nearest, control, direction, duration)),
+        });
+        if reached_goal {
+            goal_index = Some(index);
+        }
+    }
+    let Some(mut index) = goal_index else {
+        return Err(Error::MaxIterationsReached { best_partial: None });
+    };
+    debug!("kinodynamic tree has {} vertices", vertices.len());
+    let mut trajectory = Vec::new();
+    while let Some((parent, control, direction, duration)) = &vertices[index].edge {
+        trajectory.push(ControlStep {
+            state: vertices[index].state.clone(),
+            control: control.clone(),
+            direction: *direction,
+            duration: *duration,
+        });
+        index = *parent;
+    }
+    trajectory.reverse();
+    Ok(trajectory)
+}
+
+#[test]
+fn it_works() {
+    use crate::goal::angle_difference;
+    use rand::Rng;
+    use std::f64::consts::{FRAC_PI_2, PI};
+    // differential drive [x, y, theta] with the control (velocity, yaw rate)
+    let dt = 0.05;
+    let integrate = |from: &[f64], (v, w): (f64, f64), num_steps: usize| {
+        let mut q = from.to_vec();
+        let mut states = Vec::new();
+        for _ in 0..num_steps {
+            q = vec![
+                q[0] + v * q[2].cos() * dt,
+                q[1] + v * q[2].sin() * dt,
+                q[2] + w * dt,
+            ];
+            states.push(q.clone());
+        }
+        states
+    };
+    // back up toward the samples behind
+    let steer = |from: &[f64], to: &[f64]| {
+        let heading = (to[1] - from[1]).atan2(to[0] - from[0]);
+        let error = angle_difference(heading, from[2]);
+        let (v, error, direction) = if error.abs() > FRAC_PI_2 {
+            (
+                -1.0,
+                angle_difference(heading + PI, from[2]),
+                Direction::Backward,
+            )
+        } else {
+            (1.0, error, Direction::Forward)
+        };
+        let control = (v, (error / 0.5).clamp(-2.0, 2.0));
+        Some((integrate(from, control, 10), control, direction))
+    };
+    let is_free = |q: &[f64]| !(q[0].abs() < 0.5 && q[1].abs() < 1.0);
+    let is_goal = |q: &[f64]| (q[0] - 1.5).hypot(q[1]) < 0.3;
+    let random_sample = || {
+        let mut rng = rand::thread_rng();
+        if rng.gen_bool(0.1) {
+            vec![1.5, 0.0, 0.0]
+        } else {
+            vec![
+                rng.gen_range(-3.0..3.0),
+                rng.gen_range(-3.0..3.0),
+                rng.gen_range(-PI..PI),
+            ]
+        }
+    };
+    let start = [-1.5, 0.0, 0.0];
+    let trajectory =
+        kinodynamic_rrt(&start, is_goal, is_free, steer, random_sample, dt, 10000).unwrap();
+    assert!(is_goal(&trajectory.last().unwrap().state));
+    let mut q = start.to_vec();
+    for step in &trajectory {
+        let num_steps = (step.duration / dt).round() as usize;
+        let states = integrate(&q, step.control, num_steps);
+        assert!(states.iter().all(|q| is_free(q)));
+        assert_eq!(states.last().unwrap(), &step.state);
+        let backward = step.control.0 < 0.0;
+        assert_eq!(step.direction == Direction::Backward, backward);
+        q = step.state.clone();
+    }
+
+    assert_eq!(
+        kinodynamic_rrt(&start, is_goal, is_free, steer, random_sample, dt, 0).unwrap_err(),
+        Error::MaxIterationsReached { best_partial: None }
+    );
+    assert_eq!(
+        kinodynamic_rrt(
+            &[0.0, 0.0, 0.0],
+            is_goal,
+            is_free,
+            steer,
+            random_sample,
+            dt,
+            10
+        )
+        .unwrap_err(),
+        Error::StartInCollision
+    );
+    let trajectory = kinodynamic_rrt(
+        &[1.5, 0.0, 0.0],
+        is_goal,
+        is_free,
+        steer,
+        random_sample,
+        dt,
+        10,
+    )
+    .unwrap();
+    assert!(trajectory.is_empty());
+}
@@ -17,5 +17,57 @@
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
 
+#[cfg(feature = "bevy")]
+pub mod bevy_plugin;
+pub mod budget;
+pub mod cache;
+pub mod collision;
+pub mod complete;
+pub mod corpus;
+pub mod costmap;
+pub mod coverage;
+pub mod demonstration;
+pub mod diagnosis;
+pub mod execution;
+pub mod exploration;
+pub mod fallback;
+pub mod flat;
+pub mod goal;
+pub mod grid;
+pub mod groups;
+pub mod inspection;
+pub mod kinodynamic;
+pub mod nearest;
+pub mod normalization;
+pub mod orthtree;
+pub mod partial;
+pub mod path;
+pub mod pipeline;
+pub mod planner;
+pub mod postprocess;
+pub mod problems;
+#[cfg(feature = "rerun")]
+pub mod rerun_viewer;
+pub mod roadmap;
 pub mod rrt;
 pub mod rrtstar;
+pub mod sampler;
+pub mod se2;
+pub mod seeding;
+#[cfg(feature = "service")]
+pub mod service;
+pub mod so3;
+pub mod space;
+pub mod steer;
+pub mod subscription;
+pub mod trajectory;
+pub mod typed;
+pub mod validation;
+pub mod validity;
+
+pub use crate::planner::RrtPlanner;
+pub use crate::rrt::{dual_rrt_connect, smooth_path, Error};
+
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub use rand as __rand;
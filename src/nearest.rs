@@ -0,0 +1,214 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Approximate nearest neighbour search for very large trees.
+
+use kdtree::distance::squared_euclidean;
+use num_traits::float::Float;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+#[derive(Debug, Clone)]
+struct KdNode<N> {
+    point: Vec<N>,
+    index: usize,
+    split_dim: usize,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+/// Incremental kd-tree whose nearest neighbour query visits at most
+/// `max_checks` points (best bin first).
+///
+/// The returned point is the nearest one only if the search finishes within
+/// `max_checks`; otherwise it is the nearest of the visited points. For
+/// RRT this slightly weakens the bias toward unexplored regions and makes
+/// the steps a bit longer than necessary, but it keeps the query time
+/// bounded for trees with millions of vertices.
+#[derive(Debug, Clone)]
+pub struct ApproximateKdTree<N> {
+    nodes: Vec<KdNode<N>>,
+    dim: usize,
+    max_checks: usize,
+}
+
+struct Branch<N> {
+    bound: N,
+    node: usize,
+}
+
+impl<N: Float> PartialEq for Branch<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bound == other.bound
+    }
+}
+
+impl<N: Float> Eq for Branch<N> {}
+
+impl<N: Float> PartialOrd for Branch<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N: Float> Ord for Branch<N> {
+    // reversed to make BinaryHeap a min-heap of the bound
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .bound
+            .partial_cmp(&self.bound)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl<N: Float> ApproximateKdTree<N> {
+    /// Create an empty tree of `dim` dimensional points
+    pub fn new(dim: usize, max_checks: usize) -> Self {
+        Self::with_capacity(dim, max_checks, 0)
+    }
+
+    /// Create an empty tree with the memory for `capacity` points reserved
+    pub fn with_capacity(dim: usize, max_checks: usize, capacity: usize) -> Self {
+        assert!(max_checks > 0);
+        Self {
+            nodes: Vec::with_capacity(capacity),
+            dim,
+            max_checks,
+        }
+    }
+
+    /// Maximum number of the points compared by a query
+    pub fn max_checks(&self) -> usize {
+        self.max_checks
+    }
+
+    /// Number of points
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if there are no points
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Add a point with its index
+    pub fn add(&mut self, point: &[N], index: usize) {
+        assert_eq!(point.len(), self.dim);
+        let new_node = self.nodes.len();
+        let mut split_dim = 0;
+        if !self.nodes.is_empty() {
+            let mut current = 0;
+            loop {
+                let node = &self.nodes[current];
+                let go_left = point[node.split_dim] < node.point[node.split_dim];
+                split_dim = (node.split_dim + 1) % self.dim;
+                let child = if go_left { node.left } else { node.right };
+                match child {
+                    Some(c) => current = c,
+                    None => {
+                        let node = &mut self.nodes[current];
+                        if go_left {
+                            node.left = Some(new_node);
+                        } else {
+                            node.right = Some(new_node);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        self.nodes.push(KdNode {
+            point: point.to_vec(),
+            index,
+            split_dim,
+            left: None,
+            right: None,
+        });
+    }
+
+    /// Index of the (approximately) nearest point, `None` if empty
+    pub fn nearest(&self, q: &[N]) -> Option<usize> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut best = (N::infinity(), 0);
+        let mut checks = 0;
+        let mut heap = BinaryHeap::new();
+        heap.push(Branch {
+            bound: N::zero(),
+            node: 0,
+        });
+        while let Some(Branch { bound, node }) = heap.pop() {
+            if bound >= best.0 || checks >= self.max_checks {
+                break;
+            }
+            let node = &self.nodes[node];
+            checks += 1;
+            let dist = squared_euclidean(q, &node.point);
+            if dist < best.0 {
+                best = (dist, node.index);
+            }
+            let diff = q[node.split_dim] - node.point[node.split_dim];
+            let (near, far) = if diff < N::zero() {
+                (node.left, node.right)
+            } else {
+                (node.right, node.left)
+            };
+            if let Some(near) = near {
+                heap.push(Branch { bound, node: near });
+            }
+            if let Some(far) = far {
+                heap.push(Branch {
+                    bound: bound.max(diff * diff),
+                    node: far,
+                });
+            }
+        }
+        Some(best.1)
+    }
+}
+
+#[test]
+fn it_works() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let points = (0..1000)
+        .map(|_| vec![rng.gen::<f64>(), rng.gen::<f64>(), rng.gen::<f64>()])
+        .collect::<Vec<_>>();
+    let mut exact = ApproximateKdTree::new(3, usize::MAX);
+    let mut approximate = ApproximateKdTree::new(3, 10);
+    for (i, p) in points.iter().enumerate() {
+        exact.add(p, i);
+        approximate.add(p, i);
+    }
+    assert_eq!(exact.len(), 1000);
+    for _ in 0..100 {
+        let q = [rng.gen::<f64>(), rng.gen::<f64>(), rng.gen::<f64>()];
+        let brute_force = (0..points.len())
+            .min_by(|&a, &b| {
+                squared_euclidean(&q, &points[a])
+                    .partial_cmp(&squared_euclidean(&q, &points[b]))
+                    .unwrap()
+            })
+            .unwrap();
+        assert_eq!(exact.nearest(&q), Some(brute_force));
+        let found = approximate.nearest(&q).unwrap();
+        assert!(
+            squared_euclidean(&q, &points[found]) >= squared_euclidean(&q, &points[brute_force])
+        );
+    }
+}
@@ -0,0 +1,267 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Rescaling of each dimension of the configuration space to `[0, 1]`.
+//!
+//! Distances, extend lengths and RRT* radii are isotropic, so the planners
+//! behave badly when the dimensions have very different units (e.g. meters
+//! and millimeters). [`Normalization`] plans in the unit box instead, and
+//! maps the results back. [`Normalization::from_weights`] scales the
+//! dimensions so the Euclidean distance of the planners (in the kd-tree
+//! queries, the extension steps and the goal checks) is the weighted one,
+//! e.g. to trade meters against radians. For the metrics which are not
+//! weighted Euclidean see [`crate::space::Metric`].
+
+use crate::rrt::{Bounds, Error};
+use num_traits::float::Float;
+use std::fmt::Debug;
+
+/// Affine map from the box between `lower` and `upper` to the unit box
+#[derive(Debug, Clone, PartialEq)]
+pub struct Normalization<N> {
+    lower: Vec<N>,
+    upper: Vec<N>,
+}
+
+impl<N> Normalization<N>
+where
+    N: Float + Debug,
+{
+    /// Create the map of the box between `lower` and `upper`, which must be
+    /// larger than `lower` in every dimension
+    pub fn new(lower: &[N], upper: &[N]) -> Self {
+        assert_eq!(lower.len(), upper.len());
+        assert!(lower.iter().zip(upper).all(|(lower, upper)| lower < upper));
+        Self {
+            lower: lower.to_vec(),
+            upper: upper.to_vec(),
+        }
+    }
+
+    /// Create the map of `bounds`
+    pub fn from_bounds(bounds: &Bounds<N>) -> Self {
+        Self::new(&bounds.lower, &bounds.upper)
+    }
+
+    /// Create the map which scales each dimension by the square root of its
+    /// weight, so the distance of the mapped configurations is
+    /// `sqrt(sum(weights[i] * (a[i] - b[i])^2))`
+    pub fn from_weights(weights: &[N]) -> Self {
+        assert!(weights.iter().all(|w| *w > N::zero()));
+        Self {
+            lower: vec![N::zero(); weights.len()],
+            upper: weights.iter().map(|w| w.sqrt().recip()).collect(),
+        }
+    }
+
+    /// Euclidean distance of `a` and `b` after the map
+    pub fn distance(&self, a: &[N], b: &[N]) -> N {
+        self.normalize(a)
+            .iter()
+            .zip(self.normalize(b))
+            .fold(N::zero(), |sum, (a, b)| sum + (*a - b).powi(2))
+            .sqrt()
+    }
+
+    /// Map `q` into the unit box
+    pub fn normalize(&self, q: &[N]) -> Vec<N> {
+        q.iter()
+            .zip(self.lower.iter().zip(&self.upper))
+            .map(|(v, (lower, upper))| (*v - *lower) / (*upper - *lower))
+            .collect()
+    }
+
+    /// Map `x` from the unit box back to the configuration space
+    pub fn denormalize(&self, x: &[N]) -> Vec<N> {
+        x.iter()
+            .zip(self.lower.iter().zip(&self.upper))
+            .map(|(v, (lower, upper))| *lower + *v * (*upper - *lower))
+            .collect()
+    }
+
+    /// Plan with `planner` in the unit box, which gets the normalized start
+    /// and goal, `is_free` and `random_sample` in the unit box. The path is
+    /// mapped back, and starts and ends exactly at `start` and `goal`.
+    pub fn plan<FF, FR, FP, E>(
+        &self,
+        start: &[N],
+        goal: &[N],
+        mut is_free: FF,
+        random_sample: FR,
+        planner: FP,
+    ) -> Result<Vec<Vec<N>>, E>
+    where
+        FF: FnMut(&[N]) -> bool,
+        FR: Fn() -> Vec<N>,
+        FP: FnOnce(
+            &[N],
+            &[N],
+            &mut dyn FnMut(&[N]) -> bool,
+            &dyn Fn() -> Vec<N>,
+        ) -> Result<Vec<Vec<N>>, E>,
+    {
+        let path = planner(
+            &self.normalize(start),
+            &self.normalize(goal),
+            &mut |x: &[N]| is_free(&self.denormalize(x)),
+            &|| self.normalize(&random_sample()),
+        )?;
+        let mut path = path.iter().map(|x| self.denormalize(x)).collect::<Vec<_>>();
+        if let Some(first) = path.first_mut() {
+            first.copy_from_slice(start);
+        }
+        if let Some(last) = path.last_mut() {
+            last.copy_from_slice(goal);
+        }
+        Ok(path)
+    }
+
+    /// [`crate::dual_rrt_connect`] in the unit box, `extend_length` is in
+    /// the normalized units
+    pub fn dual_rrt_connect<FF, FR>(
+        &self,
+        start: &[N],
+        goal: &[N],
+        is_free: FF,
+        random_sample: FR,
+        extend_length: N,
+        num_max_try: usize,
+    ) -> Result<Vec<Vec<N>>, Error<N>>
+    where
+        FF: FnMut(&[N]) -> bool,
+        FR: Fn() -> Vec<N>,
+    {
+        self.plan(
+            start,
+            goal,
+            is_free,
+            random_sample,
+            |start, goal, is_free, random_sample| {
+                crate::rrt::dual_rrt_connect(
+                    start,
+                    goal,
+                    is_free,
+                    random_sample,
+                    extend_length,
+                    num_max_try,
+                )
+                .map_err(|e| match e {
+                    Error::MaxIterationsReached { best_partial } => Error::MaxIterationsReached {
+                        best_partial: best_partial
+                            .map(|path| path.iter().map(|x| self.denormalize(x)).collect()),
+                    },
+                    e => e,
+                })
+            },
+        )
+    }
+}
+
+#[test]
+fn it_works() {
+    use rand::Rng;
+    // millimeters and radians, with a passage near the upper bound of the angle
+    let normalization = Normalization::new(&[0.0, 0.0], &[1000.0, 0.01]);
+    assert_eq!(normalization.normalize(&[250.0, 0.005]), [0.25, 0.5]);
+    assert_eq!(normalization.denormalize(&[0.25, 0.5]), [250.0, 0.005]);
+    let is_free = |q: &[f64]| !(400.0 < q[0] && q[0] < 600.0 && q[1] < 0.008);
+    let random_sample = || {
+        let mut rng = rand::thread_rng();
+        vec![rng.gen_range(0.0..1000.0), rng.gen_range(0.0..0.01)]
+    };
+    let path = normalization
+        .dual_rrt_connect(
+            &[100.0, 0.0],
+            &[900.0, 0.0],
+            is_free,
+            random_sample,
+            0.05,
+            1000,
+        )
+        .unwrap();
+    assert_eq!(path[0], [100.0, 0.0]);
+    assert_eq!(path[path.len() - 1], [900.0, 0.0]);
+    assert!(path.iter().all(|q| is_free(q)));
+    assert!(path.iter().any(|q| q[1] >= 0.008));
+
+    // any planner, e.g. RRT*
+    let path = normalization
+        .plan(
+            &[100.0, 0.0],
+            &[900.0, 0.0],
+            is_free,
+            random_sample,
+            |start, goal, is_free, random_sample| {
+                let tree = crate::rrtstar::rrtstar_with_resolution(
+                    start,
+                    goal,
+                    is_free,
+                    random_sample,
+                    0.05,
+                    0.01,
+                    3000,
+                    0.1,
+                    true,
+                )?;
+                let mut path = tree.get_until_root(tree.goal_index.unwrap());
+                path.reverse();
+                path.push(goal.to_vec());
+                Ok::<_, crate::rrtstar::RRTStarError>(path)
+            },
+        )
+        .unwrap();
+    assert_eq!(path[0], [100.0, 0.0]);
+    assert_eq!(path[path.len() - 1], [900.0, 0.0]);
+    assert!(path.iter().all(|q| is_free(q)));
+
+    let Err(Error::MaxIterationsReached {
+        best_partial: Some(partial),
+    }) = normalization.dual_rrt_connect(
+        &[100.0, 0.0],
+        &[900.0, 0.0],
+        |q: &[f64]| !(400.0 < q[0] && q[0] < 600.0),
+        random_sample,
+        0.05,
+        10,
+    )
+    else {
+        panic!();
+    };
+    assert!((partial[0][0] - 100.0).abs() < 1e-9);
+    assert!(partial.iter().all(|q| q[0] <= 400.0));
+
+    // meters and radians, where a radian costs as much as 0.25 m
+    let weights = Normalization::from_weights(&[1.0, 0.0625]);
+    assert!((weights.distance(&[0.0, 0.0], &[3.0, 16.0]) - 5.0).abs() < 1e-9);
+    assert!((weights.denormalize(&weights.normalize(&[0.3, 2.0]))[1] - 2.0).abs() < 1e-9);
+    let is_free = |q: &[f64]| !(q[0].abs() < 0.5 && q[1].abs() < 2.0);
+    let random_sample = || {
+        let mut rng = rand::thread_rng();
+        vec![rng.gen_range(-2.0..2.0), rng.gen_range(-4.0..4.0)]
+    };
+    let path = weights
+        .dual_rrt_connect(&[-1.0, 0.0], &[1.0, 0.0], is_free, random_sample, 0.1, 1000)
+        .unwrap();
+    assert_eq!(path[0], [-1.0, 0.0]);
+    assert_eq!(path[path.len() - 1], [1.0, 0.0]);
+    for w in path.windows(2) {
+        assert!(weights.distance(&w[0], &w[1]) <= 0.1 + 1e-9);
+    }
+    // the steps are long in the cheap dimension
+    assert!(path
+        .windows(2)
+        .any(|w| (w[0][1] - w[1][1]).abs() > 0.1 + 1e-9));
+}
@@ -0,0 +1,222 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Multi-resolution occupancy maps (quadtree and octree).
+//!
+//! Large free or occupied regions are stored as single cells, so building
+//! scale maps need far less memory than dense grids of the same resolution.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Node {
+    Free,
+    Occupied,
+    // index of the first of the 2^D children
+    Inner(usize),
+}
+
+/// Occupancy map which subdivides a `D` dimensional cube into `2^D`
+/// children down to `max_depth`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Orthtree<const D: usize> {
+    origin: [f64; D],
+    size: f64,
+    max_depth: usize,
+    nodes: Vec<Node>,
+}
+
+/// 2D multi-resolution occupancy map
+pub type Quadtree = Orthtree<2>;
+/// 3D multi-resolution occupancy map
+pub type Octree = Orthtree<3>;
+
+fn box_distance<const D: usize>(p: &[f64], min: &[f64; D], size: f64) -> f64 {
+    (0..D)
+        .map(|i| {
+            let d = (min[i] - p[i]).max(p[i] - (min[i] + size)).max(0.0);
+            d * d
+        })
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn child_min<const D: usize>(min: &[f64; D], half: f64, child: usize) -> [f64; D] {
+    let mut c = *min;
+    for (i, v) in c.iter_mut().enumerate() {
+        if child & (1 << i) != 0 {
+            *v += half;
+        }
+    }
+    c
+}
+
+impl<const D: usize> Orthtree<D> {
+    /// Create a free map of the cube from `origin` with the edge length
+    /// `size`. The smallest cells have the edge length `size / 2^max_depth`.
+    pub fn new(origin: [f64; D], size: f64, max_depth: usize) -> Self {
+        assert!(size > 0.0);
+        Self {
+            origin,
+            size,
+            max_depth,
+            nodes: vec![Node::Free],
+        }
+    }
+
+    /// Edge length of the smallest cells
+    pub fn resolution(&self) -> f64 {
+        self.size / (1u64 << self.max_depth) as f64
+    }
+
+    /// Number of stored cells (including the inner ones)
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn contains(&self, p: &[f64]) -> bool {
+        (0..D).all(|i| p[i] >= self.origin[i] && p[i] < self.origin[i] + self.size)
+    }
+
+    /// Mark the box from `min` to `max` as occupied, at the resolution of the smallest cells
+    pub fn insert_box(&mut self, min: &[f64; D], max: &[f64; D]) {
+        self.insert(0, self.origin, self.size, 0, min, max);
+    }
+
+    /// Mark the smallest cell which contains `p` as occupied
+    pub fn insert_point(&mut self, p: &[f64; D]) {
+        self.insert_box(p, p);
+    }
+
+    fn insert(
+        &mut self,
+        node: usize,
+        node_min: [f64; D],
+        size: f64,
+        depth: usize,
+        min: &[f64; D],
+        max: &[f64; D],
+    ) {
+        let intersects = (0..D).all(|i| min[i] < node_min[i] + size && max[i] >= node_min[i]);
+        if !intersects || self.nodes[node] == Node::Occupied {
+            return;
+        }
+        let covered = (0..D).all(|i| min[i] <= node_min[i] && max[i] >= node_min[i] + size);
+        if covered || depth == self.max_depth {
+            // the children (if any) are left unused
+            self.nodes[node] = Node::Occupied;
+            return;
+        }
+        let first = match self.nodes[node] {
+            Node::Inner(first) => first,
+            _ => {
+                let first = self.nodes.len();
+                self.nodes.extend((0..1 << D).map(|_| Node::Free));
+                self.nodes[node] = Node::Inner(first);
+                first
+            }
+        };
+        let half = size / 2.0;
+        for child in 0..1 << D {
+            let child_min = child_min(&node_min, half, child);
+            self.insert(first + child, child_min, half, depth + 1, min, max);
+        }
+        // merge the children if all of them are occupied
+        if (0..1 << D).all(|c| self.nodes[first + c] == Node::Occupied) {
+            self.nodes[node] = Node::Occupied;
+        }
+    }
+
+    /// Returns true if `p` is inside of the map and its cell is free.
+    /// This can be used as `is_free` function of the planners.
+    pub fn is_free(&self, p: &[f64]) -> bool {
+        if !self.contains(p) {
+            return false;
+        }
+        let mut node = 0;
+        let mut min = self.origin;
+        let mut size = self.size;
+        loop {
+            match self.nodes[node] {
+                Node::Free => return true,
+                Node::Occupied => return false,
+                Node::Inner(first) => {
+                    size /= 2.0;
+                    let child = (0..D)
+                        .filter(|&i| p[i] >= min[i] + size)
+                        .fold(0, |c, i| c | 1 << i);
+                    min = child_min(&min, size, child);
+                    node = first + child;
+                }
+            }
+        }
+    }
+
+    /// Distance from `p` to the nearest occupied cell, infinity if there is none
+    pub fn clearance(&self, p: &[f64]) -> f64 {
+        let mut best = f64::INFINITY;
+        self.nearest_occupied(0, self.origin, self.size, p, &mut best);
+        best
+    }
+
+    fn nearest_occupied(&self, node: usize, min: [f64; D], size: f64, p: &[f64], best: &mut f64) {
+        let d = box_distance(p, &min, size);
+        if d >= *best {
+            return;
+        }
+        match self.nodes[node] {
+            Node::Free => {}
+            Node::Occupied => *best = d,
+            Node::Inner(first) => {
+                let half = size / 2.0;
+                let mut children = (0..1 << D)
+                    .map(|c| {
+                        let child_min = child_min(&min, half, c);
+                        (box_distance(p, &child_min, half), c, child_min)
+                    })
+                    .collect::<Vec<_>>();
+                children.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+                for (_, c, child_min) in children {
+                    self.nearest_occupied(first + c, child_min, half, p, best);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn it_works() {
+    // 64 m x 64 m building with 0.25 m cells
+    let mut map = Quadtree::new([0.0, 0.0], 64.0, 8);
+    assert_eq!(map.resolution(), 0.25);
+    map.insert_box(&[10.0, 0.0], &[10.5, 40.0]);
+    map.insert_point(&[30.1, 30.1]);
+    assert!(!map.is_free(&[10.2, 20.0]));
+    assert!(map.is_free(&[9.9, 20.0]));
+    assert!(map.is_free(&[10.2, 45.0]));
+    assert!(!map.is_free(&[30.2, 30.2]));
+    assert!(map.is_free(&[30.3, 30.2]));
+    assert!(!map.is_free(&[-1.0, 0.0]));
+    assert!((map.clearance(&[8.0, 20.0]) - 2.0).abs() < 1e-9);
+    assert!((map.clearance(&[30.125, 28.25]) - 1.75).abs() < 1e-9);
+    assert_eq!(map.clearance(&[10.2, 20.0]), 0.0);
+    // far fewer cells than the 65536 of the dense grid
+    assert!(map.num_nodes() < 10000, "{}", map.num_nodes());
+
+    let mut map = Octree::new([0.0, 0.0, 0.0], 1.0, 4);
+    assert_eq!(map.clearance(&[0.5, 0.5, 0.5]), f64::INFINITY);
+    map.insert_box(&[0.0, 0.0, 0.0], &[1.0, 1.0, 1.0]);
+    assert_eq!(map.num_nodes(), 1);
+    assert!(!map.is_free(&[0.5, 0.5, 0.5]));
+}
@@ -0,0 +1,251 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! What the planners return when they do not find a path.
+//!
+//! [`crate::rrt::dual_rrt_connect`] fails with an error, while
+//! [`crate::rrtstar::rrtstar`] returns its tree without a goal. The planners
+//! of this module behave the same way on failure, selected by [`OnFailure`]:
+//! an error, the path of the closest approach to the goal, or the trees of
+//! the search for inspection or for seeding another search.
+//! [`RrtPlanner::on_failure`](crate::RrtPlanner::on_failure) selects it for
+//! the `plan*` methods of the planner.
+
+use crate::rrt::{is_edge_free, DualRrtConnect, Error};
+use crate::rrtstar::{self, RRTStarError};
+use crate::steer::steer;
+use kdtree::distance::squared_euclidean;
+use num_traits::float::Float;
+use std::fmt::Debug;
+
+/// Result of the planners when no path is found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnFailure {
+    /// Fail with the error of the planner
+    #[default]
+    Error,
+    /// Return the path from the start to the vertex nearest to the goal
+    BestEffortPath,
+    /// Return the trees of the search
+    BestEffortTree,
+}
+
+/// Vertices and the parent of each of them, as plain data
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializedTree<N> {
+    /// Configurations of the vertices
+    pub vertices: Vec<Vec<N>>,
+    /// Index of the parent of each vertex, `None` for the root
+    pub parents: Vec<Option<usize>>,
+}
+
+/// Result of the planners of this module
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome<N> {
+    /// Path from the start to the goal
+    Solved(Vec<Vec<N>>),
+    /// Path from the start toward the goal ([`OnFailure::BestEffortPath`])
+    BestEffortPath(Vec<Vec<N>>),
+    /// Trees of the search, the one of the start first
+    /// ([`OnFailure::BestEffortTree`])
+    BestEffortTree(Vec<SerializedTree<N>>),
+}
+
+impl<N> Outcome<N> {
+    /// Returns true if the goal was reached
+    pub fn is_solved(&self) -> bool {
+        matches!(self, Self::Solved(_))
+    }
+
+    /// Path to the goal, or toward it for [`Outcome::BestEffortPath`]
+    pub fn path(&self) -> Option<&[Vec<N>]> {
+        match self {
+            Self::Solved(path) | Self::BestEffortPath(path) => Some(path),
+            Self::BestEffortTree(_) => None,
+        }
+    }
+}
+
+/// Same as [`crate::rrt::dual_rrt_connect`], and `on_failure` selects the
+/// result when the trees are not connected within `num_max_try` tries
+pub fn dual_rrt_connect<FF, FR, N>(
+    start: &[N],
+    goal: &[N],
+    mut is_free: FF,
+    random_sample: FR,
+    extend_length: N,
+    num_max_try: usize,
+    on_failure: OnFailure,
+) -> Result<Outcome<N>, Error<N>>
+where
+    FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    N: Float + Debug,
+{
+    if start.len() != goal.len() {
+        return Err(Error::DimensionMismatch {
+            start: start.len(),
+            goal: goal.len(),
+        });
+    }
+    if !is_free(start) {
+        return Err(Error::StartInCollision);
+    }
+    if !is_free(goal) {
+        return Err(Error::GoalInCollision);
+    }
+    if is_edge_free(start, goal, extend_length, &mut is_free) {
+        let mut path = vec![start.to_vec()];
+        while let (q, false) = steer(path.last().unwrap(), goal, extend_length) {
+            path.push(q);
+        }
+        path.push(goal.to_vec());
+        return Ok(Outcome::Solved(path));
+    }
+    let mut planner = DualRrtConnect::new(start, goal, extend_length);
+    for _ in 0..num_max_try {
+        if planner.step(&random_sample(), extend_length, &mut is_free) {
+            return Ok(Outcome::Solved(planner.path().unwrap().to_vec()));
+        }
+    }
+    match on_failure {
+        OnFailure::Error => Err(Error::MaxIterationsReached {
+            best_partial: Some(planner.best_partial()),
+        }),
+        OnFailure::BestEffortPath => Ok(Outcome::BestEffortPath(planner.best_partial())),
+        OnFailure::BestEffortTree => Ok(Outcome::BestEffortTree(vec![
+            planner.start_tree().serialized(),
+            planner.goal_tree().serialized(),
+        ])),
+    }
+}
+
+/// Same as [`crate::rrtstar::rrtstar`] improving the path for all of
+/// `max_iters`, and `on_failure` selects the result when the goal is not
+/// reached, instead of the tree without a goal
+#[allow(clippy::too_many_arguments)]
+pub fn rrtstar<N>(
+    start: &[N],
+    goal: &[N],
+    is_collision_free: impl FnMut(&[N]) -> bool,
+    random_sample: impl FnMut() -> Vec<N>,
+    extend_length: N,
+    validity_resolution: N,
+    max_iters: usize,
+    neighbourhood_radius: N,
+    on_failure: OnFailure,
+) -> Result<Outcome<N>, RRTStarError>
+where
+    N: Float + Debug,
+{
+    let tree = rrtstar::rrtstar_with_resolution(
+        start,
+        goal,
+        is_collision_free,
+        random_sample,
+        extend_length,
+        validity_resolution,
+        max_iters,
+        neighbourhood_radius,
+        false,
+    )?;
+    if let Some(goal_index) = tree.goal_index {
+        return Ok(Outcome::Solved(tree.path(goal_index)));
+    }
+    match on_failure {
+        OnFailure::Error => Err(RRTStarError::MaxItersReached),
+        OnFailure::BestEffortPath => {
+            let nearest = (0..tree.vertices.len())
+                .min_by(|a, b| {
+                    let distance = |i: &usize| squared_euclidean(&tree.vertices[*i].data, goal);
+                    distance(a).partial_cmp(&distance(b)).unwrap()
+                })
+                .expect("the tree has the start");
+            Ok(Outcome::BestEffortPath(tree.path(nearest)))
+        }
+        OnFailure::BestEffortTree => Ok(Outcome::BestEffortTree(vec![SerializedTree {
+            vertices: tree.vertices.iter().map(|v| v.data.clone()).collect(),
+            parents: tree.vertices.iter().map(|v| v.parent_index).collect(),
+        }])),
+    }
+}
+
+#[test]
+fn it_works() {
+    use rand::Rng;
+    let is_free = |q: &[f64]| !(q[0].abs() < 1.0 && q[1].abs() < 1.0);
+    let random_sample = || {
+        let mut rng = rand::thread_rng();
+        vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    let (start, goal) = ([-1.5, 0.0], [1.5, 0.0]);
+    let solved = dual_rrt_connect(
+        &start,
+        &goal,
+        is_free,
+        random_sample,
+        0.2,
+        1000,
+        OnFailure::Error,
+    )
+    .unwrap();
+    assert!(solved.is_solved());
+    assert_eq!(solved.path().unwrap().last().unwrap(), &goal);
+
+    // both planners fail the same way
+    let rrt =
+        |on_failure| dual_rrt_connect(&start, &goal, is_free, random_sample, 0.2, 3, on_failure);
+    let star = |on_failure| {
+        rrtstar(
+            &start,
+            &goal,
+            is_free,
+            random_sample,
+            0.2,
+            0.01,
+            3,
+            0.5,
+            on_failure,
+        )
+    };
+    assert!(matches!(
+        rrt(OnFailure::Error),
+        Err(Error::MaxIterationsReached { .. })
+    ));
+    assert!(matches!(
+        star(OnFailure::Error),
+        Err(RRTStarError::MaxItersReached)
+    ));
+    for outcome in [
+        rrt(OnFailure::BestEffortPath).unwrap(),
+        star(OnFailure::BestEffortPath).unwrap(),
+    ] {
+        assert!(!outcome.is_solved());
+        assert_eq!(outcome.path().unwrap()[0], start);
+    }
+    let Outcome::BestEffortTree(trees) = rrt(OnFailure::BestEffortTree).unwrap() else {
+        panic!("not the trees");
+    };
+    assert_eq!(trees.len(), 2);
+    assert_eq!(trees[0].vertices[0], start);
+    assert_eq!(trees[1].vertices[0], goal);
+    let Outcome::BestEffortTree(trees) = star(OnFailure::BestEffortTree).unwrap() else {
+        panic!("not the tree");
+    };
+    assert_eq!(trees[0].parents[0], None);
+    assert!(trees[0].parents[1..].iter().all(|p| p.is_some()));
+}
@@ -0,0 +1,277 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Geometric utilities for paths returned by the planners.
+
+use kdtree::distance::squared_euclidean;
+use num_traits::float::Float;
+
+/// Total length of the path
+pub fn path_length<N: Float>(path: &[Vec<N>]) -> N {
+    path.windows(2)
+        .map(|w| squared_euclidean(&w[0], &w[1]).sqrt())
+        .fold(N::zero(), |sum, d| sum + d)
+}
+
+/// Closest point to `q` on the segment from `a` to `b`, and its ratio on the segment
+pub fn closest_point_on_segment<N: Float>(q: &[N], a: &[N], b: &[N]) -> (Vec<N>, N) {
+    let ab_squared = squared_euclidean(a, b);
+    let t = if ab_squared > N::zero() {
+        let dot = q
+            .iter()
+            .zip(a.iter().zip(b))
+            .map(|(q, (a, b))| (*q - *a) * (*b - *a))
+            .fold(N::zero(), |sum, v| sum + v);
+        (dot / ab_squared).max(N::zero()).min(N::one())
+    } else {
+        N::zero()
+    };
+    let point = a.iter().zip(b).map(|(a, b)| *a + (*b - *a) * t).collect();
+    (point, t)
+}
+
+/// Distance from `q` to the nearest point of the path
+pub fn distance_to_path<N: Float>(q: &[N], path: &[Vec<N>]) -> N {
+    if path.len() == 1 {
+        return squared_euclidean(q, &path[0]).sqrt();
+    }
+    path.windows(2)
+        .map(|w| squared_euclidean(q, &closest_point_on_segment(q, &w[0], &w[1]).0).sqrt())
+        .fold(N::infinity(), N::min)
+}
+
+/// Re-anchor the path to the current state of the robot which is executing it.
+///
+/// The executed part before the nearest point on the path is trimmed. If the
+/// robot has drifted from the path, the returned path starts at `current`
+/// and connects to the nearest point. Returns `None` if the drift is more
+/// than `max_deviation` or the connection (checked every `resolution`) is
+/// not free, in which case the robot should plan again.
+pub fn reanchor<FF, N>(
+    path: &[Vec<N>],
+    current: &[N],
+    max_deviation: N,
+    mut is_free: FF,
+    resolution: N,
+) -> Option<Vec<Vec<N>>>
+where
+    FF: FnMut(&[N]) -> bool,
+    N: Float,
+{
+    let (segment, nearest, distance) = match path.len() {
+        0 => return None,
+        1 => (
+            0,
+            path[0].clone(),
+            squared_euclidean(current, &path[0]).sqrt(),
+        ),
+        _ => path
+            .windows(2)
+            .enumerate()
+            .map(|(i, w)| {
+                let (p, _) = closest_point_on_segment(current, &w[0], &w[1]);
+                let d = squared_euclidean(current, &p).sqrt();
+                (i, p, d)
+            })
+            // the later segment for ties, the robot has passed the earlier one
+            .fold(None, |best: Option<(usize, Vec<N>, N)>, c| match best {
+                Some(b) if b.2 < c.2 => Some(b),
+                _ => Some(c),
+            })
+            .unwrap(),
+    };
+    if distance > max_deviation {
+        return None;
+    }
+    let mut reanchored = Vec::new();
+    if distance > N::zero() {
+        if !crate::rrt::is_edge_free(current, &nearest, resolution, &mut is_free) {
+            return None;
+        }
+        reanchored.push(current.to_vec());
+    }
+    let rest = path.get(segment + 1..).unwrap_or(&[]);
+    if rest.first() != Some(&nearest) {
+        reanchored.push(nearest);
+    }
+    reanchored.extend_from_slice(rest);
+    Some(reanchored)
+}
+
+/// Points every `resolution` along the path (including both ends), with the
+/// distance from the previous point
+pub(crate) fn subdivide<N: Float>(path: &[Vec<N>], resolution: N) -> Vec<(Vec<N>, N)> {
+    assert!(resolution > N::zero());
+    let mut points = Vec::new();
+    if let Some(first) = path.first() {
+        points.push((first.clone(), N::zero()));
+    }
+    for w in path.windows(2) {
+        let length = squared_euclidean(&w[0], &w[1]).sqrt();
+        let num = (length / resolution).ceil().to_usize().unwrap_or(1).max(1);
+        let step = length / N::from(num).unwrap();
+        for i in 1..num {
+            let t = N::from(i).unwrap() / N::from(num).unwrap();
+            let q = w[0]
+                .iter()
+                .zip(&w[1])
+                .map(|(a, b)| *a + (*b - *a) * t)
+                .collect();
+            points.push((q, step));
+        }
+        points.push((w[1].clone(), step));
+    }
+    points
+}
+
+/// Insert interpolated waypoints so the consecutive points are at most
+/// `max_segment_length` apart (e.g. before the time parameterization). Each
+/// edge is split into equal parts, so the waypoints of `path` are kept.
+pub fn resample_path<N: Float>(path: &[Vec<N>], max_segment_length: N) -> Vec<Vec<N>> {
+    subdivide(path, max_segment_length)
+        .into_iter()
+        .map(|(q, _)| q)
+        .collect()
+}
+
+/// Clearance at the points every `resolution` along the path, measured by `sdf`
+/// (signed distance to the nearest obstacle)
+pub fn clearance_profile<FD, N>(path: &[Vec<N>], mut sdf: FD, resolution: N) -> Vec<N>
+where
+    FD: FnMut(&[N]) -> N,
+    N: Float,
+{
+    subdivide(path, resolution)
+        .iter()
+        .map(|(q, _)| sdf(q))
+        .collect()
+}
+
+/// Minimum clearance along the path, checked every `resolution`.
+///
+/// This can be used as an acceptance criterion before executing a path.
+pub fn min_clearance<FD, N>(path: &[Vec<N>], sdf: FD, resolution: N) -> N
+where
+    FD: FnMut(&[N]) -> N,
+    N: Float,
+{
+    clearance_profile(path, sdf, resolution)
+        .into_iter()
+        .fold(N::infinity(), N::min)
+}
+
+/// Discrete Fréchet distance between the vertices of two paths
+pub fn frechet_distance<N: Float>(a: &[Vec<N>], b: &[Vec<N>]) -> N {
+    coupling(a, b, |d, prev| d.max(prev))
+}
+
+/// Dynamic time warping distance (sum of the matched vertex distances) between two paths
+pub fn dtw_distance<N: Float>(a: &[Vec<N>], b: &[Vec<N>]) -> N {
+    coupling(a, b, |d, prev| d + prev)
+}
+
+/// Cost of the best monotone coupling of the vertices, where `combine`
+/// accumulates the distance of a matched pair into the cost of the best prefix
+fn coupling<N, F>(a: &[Vec<N>], b: &[Vec<N>], combine: F) -> N
+where
+    N: Float,
+    F: Fn(N, N) -> N,
+{
+    assert!(!a.is_empty() && !b.is_empty());
+    let mut table = vec![vec![N::infinity(); b.len()]; a.len()];
+    for i in 0..a.len() {
+        for j in 0..b.len() {
+            let d = squared_euclidean(&a[i], &b[j]).sqrt();
+            let prev = if i == 0 && j == 0 {
+                N::zero()
+            } else {
+                let mut prev = N::infinity();
+                if i > 0 {
+                    prev = prev.min(table[i - 1][j]);
+                }
+                if j > 0 {
+                    prev = prev.min(table[i][j - 1]);
+                }
+                if i > 0 && j > 0 {
+                    prev = prev.min(table[i - 1][j - 1]);
+                }
+                prev
+            };
+            table[i][j] = combine(d, prev);
+        }
+    }
+    table[a.len() - 1][b.len() - 1]
+}
+
+#[test]
+fn it_works() {
+    let path = vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![1.0, 1.0]];
+    assert_eq!(path_length(&path), 2.0);
+    assert_eq!(distance_to_path(&[0.5, 0.5], &path), 0.5);
+    assert_eq!(distance_to_path(&[2.0, 2.0], &path), 2.0.sqrt());
+    assert_eq!(
+        closest_point_on_segment(&[0.5, 1.0], &path[0], &path[1]),
+        (vec![0.5, 0.0], 0.5)
+    );
+
+    let sdf = |q: &[f64]| squared_euclidean(q, &[0.5, 0.5]).sqrt() - 0.2;
+    let profile = clearance_profile(&path, sdf, 0.1);
+    assert_eq!(profile.len(), 21);
+    assert!((profile[5] - 0.3).abs() < 1e-9);
+    assert!((min_clearance(&path, sdf, 0.1) - 0.3).abs() < 1e-9);
+
+    let resampled = resample_path(&path, 0.3);
+    assert_eq!(resampled.len(), 9);
+    assert!(path.iter().all(|q| resampled.contains(q)));
+    for w in resampled.windows(2) {
+        assert!((path_length(w) - 0.25).abs() < 1e-9);
+    }
+    assert!(resample_path::<f64>(&[], 0.3).is_empty());
+    assert_eq!(resample_path(&path[..1], 0.3), path[..1]);
+
+    // on the path
+    let free = |_: &[f64]| true;
+    assert_eq!(
+        reanchor(&path, &[0.5, 0.0], 0.1, free, 0.01).unwrap(),
+        [vec![0.5, 0.0], vec![1.0, 0.0], vec![1.0, 1.0]]
+    );
+    // drifted
+    assert_eq!(
+        reanchor(&path, &[1.05, 0.5], 0.1, free, 0.01).unwrap(),
+        [vec![1.05, 0.5], vec![1.0, 0.5], vec![1.0, 1.0]]
+    );
+    // at a waypoint
+    assert_eq!(
+        reanchor(&path, &[1.0, 0.0], 0.1, free, 0.01).unwrap(),
+        [vec![1.0, 0.0], vec![1.0, 1.0]]
+    );
+    assert!(reanchor(&path, &[1.5, 0.5], 0.1, free, 0.01).is_none());
+    assert!(reanchor(&path, &[1.05, 0.5], 0.1, |p: &[f64]| p[0] < 1.02, 0.01).is_none());
+
+    let shifted = vec![vec![0.0, 0.5], vec![1.0, 0.5], vec![1.0, 1.5]];
+    assert_eq!(frechet_distance(&path, &path), 0.0);
+    assert_eq!(dtw_distance(&path, &path), 0.0);
+    assert_eq!(frechet_distance(&path, &shifted), 0.5);
+    assert_eq!(dtw_distance(&path, &shifted), 1.5);
+    let dense = vec![
+        vec![0.0, 0.0],
+        vec![0.5, 0.0],
+        vec![1.0, 0.0],
+        vec![1.0, 1.0],
+    ];
+    assert_eq!(frechet_distance(&path, &dense), 0.5);
+    assert_eq!(dtw_distance(&path, &dense), 0.5);
+}
@@ -0,0 +1,174 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Coarse-to-fine planning.
+//!
+//! [`plan_corridor`] plans in a subset of the dimensions (e.g. the 2D base
+//! position of a mobile manipulator) and returns a [`Corridor`] around the
+//! found path. [`plan_in_corridor`] then plans the full problem with the
+//! projected configurations restricted to the corridor.
+
+use crate::demonstration::TubeSampler;
+use crate::path::distance_to_path;
+use crate::rrt::Error;
+use num_traits::float::Float;
+use rand::RngCore;
+use std::cell::RefCell;
+use std::fmt::Debug;
+
+/// Tube around a path in a subset of the dimensions
+#[derive(Debug, Clone)]
+pub struct Corridor<N> {
+    /// Indices of the dimensions of the corridor
+    pub dims: Vec<usize>,
+    /// Path in the projected space
+    pub path: Vec<Vec<N>>,
+    /// Radius of the tube
+    pub width: N,
+}
+
+impl<N: Float> Corridor<N> {
+    /// Project a full configuration onto the dimensions of the corridor
+    pub fn project(&self, q: &[N]) -> Vec<N> {
+        self.dims.iter().map(|&i| q[i]).collect()
+    }
+
+    /// Returns true if the projection of `q` is inside of the corridor
+    pub fn contains(&self, q: &[N]) -> bool {
+        distance_to_path(&self.project(q), &self.path) <= self.width
+    }
+}
+
+/// Plan in the `dims` dimensions and return the corridor of `width` around the path.
+///
+/// `is_free` and `random_sample` work on the projected configurations.
+#[allow(clippy::too_many_arguments)]
+pub fn plan_corridor<FF, FR, N>(
+    start: &[N],
+    goal: &[N],
+    dims: &[usize],
+    is_free: FF,
+    random_sample: FR,
+    extend_length: N,
+    num_max_try: usize,
+    width: N,
+) -> Result<Corridor<N>, Error<N>>
+where
+    FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    N: Float + Debug,
+{
+    let project = |q: &[N]| dims.iter().map(|&i| q[i]).collect::<Vec<_>>();
+    let path = crate::rrt::dual_rrt_connect(
+        &project(start),
+        &project(goal),
+        is_free,
+        random_sample,
+        extend_length,
+        num_max_try,
+    )?;
+    Ok(Corridor {
+        dims: dims.to_vec(),
+        path,
+        width,
+    })
+}
+
+/// Plan the full problem with the projected configurations kept inside of `corridor`.
+///
+/// The corridor dimensions of the samples from `random_sample` are replaced
+/// by samples in the corridor, which are drawn from `rng`.
+#[allow(clippy::too_many_arguments)]
+pub fn plan_in_corridor<FF, FR, N>(
+    corridor: &Corridor<N>,
+    start: &[N],
+    goal: &[N],
+    mut is_free: FF,
+    random_sample: FR,
+    extend_length: N,
+    num_max_try: usize,
+    rng: &mut dyn RngCore,
+) -> Result<Vec<Vec<N>>, Error<N>>
+where
+    FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    N: Float + Debug,
+{
+    let tube = TubeSampler::new(corridor.path.clone(), corridor.width);
+    let rng = RefCell::new(rng);
+    crate::rrt::dual_rrt_connect(
+        start,
+        goal,
+        |q: &[N]| corridor.contains(q) && is_free(q),
+        || {
+            let mut q = random_sample();
+            let projected = tube.sample(&mut **rng.borrow_mut());
+            for (&i, v) in corridor.dims.iter().zip(projected) {
+                q[i] = v;
+            }
+            q
+        },
+        extend_length,
+        num_max_try,
+    )
+}
+
+#[test]
+fn it_works() {
+    use rand::distributions::{Distribution, Uniform};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::cell::RefCell;
+    // (x, y) is the base position and z is an arm joint
+    let is_free_base = |p: &[f64]| !(p[0].abs() < 0.1 && p[1].abs() < 1.5);
+    let is_free = |p: &[f64]| is_free_base(p) && p[2].abs() < 1.5;
+    let rng = RefCell::new(StdRng::seed_from_u64(0));
+    let sample = |dim: usize| {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rng.borrow_mut();
+        (0..dim)
+            .map(|_| between.sample(&mut *rng))
+            .collect::<Vec<_>>()
+    };
+    let start = [-1.5, 0.0, 0.0];
+    let goal = [1.5, 0.0, 0.0];
+    let mut corridor = plan_corridor(
+        &start,
+        &goal,
+        &[0, 1],
+        is_free_base,
+        || sample(2),
+        0.1,
+        1000,
+        0.5,
+    )
+    .unwrap();
+    crate::rrt::smooth_path(&mut corridor.path, is_free_base, 0.1, 100);
+    let path = plan_in_corridor(
+        &corridor,
+        &start,
+        &goal,
+        is_free,
+        || sample(3),
+        0.1,
+        3000,
+        &mut StdRng::seed_from_u64(1),
+    )
+    .unwrap();
+    for q in &path {
+        assert!(corridor.contains(q));
+        assert!(is_free(q));
+    }
+}
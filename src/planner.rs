@@ -0,0 +1,1178 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Builder style facade of the planners.
+//!
+//! [`RrtPlanner`] keeps the options of RRT-Connect and RRT*, so new options
+//! can be added without breaking the existing callers, and its `plan*`
+//! methods run the searches with them.
+
+use crate::budget::{ConditionMonitor, IterationController, SoftDeadline, TerminationCondition};
+use crate::goal::GoalRegion;
+use crate::partial::{OnFailure, SerializedTree};
+use crate::path::path_length;
+use crate::rrt::{
+    dual_rrt_connect_batch, dual_rrt_connect_impl, dual_rrt_connect_motion_impl,
+    dual_rrt_connect_to_goals, dual_rrt_connect_to_region, dual_rrt_connect_with_goal_sampler,
+    dual_rrt_connect_with_provenance, dual_rrt_connect_with_relaxation, is_reachable, tries,
+    Bounds, CoincidentSample, Error, NearestVertex, PlanResult, Progress, RelaxedPath, Search,
+    StrategySwitch, TreeOptions, TreeView, VertexSelection, Waypoint,
+};
+use crate::rrtstar::{
+    self, rrtstar_until_impl, rrtstar_with_deadline_impl, rrtstar_with_resolution, RRTStarError,
+};
+use num_traits::float::Float;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::fmt::Debug;
+use std::iter;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+type Sampler<'a, N> = Box<dyn Fn(&mut dyn RngCore) -> Vec<N> + 'a>;
+type Batches<'b> = Box<dyn FnMut(usize) -> usize + 'b>;
+type OnExtend<'a> = Box<dyn FnMut(bool) + 'a>;
+type OnProgress<'a, N> = Box<dyn FnMut(&Progress<N>) + 'a>;
+type OnTrees<'a, N> = Box<dyn FnMut(TreeView<'_, N>, TreeView<'_, N>) + 'a>;
+type Query<'p, N> = (Cow<'p, [N]>, Cow<'p, [N]>, Option<Projection<'p, N>>);
+
+/// When the searches stop
+#[derive(Debug, Clone)]
+enum Limit {
+    Iterations(usize),
+    Budget(Duration),
+    Condition(TerminationCondition),
+}
+
+impl Limit {
+    /// Batches of tries given the number of vertices of the trees, zero to stop
+    fn batches(&self) -> Batches<'_> {
+        match self {
+            Limit::Iterations(max_iterations) => Box::new(tries(*max_iterations)),
+            Limit::Budget(budget) => {
+                let mut controller = IterationController::new(*budget);
+                Box::new(move |_| controller.next_batch())
+            }
+            Limit::Condition(condition) => {
+                let mut monitor = ConditionMonitor::new(condition);
+                Box::new(move |tree_size| match monitor.check(tree_size, None) {
+                    Some(reason) => {
+                        debug!("stopped by {reason:?}");
+                        0
+                    }
+                    None => 1,
+                })
+            }
+        }
+    }
+}
+
+/// Projection of the configurations onto the dimensions which are not frozen
+struct Projection<'f, N> {
+    dim: usize,
+    free_dims: Vec<usize>,
+    frozen: &'f [(usize, N)],
+}
+
+impl<N: Float> Projection<'_, N> {
+    fn project(&self, q: &[N]) -> Vec<N> {
+        self.free_dims.iter().map(|&i| q[i]).collect()
+    }
+    fn lift(&self, q: &[N]) -> Vec<N> {
+        let mut full = vec![N::zero(); self.dim];
+        for (&i, &v) in self.free_dims.iter().zip(q) {
+            full[i] = v;
+        }
+        for &(d, v) in self.frozen {
+            full[d] = v;
+        }
+        full
+    }
+}
+
+/// `q` of the free dimensions with the frozen ones, if any
+fn lifted<'q, N: Float>(projection: &Option<Projection<'_, N>>, q: &'q [N]) -> Cow<'q, [N]> {
+    match projection {
+        Some(projection) => Cow::Owned(projection.lift(q)),
+        None => Cow::Borrowed(q),
+    }
+}
+
+/// `error` with its partial path or its trees lifted to all the dimensions
+fn lifted_error<N: Float>(projection: &Option<Projection<'_, N>>, error: Error<N>) -> Error<N> {
+    match (projection, error) {
+        (Some(projection), Error::MaxIterationsReached { best_partial }) => {
+            Error::MaxIterationsReached {
+                best_partial: best_partial
+                    .map(|path| path.iter().map(|q| projection.lift(q)).collect()),
+            }
+        }
+        (Some(projection), Error::Unsolved { trees }) => Error::Unsolved {
+            trees: trees
+                .into_iter()
+                .map(|tree| SerializedTree {
+                    vertices: tree.vertices.iter().map(|q| projection.lift(q)).collect(),
+                    parents: tree.parents,
+                })
+                .collect(),
+        },
+        (_, error) => error,
+    }
+}
+
+/// Dual RRT Connect planner configured by its setters.
+///
+/// ```
+/// let planner = rrt::RrtPlanner::new()
+///     .start(&[-1.2, 0.0])
+///     .goal(&[1.2, 0.0])
+///     .extend_length(0.2)
+///     .max_iterations(1000)
+///     .goal_bias(0.1)
+///     .sampler(|| {
+///         use rand::Rng;
+///         let mut rng = rand::thread_rng();
+///         vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+///     });
+/// let path = planner
+///     .plan(|p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0))
+///     .unwrap();
+/// assert_eq!(path[0], [-1.2, 0.0]);
+/// ```
+///
+/// The vertex selection, the strategies and the callbacks are used by the
+/// searches of [`DualRrtConnect`](crate::rrt::DualRrtConnect) (`plan`,
+/// `plan_with_stats`, `plan_with_motion_check`, `plan_waypoints` and
+/// `is_reachable`), which are also the only ones supporting the frozen
+/// dimensions. RRT* ([`RrtPlanner::plan_star`]) does not use the options
+/// of the trees either.
+pub struct RrtPlanner<'a, N>
+where
+    N: Float + Debug,
+{
+    start: Option<Vec<N>>,
+    goal: Option<Vec<N>>,
+    extend_length: N,
+    validity_resolution: Option<N>,
+    limit: Limit,
+    goal_bias: f64,
+    seed: Option<u64>,
+    sampler: Option<Sampler<'a, N>>,
+    strategies: Vec<Sampler<'a, N>>,
+    stall_iterations: usize,
+    frozen: Vec<(usize, N)>,
+    trees: TreeOptions<N>,
+    selection: RefCell<Box<dyn VertexSelection<N> + 'a>>,
+    on_extend: Option<RefCell<OnExtend<'a>>>,
+    on_progress: Option<(usize, RefCell<OnProgress<'a, N>>)>,
+    on_trees: Option<RefCell<OnTrees<'a, N>>>,
+    neighbourhood_radius: Option<N>,
+    on_failure: OnFailure,
+}
+
+impl<'a, N: Float + Debug> Default for RrtPlanner<'a, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, N: Float + Debug> RrtPlanner<'a, N> {
+    /// Create a planner with the extend length of 0.1, 1000 iterations and
+    /// without goal bias. The start, the goal and the sampler must be set.
+    pub fn new() -> Self {
+        Self {
+            start: None,
+            goal: None,
+            extend_length: N::from(0.1).unwrap(),
+            validity_resolution: None,
+            limit: Limit::Iterations(1000),
+            goal_bias: 0.0,
+            seed: None,
+            sampler: None,
+            strategies: Vec::new(),
+            stall_iterations: 100,
+            frozen: Vec::new(),
+            trees: TreeOptions::default(),
+            selection: RefCell::new(Box::new(NearestVertex)),
+            on_extend: None,
+            on_progress: None,
+            on_trees: None,
+            neighbourhood_radius: None,
+            on_failure: OnFailure::Error,
+        }
+    }
+
+    /// Set the start configuration
+    pub fn start(mut self, start: &[N]) -> Self {
+        self.start = Some(start.to_vec());
+        self
+    }
+
+    /// Set the goal configuration
+    pub fn goal(mut self, goal: &[N]) -> Self {
+        self.goal = Some(goal.to_vec());
+        self
+    }
+
+    /// Set the maximum length of each extension
+    pub fn extend_length(mut self, extend_length: N) -> Self {
+        assert!(extend_length > N::zero());
+        self.extend_length = extend_length;
+        self
+    }
+
+    /// Check each edge every `validity_resolution` instead of only at its
+    /// end point (which is the same as the extend length)
+    pub fn validity_resolution(mut self, validity_resolution: N) -> Self {
+        assert!(validity_resolution > N::zero());
+        self.validity_resolution = Some(validity_resolution);
+        self
+    }
+
+    /// Set the maximum number of tries
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.limit = Limit::Iterations(max_iterations);
+        self
+    }
+
+    /// Search until `budget` has passed instead of for a number of tries.
+    ///
+    /// The clock is read once per batch of tries, whose size is adapted to
+    /// the measured time per try (see [`IterationController`]).
+    pub fn time_budget(mut self, budget: Duration) -> Self {
+        self.limit = Limit::Budget(budget);
+        self
+    }
+
+    /// Search until `condition` stops it instead of for a number of tries.
+    ///
+    /// The tree size of the condition is the number of vertices of the
+    /// trees. RRT-Connect returns its first solution, so the cost criteria
+    /// only apply to [`RrtPlanner::plan_star`]. Planning fails with
+    /// [`Error::InvalidConfiguration`] if the condition is not
+    /// [bounded](TerminationCondition::is_bounded).
+    pub fn termination(mut self, condition: TerminationCondition) -> Self {
+        self.limit = Limit::Condition(condition);
+        self
+    }
+
+    /// Set the probability to sample the goal instead of the sampler
+    pub fn goal_bias(mut self, goal_bias: f64) -> Self {
+        assert!((0.0..=1.0).contains(&goal_bias));
+        self.goal_bias = goal_bias;
+        self
+    }
+
+    /// Set the random sampler of the configurations
+    pub fn sampler<F>(mut self, sampler: F) -> Self
+    where
+        F: Fn() -> Vec<N> + 'a,
+    {
+        self.sampler = Some(Box::new(move |_: &mut dyn RngCore| sampler()));
+        self
+    }
+
+    /// Set the random sampler which draws from the rng of the planner, so
+    /// the plans are reproducible with [`RrtPlanner::seed`]
+    pub fn sampler_with_rng<F>(mut self, sampler: F) -> Self
+    where
+        F: Fn(&mut dyn RngCore) -> Vec<N> + 'a,
+    {
+        self.sampler = Some(Box::new(sampler));
+        self
+    }
+
+    /// Add a sampling strategy (e.g. goal biased or bridge samplers) after
+    /// the sampler, to switch to when the growth stalls.
+    ///
+    /// The gap is the distance between the trees which remains after each
+    /// connection. When the smallest gap does not improve for
+    /// [`RrtPlanner::stall_iterations`] tries, the next strategy (cyclically)
+    /// is used. The switches are reported by [`RrtPlanner::plan_with_stats`].
+    pub fn strategy<F>(mut self, strategy: F) -> Self
+    where
+        F: Fn() -> Vec<N> + 'a,
+    {
+        self.strategies
+            .push(Box::new(move |_: &mut dyn RngCore| strategy()));
+        self
+    }
+
+    /// Add a sampling strategy which draws from the rng of the planner
+    pub fn strategy_with_rng<F>(mut self, strategy: F) -> Self
+    where
+        F: Fn(&mut dyn RngCore) -> Vec<N> + 'a,
+    {
+        self.strategies.push(Box::new(strategy));
+        self
+    }
+
+    /// Set the number of tries without progress after which the next
+    /// strategy is used, 100 by default
+    pub fn stall_iterations(mut self, stall_iterations: usize) -> Self {
+        assert!(stall_iterations > 0);
+        self.stall_iterations = stall_iterations;
+        self
+    }
+
+    /// Seed the rng of the planner instead of using `thread_rng`
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Visit at most `max_nearest_checks` vertices by the nearest vertex
+    /// search (see [`ApproximateKdTree`](crate::nearest::ApproximateKdTree)).
+    ///
+    /// Use this for very large trees where the exact search dominates the
+    /// runtime. The found paths tend to be a little longer.
+    pub fn max_nearest_checks(mut self, max_nearest_checks: usize) -> Self {
+        self.trees.max_nearest_checks = Some(max_nearest_checks);
+        self
+    }
+
+    /// Reserve the memory for `capacity` vertices of each tree before planning.
+    ///
+    /// With [`RrtPlanner::max_nearest_checks`], the storage of the trees is
+    /// never reallocated (which copies the whole tree) as long as they have
+    /// fewer than `capacity` vertices, so the worst case latency of an
+    /// iteration is bounded. The exact kd-tree can not reserve its memory.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.trees.capacity = capacity;
+        self
+    }
+
+    /// Set the behavior when a sample (or the target of a connection)
+    /// coincides with its nearest vertex, which happens with discrete
+    /// samplers or goal biased sampling. [`CoincidentSample::Reached`] by
+    /// default.
+    pub fn on_coincident(mut self, on_coincident: CoincidentSample<N>) -> Self {
+        self.trees.on_coincident = on_coincident;
+        self
+    }
+
+    /// Keep the extensions within `bounds` (e.g. the bounds of the sampler),
+    /// so `is_free` is never called with configurations outside of them.
+    ///
+    /// Out of bounds steps are rejected or clamped by the policy of `bounds`.
+    /// The start and the goal are assumed to be within the bounds.
+    pub fn bounds(mut self, bounds: Bounds<N>) -> Self {
+        self.trees.bounds = Some(bounds);
+        self
+    }
+
+    /// Choose the vertex which is extended toward each sample by `selection`
+    /// (e.g. [`ExplorationScore`](crate::rrt::ExplorationScore) for EST like
+    /// planning) instead of the nearest one
+    pub fn selection<S>(mut self, selection: S) -> Self
+    where
+        S: VertexSelection<N> + 'a,
+    {
+        self.selection = RefCell::new(Box::new(selection));
+        self
+    }
+
+    /// Call `on_extend` after each extension toward a sample with true if
+    /// the tree was extended.
+    ///
+    /// This lets adaptive samplers (e.g. [`MixtureSampler`]) learn which of
+    /// their samples are useful.
+    ///
+    /// [`MixtureSampler`]: crate::sampler::MixtureSampler
+    pub fn on_extend<F>(mut self, on_extend: F) -> Self
+    where
+        F: FnMut(bool) + 'a,
+    {
+        self.on_extend = Some(RefCell::new(Box::new(on_extend)));
+        self
+    }
+
+    /// Call `on_progress` every `report_every` tries with the closest
+    /// vertices of the two trees
+    pub fn on_progress<F>(mut self, report_every: usize, on_progress: F) -> Self
+    where
+        F: FnMut(&Progress<N>) + 'a,
+    {
+        assert!(report_every > 0);
+        self.on_progress = Some((report_every, RefCell::new(Box::new(on_progress))));
+        self
+    }
+
+    /// Call `on_trees` after each extension toward a sample with the start
+    /// tree and the goal tree, e.g. to draw them as they grow (see
+    /// `rrt::rerun_viewer` with the `rerun` feature). The vertices are on
+    /// the free dimensions if some are [frozen](RrtPlanner::freeze).
+    pub fn on_trees<F>(mut self, on_trees: F) -> Self
+    where
+        F: FnMut(TreeView<'_, N>, TreeView<'_, N>) + 'a,
+    {
+        self.on_trees = Some(RefCell::new(Box::new(on_trees)));
+        self
+    }
+
+    /// Select the result of the `plan*` methods when no path is found within
+    /// the limit of the search: the error (by default), the path from the
+    /// start to the vertex nearest to the goal in place of the path (only if
+    /// there is a goal to be near to), or the trees of the search in
+    /// [`Error::Unsolved`].
+    ///
+    /// [`RrtPlanner::plan_star`] returns its tree without a goal for both
+    /// [`OnFailure::BestEffortPath`] and [`OnFailure::BestEffortTree`], and
+    /// [`RrtPlanner::is_reachable`] returns false in all cases.
+    pub fn on_failure(mut self, on_failure: OnFailure) -> Self {
+        self.on_failure = on_failure;
+        self
+    }
+
+    /// Set the radius of the neighbourhood which is rewired by
+    /// [`RrtPlanner::plan_star`]
+    pub fn neighbourhood_radius(mut self, neighbourhood_radius: N) -> Self {
+        assert!(neighbourhood_radius > N::zero());
+        self.neighbourhood_radius = Some(neighbourhood_radius);
+        self
+    }
+
+    /// Pin the dimension `dim` to `value` for the following queries.
+    ///
+    /// Sampling, extension and the goal check only operate on the free
+    /// dimensions, and every waypoint of the path (including the start and
+    /// the goal) holds `value` on `dim`.
+    pub fn freeze(mut self, dim: usize, value: N) -> Self {
+        self.frozen.retain(|&(d, _)| d != dim);
+        self.frozen.push((dim, value));
+        self
+    }
+
+    /// Release the dimension `dim` pinned by [`RrtPlanner::freeze`]
+    pub fn unfreeze(mut self, dim: usize) -> Self {
+        self.frozen.retain(|&(d, _)| d != dim);
+        self
+    }
+}
+
+impl<'a, N: Float + Debug> RrtPlanner<'a, N> {
+    /// search the path from start to goal which is free
+    pub fn plan<FF>(&self, is_free: FF) -> Result<Vec<Vec<N>>, Error<N>>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        self.plan_with_stats(is_free).map(|result| result.path)
+    }
+
+    /// Same as [`RrtPlanner::plan`], but returns the path with its length
+    /// and the statistics of the search
+    pub fn plan_with_stats<FF>(&self, mut is_free: FF) -> Result<PlanResult<N>, Error<N>>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let started = Instant::now();
+        let (start, goal, projection) = self.query()?;
+        let mut num_collision_checks = 0;
+        let is_free = |q: &[N]| {
+            num_collision_checks += 1;
+            is_free(&lifted(&projection, q))
+        };
+        let (result, num_iterations, switches) =
+            self.with_search(Some(&goal), &projection, |random_sample, search| {
+                dual_rrt_connect_impl(&start, &goal, is_free, random_sample, search)
+            })?;
+        let (path, (start_tree_size, goal_tree_size)) =
+            result.map_err(|error| lifted_error(&projection, error))?;
+        let path = path
+            .iter()
+            .map(|q| lifted(&projection, q).into_owned())
+            .collect::<Vec<_>>();
+        Ok(PlanResult {
+            length: path_length(&path),
+            path,
+            start_tree_size,
+            goal_tree_size,
+            num_collision_checks,
+            num_iterations,
+            switches,
+            elapsed: started.elapsed(),
+        })
+    }
+
+    /// Same as [`RrtPlanner::plan`], but every extension and the connection
+    /// of the trees (including the straight line from start to goal) is
+    /// checked by `is_motion_free(from, to)` instead of the configurations,
+    /// so thin obstacles can not be tunneled through (e.g. with a swept
+    /// volume check or a continuous collision detection). `from` is always
+    /// free and `is_motion_free` must also check `to`.
+    pub fn plan_with_motion_check<FM>(
+        &self,
+        mut is_motion_free: FM,
+    ) -> Result<Vec<Vec<N>>, Error<N>>
+    where
+        FM: FnMut(&[N], &[N]) -> bool,
+    {
+        let (start, goal, projection) = self.query()?;
+        let is_motion_free = |from: &[N], to: &[N]| {
+            is_motion_free(&lifted(&projection, from), &lifted(&projection, to))
+        };
+        let (result, _, _) =
+            self.with_search(Some(&goal), &projection, |random_sample, search| {
+                dual_rrt_connect_motion_impl(&start, &goal, is_motion_free, random_sample, search)
+            })?;
+        let (path, _) = result.map_err(|error| lifted_error(&projection, error))?;
+        Ok(path
+            .iter()
+            .map(|q| lifted(&projection, q).into_owned())
+            .collect())
+    }
+
+    /// Same as [`RrtPlanner::plan`], but each waypoint is annotated with the
+    /// tree and the vertex index which it comes from, to correlate the path
+    /// with the trees. The straight line between the start and the goal is
+    /// not tried first, so every waypoint is a tree vertex.
+    pub fn plan_waypoints<FF>(&self, mut is_free: FF) -> Result<Vec<Waypoint<N>>, Error<N>>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let (start, goal, projection) = self.query()?;
+        let is_free = |q: &[N]| is_free(&lifted(&projection, q));
+        let (result, _, _) =
+            self.with_search(Some(&goal), &projection, |random_sample, search| {
+                dual_rrt_connect_with_provenance(&start, &goal, is_free, random_sample, search)
+            })?;
+        let mut waypoints = result.map_err(|error| lifted_error(&projection, error))?;
+        for waypoint in &mut waypoints {
+            waypoint.position = lifted(&projection, &waypoint.position).into_owned();
+        }
+        Ok(waypoints)
+    }
+
+    /// Returns true if a path from the start to the goal is found, like
+    /// [`RrtPlanner::plan`].
+    ///
+    /// Only the trees are grown: the path is not extracted, and neither is
+    /// the best partial path on failure, for the callers which only need the
+    /// answer (e.g. task planners pruning their options). The configuration
+    /// errors of the planner are returned, and false if the start or the
+    /// goal is not free.
+    pub fn is_reachable<FF>(&self, mut is_free: FF) -> Result<bool, Error<N>>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let (start, goal, projection) = self.query()?;
+        let is_free = |q: &[N]| is_free(&lifted(&projection, q));
+        let (reachable, _, _) =
+            self.with_search(Some(&goal), &projection, |random_sample, search| {
+                is_reachable(&start, &goal, is_free, random_sample, search)
+            })?;
+        Ok(reachable)
+    }
+
+    /// Search a path from the start to a goal region (e.g. a point with a
+    /// tolerance or a predicate) instead of the goal, which ends as soon as
+    /// a vertex of the start tree is in it.
+    ///
+    /// If the region has a [`GoalRegion::sample`], a goal tree is rooted there
+    /// and connected to each new vertex of the start tree, as in
+    /// [`RrtPlanner::plan`]. The path ends at its first waypoint in the region.
+    pub fn plan_to_region<G, FF>(&self, goal: &G, is_free: FF) -> Result<Vec<Vec<N>>, Error<N>>
+    where
+        G: GoalRegion<N> + ?Sized,
+        FF: FnMut(&[N]) -> bool,
+    {
+        let start = self.unprojected_start()?;
+        let (result, _, _) = self.with_search(None, &None, |random_sample, search| {
+            dual_rrt_connect_to_region(start, goal, is_free, random_sample, search)
+        })?;
+        result
+    }
+
+    /// Search a path from the start to any of `goals` (e.g. the solutions of
+    /// the inverse kinematics) instead of the goal. The goal tree is grown
+    /// from all the free goals, and the path ends at whichever of them is
+    /// reached first.
+    pub fn plan_to_goals<FF>(&self, goals: &[Vec<N>], is_free: FF) -> Result<Vec<Vec<N>>, Error<N>>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let start = self.unprojected_start()?;
+        let (result, _, _) = self.with_search(None, &None, |random_sample, search| {
+            dual_rrt_connect_to_goals(start, goals, is_free, random_sample, search)
+        })?;
+        result
+    }
+
+    /// Same as [`RrtPlanner::plan_to_goals`], but the goals are drawn from
+    /// `sample_goal` during the search. It is called once per iteration, and
+    /// each free goal it returns is a new root of the goal tree (return
+    /// `None` to add no goal, e.g. when the inverse kinematics did not
+    /// converge).
+    pub fn plan_with_goal_sampler<FG, FF>(
+        &self,
+        sample_goal: FG,
+        is_free: FF,
+    ) -> Result<Vec<Vec<N>>, Error<N>>
+    where
+        FG: FnMut() -> Option<Vec<N>>,
+        FF: FnMut(&[N]) -> bool,
+    {
+        let start = self.unprojected_start()?;
+        let (result, _, _) = self.with_search(None, &None, |random_sample, search| {
+            dual_rrt_connect_with_goal_sampler(start, sample_goal, is_free, random_sample, search)
+        })?;
+        result
+    }
+
+    /// Get as close to the goal as possible: the path must end within the
+    /// first of the increasing `tolerances` of the goal, which is relaxed to
+    /// the next one when the limit of the search (e.g. the maximum number of
+    /// tries) is reached without a solution.
+    ///
+    /// The start tree is kept between the tolerances, so a relaxed tolerance
+    /// is often met by a vertex which is already there. If the goal is free,
+    /// a goal tree is connected to the new vertices as in [`RrtPlanner::plan`]
+    /// and its paths end exactly at the goal.
+    pub fn plan_relaxed<FF>(
+        &self,
+        tolerances: &[N],
+        is_free: FF,
+    ) -> Result<RelaxedPath<N>, Error<N>>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let start = self.unprojected_start()?;
+        let goal = self.required_goal()?;
+        let (result, _, _) = self.with_search(Some(goal), &None, |random_sample, search| {
+            dual_rrt_connect_with_relaxation(
+                start,
+                goal,
+                is_free,
+                random_sample,
+                tolerances,
+                search,
+                || self.limit.batches(),
+            )
+        })?;
+        result
+    }
+
+    /// Same as [`RrtPlanner::plan`], but the configurations are checked in
+    /// batches by `is_free_batch`, which returns whether each of them is free
+    /// (e.g. vectorized, on a GPU or by a physics engine).
+    ///
+    /// Each try draws `batch_size` samples, checks the extensions of the tree
+    /// toward all of them in one batch, and then the steps of the other tree
+    /// toward all of the new vertices in another batch. The trees are updated
+    /// between the batches. The edges are checked every
+    /// [`validity_resolution`](RrtPlanner::validity_resolution), with all
+    /// their points in the same batch.
+    pub fn plan_batch<FB>(
+        &self,
+        batch_size: usize,
+        is_free_batch: FB,
+    ) -> Result<Vec<Vec<N>>, Error<N>>
+    where
+        FB: FnMut(&[Vec<N>]) -> Vec<bool>,
+    {
+        let start = self.unprojected_start()?;
+        let goal = self.required_goal()?;
+        let (result, _, _) = self.with_search(Some(goal), &None, |random_sample, search| {
+            dual_rrt_connect_batch(
+                start,
+                goal,
+                is_free_batch,
+                random_sample,
+                batch_size,
+                search,
+            )
+        })?;
+        result
+    }
+
+    /// Same as [`RrtPlanner::plan_batch`], with the collision checks of each
+    /// batch in parallel using rayon.
+    ///
+    /// Only the collision checks run in parallel. The nearest neighbour
+    /// queries and the insertions into the kd-trees stay on the calling
+    /// thread, between the parallel phases, so the trees are never shared
+    /// and there is no lock-free or sharded kd-tree. This pays off when
+    /// `is_free` is expensive (e.g. the meshes of a 7-DOF arm), and then
+    /// scales with the number of threads, but not when the nearest neighbour
+    /// search dominates (cheap checks and large trees).
+    #[cfg(feature = "rayon")]
+    pub fn plan_parallel<FF>(&self, batch_size: usize, is_free: FF) -> Result<Vec<Vec<N>>, Error<N>>
+    where
+        FF: Fn(&[N]) -> bool + Sync,
+        N: Send + Sync,
+    {
+        use rayon::prelude::*;
+        self.plan_batch(batch_size, |batch: &[Vec<N>]| {
+            batch.par_iter().map(|q| is_free(q)).collect()
+        })
+    }
+
+    /// Search and improve the path from start to goal with RRT*, which
+    /// rewires the tree within [`RrtPlanner::neighbourhood_radius`].
+    ///
+    /// The search runs for all the tries, until the time budget has passed
+    /// or until the termination condition stops it, and returns the tree
+    /// with the best solution found. Without any solution it fails with
+    /// [`Error::MaxIterationsReached`], or [`Error::DeadlineReached`] for the
+    /// time budget, unless [`RrtPlanner::on_failure`] keeps the tree.
+    pub fn plan_star<FF>(&self, is_free: FF) -> Result<rrtstar::Tree<N, f32>, Error<N>>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let start = self.unprojected_start()?;
+        let goal = self.required_goal()?;
+        let neighbourhood_radius = self
+            .neighbourhood_radius
+            .ok_or(Error::InvalidConfiguration(
+                "neighbourhood radius is not set",
+            ))?;
+        let validity_resolution = self.validity_resolution.unwrap_or(self.extend_length);
+        let (result, _, _) =
+            self.with_search(Some(goal), &None, |random_sample, _| match &self.limit {
+                Limit::Iterations(max_iterations) => rrtstar_with_resolution(
+                    start,
+                    goal,
+                    is_free,
+                    random_sample,
+                    self.extend_length,
+                    validity_resolution,
+                    *max_iterations,
+                    neighbourhood_radius,
+                    false,
+                )
+                .map(|tree| (tree, RRTStarError::MaxItersReached)),
+                Limit::Budget(budget) => rrtstar_with_deadline_impl(
+                    start,
+                    goal,
+                    is_free,
+                    random_sample,
+                    self.extend_length,
+                    validity_resolution,
+                    neighbourhood_radius,
+                    SoftDeadline::new(*budget),
+                )
+                .map(|tree| (tree, RRTStarError::DeadlineReached)),
+                Limit::Condition(condition) => rrtstar_until_impl(
+                    start,
+                    goal,
+                    is_free,
+                    random_sample,
+                    self.extend_length,
+                    validity_resolution,
+                    neighbourhood_radius,
+                    condition,
+                ),
+            })?;
+        let (tree, unsolved) = result.map_err(|error| {
+            debug!("RRT* failed: {error}");
+            Error::from(error)
+        })?;
+        if tree.goal_index.is_some() || self.on_failure != OnFailure::Error {
+            return Ok(tree);
+        }
+        debug!("RRT* failed: {unsolved}");
+        Err(unsolved.into())
+    }
+
+    fn required_start(&self) -> Result<&[N], Error<N>> {
+        self.start
+            .as_deref()
+            .ok_or(Error::InvalidConfiguration("start is not set"))
+    }
+
+    fn required_goal(&self) -> Result<&[N], Error<N>> {
+        self.goal
+            .as_deref()
+            .ok_or(Error::InvalidConfiguration("goal is not set"))
+    }
+
+    // start of the searches which do not support the frozen dimensions
+    fn unprojected_start(&self) -> Result<&[N], Error<N>> {
+        if !self.frozen.is_empty() {
+            return Err(Error::InvalidConfiguration(
+                "frozen dimensions are not supported by this search",
+            ));
+        }
+        self.required_start()
+    }
+
+    /// Start and goal on the free dimensions, and the projection onto them
+    /// if some dimensions are frozen
+    fn query(&self) -> Result<Query<'_, N>, Error<N>> {
+        let start = self.required_start()?;
+        let goal = self.required_goal()?;
+        if self.frozen.is_empty() {
+            return Ok((Cow::Borrowed(start), Cow::Borrowed(goal), None));
+        }
+        if goal.len() != start.len() {
+            return Err(Error::InvalidConfiguration(
+                "start and goal have different dimensions",
+            ));
+        }
+        if self.frozen.iter().any(|&(d, _)| d >= start.len()) {
+            return Err(Error::InvalidConfiguration(
+                "frozen dimension is out of range",
+            ));
+        }
+        let free_dims = (0..start.len())
+            .filter(|i| self.frozen.iter().all(|&(d, _)| d != *i))
+            .collect::<Vec<_>>();
+        if free_dims.is_empty() {
+            return Err(Error::InvalidConfiguration("all the dimensions are frozen"));
+        }
+        let projection = Projection {
+            dim: start.len(),
+            free_dims,
+            frozen: &self.frozen,
+        };
+        Ok((
+            Cow::Owned(projection.project(start)),
+            Cow::Owned(projection.project(goal)),
+            Some(projection),
+        ))
+    }
+
+    /// Run `search` with the sampler and the options of the planner, and
+    /// returns its result, the number of tries and the strategy switches.
+    ///
+    /// `goal` is sampled with the goal bias, and the other samples are
+    /// projected onto the free dimensions.
+    fn with_search<T>(
+        &self,
+        goal: Option<&[N]>,
+        projection: &Option<Projection<'_, N>>,
+        search: impl FnOnce(&dyn Fn() -> Vec<N>, Search<'_, N>) -> T,
+    ) -> Result<(T, usize, Vec<StrategySwitch>), Error<N>> {
+        let sampler = self
+            .sampler
+            .as_ref()
+            .ok_or(Error::InvalidConfiguration("sampler is not set"))?;
+        if matches!(&self.limit, Limit::Condition(condition) if !condition.is_bounded()) {
+            return Err(Error::InvalidConfiguration(
+                "the termination condition has no timeout, iteration limit or cancellation",
+            ));
+        }
+        let strategies = iter::once(sampler)
+            .chain(&self.strategies)
+            .collect::<Vec<_>>();
+        let rng: RefCell<Box<dyn RngCore>> = RefCell::new(match self.seed {
+            Some(seed) => Box::new(StdRng::seed_from_u64(seed)),
+            None => Box::new(rand::thread_rng()),
+        });
+        let current = Cell::new(0);
+        let random_sample = || {
+            let mut rng = rng.borrow_mut();
+            let rng: &mut dyn RngCore = &mut **rng;
+            match goal {
+                Some(goal) if self.goal_bias > 0.0 && rng.gen_bool(self.goal_bias) => goal.to_vec(),
+                _ => {
+                    let q = strategies[current.get()](rng);
+                    match projection {
+                        Some(projection) => projection.project(&q),
+                        None => q,
+                    }
+                }
+            }
+        };
+        let mut num_tries = 0;
+        let mut switches = Vec::new();
+        let mut best_gap = N::infinity();
+        let mut num_stalled = 0;
+        let mut on_extend = self.on_extend.as_ref().map(RefCell::borrow_mut);
+        let mut on_progress = self
+            .on_progress
+            .as_ref()
+            .map(|(report_every, on_progress)| (*report_every, on_progress.borrow_mut()));
+        let mut on_trees = self.on_trees.as_ref().map(RefCell::borrow_mut);
+        let mut selection = self.selection.borrow_mut();
+        let result = search(
+            &random_sample,
+            Search {
+                extend_length: self.extend_length,
+                validity_resolution: self.validity_resolution.unwrap_or(self.extend_length),
+                trees: self.trees.clone(),
+                next_batch: self.limit.batches(),
+                selection: Box::new(&mut **selection),
+                on_extend: Box::new(|succeeded, gap, start_tree, goal_tree| {
+                    num_tries += 1;
+                    if let Some(on_extend) = on_extend.as_deref_mut() {
+                        on_extend(succeeded);
+                    }
+                    if let Some(on_trees) = on_trees.as_deref_mut() {
+                        on_trees(start_tree, goal_tree);
+                    }
+                    if let Some((report_every, on_progress)) = &mut on_progress {
+                        if num_tries % *report_every == 0 {
+                            let (start_index, goal_index, gap) =
+                                start_tree.closest_pair(&goal_tree);
+                            on_progress(&Progress {
+                                num_tries,
+                                start_tree_size: start_tree.len(),
+                                goal_tree_size: goal_tree.len(),
+                                closest_pair: (
+                                    lifted(projection, start_tree.vertex(start_index)).into_owned(),
+                                    lifted(projection, goal_tree.vertex(goal_index)).into_owned(),
+                                ),
+                                gap,
+                            });
+                        }
+                    }
+                    if gap < best_gap {
+                        best_gap = gap;
+                        num_stalled = 0;
+                        return;
+                    }
+                    num_stalled += 1;
+                    if num_stalled >= self.stall_iterations && strategies.len() > 1 {
+                        let from = current.get();
+                        let to = (from + 1) % strategies.len();
+                        debug!("stalled, switching the strategy from {from} to {to}");
+                        current.set(to);
+                        switches.push(StrategySwitch {
+                            iteration: num_tries,
+                            from,
+                            to,
+                        });
+                        num_stalled = 0;
+                    }
+                }),
+                rng: Box::new(SharedRng(&rng)),
+                on_failure: self.on_failure,
+            },
+        );
+        Ok((result, num_tries, switches))
+    }
+}
+
+/// The rng of the planner, which is shared by the sampler and the search
+struct SharedRng<'r>(&'r RefCell<Box<dyn RngCore>>);
+
+impl RngCore for SharedRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        self.0.borrow_mut().next_u32()
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.0.borrow_mut().next_u64()
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.borrow_mut().fill_bytes(dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.borrow_mut().try_fill_bytes(dest)
+    }
+}
+
+#[test]
+fn it_works() {
+    use rand::distributions::{Distribution, Uniform};
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let planner = RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .goal_bias(0.05);
+    assert_eq!(
+        planner.plan(is_free).unwrap_err(),
+        Error::InvalidConfiguration("sampler is not set")
+    );
+    let planner = planner.sampler(|| {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    });
+    let result = planner.plan(is_free).unwrap();
+    assert!(result.len() >= 4);
+    assert_eq!(result[0], [-1.2, 0.0]);
+    assert_eq!(result.last().unwrap(), &[1.2, 0.0]);
+    assert!(planner.max_iterations(0).plan(is_free).is_err());
+    assert_eq!(
+        RrtPlanner::<f64>::new().plan(is_free).unwrap_err(),
+        Error::InvalidConfiguration("start is not set")
+    );
+
+    let planner = RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .goal_bias(0.05)
+        .sampler_with_rng(|rng| vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)])
+        .seed(3);
+    assert_eq!(planner.plan(is_free), planner.plan(is_free));
+
+    let planner = planner.freeze(2, 0.5).freeze(2, 0.3);
+    assert_eq!(
+        planner.plan(|p: &[f64]| p.len() == 3).unwrap_err(),
+        Error::InvalidConfiguration("frozen dimension is out of range")
+    );
+    assert_eq!(
+        RrtPlanner::new()
+            .start(&[-1.2, 0.0, 0.0])
+            .goal(&[1.2, 0.0])
+            .sampler(|| vec![0.0, 0.0, 0.0])
+            .freeze(2, 0.3)
+            .plan(is_free)
+            .unwrap_err(),
+        Error::InvalidConfiguration("start and goal have different dimensions")
+    );
+    let planner = RrtPlanner::new()
+        .start(&[-1.2, 0.0, 0.0])
+        .goal(&[1.2, 0.0, 1.0])
+        .extend_length(0.2)
+        .sampler(|| {
+            let mut rng = rand::thread_rng();
+            vec![
+                rng.gen_range(-2.0..2.0),
+                rng.gen_range(-2.0..2.0),
+                rng.gen_range(-2.0..2.0),
+            ]
+        })
+        .freeze(2, 0.3);
+    let result = planner
+        .plan(|p: &[f64]| p.len() == 3 && p[2] == 0.3 && is_free(p))
+        .unwrap();
+    assert!(result.iter().all(|p| p[2] == 0.3));
+    assert_eq!(result[0], [-1.2, 0.0, 0.3]);
+    assert_eq!(result.last().unwrap(), &[1.2, 0.0, 0.3]);
+    let waypoints = planner
+        .plan_waypoints(|p: &[f64]| p.len() == 3 && p[2] == 0.3 && is_free(p))
+        .unwrap();
+    assert!(waypoints.iter().all(|w| w.position[2] == 0.3));
+    assert_eq!(
+        planner
+            .freeze(0, 0.0)
+            .freeze(1, 0.0)
+            .plan(is_free)
+            .unwrap_err(),
+        Error::InvalidConfiguration("all the dimensions are frozen")
+    );
+    assert!(RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .sampler(|| vec![0.0, 0.0])
+        .freeze(1, 0.0)
+        .unfreeze(1)
+        .max_iterations(0)
+        .plan(is_free)
+        .is_err());
+}
+
+#[test]
+fn star_works() {
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let planner = RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .max_iterations(2000)
+        .goal_bias(0.05)
+        .sampler_with_rng(|rng| vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)])
+        .seed(1);
+    assert_eq!(
+        planner.plan_star(is_free).unwrap_err(),
+        Error::InvalidConfiguration("neighbourhood radius is not set")
+    );
+    let planner = planner.neighbourhood_radius(0.5);
+    let tree = planner.plan_star(is_free).unwrap();
+    let path = tree.path(tree.goal_index.unwrap());
+    assert_eq!(path[0], [-1.2, 0.0]);
+    assert_eq!(path[path.len() - 1], [1.2, 0.0]);
+    let planner = planner.time_budget(Duration::from_millis(100));
+    assert!(planner.plan_star(is_free).is_ok());
+    let planner = planner.termination(TerminationCondition::new().max_iterations(2000));
+    assert!(planner.plan_star(is_free).is_ok());
+    // the errors of RRT* keep their kind
+    let wall = |p: &[f64]| p[0].abs() > 0.1;
+    assert_eq!(
+        planner.plan_star(wall).unwrap_err(),
+        Error::MaxIterationsReached { best_partial: None }
+    );
+    let planner = planner.time_budget(Duration::from_millis(50));
+    assert_eq!(planner.plan_star(wall).unwrap_err(), Error::DeadlineReached);
+    assert_eq!(
+        planner.freeze(1, 0.0).plan_star(is_free).unwrap_err(),
+        Error::InvalidConfiguration("frozen dimensions are not supported by this search")
+    );
+}
+
+#[test]
+fn on_failure_works() {
+    use crate::rrt::TreeRole;
+    let (start, goal) = ([-1.2, 0.0], [1.2, 0.0]);
+    let wall = |p: &[f64]| p[0].abs() > 0.1;
+    let planner = RrtPlanner::new()
+        .start(&start)
+        .goal(&goal)
+        .extend_length(0.2)
+        .max_iterations(50)
+        .neighbourhood_radius(0.5)
+        .sampler_with_rng(|rng| vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)])
+        .seed(1);
+    assert!(matches!(
+        planner.plan(wall),
+        Err(Error::MaxIterationsReached {
+            best_partial: Some(_)
+        })
+    ));
+    assert!(planner.plan_star(wall).is_err());
+
+    let planner = planner.on_failure(OnFailure::BestEffortPath);
+    let path = planner.plan(wall).unwrap();
+    assert_eq!(path[0], start);
+    assert!(path.iter().all(|q| q[0] < -0.1));
+    let result = planner.plan_with_stats(wall).unwrap();
+    assert_eq!(result.path, path);
+    assert!(result.start_tree_size > 1);
+    let waypoints = planner.plan_waypoints(wall).unwrap();
+    assert_eq!(waypoints[0].vertex, 0);
+    assert!(waypoints.iter().all(|w| w.tree == TreeRole::Start));
+    let relaxed = planner.plan_relaxed(&[0.1], wall).unwrap();
+    assert!(relaxed.distance > relaxed.tolerance);
+    let check = |batch: &[Vec<f64>]| batch.iter().map(|q| wall(q)).collect();
+    assert_eq!(planner.plan_batch(8, check).unwrap()[0], start);
+    assert_eq!(
+        planner.plan_to_goals(&[goal.to_vec()], wall).unwrap()[0],
+        start
+    );
+    assert!(!planner.is_reachable(wall).unwrap());
+    assert_eq!(planner.plan_star(wall).unwrap().goal_index, None);
+
+    let planner = planner.on_failure(OnFailure::BestEffortTree);
+    let Err(Error::Unsolved { trees }) = planner.plan(wall) else {
+        panic!("not the trees");
+    };
+    assert_eq!(trees.len(), 2);
+    assert_eq!(trees[0].vertices[0], start);
+    assert_eq!(trees[1].vertices[0], goal);
+    assert!(matches!(
+        planner.plan_to_region(&crate::goal::PointGoal::new(&goal, 0.1), wall),
+        Err(Error::Unsolved { .. })
+    ));
+    // the trees are lifted to the frozen dimensions
+    let Err(Error::Unsolved { trees }) = planner
+        .start(&[-1.2, 0.0, 0.3])
+        .goal(&[1.2, 0.0, 0.3])
+        .sampler_with_rng(|rng| vec![rng.gen_range(-2.0..2.0); 3])
+        .freeze(2, 0.3)
+        .plan(wall)
+    else {
+        panic!("not the trees");
+    };
+    assert!(trees
+        .iter()
+        .flat_map(|tree| &tree.vertices)
+        .all(|q| q.len() == 3 && q[2] == 0.3));
+}
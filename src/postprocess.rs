@@ -0,0 +1,468 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Post-processing of the planned paths.
+//!
+//! A [`PostProcessingPipeline`] runs [`PostProcessor`]s (e.g. [`Shortcut`],
+//! [`Simplify`], [`Smooth`], [`Spline`], [`Resample`]) in the declared order within a
+//! shared time budget, and optionally time parameterizes the result.
+
+use crate::budget::IterationController;
+use crate::path::{path_length, resample_path};
+use crate::rrt::{is_edge_free, smooth_path};
+use crate::trajectory::{time_parameterize, Limits, Trajectory};
+use num_traits::float::Float;
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+
+/// Step of a [`PostProcessingPipeline`]
+pub trait PostProcessor<N> {
+    /// Name of the step, used in the statistics
+    fn name(&self) -> &str;
+    /// Improve `path` keeping it free, within `budget` if it is iterative
+    fn process(
+        &mut self,
+        path: &mut Vec<Vec<N>>,
+        is_free: &mut dyn FnMut(&[N]) -> bool,
+        budget: Duration,
+    );
+}
+
+/// Random shortcuts by [`smooth_path`], until `num_max_try` tries are done
+/// or the budget is used up
+#[derive(Debug, Clone)]
+pub struct Shortcut<N> {
+    /// Extend length of the shortcuts, which are checked at its steps
+    pub extend_length: N,
+    /// Maximum number of tries
+    pub num_max_try: usize,
+}
+
+impl<N: Float + Debug> PostProcessor<N> for Shortcut<N> {
+    fn name(&self) -> &str {
+        "shortcut"
+    }
+    fn process(
+        &mut self,
+        path: &mut Vec<Vec<N>>,
+        is_free: &mut dyn FnMut(&[N]) -> bool,
+        budget: Duration,
+    ) {
+        let mut controller = IterationController::new(budget);
+        let mut remaining = self.num_max_try;
+        while remaining > 0 {
+            let batch = controller.next_batch().min(remaining);
+            if batch == 0 {
+                break;
+            }
+            smooth_path(path, &mut *is_free, self.extend_length, batch);
+            remaining -= batch;
+        }
+    }
+}
+
+/// Removes the waypoints whose neighbours can be connected directly
+/// (checked every `validity_resolution`)
+#[derive(Debug, Clone)]
+pub struct Simplify<N> {
+    /// Resolution of the collision checks of the new edges
+    pub validity_resolution: N,
+}
+
+impl<N: Float + Debug> PostProcessor<N> for Simplify<N> {
+    fn name(&self) -> &str {
+        "simplify"
+    }
+    fn process(
+        &mut self,
+        path: &mut Vec<Vec<N>>,
+        is_free: &mut dyn FnMut(&[N]) -> bool,
+        _budget: Duration,
+    ) {
+        let mut i = 0;
+        while i + 2 < path.len() {
+            if is_edge_free(&path[i], &path[i + 2], self.validity_resolution, is_free) {
+                path.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Corner cutting (Chaikin): each corner is replaced by the points at the
+/// quarter and three quarters of its edges if the new edges are free
+/// (checked every `validity_resolution`), `num_iterations` times
+#[derive(Debug, Clone)]
+pub struct Smooth<N> {
+    /// Number of the cutting passes
+    pub num_iterations: usize,
+    /// Resolution of the collision checks of the new edges
+    pub validity_resolution: N,
+}
+
+impl<N: Float + Debug> PostProcessor<N> for Smooth<N> {
+    fn name(&self) -> &str {
+        "smooth"
+    }
+    fn process(
+        &mut self,
+        path: &mut Vec<Vec<N>>,
+        is_free: &mut dyn FnMut(&[N]) -> bool,
+        budget: Duration,
+    ) {
+        let deadline = Instant::now() + budget;
+        let quarter = N::from(0.25).unwrap();
+        let lerp = |a: &[N], b: &[N], t: N| -> Vec<N> {
+            a.iter().zip(b).map(|(a, b)| *a + (*b - *a) * t).collect()
+        };
+        for _ in 0..self.num_iterations {
+            if path.len() < 3 || Instant::now() >= deadline {
+                return;
+            }
+            let mut smoothed = vec![path[0].clone()];
+            for k in 1..path.len() - 1 {
+                let prev = smoothed.last().unwrap().clone();
+                let a = lerp(&path[k], &path[k - 1], quarter);
+                let b = lerp(&path[k], &path[k + 1], quarter);
+                let next = &path[k + 1];
+                if is_edge_free(&prev, &a, self.validity_resolution, is_free)
+                    && is_edge_free(&a, &b, self.validity_resolution, is_free)
+                    && is_edge_free(&b, next, self.validity_resolution, is_free)
+                {
+                    smoothed.push(a);
+                    smoothed.push(b);
+                } else {
+                    smoothed.push(path[k].clone());
+                }
+            }
+            smoothed.push(path[path.len() - 1].clone());
+            *path = smoothed;
+        }
+    }
+}
+
+/// Splits the edges so the waypoints are at most `resolution` apart (see
+/// [`resample_path`])
+#[derive(Debug, Clone)]
+pub struct Resample<N> {
+    /// Maximum distance between the waypoints
+    pub resolution: N,
+}
+
+impl<N: Float + Debug> PostProcessor<N> for Resample<N> {
+    fn name(&self) -> &str {
+        "resample"
+    }
+    fn process(
+        &mut self,
+        path: &mut Vec<Vec<N>>,
+        _is_free: &mut dyn FnMut(&[N]) -> bool,
+        _budget: Duration,
+    ) {
+        *path = resample_path(path, self.resolution);
+    }
+}
+
+/// Catmull-Rom spline through the waypoints of `path`, resampled so its
+/// points are at most `resolution` apart.
+///
+/// The spline passes through every waypoint with a continuous tangent, so
+/// it has no corners to feed to a controller. The curve of each edge is
+/// checked at its points, and an edge whose curve is not free is kept
+/// straight, so the result is free if `path` is.
+pub fn smooth_spline<FF, N>(path: &[Vec<N>], mut is_free: FF, resolution: N) -> Vec<Vec<N>>
+where
+    FF: FnMut(&[N]) -> bool,
+    N: Float + Debug,
+{
+    assert!(resolution > N::zero());
+    let Some(first) = path.first() else {
+        return Vec::new();
+    };
+    let half = N::from(0.5).unwrap();
+    let mut smoothed = vec![first.clone()];
+    for k in 0..path.len().saturating_sub(1) {
+        // the end waypoints are repeated as the missing control points
+        let p0 = &path[k.saturating_sub(1)];
+        let (p1, p2) = (&path[k], &path[k + 1]);
+        let p3 = &path[(k + 2).min(path.len() - 1)];
+        let curve = |t: N| -> Vec<N> {
+            (0..p1.len())
+                .map(|d| {
+                    let (a, b, c, e) = (p0[d], p1[d], p2[d], p3[d]);
+                    let two = N::one() + N::one();
+                    let three = two + N::one();
+                    half * (two * b
+                        + (c - a) * t
+                        + (two * a - (two + three) * b + (two + two) * c - e) * t * t
+                        + (three * b - a - three * c + e) * t * t * t)
+                })
+                .collect()
+        };
+        let mut num = 1;
+        let points = loop {
+            // ends exactly at the waypoint, without the rounding of the curve
+            let points = (1..num)
+                .map(|i| curve(N::from(i).unwrap() / N::from(num).unwrap()))
+                .chain([p2.clone()])
+                .collect::<Vec<_>>();
+            let mut previous = p1;
+            if points.iter().all(|q| {
+                let close = path_length(&[previous.clone(), q.clone()]) <= resolution;
+                previous = q;
+                close
+            }) {
+                break points;
+            }
+            num *= 2;
+        };
+        let mut previous = p1.clone();
+        let is_curve_free = points.iter().all(|q| {
+            let free = is_edge_free(&previous, q, resolution, &mut is_free);
+            previous = q.clone();
+            free
+        });
+        if is_curve_free {
+            smoothed.extend(points);
+        } else {
+            let edge = [p1.clone(), p2.clone()];
+            smoothed.extend(resample_path(&edge, resolution).into_iter().skip(1));
+        }
+    }
+    smoothed
+}
+
+/// Fits [`smooth_spline`] through the waypoints
+#[derive(Debug, Clone)]
+pub struct Spline<N> {
+    /// Maximum distance between the points of the spline
+    pub resolution: N,
+}
+
+impl<N: Float + Debug> PostProcessor<N> for Spline<N> {
+    fn name(&self) -> &str {
+        "spline"
+    }
+    fn process(
+        &mut self,
+        path: &mut Vec<Vec<N>>,
+        is_free: &mut dyn FnMut(&[N]) -> bool,
+        _budget: Duration,
+    ) {
+        *path = smooth_spline(path, is_free, self.resolution);
+    }
+}
+
+/// Statistics of a step of [`PostProcessingPipeline`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepStats<N> {
+    /// Name of the step
+    pub name: String,
+    /// Time spent in the step
+    pub elapsed: Duration,
+    /// Length of the path after the step
+    pub length: N,
+    /// Number of waypoints after the step
+    pub num_waypoints: usize,
+}
+
+/// Result of [`PostProcessingPipeline::run`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostProcessed<N> {
+    /// Processed path
+    pub path: Vec<Vec<N>>,
+    /// Time parameterized path, if requested
+    pub trajectory: Option<Trajectory<N>>,
+    /// Statistics of the steps in order
+    pub steps: Vec<StepStats<N>>,
+}
+
+/// Post-processing steps run in the declared order
+pub struct PostProcessingPipeline<'a, N> {
+    steps: Vec<Box<dyn PostProcessor<N> + 'a>>,
+    time_parameterization: Option<(Limits<N>, N)>,
+}
+
+impl<'a, N> Default for PostProcessingPipeline<'a, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, N> PostProcessingPipeline<'a, N> {
+    /// Create a pipeline without steps
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            time_parameterization: None,
+        }
+    }
+
+    /// Add a step after the existing ones
+    pub fn then<P: PostProcessor<N> + 'a>(mut self, step: P) -> Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    /// Time parameterize the final path within `limits` (see
+    /// [`time_parameterize`])
+    pub fn time_parameterize(mut self, limits: Limits<N>, resolution: N) -> Self {
+        self.time_parameterization = Some((limits, resolution));
+        self
+    }
+}
+
+impl<'a, N: Float + Debug> PostProcessingPipeline<'a, N> {
+    /// Run the steps on `path`. Each step may use the rest of `budget`
+    /// which the previous steps have left.
+    pub fn run<FF>(
+        &mut self,
+        path: &[Vec<N>],
+        mut is_free: FF,
+        budget: Duration,
+    ) -> PostProcessed<N>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let deadline = Instant::now() + budget;
+        let mut path = path.to_vec();
+        let mut steps = Vec::with_capacity(self.steps.len());
+        for step in &mut self.steps {
+            let started = Instant::now();
+            step.process(
+                &mut path,
+                &mut is_free,
+                deadline.saturating_duration_since(started),
+            );
+            steps.push(StepStats {
+                name: step.name().to_owned(),
+                elapsed: started.elapsed(),
+                length: path_length(&path),
+                num_waypoints: path.len(),
+            });
+        }
+        let trajectory = self
+            .time_parameterization
+            .as_ref()
+            .map(|(limits, resolution)| time_parameterize(&path, limits, *resolution));
+        PostProcessed {
+            path,
+            trajectory,
+            steps,
+        }
+    }
+}
+
+#[test]
+fn it_works() {
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let path = vec![
+        vec![-1.5, 0.0],
+        vec![-1.5, 1.5],
+        vec![-1.0, 1.6],
+        vec![0.0, 2.0],
+        vec![1.5, 1.5],
+        vec![1.5, 0.0],
+    ];
+    let mut pipeline = PostProcessingPipeline::new()
+        .then(Shortcut {
+            extend_length: 0.1,
+            num_max_try: 100,
+        })
+        .then(Simplify {
+            validity_resolution: 0.01,
+        })
+        .then(Smooth {
+            num_iterations: 2,
+            validity_resolution: 0.01,
+        })
+        .then(Resample { resolution: 0.1 })
+        .time_parameterize(
+            Limits {
+                max_velocity: vec![1.0, 1.0],
+                max_acceleration: vec![2.0, 2.0],
+            },
+            0.05,
+        );
+    let processed = pipeline.run(&path, is_free, Duration::from_secs(1));
+    let names = processed
+        .steps
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["shortcut", "simplify", "smooth", "resample"]);
+    assert!(processed.steps[1].length <= path_length(&path));
+    assert_eq!(processed.path[0], path[0]);
+    assert_eq!(processed.path.last(), path.last());
+    for w in processed.path.windows(2) {
+        assert!(path_length(w) <= 0.1 + 1e-9);
+        assert!(is_edge_free(&w[0], &w[1], 0.01, &mut { is_free }));
+    }
+    let trajectory = processed.trajectory.unwrap();
+    assert_eq!(trajectory.position(trajectory.duration()), path[5]);
+
+    // without budget only the non iterative steps run
+    let processed = PostProcessingPipeline::new()
+        .then(Shortcut {
+            extend_length: 0.1,
+            num_max_try: 100,
+        })
+        .then(Simplify {
+            validity_resolution: 0.01,
+        })
+        .run(&path, is_free, Duration::ZERO);
+    assert_eq!(processed.steps[0].num_waypoints, path.len());
+    assert!(processed.steps[1].num_waypoints < path.len());
+    assert!(processed.trajectory.is_none());
+
+    // the spline passes through the waypoints without corners
+    let turn = |w: &[Vec<f64>]| {
+        let (a, b) = (
+            [w[1][0] - w[0][0], w[1][1] - w[0][1]],
+            [w[2][0] - w[1][0], w[2][1] - w[1][1]],
+        );
+        let cos = (a[0] * b[0] + a[1] * b[1]) / (a[0].hypot(a[1]) * b[0].hypot(b[1]));
+        cos.clamp(-1.0, 1.0).acos()
+    };
+    let zigzag = vec![
+        vec![-1.5, 1.2],
+        vec![-0.5, 1.8],
+        vec![0.5, 1.2],
+        vec![1.5, 1.8],
+    ];
+    let spline = smooth_spline(&zigzag, is_free, 0.05);
+    assert_eq!(spline[0], zigzag[0]);
+    assert_eq!(spline.last(), zigzag.last());
+    assert!(zigzag.iter().all(|q| spline
+        .iter()
+        .any(|p| path_length(&[p.clone(), q.clone()]) < 1e-9)));
+    for w in spline.windows(2) {
+        assert!(path_length(w) <= 0.05 + 1e-9);
+    }
+    assert!(spline.windows(3).map(turn).fold(0.0, f64::max) < 0.2);
+    assert!(zigzag.windows(3).map(turn).fold(0.0, f64::max) > 1.0);
+    // the curve after the corner would dip into the obstacle, so the edge
+    // along it stays straight
+    let corner = vec![vec![-1.5, 1.5], vec![-1.0, 1.0], vec![1.0, 1.0]];
+    let spline = smooth_spline(&corner, is_free, 0.05);
+    assert!(spline.iter().all(|q| is_free(q)));
+    assert!(spline.iter().filter(|q| q[0] > -1.0).all(|q| q[1] == 1.0));
+    assert!(spline
+        .iter()
+        .filter(|q| q[0] < -1.0)
+        .any(|q| (q[0] + q[1]).abs() > 1e-3));
+}
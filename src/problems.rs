@@ -0,0 +1,203 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Random problem generator for benchmarks and tests.
+//!
+//! Generated problems always have a feasible corridor from start to goal.
+
+use crate::collision::{AxisAlignedBox, Obstacle, Scene, Sphere};
+use kdtree::distance::squared_euclidean;
+use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
+
+/// Parameters of the generated problems
+#[derive(Debug, Clone)]
+pub struct ProblemConfig {
+    /// Dimension of the configuration space
+    pub dim: usize,
+    /// The workspace is `[-half_extent, half_extent]` in all dimensions
+    pub half_extent: f64,
+    /// Target fraction of the workspace volume covered by obstacles, in `[0, 1)`
+    pub density: f64,
+    /// Minimum obstacle size (radius or half width)
+    pub min_size: f64,
+    /// Maximum obstacle size (radius or half width)
+    pub max_size: f64,
+    /// Width of the free corridor kept between start and goal
+    pub corridor_width: f64,
+    /// Number of random waypoints of the corridor
+    pub num_corridor_waypoints: usize,
+    /// Max number of obstacle placement trials
+    pub num_max_try: usize,
+}
+
+impl Default for ProblemConfig {
+    fn default() -> Self {
+        Self {
+            dim: 2,
+            half_extent: 2.0,
+            density: 0.3,
+            min_size: 0.1,
+            max_size: 0.4,
+            corridor_width: 0.2,
+            num_corridor_waypoints: 2,
+            num_max_try: 10000,
+        }
+    }
+}
+
+/// Generated planning problem
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// Start configuration
+    pub start: Vec<f64>,
+    /// Goal configuration
+    pub goal: Vec<f64>,
+    /// Lower bounds of the workspace
+    pub lower: Vec<f64>,
+    /// Upper bounds of the workspace
+    pub upper: Vec<f64>,
+    /// Obstacles
+    pub scene: Scene<f64>,
+    /// Polyline from start to goal which is kept free of obstacles
+    pub corridor: Vec<Vec<f64>>,
+}
+
+impl Problem {
+    /// Returns true if `q` is not inside of any obstacle
+    pub fn is_free(&self, q: &[f64]) -> bool {
+        self.scene.is_free(q)
+    }
+
+    /// Uniform sample in the workspace
+    pub fn random_sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<f64> {
+        self.lower
+            .iter()
+            .zip(self.upper.iter())
+            .map(|(l, u)| rng.gen_range(*l..*u))
+            .collect()
+    }
+}
+
+/// Generate a random problem
+pub fn generate<R: Rng + ?Sized>(config: &ProblemConfig, rng: &mut R) -> Problem {
+    assert!(config.dim > 0);
+    assert!(config.min_size > 0.0 && config.min_size <= config.max_size);
+    let extent = config.half_extent;
+    let lower = vec![-extent; config.dim];
+    let upper = vec![extent; config.dim];
+    let margin = config.corridor_width.max(extent * 0.05);
+    let start = vec![-extent + margin; config.dim];
+    let goal = vec![extent - margin; config.dim];
+
+    let position = Uniform::new(-extent + margin, extent - margin);
+    let mut corridor = vec![start.clone()];
+    for _ in 0..config.num_corridor_waypoints {
+        corridor.push((0..config.dim).map(|_| position.sample(rng)).collect());
+    }
+    corridor.push(goal.clone());
+
+    // Points on the corridor every `spacing`. Keeping obstacles farther than
+    // `corridor_width / 2 + spacing / 2` from all of them keeps the whole
+    // corridor free.
+    let spacing = config.corridor_width / 2.0;
+    let corridor_points = corridor
+        .windows(2)
+        .flat_map(|w| {
+            let length = squared_euclidean(&w[0], &w[1]).sqrt();
+            let num = (length / spacing).ceil().max(1.0) as usize;
+            (0..=num).map(move |i| {
+                let t = i as f64 / num as f64;
+                w[0].iter()
+                    .zip(w[1].iter())
+                    .map(|(a, b)| a + (b - a) * t)
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect::<Vec<_>>();
+    let clearance = config.corridor_width / 2.0 + spacing / 2.0;
+
+    let total_volume = (2.0 * extent).powi(config.dim as i32);
+    let size = Uniform::new_inclusive(config.min_size, config.max_size);
+    let center = Uniform::new(-extent, extent);
+    let mut covered = 0.0;
+    let mut obstacles = Vec::new();
+    for _ in 0..config.num_max_try {
+        if covered >= config.density * total_volume {
+            break;
+        }
+        let c = (0..config.dim)
+            .map(|_| center.sample(rng))
+            .collect::<Vec<_>>();
+        let s = size.sample(rng);
+        let obstacle = if rng.gen_bool(0.5) {
+            Obstacle::Sphere(Sphere {
+                center: c,
+                radius: s,
+            })
+        } else {
+            Obstacle::Box(AxisAlignedBox {
+                min: c.iter().map(|v| v - s).collect(),
+                max: c.iter().map(|v| v + s).collect(),
+            })
+        };
+        if corridor_points
+            .iter()
+            .all(|p| obstacle.distance(p) > clearance)
+        {
+            // bounding box volume is used for both shapes, which is good enough
+            // to control the clutter.
+            covered += (2.0 * s).powi(config.dim as i32);
+            obstacles.push(obstacle);
+        }
+    }
+
+    Problem {
+        start,
+        goal,
+        lower,
+        upper,
+        scene: Scene { obstacles },
+        corridor,
+    }
+}
+
+#[test]
+fn it_works() {
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    for dim in 2..5 {
+        let problem = generate(
+            &ProblemConfig {
+                dim,
+                ..Default::default()
+            },
+            &mut rng,
+        );
+        assert!(!problem.scene.obstacles.is_empty());
+        for w in problem.corridor.windows(2) {
+            for i in 0..=100 {
+                let t = i as f64 / 100.0;
+                let p = w[0]
+                    .iter()
+                    .zip(w[1].iter())
+                    .map(|(a, b)| a + (b - a) * t)
+                    .collect::<Vec<_>>();
+                assert!(problem.is_free(&p));
+            }
+        }
+    }
+}
@@ -0,0 +1,268 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Live visualization of the searches in the [Rerun](https://rerun.io) viewer.
+//!
+//! [`TreeRecorder`] streams the new vertices and edges of the trees and the
+//! path through their closest vertices (the best path so far) from
+//! [`RrtPlanner::on_trees`], the gap between the trees from
+//! [`RrtPlanner::on_progress`], the samples from [`TreeRecorder::sampler`]
+//! and the found path to a [`RecordingStream`]. Each try is a step of the
+//! `try` timeline, and only the new vertices and edges are logged at each
+//! step, so set the visible time range of the tree entities to the whole
+//! timeline to see the trees as they grow.
+//!
+//! The first three dimensions of the configurations are drawn (a missing
+//! third one is zero).
+//!
+//! ```no_run
+//! use rand::Rng;
+//! use rrt::rerun_viewer::TreeRecorder;
+//!
+//! let stream = rerun::RecordingStreamBuilder::new("rrt").spawn().unwrap();
+//! let recorder = TreeRecorder::new(stream, "rrt");
+//! let planner = recorder.attach(
+//!     rrt::RrtPlanner::new()
+//!         .start(&[-1.2, 0.0])
+//!         .goal(&[1.2, 0.0])
+//!         .extend_length(0.2)
+//!         .sampler(recorder.sampler(|| {
+//!             let mut rng = rand::thread_rng();
+//!             vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+//!         })),
+//! );
+//! let path = planner
+//!     .plan(|p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0))
+//!     .unwrap();
+//! recorder.log_path(&path);
+//! ```
+
+use crate::rrt::{Progress, TreeView};
+use crate::RrtPlanner;
+use num_traits::float::Float;
+use rerun::{AsComponents, Color, LineStrips3D, Points3D, RecordingStream, Scalar};
+use std::cell::Cell;
+use std::fmt::Debug;
+use tracing::debug;
+
+const START_COLOR: [u8; 3] = [80, 160, 255];
+const GOAL_COLOR: [u8; 3] = [255, 160, 80];
+const SAMPLE_COLOR: [u8; 3] = [160, 160, 160];
+const PATH_COLOR: [u8; 3] = [80, 220, 80];
+/// Number of tries between the updates of the best path and the gap
+const REPORT_EVERY: usize = 10;
+
+/// Streams the trees, the samples and the paths of a search to Rerun
+pub struct TreeRecorder {
+    stream: RecordingStream,
+    entity: String,
+    num_tries: Cell<i64>,
+    // number of the vertices of the start tree and the goal tree which are
+    // already logged
+    num_logged: Cell<(usize, usize)>,
+}
+
+impl TreeRecorder {
+    /// Recorder which logs under the entity path `entity`
+    pub fn new(stream: RecordingStream, entity: &str) -> Self {
+        Self {
+            stream,
+            entity: entity.to_string(),
+            num_tries: Cell::new(0),
+            num_logged: Cell::new((0, 0)),
+        }
+    }
+
+    /// `planner` which streams its trees and its progress to this recorder.
+    /// It replaces the [`RrtPlanner::on_trees`] and
+    /// [`RrtPlanner::on_progress`] callbacks.
+    pub fn attach<'a, N>(&'a self, planner: RrtPlanner<'a, N>) -> RrtPlanner<'a, N>
+    where
+        N: Float + Debug,
+    {
+        planner
+            .on_trees(|start_tree: TreeView<'_, N>, goal_tree: TreeView<'_, N>| {
+                self.log_trees(start_tree, goal_tree)
+            })
+            .on_progress(REPORT_EVERY, |progress: &Progress<N>| {
+                self.log_progress(progress)
+            })
+    }
+
+    /// `sampler` which logs each of its samples
+    pub fn sampler<'a, N, F>(&'a self, sampler: F) -> impl Fn() -> Vec<N> + 'a
+    where
+        N: Float,
+        F: Fn() -> Vec<N> + 'a,
+    {
+        move || {
+            let q = sampler();
+            self.log(
+                "sample",
+                &Points3D::new([point(&q)])
+                    .with_colors([color(SAMPLE_COLOR)])
+                    .with_radii([0.02]),
+            );
+            q
+        }
+    }
+
+    /// Log the vertices and the edges which were added to the trees since
+    /// the last call as the next try, and every few tries the path from the
+    /// start to the goal through the closest vertices of the trees
+    pub fn log_trees<N>(&self, start_tree: TreeView<'_, N>, goal_tree: TreeView<'_, N>)
+    where
+        N: Float + Debug,
+    {
+        let num_tries = self.num_tries.get() + 1;
+        self.num_tries.set(num_tries);
+        self.stream.set_time_sequence("try", num_tries);
+        let (num_start, num_goal) = self.num_logged.get();
+        self.log_new_vertices("start_tree", start_tree, num_start, START_COLOR);
+        self.log_new_vertices("goal_tree", goal_tree, num_goal, GOAL_COLOR);
+        self.num_logged.set((start_tree.len(), goal_tree.len()));
+        if num_tries % REPORT_EVERY as i64 == 0 && !goal_tree.is_empty() {
+            let (start_index, goal_index, _) = start_tree.closest_pair(&goal_tree);
+            let mut best_path = branch(start_tree, start_index);
+            best_path.reverse();
+            best_path.extend(branch(goal_tree, goal_index));
+            self.log(
+                "best_path",
+                &LineStrips3D::new([best_path]).with_colors([color(PATH_COLOR)]),
+            );
+        }
+    }
+
+    /// Log the gap between the closest vertices of the trees
+    pub fn log_progress<N>(&self, progress: &Progress<N>)
+    where
+        N: Float + Debug,
+    {
+        let (q_start, q_goal) = &progress.closest_pair;
+        self.log(
+            "gap",
+            &LineStrips3D::new([vec![point(q_start), point(q_goal)]])
+                .with_colors([color(SAMPLE_COLOR)]),
+        );
+        self.log(
+            "gap_length",
+            &Scalar::new(progress.gap.to_f64().unwrap_or(f64::NAN)),
+        );
+    }
+
+    /// Log the path found by the search
+    pub fn log_path<N>(&self, path: &[Vec<N>])
+    where
+        N: Float,
+    {
+        self.log(
+            "path",
+            &LineStrips3D::new([path.iter().map(|q| point(q)).collect::<Vec<_>>()])
+                .with_colors([color(PATH_COLOR)])
+                .with_radii([0.01]),
+        );
+    }
+
+    fn log_new_vertices<N>(&self, name: &str, tree: TreeView<'_, N>, from: usize, rgb: [u8; 3])
+    where
+        N: Float + Debug,
+    {
+        // a new search restarts the trees
+        let from = if from > tree.len() { 0 } else { from };
+        if from == tree.len() {
+            return;
+        }
+        let new = from..tree.len();
+        self.log(
+            &format!("{name}/vertices"),
+            &Points3D::new(new.clone().map(|i| point(tree.vertex(i))))
+                .with_colors([color(rgb)])
+                .with_radii([0.01]),
+        );
+        self.log(
+            &format!("{name}/edges"),
+            &LineStrips3D::new(new.filter_map(|i| {
+                let parent = tree.parent(i)?;
+                Some(vec![point(tree.vertex(parent)), point(tree.vertex(i))])
+            }))
+            .with_colors([color(rgb)]),
+        );
+    }
+
+    fn log(&self, path: &str, archetype: &impl AsComponents) {
+        let path = format!("{}/{path}", self.entity);
+        if let Err(e) = self.stream.log(path.as_str(), archetype) {
+            debug!("failed to log {path} to rerun: {e}");
+        }
+    }
+}
+
+/// Positions of the vertices from `index` to the root of `tree`
+fn branch<N>(tree: TreeView<'_, N>, index: usize) -> Vec<[f32; 3]>
+where
+    N: Float + Debug,
+{
+    let mut branch = vec![point(tree.vertex(index))];
+    let mut index = index;
+    while let Some(parent) = tree.parent(index) {
+        branch.push(point(tree.vertex(parent)));
+        index = parent;
+    }
+    branch
+}
+
+fn color([r, g, b]: [u8; 3]) -> Color {
+    Color::from_rgb(r, g, b)
+}
+
+/// Position of `q` in the viewer
+fn point<N: Float>(q: &[N]) -> [f32; 3] {
+    let mut p = [0.0; 3];
+    for (x, v) in p.iter_mut().zip(q) {
+        *x = v.to_f32().unwrap_or(f32::NAN);
+    }
+    p
+}
+
+#[test]
+fn it_works() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::cell::RefCell;
+    let (stream, storage) = rerun::RecordingStreamBuilder::new("rrt").memory().unwrap();
+    let recorder = TreeRecorder::new(stream.clone(), "rrt");
+    let rng = RefCell::new(StdRng::seed_from_u64(0));
+    let planner = recorder.attach(
+        RrtPlanner::new()
+            .start(&[-1.2, 0.0])
+            .goal(&[1.2, 0.0])
+            .extend_length(0.2)
+            .sampler(recorder.sampler(|| {
+                let mut rng = rng.borrow_mut();
+                vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+            })),
+    );
+    let path = planner
+        .plan(|p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0))
+        .unwrap();
+    recorder.log_path(&path);
+    assert!(recorder.num_tries.get() > 0);
+    let (num_start, num_goal) = recorder.num_logged.get();
+    assert!(num_start > 1 && num_goal > 1);
+    stream.flush_blocking();
+    assert!(!storage.take().is_empty());
+    assert_eq!(point(&[1.0, 2.0]), [1.0, 2.0, 0.0]);
+    assert_eq!(point(&[1.0, 2.0, 3.0, 4.0]), [1.0, 2.0, 3.0]);
+}
@@ -0,0 +1,735 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Roadmap graph and its versioned binary storage format.
+//!
+//! The stored file starts with a header (magic, format version, environment
+//! hash and dimension), followed by the vertices and edges as little endian
+//! numbers, and ends with a checksum of everything before it. Loading checks
+//! the environment hash, so a roadmap is not reused against a changed scene
+//! by accident.
+
+use crate::collision::{Obstacle, Scene};
+use crate::rrt::{dual_rrt_connect, is_edge_free, Error};
+#[cfg(feature = "rayon")]
+use crate::seeding::SeedSequence;
+use kdtree::distance::squared_euclidean;
+use num_traits::float::Float;
+#[cfg(feature = "rayon")]
+use rand::RngCore;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt::Debug;
+use std::io::{Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"RRTRDMAP";
+
+/// Current version of the roadmap format
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Roadmap error
+#[derive(Debug, derive_more::Error, derive_more::Display)]
+pub enum RoadmapError {
+    /// Failed to read or write
+    #[display(fmt = "{_0}")]
+    Io(std::io::Error),
+    /// The data is not a roadmap
+    #[display(fmt = "not a roadmap")]
+    InvalidMagic,
+    /// The roadmap was written by an unsupported format version
+    #[display(fmt = "unsupported roadmap format version {_0}")]
+    UnsupportedVersion(#[error(not(source))] u32),
+    /// The checksum does not match
+    #[display(fmt = "roadmap is corrupted")]
+    Corrupted,
+    /// The roadmap was built for another environment
+    #[display(fmt = "roadmap was built for environment {found:#x}, expected {expected:#x}")]
+    EnvironmentMismatch {
+        /// The hash of the current environment
+        expected: u64,
+        /// The hash stored in the roadmap
+        found: u64,
+    },
+}
+
+/// Undirected graph of free configurations connected by free edges
+#[derive(Debug, Clone, PartialEq)]
+pub struct Roadmap<N> {
+    /// Configurations
+    pub vertices: Vec<Vec<N>>,
+    /// Pairs of vertex indices
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl<N> Default for Roadmap<N> {
+    fn default() -> Self {
+        Self {
+            vertices: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+}
+
+impl<N: Float> Roadmap<N> {
+    /// Add a vertex and return its index
+    pub fn add_vertex(&mut self, q: &[N]) -> usize {
+        self.vertices.push(q.to_vec());
+        self.vertices.len() - 1
+    }
+
+    /// Add an edge between two vertices
+    pub fn add_edge(&mut self, a: usize, b: usize) {
+        assert!(a < self.vertices.len() && b < self.vertices.len());
+        self.edges.push((a, b));
+    }
+
+    /// Indices of the vertices connected to each vertex
+    pub fn adjacency(&self) -> Vec<Vec<usize>> {
+        let mut adjacency = vec![Vec::new(); self.vertices.len()];
+        for &(a, b) in &self.edges {
+            adjacency[a].push(b);
+            adjacency[b].push(a);
+        }
+        adjacency
+    }
+
+    /// Indices of the vertices of the shortest path from `from` to `to`, `None` if not connected
+    pub fn shortest_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let adjacency = self.adjacency();
+        let mut costs = vec![N::infinity(); self.vertices.len()];
+        let mut previous = vec![None; self.vertices.len()];
+        let mut heap = BinaryHeap::new();
+        costs[from] = N::zero();
+        heap.push(Candidate {
+            cost: N::zero(),
+            index: from,
+        });
+        while let Some(Candidate { cost, index }) = heap.pop() {
+            if index == to {
+                let mut path = vec![to];
+                while let Some(p) = previous[*path.last().unwrap()] {
+                    path.push(p);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if cost > costs[index] {
+                continue;
+            }
+            for &next in &adjacency[index] {
+                let next_cost =
+                    cost + squared_euclidean(&self.vertices[index], &self.vertices[next]).sqrt();
+                if next_cost < costs[next] {
+                    costs[next] = next_cost;
+                    previous[next] = Some(index);
+                    heap.push(Candidate {
+                        cost: next_cost,
+                        index: next,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Write the roadmap built for the environment of `environment_hash`
+    pub fn write<W: Write>(
+        &self,
+        mut writer: W,
+        environment_hash: u64,
+    ) -> Result<(), RoadmapError> {
+        let dim = self.vertices.first().map(|v| v.len()).unwrap_or(0);
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        buf.extend_from_slice(&environment_hash.to_le_bytes());
+        buf.extend_from_slice(&(dim as u64).to_le_bytes());
+        buf.extend_from_slice(&(self.vertices.len() as u64).to_le_bytes());
+        for v in &self.vertices {
+            assert_eq!(v.len(), dim);
+            for x in v {
+                buf.extend_from_slice(&x.to_f64().unwrap().to_le_bytes());
+            }
+        }
+        buf.extend_from_slice(&(self.edges.len() as u64).to_le_bytes());
+        for (a, b) in &self.edges {
+            buf.extend_from_slice(&(*a as u64).to_le_bytes());
+            buf.extend_from_slice(&(*b as u64).to_le_bytes());
+        }
+        let checksum = fnv1a(&buf);
+        buf.extend_from_slice(&checksum.to_le_bytes());
+        writer.write_all(&buf).map_err(RoadmapError::Io)
+    }
+
+    /// Read a roadmap, which must have been built for the environment of `environment_hash`
+    pub fn read<R: Read>(mut reader: R, environment_hash: u64) -> Result<Self, RoadmapError> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).map_err(RoadmapError::Io)?;
+        if buf.len() < MAGIC.len() || &buf[..MAGIC.len()] != MAGIC {
+            return Err(RoadmapError::InvalidMagic);
+        }
+        if buf.len() < MAGIC.len() + 4 + 8 {
+            return Err(RoadmapError::Corrupted);
+        }
+        let (payload, checksum) = buf.split_at(buf.len() - 8);
+        if fnv1a(payload) != u64::from_le_bytes(checksum.try_into().unwrap()) {
+            return Err(RoadmapError::Corrupted);
+        }
+        let mut cursor = Cursor {
+            data: payload,
+            pos: MAGIC.len(),
+        };
+        let version = u32::from_le_bytes(cursor.take()?);
+        if version != FORMAT_VERSION {
+            return Err(RoadmapError::UnsupportedVersion(version));
+        }
+        let found = cursor.u64()?;
+        if found != environment_hash {
+            return Err(RoadmapError::EnvironmentMismatch {
+                expected: environment_hash,
+                found,
+            });
+        }
+        let dim = cursor.u64()? as usize;
+        let num_vertices = cursor.u64()? as usize;
+        let mut roadmap = Roadmap::default();
+        for _ in 0..num_vertices {
+            let v = (0..dim)
+                .map(|_| Ok(N::from(f64::from_le_bytes(cursor.take()?)).unwrap()))
+                .collect::<Result<Vec<_>, RoadmapError>>()?;
+            roadmap.vertices.push(v);
+        }
+        let num_edges = cursor.u64()? as usize;
+        for _ in 0..num_edges {
+            let a = cursor.u64()? as usize;
+            let b = cursor.u64()? as usize;
+            if a >= num_vertices || b >= num_vertices {
+                return Err(RoadmapError::Corrupted);
+            }
+            roadmap.edges.push((a, b));
+        }
+        Ok(roadmap)
+    }
+
+    /// Save the roadmap to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P, environment_hash: u64) -> Result<(), RoadmapError> {
+        let file = std::fs::File::create(path).map_err(RoadmapError::Io)?;
+        self.write(std::io::BufWriter::new(file), environment_hash)
+    }
+
+    /// Load a roadmap from a file
+    pub fn load<P: AsRef<Path>>(path: P, environment_hash: u64) -> Result<Self, RoadmapError> {
+        let file = std::fs::File::open(path).map_err(RoadmapError::Io)?;
+        Self::read(std::io::BufReader::new(file), environment_hash)
+    }
+}
+
+/// Start and goal of a query
+pub type Query<N> = (Vec<N>, Vec<N>);
+
+// path of a query, and true if it was found without the roadmap
+type Answer<N> = Result<(Vec<Vec<N>>, bool), Error<N>>;
+
+impl<N: Float + Debug> Roadmap<N> {
+    // Vertices within `radius` of `q` with free edges, and their distances
+    fn connect<FF>(&self, q: &[N], radius: N, resolution: N, is_free: &mut FF) -> Vec<(usize, N)>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        self.vertices
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i, squared_euclidean(q, v).sqrt()))
+            .filter(|&(i, d)| {
+                d <= radius && is_edge_free(q, &self.vertices[i], resolution, is_free)
+            })
+            .collect()
+    }
+
+    /// Shortest path from `start` to `goal` through the roadmap. Both are
+    /// connected to the vertices within `connection_radius` whose edges are
+    /// free (checked every `validity_resolution`).
+    pub fn query<FF>(
+        &self,
+        start: &[N],
+        goal: &[N],
+        mut is_free: FF,
+        connection_radius: N,
+        validity_resolution: N,
+    ) -> Option<Vec<Vec<N>>>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let sources = self.connect(start, connection_radius, validity_resolution, &mut is_free);
+        if sources.is_empty() {
+            return None;
+        }
+        let targets = self.connect(goal, connection_radius, validity_resolution, &mut is_free);
+        let adjacency = self.adjacency();
+        let mut costs = vec![N::infinity(); self.vertices.len()];
+        let mut previous = vec![None; self.vertices.len()];
+        let mut heap = BinaryHeap::new();
+        for &(index, cost) in &sources {
+            costs[index] = cost;
+            heap.push(Candidate { cost, index });
+        }
+        while let Some(Candidate { cost, index }) = heap.pop() {
+            if cost > costs[index] {
+                continue;
+            }
+            for &next in &adjacency[index] {
+                let next_cost =
+                    cost + squared_euclidean(&self.vertices[index], &self.vertices[next]).sqrt();
+                if next_cost < costs[next] {
+                    costs[next] = next_cost;
+                    previous[next] = Some(index);
+                    heap.push(Candidate {
+                        cost: next_cost,
+                        index: next,
+                    });
+                }
+            }
+        }
+        let (last, _) = targets
+            .into_iter()
+            .map(|(i, d)| (i, costs[i] + d))
+            .filter(|(_, cost)| cost.is_finite())
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))?;
+        let mut path = vec![goal.to_vec(), self.vertices[last].clone()];
+        let mut index = last;
+        while let Some(p) = previous[index] {
+            path.push(self.vertices[p].clone());
+            index = p;
+        }
+        path.push(start.to_vec());
+        path.reverse();
+        path.dedup();
+        Some(path)
+    }
+
+    /// Add a free path, connecting its points to the vertices within
+    /// `connection_radius` whose edges are free
+    pub fn insert_path<FF>(
+        &mut self,
+        path: &[Vec<N>],
+        mut is_free: FF,
+        connection_radius: N,
+        validity_resolution: N,
+    ) where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let mut previous = None;
+        for q in path {
+            let neighbours = self.connect(q, connection_radius, validity_resolution, &mut is_free);
+            let index = self.add_vertex(q);
+            for (neighbour, _) in neighbours {
+                if Some(neighbour) != previous {
+                    self.add_edge(neighbour, index);
+                }
+            }
+            if let Some(previous) = previous {
+                self.add_edge(previous, index);
+            }
+            previous = Some(index);
+        }
+    }
+
+    /// Answer many queries (e.g. candidate grasps or placements of a task
+    /// planner) at once.
+    ///
+    /// Each query is answered through the roadmap if possible (see
+    /// [`Roadmap::query`]), otherwise by [`dual_rrt_connect`], whose paths
+    /// are added to the roadmap after the batch so the later queries can
+    /// reuse them. The results are in the order of `queries`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn plan_batch<FF, FR>(
+        &mut self,
+        queries: &[Query<N>],
+        mut is_free: FF,
+        random_sample: FR,
+        extend_length: N,
+        connection_radius: N,
+        num_max_try: usize,
+    ) -> Vec<Result<Vec<Vec<N>>, Error<N>>>
+    where
+        FF: FnMut(&[N]) -> bool,
+        FR: Fn() -> Vec<N>,
+    {
+        let answers = queries
+            .iter()
+            .map(|(start, goal)| {
+                self.answer(
+                    start,
+                    goal,
+                    &mut is_free,
+                    &random_sample,
+                    extend_length,
+                    connection_radius,
+                    num_max_try,
+                )
+            })
+            .collect::<Vec<_>>();
+        self.insert_answers(answers, is_free, extend_length, connection_radius)
+    }
+
+    /// Same as [`Roadmap::plan_batch`], but the queries are answered in
+    /// parallel using rayon.
+    #[cfg(feature = "rayon")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn plan_batch_parallel<FF, FR>(
+        &mut self,
+        queries: &[Query<N>],
+        is_free: FF,
+        random_sample: FR,
+        extend_length: N,
+        connection_radius: N,
+        num_max_try: usize,
+    ) -> Vec<Result<Vec<Vec<N>>, Error<N>>>
+    where
+        FF: Fn(&[N]) -> bool + Sync,
+        FR: Fn() -> Vec<N> + Sync,
+        N: Send + Sync,
+    {
+        use rayon::prelude::*;
+        let answers = queries
+            .par_iter()
+            .map(|(start, goal)| {
+                self.answer(
+                    start,
+                    goal,
+                    &mut |q: &[N]| is_free(q),
+                    &random_sample,
+                    extend_length,
+                    connection_radius,
+                    num_max_try,
+                )
+            })
+            .collect::<Vec<_>>();
+        self.insert_answers(answers, is_free, extend_length, connection_radius)
+    }
+
+    /// Same as [`Roadmap::plan_batch_parallel`], but the samples of the
+    /// query `i` are drawn from `seeds.rng(i)`, so the results and the
+    /// roadmap are reproducible whatever the number of threads.
+    #[cfg(feature = "rayon")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn plan_batch_parallel_seeded<FF, FR>(
+        &mut self,
+        queries: &[Query<N>],
+        is_free: FF,
+        random_sample: FR,
+        extend_length: N,
+        connection_radius: N,
+        num_max_try: usize,
+        seeds: SeedSequence,
+    ) -> Vec<Result<Vec<Vec<N>>, Error<N>>>
+    where
+        FF: Fn(&[N]) -> bool + Sync,
+        FR: Fn(&mut dyn RngCore) -> Vec<N> + Sync,
+        N: Send + Sync,
+    {
+        use rayon::prelude::*;
+        use std::cell::RefCell;
+        let answers = queries
+            .par_iter()
+            .enumerate()
+            .map(|(i, (start, goal))| {
+                let rng = RefCell::new(seeds.rng(i as u64));
+                self.answer(
+                    start,
+                    goal,
+                    &mut |q: &[N]| is_free(q),
+                    &|| random_sample(&mut *rng.borrow_mut()),
+                    extend_length,
+                    connection_radius,
+                    num_max_try,
+                )
+            })
+            .collect::<Vec<_>>();
+        self.insert_answers(answers, is_free, extend_length, connection_radius)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn answer<FF, FR>(
+        &self,
+        start: &[N],
+        goal: &[N],
+        is_free: &mut FF,
+        random_sample: &FR,
+        extend_length: N,
+        connection_radius: N,
+        num_max_try: usize,
+    ) -> Answer<N>
+    where
+        FF: FnMut(&[N]) -> bool,
+        FR: Fn() -> Vec<N>,
+    {
+        if let Some(path) = self.query(start, goal, &mut *is_free, connection_radius, extend_length)
+        {
+            return Ok((path, false));
+        }
+        dual_rrt_connect(
+            start,
+            goal,
+            is_free,
+            random_sample,
+            extend_length,
+            num_max_try,
+        )
+        .map(|path| (path, true))
+    }
+
+    fn insert_answers<FF>(
+        &mut self,
+        answers: Vec<Answer<N>>,
+        mut is_free: FF,
+        extend_length: N,
+        connection_radius: N,
+    ) -> Vec<Result<Vec<Vec<N>>, Error<N>>>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        answers
+            .into_iter()
+            .map(|answer| {
+                let (path, is_new) = answer?;
+                if is_new {
+                    self.insert_path(&path, &mut is_free, connection_radius, extend_length);
+                }
+                Ok(path)
+            })
+            .collect()
+    }
+}
+
+struct Candidate<N> {
+    cost: N,
+    index: usize,
+}
+
+impl<N: Float> PartialEq for Candidate<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl<N: Float> Eq for Candidate<N> {}
+
+impl<N: Float> PartialOrd for Candidate<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N: Float> Ord for Candidate<N> {
+    // reversed to make BinaryHeap a min-heap of the cost
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Cursor<'_> {
+    fn take<const L: usize>(&mut self) -> Result<[u8; L], RoadmapError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + L)
+            .ok_or(RoadmapError::Corrupted)?;
+        self.pos += L;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> Result<u64, RoadmapError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Hash of the obstacles of the scene, to be used as the environment hash
+pub fn scene_hash<N: Float>(scene: &Scene<N>) -> u64 {
+    let mut buf = Vec::new();
+    let mut push = |values: &[N]| {
+        for v in values {
+            buf.extend_from_slice(&v.to_f64().unwrap().to_le_bytes());
+        }
+    };
+    for obstacle in &scene.obstacles {
+        match obstacle {
+            Obstacle::Sphere(s) => {
+                push(&s.center);
+                push(&[s.radius]);
+            }
+            Obstacle::Box(b) => {
+                push(&b.min);
+                push(&b.max);
+            }
+        }
+    }
+    fnv1a(&buf)
+}
+
+#[test]
+fn it_works() {
+    use crate::collision::Sphere;
+    let mut roadmap = Roadmap::default();
+    let a = roadmap.add_vertex(&[0.0, 0.0]);
+    let b = roadmap.add_vertex(&[1.0, 0.5]);
+    roadmap.add_edge(a, b);
+    let c = roadmap.add_vertex(&[2.0, 0.0]);
+    roadmap.add_edge(b, c);
+    roadmap.add_vertex(&[5.0, 5.0]);
+    assert_eq!(roadmap.shortest_path(a, c), Some(vec![a, b, c]));
+    assert_eq!(roadmap.shortest_path(a, 3), None);
+    let free = |_: &[f64]| true;
+    assert_eq!(
+        roadmap.query(&[0.0, -0.5], &[2.0, 0.5], free, 0.6, 0.1),
+        Some(vec![
+            vec![0.0, -0.5],
+            vec![0.0, 0.0],
+            vec![1.0, 0.5],
+            vec![2.0, 0.0],
+            vec![2.0, 0.5],
+        ])
+    );
+    assert_eq!(
+        roadmap.query(&[0.0, 0.0], &[5.0, 4.0], free, 0.6, 0.1),
+        None
+    );
+    let scene = Scene {
+        obstacles: vec![Obstacle::Sphere(Sphere {
+            center: vec![0.5, 2.0],
+            radius: 0.5,
+        })],
+    };
+    let hash = scene_hash(&scene);
+    assert_ne!(hash, scene_hash(&Scene::<f64>::default()));
+
+    let mut data = Vec::new();
+    roadmap.write(&mut data, hash).unwrap();
+    assert_eq!(Roadmap::<f64>::read(&data[..], hash).unwrap(), roadmap);
+    assert!(matches!(
+        Roadmap::<f64>::read(&data[..], hash + 1),
+        Err(RoadmapError::EnvironmentMismatch { .. })
+    ));
+    data[20] ^= 1;
+    assert!(matches!(
+        Roadmap::<f64>::read(&data[..], hash),
+        Err(RoadmapError::Corrupted)
+    ));
+    assert!(matches!(
+        Roadmap::<f64>::read(&b"roadmap"[..], hash),
+        Err(RoadmapError::InvalidMagic)
+    ));
+
+    // queries around an obstacle, the second batch reuses the first paths
+    use rand::distributions::{Distribution, Uniform};
+    let mut is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let queries = (0..4)
+        .map(|i| {
+            let y = -0.6 + 0.4 * i as f64;
+            (vec![-1.5, y], vec![1.5, -y])
+        })
+        .collect::<Vec<_>>();
+    let mut roadmap = Roadmap::default();
+    for result in roadmap.plan_batch(&queries, is_free, sample, 0.2, 0.5, 1000) {
+        let path = result.unwrap();
+        for w in path.windows(2) {
+            assert!(crate::rrt::is_edge_free(&w[0], &w[1], 0.2, &mut is_free));
+        }
+    }
+    let num_vertices = roadmap.vertices.len();
+    assert!(num_vertices > 0);
+    let results = roadmap.plan_batch(&queries, is_free, || unreachable!(), 0.2, 0.5, 1000);
+    for ((start, goal), result) in queries.iter().zip(results) {
+        let path = result.unwrap();
+        assert_eq!(&path[0], start);
+        assert_eq!(path.last().unwrap(), goal);
+    }
+    assert_eq!(roadmap.vertices.len(), num_vertices);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_works() {
+    let queries = vec![
+        (vec![-1.5, 0.0], vec![1.5, 0.0]),
+        (vec![-1.5, 0.5], vec![1.5, -0.5]),
+    ];
+    let mut roadmap = Roadmap::default();
+    let results = roadmap.plan_batch_parallel(
+        &queries,
+        |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0),
+        || {
+            use rand::Rng;
+            let mut rng = rand::thread_rng();
+            vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+        },
+        0.2,
+        0.5,
+        1000,
+    );
+    assert!(results.iter().all(|r| r.is_ok()));
+    assert!(!roadmap.vertices.is_empty());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_seeded_works() {
+    use rand::Rng;
+    let queries = (0..8)
+        .map(|i| (vec![-1.5, i as f64 * 0.1], vec![1.5, -(i as f64) * 0.1]))
+        .collect::<Vec<_>>();
+    let plan = |num_threads| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .unwrap();
+        let mut roadmap = Roadmap::default();
+        let results = pool.install(|| {
+            roadmap.plan_batch_parallel_seeded(
+                &queries,
+                |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0),
+                |rng: &mut dyn RngCore| vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)],
+                0.2,
+                0.5,
+                1000,
+                SeedSequence::new(7),
+            )
+        });
+        (results, roadmap.vertices)
+    };
+    let (results, vertices) = plan(1);
+    assert!(results.iter().all(|r| r.is_ok()));
+    assert_eq!(plan(4), (results, vertices));
+}
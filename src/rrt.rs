@@ -17,93 +17,411 @@
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
 
+use crate::flat::FlatPoints;
+use crate::goal::GoalRegion;
+use crate::nearest::ApproximateKdTree;
+use crate::partial::{OnFailure, SerializedTree};
+use crate::steer::steer;
 use kdtree::distance::squared_euclidean;
 use num_traits::float::Float;
 use num_traits::identities::Zero;
 use rand::distributions::{Distribution, Uniform};
+use rand::{Rng, RngCore};
 use std::fmt::Debug;
+use std::iter;
 use std::mem;
+use std::sync::OnceLock;
+use std::time::Duration;
 use tracing::debug;
 
-#[derive(Debug)]
-enum ExtendStatus {
+/// Error of the planners
+#[derive(Debug, Clone, PartialEq, derive_more::Error, derive_more::Display)]
+pub enum Error<N> {
+    /// The start configuration is not free
+    #[display(fmt = "the start is in collision")]
+    StartInCollision,
+    /// The goal configuration is not free
+    #[display(fmt = "the goal is in collision")]
+    GoalInCollision,
+    /// The start and the goal have different dimensions
+    #[display(fmt = "the start has {start} dimensions but the goal has {goal}")]
+    DimensionMismatch {
+        /// Dimension of the start
+        start: usize,
+        /// Dimension of the goal
+        goal: usize,
+    },
+    /// No path was found within the tries or the time budget
+    #[display(fmt = "failed to find a path within the maximum number of iterations")]
+    MaxIterationsReached {
+        /// Path from the start to the vertex of its tree nearest to the goal
+        best_partial: Option<Vec<Vec<N>>>,
+    },
+    /// No path was found within the tries, with the trees of the search
+    /// ([`OnFailure::BestEffortTree`])
+    #[display(fmt = "failed to find a path within the maximum number of iterations")]
+    Unsolved {
+        /// Trees of the search, the one of the start first
+        trees: Vec<SerializedTree<N>>,
+    },
+    /// No path was found before the deadline of an anytime planner
+    #[display(fmt = "failed to find a path before the deadline")]
+    DeadlineReached,
+    /// There is no path through the lattice of the resolution (see
+    /// [`crate::complete`])
+    #[display(fmt = "no path exists at the resolution of the lattice")]
+    NoPathAtResolution {
+        /// Spacing of the lattice
+        resolution: N,
+    },
+    /// The planner is not configured correctly
+    #[display(fmt = "invalid configuration: {_0}")]
+    InvalidConfiguration(#[error(not(source))] &'static str),
+}
+
+/// Result of the planners
+pub type RRTResult<N> = Result<Vec<Vec<N>>, Error<N>>;
+
+/// Result of [`Tree::extend`] and [`Tree::connect`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtendStatus {
+    /// The target was added (or already was) as the vertex
     Reached(usize),
+    /// The vertex was added toward the target
     Advanced(usize),
+    /// The extension was blocked
     Trapped,
 }
 
 /// Node that contains user data
 #[derive(Debug, Clone)]
-struct Node<T> {
-    parent_index: Option<usize>,
-    data: T,
+pub struct Node<T> {
+    /// Index of the parent node, `None` for the root
+    pub parent_index: Option<usize>,
+    /// User data
+    pub data: T,
 }
 
-impl<T> Node<T> {
-    fn new(data: T) -> Self {
-        Node {
-            parent_index: None,
-            data,
-        }
+/// Which tree of the bidirectional search a vertex belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TreeRole {
+    /// Tree rooted at the start
+    Start,
+    /// Tree rooted at the goal
+    Goal,
+}
+
+/// Waypoint of a path with the tree vertex which it comes from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waypoint<N> {
+    /// Configuration
+    pub position: Vec<N>,
+    /// Tree of the vertex
+    pub tree: TreeRole,
+    /// Index of the vertex in its tree
+    pub vertex: usize,
+}
+
+/// What to do when the target of an extension coincides with the nearest vertex
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CoincidentSample<N> {
+    /// The nearest vertex is treated as reached, no vertex is added
+    #[default]
+    Reached,
+    /// The extension is trapped. Note that this also prevents connecting
+    /// the trees when a vertex coincides with the target of the connection.
+    Skip,
+    /// The target is moved randomly by up to the given distance in each dimension
+    Jitter(N),
+}
+
+/// What to do when an extension steps outside of the [`Bounds`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutOfBounds {
+    /// The extension is trapped
+    #[default]
+    Reject,
+    /// The new configuration is clamped into the bounds
+    Clamp,
+}
+
+/// Axis aligned bounds of the state space
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bounds<N> {
+    /// Lower bounds
+    pub lower: Vec<N>,
+    /// Upper bounds
+    pub upper: Vec<N>,
+    /// What to do with the configurations outside of the bounds
+    pub policy: OutOfBounds,
+}
+
+impl<N: Float> Bounds<N> {
+    /// Returns true if `q` is within the bounds (inclusive)
+    pub fn contains(&self, q: &[N]) -> bool {
+        q.iter()
+            .zip(self.lower.iter().zip(&self.upper))
+            .all(|(v, (lower, upper))| lower <= v && v <= upper)
     }
+
+    /// The nearest configuration to `q` within the bounds
+    pub fn clamp(&self, q: &[N]) -> Vec<N> {
+        q.iter()
+            .zip(self.lower.iter().zip(&self.upper))
+            .map(|(v, (lower, upper))| v.max(*lower).min(*upper))
+            .collect()
+    }
+}
+
+/// Nearest neighbour search structure
+#[derive(Debug)]
+enum NearestNeighbours<N>
+where
+    N: Float + Zero + Debug,
+{
+    Exact(kdtree::KdTree<N, usize, Vec<N>>),
+    Approximate(ApproximateKdTree<N>),
 }
 
-/// RRT
+/// RRT, which can be grown and inspected by the users
 #[derive(Debug)]
-struct Tree<N>
+pub struct Tree<N>
 where
     N: Float + Zero + Debug,
 {
-    kdtree: kdtree::KdTree<N, usize, Vec<N>>,
-    vertices: Vec<Node<Vec<N>>>,
+    kdtree: NearestNeighbours<N>,
+    // coordinates of the vertices, and their parents
+    points: FlatPoints<N>,
+    parents: Vec<Option<usize>>,
     name: &'static str,
+    on_coincident: CoincidentSample<N>,
+    bounds: Option<Bounds<N>>,
+    // length of the path from the root, and the number of children
+    costs: Vec<N>,
+    num_children: Vec<usize>,
 }
 
 impl<N> Tree<N>
 where
     N: Float + Zero + Debug,
 {
-    fn new(name: &'static str, dim: usize) -> Self {
+    /// Create a tree which has only `root` (vertex 0)
+    pub fn new(root: &[N]) -> Self {
+        let mut tree = Self::with_options("start", root.len(), None, CoincidentSample::Reached, 0);
+        tree.add_vertex(root);
+        tree
+    }
+    fn with_options(
+        name: &'static str,
+        dim: usize,
+        max_nearest_checks: Option<usize>,
+        on_coincident: CoincidentSample<N>,
+        capacity: usize,
+    ) -> Self {
         Tree {
-            kdtree: kdtree::KdTree::new(dim),
-            vertices: Vec::new(),
+            kdtree: match max_nearest_checks {
+                Some(max_checks) => NearestNeighbours::Approximate(
+                    ApproximateKdTree::with_capacity(dim, max_checks, capacity),
+                ),
+                None => NearestNeighbours::Exact(kdtree::KdTree::new(dim)),
+            },
+            points: FlatPoints::with_capacity(dim, capacity),
+            parents: Vec::with_capacity(capacity),
             name,
+            on_coincident,
+            bounds: None,
+            costs: Vec::with_capacity(capacity),
+            num_children: Vec::with_capacity(capacity),
         }
     }
-    fn add_vertex(&mut self, q: &[N]) -> usize {
-        let index = self.vertices.len();
-        self.kdtree.add(q.to_vec(), index).unwrap();
-        self.vertices.push(Node::new(q.to_vec()));
+    /// Add `q` without parent, and return its index.
+    /// [`Tree::add_edge`] must be called to attach it to the tree.
+    pub fn add_vertex(&mut self, q: &[N]) -> usize {
+        let index = self.len();
+        match &mut self.kdtree {
+            NearestNeighbours::Exact(kdtree) => kdtree.add(q.to_vec(), index).unwrap(),
+            NearestNeighbours::Approximate(kdtree) => kdtree.add(q, index),
+        }
+        self.points.push(q);
+        self.parents.push(None);
+        self.costs.push(N::zero());
+        self.num_children.push(0);
         index
     }
-    fn add_edge(&mut self, q1_index: usize, q2_index: usize) {
-        self.vertices[q2_index].parent_index = Some(q1_index);
+    /// Make `q1_index` the parent of `q2_index`, which must not have a parent.
+    /// The edge is not collision checked.
+    pub fn add_edge(&mut self, q1_index: usize, q2_index: usize) {
+        assert!(self.parents[q2_index].is_none());
+        self.parents[q2_index] = Some(q1_index);
+        self.costs[q2_index] = self.costs[q1_index]
+            + squared_euclidean(self.vertex(q1_index), self.vertex(q2_index)).sqrt();
+        self.num_children[q1_index] += 1;
+    }
+    /// Number of vertices
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+    /// Returns true if there is no vertex
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+    /// Node of the vertex, whose data is a view of the coordinates
+    pub fn node(&self, index: usize) -> Node<&[N]> {
+        Node {
+            parent_index: self.parents[index],
+            data: self.vertex(index),
+        }
+    }
+    /// Configuration of the vertex
+    pub fn vertex(&self, index: usize) -> &[N] {
+        &self.points[index]
     }
-    fn get_nearest_index(&self, q: &[N]) -> usize {
-        *self.kdtree.nearest(q, 1, &squared_euclidean).unwrap()[0].1
+    /// Configurations of all the vertices, in the order of their indices
+    pub fn vertices(&self) -> &FlatPoints<N> {
+        &self.points
     }
-    fn extend<FF>(&mut self, q_target: &[N], extend_length: N, is_free: &mut FF) -> ExtendStatus
+    /// Length of the path from the root to the vertex
+    pub fn cost(&self, index: usize) -> N {
+        self.costs[index]
+    }
+    /// Number of children of the vertex
+    pub fn num_children(&self, index: usize) -> usize {
+        self.num_children[index]
+    }
+    /// Edges as the pairs of the parent and the child
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.parents
+            .iter()
+            .enumerate()
+            .filter_map(|(i, parent)| parent.map(|parent| (parent, i)))
+    }
+    /// Path from the root to the vertex
+    pub fn path(&self, index: usize) -> Vec<Vec<N>> {
+        let mut path = self.get_until_root(index);
+        path.reverse();
+        path.push(self.vertex(index).to_vec());
+        path
+    }
+    /// Same as [`Tree::path`], in a single flat buffer
+    pub fn flat_path(&self, index: usize) -> FlatPoints<N> {
+        let mut path = FlatPoints::new(self.points.dim());
+        let mut cur_index = Some(index);
+        while let Some(index) = cur_index {
+            path.push(self.vertex(index));
+            cur_index = self.parents[index];
+        }
+        path.reverse();
+        path
+    }
+    /// Vertex nearest to `q`
+    pub fn nearest(&self, q: &[N]) -> usize {
+        match &self.kdtree {
+            NearestNeighbours::Exact(kdtree) => {
+                *kdtree.nearest(q, 1, &squared_euclidean).unwrap()[0].1
+            }
+            NearestNeighbours::Approximate(kdtree) => kdtree.nearest(q).unwrap(),
+        }
+    }
+    /// Closest vertices of this tree and `other` as `(index, other_index,
+    /// distance)`, searched from each vertex of the smaller tree in the
+    /// kd-tree of the larger one
+    pub fn closest_pair(&self, other: &Tree<N>) -> (usize, usize, N) {
+        if self.len() > other.len() {
+            let (other_index, index, distance) = other.closest_pair(self);
+            return (index, other_index, distance);
+        }
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(index, vertex)| {
+                let other_index = other.nearest(vertex);
+                let distance = squared_euclidean(vertex, other.vertex(other_index));
+                (index, other_index, distance.sqrt())
+            })
+            .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap())
+            .expect("trees must not be empty")
+    }
+    /// Add a vertex from the nearest one toward `q_target`, at most
+    /// `extend_length` away, if the edge is free (checked every
+    /// `validity_resolution`). `rng` jitters the samples which coincide with
+    /// a vertex, see [`CoincidentSample::Jitter`].
+    pub fn extend<FF>(
+        &mut self,
+        q_target: &[N],
+        extend_length: N,
+        validity_resolution: N,
+        is_free: &mut FF,
+        rng: &mut dyn RngCore,
+    ) -> ExtendStatus
     where
         FF: FnMut(&[N]) -> bool,
+    {
+        let nearest_index = self.nearest(q_target);
+        self.extend_from(
+            nearest_index,
+            q_target,
+            extend_length,
+            &mut |from, to| is_edge_free(from, to, validity_resolution, is_free),
+            rng,
+        )
+    }
+    fn extend_from<FM>(
+        &mut self,
+        nearest_index: usize,
+        q_target: &[N],
+        extend_length: N,
+        is_motion_free: &mut FM,
+        rng: &mut dyn RngCore,
+    ) -> ExtendStatus
+    where
+        FM: FnMut(&[N], &[N]) -> bool,
     {
         assert!(extend_length > N::zero());
-        let nearest_index = self.get_nearest_index(q_target);
-        let nearest_q = &self.vertices[nearest_index].data;
-        let diff_dist = squared_euclidean(q_target, nearest_q).sqrt();
-        let q_new = if diff_dist < extend_length {
-            q_target.to_vec()
-        } else {
-            nearest_q
-                .iter()
-                .zip(q_target)
-                .map(|(near, target)| *near + (*target - *near) * extend_length / diff_dist)
-                .collect::<Vec<_>>()
-        };
+        let nearest_q = self.vertex(nearest_index);
+        if nearest_q == q_target {
+            match self.on_coincident {
+                CoincidentSample::Reached => return ExtendStatus::Reached(nearest_index),
+                CoincidentSample::Skip => return ExtendStatus::Trapped,
+                CoincidentSample::Jitter(radius) => {
+                    let jittered = q_target
+                        .iter()
+                        .map(|v| *v + radius * N::from(rng.gen_range(-1.0..=1.0)).unwrap())
+                        .collect::<Vec<_>>();
+                    if jittered.as_slice() == q_target {
+                        return ExtendStatus::Trapped;
+                    }
+                    return self.extend_from(
+                        nearest_index,
+                        &jittered,
+                        extend_length,
+                        is_motion_free,
+                        rng,
+                    );
+                }
+            }
+        }
+        let (mut q_new, mut reached) = steer(nearest_q, q_target, extend_length);
+        if let Some(bounds) = &self.bounds {
+            if !bounds.contains(&q_new) {
+                match bounds.policy {
+                    OutOfBounds::Reject => return ExtendStatus::Trapped,
+                    OutOfBounds::Clamp => {
+                        q_new = bounds.clamp(&q_new);
+                        if q_new == nearest_q {
+                            return ExtendStatus::Trapped;
+                        }
+                        reached = q_new == q_target;
+                    }
+                }
+            }
+        }
         debug!("q_new={q_new:?}");
-        if is_free(&q_new) {
+        if is_motion_free(nearest_q, &q_new) {
             let new_index = self.add_vertex(&q_new);
             self.add_edge(nearest_index, new_index);
-            if squared_euclidean(&q_new, q_target).sqrt() < extend_length {
+            // Reached only if the target itself was added, the remaining
+            // segment is validated by the next extend.
+            if reached {
                 return ExtendStatus::Reached(new_index);
             }
             debug!("target = {q_target:?}");
@@ -112,132 +430,1756 @@ where
         }
         ExtendStatus::Trapped
     }
-    fn connect<FF>(&mut self, q_target: &[N], extend_length: N, is_free: &mut FF) -> ExtendStatus
+    /// Extend toward `q_target` until it is reached or trapped
+    pub fn connect<FF>(
+        &mut self,
+        q_target: &[N],
+        extend_length: N,
+        validity_resolution: N,
+        is_free: &mut FF,
+        rng: &mut dyn RngCore,
+    ) -> ExtendStatus
     where
         FF: FnMut(&[N]) -> bool,
     {
-        loop {
+        self.connect_with(
+            q_target,
+            extend_length,
+            &mut |from, to| is_edge_free(from, to, validity_resolution, is_free),
+            rng,
+        )
+        .0
+    }
+    /// Returns the status and the last vertex extended from, which is the
+    /// closest one to `q_target` unless it is reached.
+    fn connect_with<FM>(
+        &mut self,
+        q_target: &[N],
+        extend_length: N,
+        is_motion_free: &mut FM,
+        rng: &mut dyn RngCore,
+    ) -> (ExtendStatus, usize)
+    where
+        FM: FnMut(&[N], &[N]) -> bool,
+    {
+        let mut last_index = self.nearest(q_target);
+        let mut status = self.extend_from(last_index, q_target, extend_length, is_motion_free, rng);
+        // The last added vertex is the nearest to the target, so there is no
+        // need to search it again (and the approximate search might miss it).
+        while let ExtendStatus::Advanced(index) = status {
             debug!("connecting...{q_target:?}");
-            match self.extend(q_target, extend_length, is_free) {
-                ExtendStatus::Trapped => return ExtendStatus::Trapped,
-                ExtendStatus::Reached(index) => return ExtendStatus::Reached(index),
-                ExtendStatus::Advanced(_) => {}
-            };
+            last_index = index;
+            status = self.extend_from(index, q_target, extend_length, is_motion_free, rng);
+        }
+        (status, last_index)
+    }
+    /// Remove the vertices which are no longer free, or whose edge from the
+    /// parent is blocked (checked every `validity_resolution`), with all
+    /// their descendants, e.g. after the world has changed. The root is kept
+    /// as it is an end of the path, the other vertices without parent only
+    /// if they are free.
+    ///
+    /// Returns the new index of each vertex, `None` if it was removed.
+    pub fn prune<FF>(&mut self, validity_resolution: N, is_free: &mut FF) -> Vec<Option<usize>>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let max_nearest_checks = match &self.kdtree {
+            NearestNeighbours::Exact(_) => None,
+            NearestNeighbours::Approximate(kdtree) => Some(kdtree.max_checks()),
+        };
+        let mut pruned = Self::with_options(
+            self.name,
+            self.points.dim(),
+            max_nearest_checks,
+            self.on_coincident,
+            self.len(),
+        );
+        pruned.bounds = self.bounds.take();
+        let mut children = vec![Vec::new(); self.len()];
+        for (parent, child) in self.edges() {
+            children[parent].push(child);
+        }
+        // the parents are added before their children, whatever the order of
+        // the indices is (the vertices on a cycle are never reached)
+        let mut new_indices = vec![None; self.len()];
+        for root in (0..self.len()).filter(|&index| self.parents[index].is_none()) {
+            let q = self.vertex(root);
+            if root != 0 && !is_free(q) {
+                continue;
+            }
+            new_indices[root] = Some(pruned.add_vertex(q));
+            let mut stack = children[root].clone();
+            while let Some(index) = stack.pop() {
+                let parent = self.parents[index].unwrap();
+                let q = self.vertex(index);
+                if is_edge_free(self.vertex(parent), q, validity_resolution, is_free) {
+                    let new_index = pruned.add_vertex(q);
+                    pruned.add_edge(new_indices[parent].unwrap(), new_index);
+                    new_indices[index] = Some(new_index);
+                    stack.extend_from_slice(&children[index]);
+                }
+            }
+        }
+        *self = pruned;
+        new_indices
+    }
+    /// Vertices and parents of the tree, as plain data
+    pub(crate) fn serialized(&self) -> SerializedTree<N> {
+        SerializedTree {
+            vertices: self.points.to_vecs(),
+            parents: self.parents.clone(),
+        }
+    }
+    /// Whether the root is the start or the goal
+    pub fn role(&self) -> TreeRole {
+        if self.name == "start" {
+            TreeRole::Start
+        } else {
+            TreeRole::Goal
         }
     }
     fn get_until_root(&self, index: usize) -> Vec<Vec<N>> {
         let mut nodes = Vec::new();
         let mut cur_index = index;
-        while let Some(parent_index) = self.vertices[cur_index].parent_index {
+        while let Some(parent_index) = self.parents[cur_index] {
             cur_index = parent_index;
-            nodes.push(self.vertices[cur_index].data.clone())
+            nodes.push(self.vertex(cur_index).to_vec())
         }
         nodes
     }
 }
 
+/// Read only view of a tree, given to [`VertexSelection`] and the callbacks
+/// of the bidirectional search
+#[derive(Debug, Clone, Copy)]
+pub struct TreeView<'a, N>
+where
+    N: Float + Zero + Debug,
+{
+    tree: &'a Tree<N>,
+}
+
+impl<'a, N> TreeView<'a, N>
+where
+    N: Float + Zero + Debug,
+{
+    /// Number of vertices
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+    /// Returns true if there is no vertex
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+    /// True if the root is the start, false if it is the goal
+    pub fn is_start_tree(&self) -> bool {
+        self.tree.role() == TreeRole::Start
+    }
+    /// Configuration of the vertex
+    pub fn vertex(&self, index: usize) -> &'a [N] {
+        self.tree.vertex(index)
+    }
+    /// Parent of the vertex, `None` for the root
+    pub fn parent(&self, index: usize) -> Option<usize> {
+        self.tree.parents[index]
+    }
+    /// Number of children of the vertex
+    pub fn num_children(&self, index: usize) -> usize {
+        self.tree.num_children[index]
+    }
+    /// Length of the path from the root to the vertex
+    pub fn cost(&self, index: usize) -> N {
+        self.tree.costs[index]
+    }
+    /// Vertex nearest to `q`
+    pub fn nearest(&self, q: &[N]) -> usize {
+        self.tree.nearest(q)
+    }
+    /// Closest vertices of this tree and `other`, see [`Tree::closest_pair`]
+    pub fn closest_pair(&self, other: &TreeView<'_, N>) -> (usize, usize, N) {
+        self.tree.closest_pair(other.tree)
+    }
+}
+
+/// Chooses the vertex which is extended toward each sample.
+///
+/// The connection of the other tree always starts from its nearest vertex.
+pub trait VertexSelection<N>
+where
+    N: Float + Zero + Debug,
+{
+    /// Returns the index of the vertex of `tree` to extend toward `q_target`,
+    /// drawing the random choices from `rng`
+    fn select(&mut self, tree: &TreeView<'_, N>, q_target: &[N], rng: &mut dyn RngCore) -> usize;
+}
+
+impl<N, S> VertexSelection<N> for &mut S
+where
+    N: Float + Zero + Debug,
+    S: VertexSelection<N> + ?Sized,
+{
+    fn select(&mut self, tree: &TreeView<'_, N>, q_target: &[N], rng: &mut dyn RngCore) -> usize {
+        (**self).select(tree, q_target, rng)
+    }
+}
+
+/// The vertex nearest to the sample (RRT)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NearestVertex;
+
+impl<N> VertexSelection<N> for NearestVertex
+where
+    N: Float + Zero + Debug,
+{
+    fn select(&mut self, tree: &TreeView<'_, N>, q_target: &[N], _rng: &mut dyn RngCore) -> usize {
+        tree.nearest(q_target)
+    }
+}
+
+/// A random vertex, with the probability inversely proportional to one plus
+/// its number of children, so the sparsely explored vertices are preferred
+/// (EST). The sample gives only the direction of the extension.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExplorationScore;
+
+impl<N> VertexSelection<N> for ExplorationScore
+where
+    N: Float + Zero + Debug,
+{
+    fn select(&mut self, tree: &TreeView<'_, N>, _q_target: &[N], rng: &mut dyn RngCore) -> usize {
+        let weight = |i: usize| 1.0 / (1.0 + tree.num_children(i) as f64);
+        let total = (0..tree.len()).map(weight).sum::<f64>();
+        let mut r = rng.gen::<f64>() * total;
+        (0..tree.len())
+            .position(|i| {
+                r -= weight(i);
+                r < 0.0
+            })
+            .unwrap_or(tree.len() - 1)
+    }
+}
+
+/// Among the vertices which are at most the given distance farther from the
+/// sample than the nearest one, the vertex with the smallest cost from the
+/// root plus the distance to the sample (like choosing the parent in RRT*).
+/// This visits all the vertices, so it is for small trees.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BestCost<N>(pub N);
+
+impl<N> VertexSelection<N> for BestCost<N>
+where
+    N: Float + Zero + Debug,
+{
+    fn select(&mut self, tree: &TreeView<'_, N>, q_target: &[N], _rng: &mut dyn RngCore) -> usize {
+        let distance = |i: usize| squared_euclidean(tree.vertex(i), q_target).sqrt();
+        let nearest = tree.nearest(q_target);
+        let max_distance = distance(nearest) + self.0;
+        (0..tree.len())
+            .filter(|&i| distance(i) <= max_distance)
+            .fold(nearest, |best, i| {
+                if tree.cost(i) + distance(i) < tree.cost(best) + distance(best) {
+                    i
+                } else {
+                    best
+                }
+            })
+    }
+}
+
+/// Returns true if all the points on the segment from `from` to `to` which
+/// are checked every `resolution` (including `to`) are free.
+/// `from` is assumed to be free.
+pub(crate) fn is_edge_free<FF, N>(from: &[N], to: &[N], resolution: N, is_free: &mut FF) -> bool
+where
+    FF: FnMut(&[N]) -> bool + ?Sized,
+    N: Float,
+{
+    assert!(resolution > N::zero());
+    let dist = squared_euclidean(from, to).sqrt();
+    let num = (dist / resolution).ceil().to_usize().unwrap_or(1).max(1);
+    let num_n = N::from(num).unwrap();
+    (1..=num).all(|i| {
+        let t = N::from(i).unwrap() / num_n;
+        let q = from
+            .iter()
+            .zip(to)
+            .map(|(a, b)| *a + (*b - *a) * t)
+            .collect::<Vec<_>>();
+        is_free(&q)
+    })
+}
+
+/// The points on the segment from `from` to `to` which [`is_edge_free`]
+/// checks, or only `to` if it is `from`.
+fn edge_points<N>(from: &[N], to: &[N], resolution: N) -> Vec<Vec<N>>
+where
+    N: Float,
+{
+    let mut points = Vec::new();
+    is_edge_free(from, to, resolution, &mut |q: &[N]| {
+        points.push(q.to_vec());
+        true
+    });
+    points
+}
+
 /// search the path from start to goal which is free, using random_sample function
+///
+/// If the straight line from start to goal is free, it is returned (split
+/// every `extend_length`) without building the trees. The other options of
+/// the search are set by [`RrtPlanner`](crate::RrtPlanner).
 pub fn dual_rrt_connect<FF, FR, N>(
     start: &[N],
     goal: &[N],
-    mut is_free: FF,
+    is_free: FF,
     random_sample: FR,
     extend_length: N,
     num_max_try: usize,
-) -> Result<Vec<Vec<N>>, String>
+) -> Result<Vec<Vec<N>>, Error<N>>
 where
     FF: FnMut(&[N]) -> bool,
     FR: Fn() -> Vec<N>,
     N: Float + Debug,
 {
-    assert_eq!(start.len(), goal.len());
-    let mut tree_a = Tree::new("start", start.len());
-    let mut tree_b = Tree::new("goal", start.len());
-    tree_a.add_vertex(start);
-    tree_b.add_vertex(goal);
-    for _ in 0..num_max_try {
-        debug!("tree_a = {:?}", tree_a.vertices.len());
-        debug!("tree_b = {:?}", tree_b.vertices.len());
-        let q_rand = random_sample();
-        let extend_status = tree_a.extend(&q_rand, extend_length, &mut is_free);
-        match extend_status {
-            ExtendStatus::Trapped => {}
-            ExtendStatus::Advanced(new_index) | ExtendStatus::Reached(new_index) => {
-                let q_new = &tree_a.vertices[new_index].data;
-                if let ExtendStatus::Reached(reach_index) =
-                    tree_b.connect(q_new, extend_length, &mut is_free)
-                {
-                    let mut a_all = tree_a.get_until_root(new_index);
-                    let mut b_all = tree_b.get_until_root(reach_index);
-                    a_all.reverse();
-                    a_all.append(&mut b_all);
-                    if tree_b.name == "start" {
-                        a_all.reverse();
-                    }
-                    return Ok(a_all);
-                }
-            }
+    let search = Search::new(extend_length, tries(num_max_try));
+    dual_rrt_connect_impl(start, goal, is_free, random_sample, search).map(|(path, _)| path)
+}
+
+/// Options of the trees of the bidirectional searches
+#[derive(Debug, Clone)]
+pub(crate) struct TreeOptions<N> {
+    /// Maximum number of vertices visited by the nearest vertex search,
+    /// `None` for the exact search
+    pub(crate) max_nearest_checks: Option<usize>,
+    pub(crate) on_coincident: CoincidentSample<N>,
+    /// Number of vertices of each tree whose memory is reserved
+    pub(crate) capacity: usize,
+    pub(crate) bounds: Option<Bounds<N>>,
+}
+
+impl<N> Default for TreeOptions<N> {
+    fn default() -> Self {
+        Self {
+            max_nearest_checks: None,
+            on_coincident: CoincidentSample::Reached,
+            capacity: 0,
+            bounds: None,
         }
-        mem::swap(&mut tree_a, &mut tree_b);
     }
-    Err("failed".to_string())
 }
 
-/// select random two points, and try to connect.
-pub fn smooth_path<FF, N>(
-    path: &mut Vec<Vec<N>>,
+impl<N> TreeOptions<N>
+where
+    N: Float + Debug,
+{
+    /// Tree which has only `root` (vertex 0)
+    fn tree(&self, name: &'static str, root: &[N]) -> Tree<N> {
+        let mut tree = self.empty_tree(name, root.len());
+        tree.add_vertex(root);
+        tree
+    }
+    fn empty_tree(&self, name: &'static str, dim: usize) -> Tree<N> {
+        let mut tree = Tree::with_options(
+            name,
+            dim,
+            self.max_nearest_checks,
+            self.on_coincident,
+            self.capacity,
+        );
+        tree.bounds.clone_from(&self.bounds);
+        tree
+    }
+}
+
+/// Options and callbacks of the searches, which are set by the setters of
+/// [`RrtPlanner`](crate::RrtPlanner)
+pub(crate) struct Search<'s, N>
+where
+    N: Float + Debug,
+{
+    pub(crate) extend_length: N,
+    /// Distance between the checked configurations of an edge
+    pub(crate) validity_resolution: N,
+    pub(crate) trees: TreeOptions<N>,
+    /// Number of tries of the next batch given the number of vertices of
+    /// the trees, zero to stop
+    pub(crate) next_batch: Box<dyn FnMut(usize) -> usize + 's>,
+    pub(crate) selection: Box<dyn VertexSelection<N> + 's>,
+    /// Called after each extension toward a sample with true if the tree was
+    /// extended, the remaining gap to the other tree, and the start tree and
+    /// the goal tree
+    #[allow(clippy::type_complexity)]
+    pub(crate) on_extend: Box<dyn FnMut(bool, N, TreeView<'_, N>, TreeView<'_, N>) + 's>,
+    /// Random numbers of the search other than the samples
+    pub(crate) rng: Box<dyn RngCore + 's>,
+    /// Result of the search when the trees are not connected
+    pub(crate) on_failure: OnFailure,
+}
+
+impl<'s, N> Search<'s, N>
+where
+    N: Float + Debug,
+{
+    /// Search with the default options, whose edges are checked at their ends
+    pub(crate) fn new<FB>(extend_length: N, next_batch: FB) -> Self
+    where
+        FB: FnMut(usize) -> usize + 's,
+    {
+        Self {
+            extend_length,
+            validity_resolution: extend_length,
+            trees: TreeOptions::default(),
+            next_batch: Box::new(next_batch),
+            selection: Box::new(NearestVertex),
+            on_extend: Box::new(|_, _, _, _| {}),
+            rng: Box::new(rand::thread_rng()),
+            on_failure: OnFailure::Error,
+        }
+    }
+}
+
+/// Returns the batches of `num_max_try` tries
+pub(crate) fn tries(num_max_try: usize) -> impl FnMut(usize) -> usize {
+    let mut remaining = num_max_try;
+    move |_| mem::take(&mut remaining)
+}
+
+/// Result of a search whose trees are not connected, as selected by
+/// `on_failure`: the error with `best_partial`, `best_partial` mapped to the
+/// result of the search by `partial`, or the error with `trees`.
+/// [`OnFailure::BestEffortPath`] without `best_partial` is the error.
+fn unsolved<'t, T, N>(
+    on_failure: OnFailure,
+    best_partial: Option<Vec<Vec<N>>>,
+    trees: impl IntoIterator<Item = &'t Tree<N>>,
+    partial: impl FnOnce(Vec<Vec<N>>) -> T,
+) -> Result<T, Error<N>>
+where
+    N: Float + Debug + 't,
+{
+    match (on_failure, best_partial) {
+        (OnFailure::BestEffortPath, Some(path)) => Ok(partial(path)),
+        (OnFailure::BestEffortTree, _) => Err(Error::Unsolved {
+            trees: trees.into_iter().map(Tree::serialized).collect(),
+        }),
+        (_, best_partial) => Err(Error::MaxIterationsReached { best_partial }),
+    }
+}
+
+/// Path from the start to the goal, and the number of vertices of the start
+/// tree and the goal tree (zero if the straight line was free)
+pub(crate) type ConnectedPath<N> = (Vec<Vec<N>>, (usize, usize));
+
+pub(crate) fn dual_rrt_connect_impl<FF, FR, N>(
+    start: &[N],
+    goal: &[N],
     mut is_free: FF,
-    extend_length: N,
-    num_max_try: usize,
-) where
+    random_sample: FR,
+    search: Search<'_, N>,
+) -> Result<ConnectedPath<N>, Error<N>>
+where
     FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
     N: Float + Debug,
 {
-    if path.len() < 3 {
-        return;
+    if !is_free(start) {
+        return Err(Error::StartInCollision);
     }
-    let mut rng = rand::thread_rng();
-    for _ in 0..num_max_try {
-        let range1 = Uniform::new(0, path.len() - 2);
-        let ind1 = range1.sample(&mut rng);
-        let range2 = Uniform::new(ind1 + 2, path.len());
-        let ind2 = range2.sample(&mut rng);
-        let mut base_point = path[ind1].clone();
-        let point2 = path[ind2].clone();
-        let mut is_searching = true;
-        while is_searching {
-            let diff_dist = squared_euclidean(&base_point, &point2).sqrt();
-            if diff_dist < extend_length {
-                // reached!
-                // remove path[ind1+1] ... path[ind2-1]
-                let remove_index = ind1 + 1;
-                for _ in 0..(ind2 - ind1 - 1) {
-                    path.remove(remove_index);
-                }
-                if path.len() == 2 {
-                    return;
-                }
-                is_searching = false;
-            } else {
-                let check_point = base_point
-                    .iter()
-                    .zip(point2.iter())
-                    .map(|(near, target)| *near + (*target - *near) * extend_length / diff_dist)
-                    .collect::<Vec<_>>();
-                if !is_free(&check_point) {
-                    // trapped
-                    is_searching = false;
-                } else {
-                    // continue to extend
-                    base_point = check_point;
-                }
+    if !is_free(goal) {
+        return Err(Error::GoalInCollision);
+    }
+    let validity_resolution = search.validity_resolution;
+    dual_rrt_connect_motion_impl(
+        start,
+        goal,
+        |from: &[N], to: &[N]| is_edge_free(from, to, validity_resolution, &mut is_free),
+        random_sample,
+        search,
+    )
+}
+
+/// Same as [`dual_rrt_connect_impl`], but every extension and the connection
+/// of the trees (including the straight line from start to goal) is checked
+/// by `is_motion_free(from, to)`, which must also check `to`.
+pub(crate) fn dual_rrt_connect_motion_impl<FM, FR, N>(
+    start: &[N],
+    goal: &[N],
+    mut is_motion_free: FM,
+    random_sample: FR,
+    search: Search<'_, N>,
+) -> Result<ConnectedPath<N>, Error<N>>
+where
+    FM: FnMut(&[N], &[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    N: Float + Debug,
+{
+    if start.len() != goal.len() {
+        return Err(Error::DimensionMismatch {
+            start: start.len(),
+            goal: goal.len(),
+        });
+    }
+    // many queries are trivial, try the straight line first
+    if is_motion_free(start, goal) {
+        let mut path = vec![start.to_vec()];
+        while let (q, false) = steer(path.last().unwrap(), goal, search.extend_length) {
+            path.push(q);
+        }
+        path.push(goal.to_vec());
+        return Ok((path, (0, 0)));
+    }
+    let on_failure = search.on_failure;
+    let planner = grow_trees(start, goal, &mut is_motion_free, random_sample, search);
+    let tree_sizes = (planner.start_tree().len(), planner.goal_tree().len());
+    if !planner.is_connected() {
+        return unsolved(
+            on_failure,
+            Some(planner.best_partial()),
+            [planner.start_tree(), planner.goal_tree()],
+            |path| (path, tree_sizes),
+        );
+    }
+    planner.joined();
+    Ok((planner.joined.into_inner().unwrap().0, tree_sizes))
+}
+
+/// Grow the trees rooted at `start` and `goal` until they are connected or
+/// the tries of the search are used up
+fn grow_trees<FM, FR, N>(
+    start: &[N],
+    goal: &[N],
+    is_motion_free: &mut FM,
+    random_sample: FR,
+    mut search: Search<'_, N>,
+) -> DualRrtConnect<N>
+where
+    FM: FnMut(&[N], &[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    N: Float + Debug,
+{
+    let mut planner =
+        DualRrtConnect::with_options(start, goal, search.extend_length, &search.trees);
+    'tries: loop {
+        let tree_size = planner.start_tree().len() + planner.goal_tree().len();
+        let batch = (search.next_batch)(tree_size);
+        if batch == 0 {
+            break;
+        }
+        for _ in 0..batch {
+            let q_rand = random_sample();
+            if planner.step_with(
+                &q_rand,
+                is_motion_free,
+                &mut *search.selection,
+                &mut *search.on_extend,
+                &mut *search.rng,
+            ) {
+                break 'tries;
             }
         }
     }
+    // last attempt from the closest vertices, which the samples may have missed
+    if !planner.is_connected() {
+        planner.connect_closest(is_motion_free, &mut *search.rng);
+    }
+    planner
 }
 
-#[test]
-fn it_works() {
+/// Returns true if a path from `start` to `goal` is found. Only the trees
+/// are grown: the path is not extracted, and neither is the best partial
+/// path on failure.
+pub(crate) fn is_reachable<FF, FR, N>(
+    start: &[N],
+    goal: &[N],
+    mut is_free: FF,
+    random_sample: FR,
+    search: Search<'_, N>,
+) -> bool
+where
+    FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    N: Float + Debug,
+{
+    if start.len() != goal.len() || !is_free(start) || !is_free(goal) {
+        return false;
+    }
+    let validity_resolution = search.validity_resolution;
+    let mut is_motion_free =
+        |from: &[N], to: &[N]| is_edge_free(from, to, validity_resolution, &mut is_free);
+    is_motion_free(start, goal)
+        || grow_trees(start, goal, &mut is_motion_free, random_sample, search).is_connected()
+}
+
+/// Same as [`dual_rrt_connect_impl`], but each waypoint is annotated with the
+/// tree and the vertex index which it comes from. The straight line between
+/// the start and the goal is not tried first, so every waypoint is a tree
+/// vertex.
+pub(crate) fn dual_rrt_connect_with_provenance<FF, FR, N>(
+    start: &[N],
+    goal: &[N],
+    mut is_free: FF,
+    random_sample: FR,
+    search: Search<'_, N>,
+) -> Result<Vec<Waypoint<N>>, Error<N>>
+where
+    FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    N: Float + Debug,
+{
+    if start.len() != goal.len() {
+        return Err(Error::DimensionMismatch {
+            start: start.len(),
+            goal: goal.len(),
+        });
+    }
+    if !is_free(start) {
+        return Err(Error::StartInCollision);
+    }
+    if !is_free(goal) {
+        return Err(Error::GoalInCollision);
+    }
+    let validity_resolution = search.validity_resolution;
+    let mut is_motion_free =
+        |from: &[N], to: &[N]| is_edge_free(from, to, validity_resolution, &mut is_free);
+    let on_failure = search.on_failure;
+    let planner = grow_trees(start, goal, &mut is_motion_free, random_sample, search);
+    if let Some(waypoints) = planner.waypoints() {
+        return Ok(waypoints);
+    }
+    let start_tree = planner.start_tree();
+    unsolved(
+        on_failure,
+        Some(planner.best_partial()),
+        [start_tree, planner.goal_tree()],
+        |_| {
+            // the vertices of the best partial path, from its end
+            let mut vertices = vec![start_tree.nearest(planner.goal_tree().vertex(0))];
+            while let Some(parent) = start_tree.node(*vertices.last().unwrap()).parent_index {
+                vertices.push(parent);
+            }
+            vertices
+                .into_iter()
+                .rev()
+                .map(|vertex| Waypoint {
+                    position: start_tree.vertex(vertex).to_vec(),
+                    tree: TreeRole::Start,
+                    vertex,
+                })
+                .collect()
+        },
+    )
+}
+
+/// Same as [`dual_rrt_connect_impl`], but the goal is a region (e.g. a point
+/// with a tolerance or a predicate), and the search ends as soon as a vertex
+/// of the start tree is in it.
+///
+/// If the region has a [`GoalRegion::sample`], a goal tree is rooted there
+/// and connected to each new vertex of the start tree. The path ends at its
+/// first waypoint in the region.
+pub(crate) fn dual_rrt_connect_to_region<FF, FR, G, N>(
+    start: &[N],
+    goal: &G,
+    mut is_free: FF,
+    random_sample: FR,
+    mut search: Search<'_, N>,
+) -> Result<Vec<Vec<N>>, Error<N>>
+where
+    FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    G: GoalRegion<N> + ?Sized,
+    N: Float + Debug,
+{
+    if !is_free(start) {
+        return Err(Error::StartInCollision);
+    }
+    if goal.contains(start) {
+        return Ok(vec![start.to_vec()]);
+    }
+    let q_goal = goal.sample();
+    if let Some(q_goal) = &q_goal {
+        if q_goal.len() != start.len() {
+            return Err(Error::DimensionMismatch {
+                start: start.len(),
+                goal: q_goal.len(),
+            });
+        }
+    }
+    let mut goal_tree = q_goal
+        .as_ref()
+        .filter(|q| is_free(q))
+        .map(|q| search.trees.tree("goal", q));
+    let mut start_tree = search.trees.tree("start", start);
+    let (extend_length, validity_resolution) = (search.extend_length, search.validity_resolution);
+    let mut is_motion_free =
+        |from: &[N], to: &[N]| is_edge_free(from, to, validity_resolution, &mut is_free);
+    loop {
+        let tree_size = start_tree.len() + goal_tree.as_ref().map_or(0, Tree::len);
+        let batch = (search.next_batch)(tree_size);
+        if batch == 0 {
+            break;
+        }
+        for _ in 0..batch {
+            let q_rand = random_sample();
+            let nearest = start_tree.nearest(&q_rand);
+            let new_index = match start_tree.extend_from(
+                nearest,
+                &q_rand,
+                extend_length,
+                &mut is_motion_free,
+                &mut *search.rng,
+            ) {
+                ExtendStatus::Advanced(index) | ExtendStatus::Reached(index) => index,
+                ExtendStatus::Trapped => continue,
+            };
+            if goal.contains(start_tree.vertex(new_index)) {
+                return Ok(start_tree.path(new_index));
+            }
+            if let Some(goal_tree) = &mut goal_tree {
+                let q_new = start_tree.vertex(new_index);
+                let (status, _) = goal_tree.connect_with(
+                    q_new,
+                    extend_length,
+                    &mut is_motion_free,
+                    &mut *search.rng,
+                );
+                if let ExtendStatus::Reached(reach_index) = status {
+                    let mut path = joined_path(&start_tree, new_index, goal_tree, reach_index).0;
+                    // end at the first waypoint in the region
+                    if let Some(index) = path.iter().position(|q| goal.contains(q)) {
+                        path.truncate(index + 1);
+                    }
+                    return Ok(path);
+                }
+            }
+        }
+    }
+    unsolved(
+        search.on_failure,
+        q_goal.map(|q| start_tree.path(start_tree.nearest(&q))),
+        iter::once(&start_tree).chain(&goal_tree),
+        |path| path,
+    )
+}
+
+/// Same as [`dual_rrt_connect_impl`], but with many goals (e.g. the
+/// solutions of the inverse kinematics). The goal tree is grown from all the
+/// free goals, and the path ends at whichever of them is reached first.
+pub(crate) fn dual_rrt_connect_to_goals<FF, FR, N>(
+    start: &[N],
+    goals: &[Vec<N>],
+    mut is_free: FF,
+    random_sample: FR,
+    search: Search<'_, N>,
+) -> Result<Vec<Vec<N>>, Error<N>>
+where
+    FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    N: Float + Debug,
+{
+    if !is_free(start) {
+        return Err(Error::StartInCollision);
+    }
+    if goals.is_empty() {
+        return Err(Error::InvalidConfiguration("there is no goal"));
+    }
+    let mut goal_tree = search.trees.empty_tree("goal", start.len());
+    for goal in goals {
+        if goal.len() != start.len() {
+            return Err(Error::DimensionMismatch {
+                start: start.len(),
+                goal: goal.len(),
+            });
+        }
+        if is_free(goal) {
+            goal_tree.add_vertex(goal);
+        }
+    }
+    if goal_tree.is_empty() {
+        return Err(Error::GoalInCollision);
+    }
+    multi_goal_rrt_connect(start, goal_tree, || None, is_free, random_sample, search)
+}
+
+/// Same as [`dual_rrt_connect_to_goals`], but the goals are drawn from
+/// `sample_goal` during the search. It is called once per iteration, and
+/// each free goal it returns is a new root of the goal tree (`None` adds no
+/// goal, e.g. when the inverse kinematics did not converge).
+pub(crate) fn dual_rrt_connect_with_goal_sampler<FF, FR, FG, N>(
+    start: &[N],
+    sample_goal: FG,
+    is_free: FF,
+    random_sample: FR,
+    search: Search<'_, N>,
+) -> Result<Vec<Vec<N>>, Error<N>>
+where
+    FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    FG: FnMut() -> Option<Vec<N>>,
+    N: Float + Debug,
+{
+    let goal_tree = search.trees.empty_tree("goal", start.len());
+    multi_goal_rrt_connect(
+        start,
+        goal_tree,
+        sample_goal,
+        is_free,
+        random_sample,
+        search,
+    )
+}
+
+fn multi_goal_rrt_connect<FF, FR, FG, N>(
+    start: &[N],
+    goal_tree: Tree<N>,
+    mut sample_goal: FG,
+    mut is_free: FF,
+    random_sample: FR,
+    mut search: Search<'_, N>,
+) -> Result<Vec<Vec<N>>, Error<N>>
+where
+    FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    FG: FnMut() -> Option<Vec<N>>,
+    N: Float + Debug,
+{
+    if !is_free(start) {
+        return Err(Error::StartInCollision);
+    }
+    let (extend_length, validity_resolution) = (search.extend_length, search.validity_resolution);
+    let mut start_tree = search.trees.tree("start", start);
+    let mut goal_tree = goal_tree;
+    let mut tree_a = &mut start_tree;
+    let mut tree_b = &mut goal_tree;
+    loop {
+        let batch = (search.next_batch)(tree_a.len() + tree_b.len());
+        if batch == 0 {
+            break;
+        }
+        for _ in 0..batch {
+            if let Some(goal) = sample_goal() {
+                if goal.len() != start.len() {
+                    return Err(Error::DimensionMismatch {
+                        start: start.len(),
+                        goal: goal.len(),
+                    });
+                }
+                if is_free(&goal) {
+                    let goal_tree = if tree_a.role() == TreeRole::Goal {
+                        &mut *tree_a
+                    } else {
+                        &mut *tree_b
+                    };
+                    goal_tree.add_vertex(&goal);
+                }
+            }
+            let mut is_motion_free =
+                |from: &[N], to: &[N]| is_edge_free(from, to, validity_resolution, &mut is_free);
+            let q_rand = random_sample();
+            if tree_a.is_empty() {
+                mem::swap(&mut tree_a, &mut tree_b);
+            }
+            let nearest = tree_a.nearest(&q_rand);
+            let new_index = match tree_a.extend_from(
+                nearest,
+                &q_rand,
+                extend_length,
+                &mut is_motion_free,
+                &mut *search.rng,
+            ) {
+                ExtendStatus::Advanced(index) | ExtendStatus::Reached(index) => index,
+                ExtendStatus::Trapped => {
+                    mem::swap(&mut tree_a, &mut tree_b);
+                    continue;
+                }
+            };
+            if !tree_b.is_empty() {
+                let q_new = tree_a.vertex(new_index).to_vec();
+                let (status, _) = tree_b.connect_with(
+                    &q_new,
+                    extend_length,
+                    &mut is_motion_free,
+                    &mut *search.rng,
+                );
+                if let ExtendStatus::Reached(reach_index) = status {
+                    return Ok(joined_path(tree_a, new_index, tree_b, reach_index).0);
+                }
+            }
+            mem::swap(&mut tree_a, &mut tree_b);
+        }
+    }
+    unsolved(
+        search.on_failure,
+        (!goal_tree.is_empty()).then(|| start_tree.path(start_tree.nearest(goal_tree.vertex(0)))),
+        [&start_tree, &goal_tree],
+        |path| path,
+    )
+}
+
+/// Result of [`RrtPlanner::plan_relaxed`](crate::RrtPlanner::plan_relaxed)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelaxedPath<N> {
+    /// Path from the start toward the goal
+    pub path: Vec<Vec<N>>,
+    /// Tolerance of the schedule with which the path was found
+    pub tolerance: N,
+    /// Distance from the end of the path to the goal
+    pub distance: N,
+}
+
+/// Get as close to `goal` as possible: the path must end within the first
+/// of the increasing `tolerances` of the goal, which is relaxed to the next
+/// one when the tries of `tries_per_tolerance()` are used up.
+///
+/// The start tree is kept between the tolerances, so a relaxed tolerance
+/// is often met by a vertex which is already there. If the goal is free, a
+/// goal tree is connected to the new vertices and its paths end exactly at
+/// the goal.
+pub(crate) fn dual_rrt_connect_with_relaxation<'s, FF, FR, FT, N>(
+    start: &[N],
+    goal: &[N],
+    mut is_free: FF,
+    random_sample: FR,
+    tolerances: &[N],
+    mut search: Search<'s, N>,
+    mut tries_per_tolerance: FT,
+) -> Result<RelaxedPath<N>, Error<N>>
+where
+    FF: FnMut(&[N]) -> bool,
+    FR: Fn() -> Vec<N>,
+    FT: FnMut() -> Box<dyn FnMut(usize) -> usize + 's>,
+    N: Float + Debug,
+{
+    if start.len() != goal.len() {
+        return Err(Error::DimensionMismatch {
+            start: start.len(),
+            goal: goal.len(),
+        });
+    }
+    if tolerances.is_empty() || tolerances.windows(2).any(|w| w[0] > w[1]) {
+        return Err(Error::InvalidConfiguration(
+            "the tolerances must be increasing",
+        ));
+    }
+    if !is_free(start) {
+        return Err(Error::StartInCollision);
+    }
+    let distance = |q: &[N]| squared_euclidean(q, goal).sqrt();
+    let mut goal_tree = is_free(goal).then(|| search.trees.tree("goal", goal));
+    let mut start_tree = search.trees.tree("start", start);
+    let (extend_length, validity_resolution) = (search.extend_length, search.validity_resolution);
+    let mut is_motion_free =
+        |from: &[N], to: &[N]| is_edge_free(from, to, validity_resolution, &mut is_free);
+    for &tolerance in tolerances {
+        let nearest = start_tree.nearest(goal);
+        if distance(start_tree.vertex(nearest)) <= tolerance {
+            return Ok(RelaxedPath {
+                path: start_tree.path(nearest),
+                tolerance,
+                distance: distance(start_tree.vertex(nearest)),
+            });
+        }
+        let mut next_batch = tries_per_tolerance();
+        loop {
+            let tree_size = start_tree.len() + goal_tree.as_ref().map_or(0, Tree::len);
+            let batch = next_batch(tree_size);
+            if batch == 0 {
+                break;
+            }
+            for _ in 0..batch {
+                let q_rand = random_sample();
+                let nearest = start_tree.nearest(&q_rand);
+                let new_index = match start_tree.extend_from(
+                    nearest,
+                    &q_rand,
+                    extend_length,
+                    &mut is_motion_free,
+                    &mut *search.rng,
+                ) {
+                    ExtendStatus::Advanced(index) | ExtendStatus::Reached(index) => index,
+                    ExtendStatus::Trapped => continue,
+                };
+                let new_distance = distance(start_tree.vertex(new_index));
+                if new_distance <= tolerance {
+                    return Ok(RelaxedPath {
+                        path: start_tree.path(new_index),
+                        tolerance,
+                        distance: new_distance,
+                    });
+                }
+                if let Some(goal_tree) = &mut goal_tree {
+                    let q_new = start_tree.vertex(new_index);
+                    let (status, _) = goal_tree.connect_with(
+                        q_new,
+                        extend_length,
+                        &mut is_motion_free,
+                        &mut *search.rng,
+                    );
+                    if let ExtendStatus::Reached(reach_index) = status {
+                        return Ok(RelaxedPath {
+                            path: joined_path(&start_tree, new_index, goal_tree, reach_index).0,
+                            tolerance,
+                            distance: N::zero(),
+                        });
+                    }
+                }
+            }
+        }
+        debug!("relaxing the goal tolerance from {tolerance:?}");
+    }
+    let tolerance = *tolerances.last().unwrap();
+    unsolved(
+        search.on_failure,
+        Some(start_tree.path(start_tree.nearest(goal))),
+        iter::once(&start_tree).chain(&goal_tree),
+        |path| RelaxedPath {
+            distance: distance(path.last().unwrap()),
+            path,
+            tolerance,
+        },
+    )
+}
+
+/// Same as [`dual_rrt_connect_impl`], but the configurations are checked in
+/// batches by `is_free_batch`, which returns whether each of them is free
+/// (e.g. vectorized, on a GPU or by a physics engine).
+///
+/// Each try draws `batch_size` samples, checks the extensions of the tree
+/// toward all of them in one batch, and then the steps of the other tree
+/// toward all of the new vertices in another batch. The trees are updated
+/// between the batches. The edges are checked every `validity_resolution`
+/// of `search`, with all their points in the same batch.
+pub(crate) fn dual_rrt_connect_batch<FB, FR, N>(
+    start: &[N],
+    goal: &[N],
+    mut is_free_batch: FB,
+    random_sample: FR,
+    batch_size: usize,
+    mut search: Search<'_, N>,
+) -> Result<Vec<Vec<N>>, Error<N>>
+where
+    FB: FnMut(&[Vec<N>]) -> Vec<bool>,
+    FR: Fn() -> Vec<N>,
+    N: Float + Debug,
+{
+    if batch_size == 0 {
+        return Err(Error::InvalidConfiguration(
+            "the batch size must be positive",
+        ));
+    }
+    if start.len() != goal.len() {
+        return Err(Error::DimensionMismatch {
+            start: start.len(),
+            goal: goal.len(),
+        });
+    }
+    let (extend_length, validity_resolution) = (search.extend_length, search.validity_resolution);
+    let mut check = |batch: &[Vec<N>]| {
+        let result = is_free_batch(batch);
+        assert_eq!(result.len(), batch.len(), "one result per configuration");
+        result
+    };
+    // whether each edge is free, with the points of all of them in one batch
+    let mut check_edges = |edges: &[(&[N], &[N])]| {
+        let points = edges
+            .iter()
+            .map(|(from, to)| edge_points(from, to, validity_resolution))
+            .collect::<Vec<_>>();
+        let results = check(&points.concat());
+        let mut offset = 0;
+        points
+            .iter()
+            .map(|points| {
+                offset += points.len();
+                results[offset - points.len()..offset]
+                    .iter()
+                    .all(|free| *free)
+            })
+            .collect::<Vec<_>>()
+    };
+    match check_edges(&[(start, start), (goal, goal)])[..] {
+        [false, _] => return Err(Error::StartInCollision),
+        [_, false] => return Err(Error::GoalInCollision),
+        _ => {}
+    }
+    let mut straight = vec![start.to_vec()];
+    while let (q, false) = steer(straight.last().unwrap(), goal, extend_length) {
+        straight.push(q);
+    }
+    straight.push(goal.to_vec());
+    let edges = straight
+        .windows(2)
+        .map(|edge| (&edge[0][..], &edge[1][..]))
+        .collect::<Vec<_>>();
+    if check_edges(&edges).iter().all(|free| *free) {
+        return Ok(straight);
+    }
+    let mut start_tree = search.trees.tree("start", start);
+    let mut goal_tree = search.trees.tree("goal", goal);
+    let (mut tree_a, mut tree_b) = (&mut start_tree, &mut goal_tree);
+    let mut remaining = 0;
+    loop {
+        if remaining == 0 {
+            remaining = (search.next_batch)(tree_a.len() + tree_b.len());
+            if remaining == 0 {
+                break;
+            }
+        }
+        let num_samples = batch_size.min(remaining);
+        remaining -= num_samples;
+        let extensions = (0..num_samples)
+            .filter_map(|_| {
+                let q_rand = random_sample();
+                let nearest = tree_a.nearest(&q_rand);
+                let q_nearest = tree_a.vertex(nearest);
+                let (q_new, _) = steer(q_nearest, &q_rand, extend_length);
+                let q_new = match &tree_a.bounds {
+                    Some(bounds) if !bounds.contains(&q_new) => match bounds.policy {
+                        OutOfBounds::Reject => return None,
+                        OutOfBounds::Clamp => bounds.clamp(&q_new),
+                    },
+                    _ => q_new,
+                };
+                (q_new != q_nearest).then_some((nearest, q_new))
+            })
+            .collect::<Vec<_>>();
+        let edges = extensions
+            .iter()
+            .map(|(nearest, q_new)| (tree_a.vertex(*nearest), &q_new[..]))
+            .collect::<Vec<_>>();
+        let results = check_edges(&edges);
+        let new_indices = extensions
+            .into_iter()
+            .zip(results)
+            .filter(|(_, free)| *free)
+            .map(|((nearest, q_new), _)| {
+                let index = tree_a.add_vertex(&q_new);
+                tree_a.add_edge(nearest, index);
+                index
+            })
+            .collect::<Vec<_>>();
+        // all the steps of the other tree toward each new vertex, which stay
+        // within the bounds as both ends are
+        let chains = new_indices
+            .iter()
+            .map(|&new_index| {
+                let target = tree_a.vertex(new_index);
+                let from = tree_b.nearest(target);
+                let mut chain = vec![tree_b.vertex(from).to_vec()];
+                while chain.last().unwrap() != target {
+                    let (q, _) = steer(chain.last().unwrap(), target, extend_length);
+                    chain.push(q);
+                }
+                (new_index, from, chain)
+            })
+            .collect::<Vec<_>>();
+        let steps = chains
+            .iter()
+            .flat_map(|(_, _, chain)| chain.windows(2).map(|step| (&step[0][..], &step[1][..])))
+            .collect::<Vec<_>>();
+        let results = check_edges(&steps);
+        let mut offset = 0;
+        for (new_index, from, mut chain) in chains {
+            chain.remove(0);
+            let num_free = results[offset..offset + chain.len()]
+                .iter()
+                .take_while(|free| **free)
+                .count();
+            offset += chain.len();
+            let mut parent = from;
+            for q in &chain[..num_free] {
+                let index = tree_b.add_vertex(q);
+                tree_b.add_edge(parent, index);
+                parent = index;
+            }
+            if num_free == chain.len() {
+                return Ok(joined_path(tree_a, new_index, tree_b, parent).0);
+            }
+        }
+        mem::swap(&mut tree_a, &mut tree_b);
+    }
+    unsolved(
+        search.on_failure,
+        Some(start_tree.path(start_tree.nearest(goal))),
+        [&start_tree, &goal_tree],
+        |path| path,
+    )
+}
+
+/// Switch of the sampling strategy of the
+/// [`RrtPlanner::strategy`](crate::RrtPlanner::strategy) option
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrategySwitch {
+    /// Number of tries before the switch
+    pub iteration: usize,
+    /// Index of the previous strategy
+    pub from: usize,
+    /// Index of the new strategy
+    pub to: usize,
+}
+
+/// Path found by [`RrtPlanner::plan_with_stats`](crate::RrtPlanner::plan_with_stats)
+/// with the statistics to compare planners
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanResult<N> {
+    /// Path from the start to the goal
+    pub path: Vec<Vec<N>>,
+    /// Length of the path
+    pub length: N,
+    /// Number of vertices of the start tree, zero if the straight line was free
+    pub start_tree_size: usize,
+    /// Number of vertices of the goal tree, zero if the straight line was free
+    pub goal_tree_size: usize,
+    /// Number of calls of `is_free`
+    pub num_collision_checks: usize,
+    /// Number of tries used
+    pub num_iterations: usize,
+    /// Switches of the sampling strategy
+    pub switches: Vec<StrategySwitch>,
+    /// Wall-clock time of planning
+    pub elapsed: Duration,
+}
+
+/// Progress of the bidirectional search, reported by the
+/// [`RrtPlanner::on_progress`](crate::RrtPlanner::on_progress) callback
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress<N> {
+    /// Number of tries so far
+    pub num_tries: usize,
+    /// Number of vertices of the start tree
+    pub start_tree_size: usize,
+    /// Number of vertices of the goal tree
+    pub goal_tree_size: usize,
+    /// Closest vertices of the start tree and the goal tree
+    pub closest_pair: (Vec<N>, Vec<N>),
+    /// Distance between the closest vertices
+    pub gap: N,
+}
+
+/// Path and the tree and the vertex of each of its waypoints
+type JoinedPath<N> = (Vec<Vec<N>>, Vec<(TreeRole, usize)>);
+
+/// Bidirectional RRT-Connect, which is grown one try at a time by
+/// [`DualRrtConnect::step`], so both trees can be inspected between the
+/// tries and the search can be resumed. [`dual_rrt_connect`] and its
+/// variants run it until the trees are connected.
+#[derive(Debug)]
+pub struct DualRrtConnect<N>
+where
+    N: Float + Zero + Debug,
+{
+    // the tree which is extended by the next try, and the other one
+    tree_a: Tree<N>,
+    tree_b: Tree<N>,
+    extend_length: N,
+    num_tries: usize,
+    // vertices of `tree_a` and `tree_b` which were connected, and the path
+    // through them, which is built on the first request
+    connection: Option<(usize, usize)>,
+    joined: OnceLock<JoinedPath<N>>,
+}
+
+impl<N> DualRrtConnect<N>
+where
+    N: Float + Debug,
+{
+    /// Create the trees rooted at `start` and `goal`, which must be free
+    pub fn new(start: &[N], goal: &[N], extend_length: N) -> Self {
+        Self::with_options(start, goal, extend_length, &TreeOptions::default())
+    }
+    fn with_options(start: &[N], goal: &[N], extend_length: N, options: &TreeOptions<N>) -> Self {
+        assert_eq!(start.len(), goal.len());
+        Self {
+            tree_a: options.tree("start", start),
+            tree_b: options.tree("goal", goal),
+            extend_length,
+            num_tries: 0,
+            connection: None,
+            joined: OnceLock::new(),
+        }
+    }
+    /// Tree rooted at the start
+    pub fn start_tree(&self) -> &Tree<N> {
+        self.views().0.tree
+    }
+    /// Tree rooted at the goal
+    pub fn goal_tree(&self) -> &Tree<N> {
+        self.views().1.tree
+    }
+    /// Number of tries so far
+    pub fn num_tries(&self) -> usize {
+        self.num_tries
+    }
+    /// True once the trees are connected
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
+    /// Path from the start to the goal, `None` until the trees are connected
+    pub fn path(&self) -> Option<&[Vec<N>]> {
+        self.joined().map(|(path, _)| path.as_slice())
+    }
+    /// Waypoints of [`DualRrtConnect::path`] with the tree and the vertex
+    /// which each of them comes from
+    pub fn waypoints(&self) -> Option<Vec<Waypoint<N>>> {
+        let (path, provenance) = self.joined()?;
+        Some(
+            path.iter()
+                .zip(provenance)
+                .map(|(position, &(tree, vertex))| Waypoint {
+                    position: position.clone(),
+                    tree,
+                    vertex,
+                })
+                .collect(),
+        )
+    }
+    /// Closest vertices of the start tree and the goal tree as
+    /// `(start_index, goal_index, distance)`
+    pub fn closest_pair(&self) -> (usize, usize, N) {
+        self.start_tree().closest_pair(self.goal_tree())
+    }
+    /// Remove the parts of the trees which are blocked in a changed world
+    /// (e.g. after a new sensor scan) by [`Tree::prune`], so the search goes
+    /// on with the rest instead of starting over. The trees stay connected
+    /// if both connected vertices are kept, as the path then is too.
+    ///
+    /// Returns the number of the removed vertices.
+    pub fn revalidate<FF>(&mut self, validity_resolution: N, is_free: &mut FF) -> usize
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let num_vertices = self.tree_a.len() + self.tree_b.len();
+        let new_a = self.tree_a.prune(validity_resolution, is_free);
+        let new_b = self.tree_b.prune(validity_resolution, is_free);
+        self.connection = self
+            .connection
+            .and_then(|(index_a, index_b)| Some((new_a[index_a]?, new_b[index_b]?)));
+        self.joined = OnceLock::new();
+        num_vertices - self.tree_a.len() - self.tree_b.len()
+    }
+    /// Path from the start to the vertex of the start tree nearest to the goal
+    pub fn best_partial(&self) -> Vec<Vec<N>> {
+        let start_tree = self.start_tree();
+        start_tree.path(start_tree.nearest(self.goal_tree().vertex(0)))
+    }
+    /// Extend one tree from its nearest vertex toward `q_rand`, connect the
+    /// other tree to the new vertex, and swap the roles of the trees for the
+    /// next try. The edges are checked every `validity_resolution`.
+    ///
+    /// Returns true once the trees are connected. The trees of
+    /// [`DualRrtConnect::new`] are extended from the nearest vertex and never
+    /// jitter the samples, so the result depends only on the samples.
+    pub fn step<FF>(&mut self, q_rand: &[N], validity_resolution: N, is_free: &mut FF) -> bool
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        self.step_with(
+            q_rand,
+            &mut |from: &[N], to: &[N]| is_edge_free(from, to, validity_resolution, is_free),
+            &mut NearestVertex,
+            &mut |_, _, _, _| {},
+            &mut rand::rngs::mock::StepRng::new(0, 0),
+        )
+    }
+    fn step_with<FM, S, FE>(
+        &mut self,
+        q_rand: &[N],
+        is_motion_free: &mut FM,
+        selection: &mut S,
+        on_extend: &mut FE,
+        rng: &mut dyn RngCore,
+    ) -> bool
+    where
+        FM: FnMut(&[N], &[N]) -> bool,
+        FE: FnMut(bool, N, TreeView<'_, N>, TreeView<'_, N>) + ?Sized,
+        S: VertexSelection<N> + ?Sized,
+    {
+        if self.is_connected() {
+            return true;
+        }
+        self.num_tries += 1;
+        debug!("tree_a = {:?}", self.tree_a.len());
+        debug!("tree_b = {:?}", self.tree_b.len());
+        let from = selection.select(&TreeView { tree: &self.tree_a }, q_rand, rng);
+        let extend_status =
+            self.tree_a
+                .extend_from(from, q_rand, self.extend_length, is_motion_free, rng);
+        match extend_status {
+            ExtendStatus::Trapped => {
+                let (start_tree, goal_tree) = self.views();
+                on_extend(false, N::infinity(), start_tree, goal_tree);
+            }
+            ExtendStatus::Advanced(new_index) | ExtendStatus::Reached(new_index) => {
+                let q_new = self.tree_a.vertex(new_index);
+                let (connect_status, last_index) =
+                    self.tree_b
+                        .connect_with(q_new, self.extend_length, is_motion_free, rng);
+                let gap = match connect_status {
+                    ExtendStatus::Reached(_) => N::zero(),
+                    _ => squared_euclidean(q_new, self.tree_b.vertex(last_index)).sqrt(),
+                };
+                let (start_tree, goal_tree) = self.views();
+                on_extend(true, gap, start_tree, goal_tree);
+                if let ExtendStatus::Reached(reach_index) = connect_status {
+                    self.connection = Some((new_index, reach_index));
+                    return true;
+                }
+            }
+        }
+        mem::swap(&mut self.tree_a, &mut self.tree_b);
+        false
+    }
+    // Connect the tree to the closest vertex of the other one
+    fn connect_closest<FM>(&mut self, is_motion_free: &mut FM, rng: &mut dyn RngCore) -> bool
+    where
+        FM: FnMut(&[N], &[N]) -> bool,
+    {
+        let (index_a, _, _) = self.tree_a.closest_pair(&self.tree_b);
+        let q_closest = self.tree_a.vertex(index_a).to_vec();
+        let (connect_status, _) =
+            self.tree_b
+                .connect_with(&q_closest, self.extend_length, is_motion_free, rng);
+        if let ExtendStatus::Reached(reach_index) = connect_status {
+            self.connection = Some((index_a, reach_index));
+        }
+        self.is_connected()
+    }
+    // Path through the connected vertices and where its waypoints come from
+    fn joined(&self) -> Option<&JoinedPath<N>> {
+        let (index_a, index_b) = self.connection?;
+        Some(
+            self.joined
+                .get_or_init(|| joined_path(&self.tree_a, index_a, &self.tree_b, index_b)),
+        )
+    }
+    // Views of the start tree and the goal tree
+    fn views(&self) -> (TreeView<'_, N>, TreeView<'_, N>) {
+        let (tree_a, tree_b) = (
+            TreeView { tree: &self.tree_a },
+            TreeView { tree: &self.tree_b },
+        );
+        if tree_a.is_start_tree() {
+            (tree_a, tree_b)
+        } else {
+            (tree_b, tree_a)
+        }
+    }
+}
+
+/// Path from the start to the goal through the vertex `index_a` of `tree_a`,
+/// which is the same as the vertex `index_b` of `tree_b`, and the tree and
+/// the vertex of each waypoint
+fn joined_path<N>(
+    tree_a: &Tree<N>,
+    index_a: usize,
+    tree_b: &Tree<N>,
+    index_b: usize,
+) -> JoinedPath<N>
+where
+    N: Float + Debug,
+{
+    let ancestors = |tree: &Tree<N>, mut index: usize| {
+        let mut indices = Vec::new();
+        while let Some(parent) = tree.parents[index] {
+            indices.push(parent);
+            index = parent;
+        }
+        indices
+    };
+    let mut indices_a = ancestors(tree_a, index_a);
+    indices_a.reverse();
+    indices_a.push(index_a);
+    let mut provenance = indices_a
+        .into_iter()
+        .map(|i| (tree_a.role(), i))
+        .chain(
+            ancestors(tree_b, index_b)
+                .into_iter()
+                .map(|i| (tree_b.role(), i)),
+        )
+        .collect::<Vec<_>>();
+    if tree_b.role() == TreeRole::Start {
+        provenance.reverse();
+    }
+    let path = provenance
+        .iter()
+        .map(|&(role, i)| {
+            let tree = if role == tree_a.role() {
+                tree_a
+            } else {
+                tree_b
+            };
+            tree.vertex(i).to_vec()
+        })
+        .collect();
+    (path, provenance)
+}
+
+/// select random two points, and try to connect.
+pub fn smooth_path<FF, N>(path: &mut Vec<Vec<N>>, is_free: FF, extend_length: N, num_max_try: usize)
+where
+    FF: FnMut(&[N]) -> bool,
+    N: Float + Debug,
+{
+    smooth_path_with_rng(
+        path,
+        is_free,
+        extend_length,
+        num_max_try,
+        &mut rand::thread_rng(),
+    );
+}
+
+/// Same as [`smooth_path`], but the points are selected by `rng`, so the
+/// result is reproducible with a seeded `rng`.
+pub fn smooth_path_with_rng<FF, N>(
+    path: &mut Vec<Vec<N>>,
+    mut is_free: FF,
+    extend_length: N,
+    num_max_try: usize,
+    mut rng: &mut dyn RngCore,
+) where
+    FF: FnMut(&[N]) -> bool,
+    N: Float + Debug,
+{
+    if path.len() < 3 {
+        return;
+    }
+    for _ in 0..num_max_try {
+        let range1 = Uniform::new(0, path.len() - 2);
+        let ind1 = range1.sample(&mut rng);
+        let range2 = Uniform::new(ind1 + 2, path.len());
+        let ind2 = range2.sample(&mut rng);
+        let mut base_point = path[ind1].clone();
+        let point2 = path[ind2].clone();
+        let mut is_searching = true;
+        while is_searching {
+            let diff_dist = squared_euclidean(&base_point, &point2).sqrt();
+            if diff_dist < extend_length {
+                // reached!
+                // remove path[ind1+1] ... path[ind2-1]
+                let remove_index = ind1 + 1;
+                for _ in 0..(ind2 - ind1 - 1) {
+                    path.remove(remove_index);
+                }
+                if path.len() == 2 {
+                    return;
+                }
+                is_searching = false;
+            } else {
+                let check_point = base_point
+                    .iter()
+                    .zip(point2.iter())
+                    .map(|(near, target)| *near + (*target - *near) * extend_length / diff_dist)
+                    .collect::<Vec<_>>();
+                if !is_free(&check_point) {
+                    // trapped
+                    is_searching = false;
+                } else {
+                    // continue to extend
+                    base_point = check_point;
+                }
+            }
+        }
+    }
+}
+
+/// Deterministic version of [`smooth_path`]: from each waypoint in order,
+/// connect to the farthest later waypoint which can be reached by a free
+/// straight edge (checked every `extend_length`), trying all of the pairs.
+///
+/// The result depends only on the path and `is_free`, so it is reproducible
+/// and testable, at the cost of `O(n^2)` edge checks for `n` waypoints.
+pub fn smooth_path_greedy<FF, N>(path: &mut Vec<Vec<N>>, mut is_free: FF, extend_length: N)
+where
+    FF: FnMut(&[N]) -> bool,
+    N: Float + Debug,
+{
+    let mut i = 0;
+    while i + 2 < path.len() {
+        if let Some(j) = (i + 2..path.len())
+            .rev()
+            .find(|&j| is_edge_free(&path[i], &path[j], extend_length, &mut is_free))
+        {
+            path.drain(i + 1..j);
+        }
+        i += 1;
+    }
+}
+
+/// select random two points, and try to connect them by `steer`.
+///
+/// `steer(from, to)` returns the local path from `from` to `to` including
+/// both ends (e.g. a Dubins curve sampled densely enough for collision
+/// checking), or `None` if `to` can not be reached. Unlike [`smooth_path`],
+/// the shortcuts follow the motion constraints of the vehicle. A shortcut is
+/// used only if it starts and ends at the chosen waypoints, all of its points
+/// are free and it is shorter than the part of the path it replaces. The
+/// waypoints are chosen by `rng`.
+pub fn smooth_path_with_steering<FF, FS, N>(
+    path: &mut Vec<Vec<N>>,
+    mut is_free: FF,
+    mut steer: FS,
+    num_max_try: usize,
+    mut rng: &mut dyn RngCore,
+) where
+    FF: FnMut(&[N]) -> bool,
+    FS: FnMut(&[N], &[N]) -> Option<Vec<Vec<N>>>,
+    N: Float + Debug,
+{
+    for _ in 0..num_max_try {
+        if path.len() < 3 {
+            return;
+        }
+        let ind1 = Uniform::new(0, path.len() - 2).sample(&mut rng);
+        let ind2 = Uniform::new(ind1 + 2, path.len()).sample(&mut rng);
+        let shortcut = match steer(&path[ind1], &path[ind2]) {
+            Some(shortcut) if shortcut.len() >= 2 => shortcut,
+            _ => continue,
+        };
+        // an inexact steering function would leave a gap in the path
+        let coincides = |a: &[N], b: &[N]| squared_euclidean(a, b) <= N::epsilon();
+        if !coincides(&shortcut[0], &path[ind1])
+            || !coincides(&shortcut[shortcut.len() - 1], &path[ind2])
+        {
+            continue;
+        }
+        if crate::path::path_length(&shortcut) >= crate::path::path_length(&path[ind1..=ind2])
+            || !shortcut[1..shortcut.len() - 1].iter().all(|q| is_free(q))
+        {
+            continue;
+        }
+        path.splice(
+            ind1 + 1..ind2,
+            shortcut[1..shortcut.len() - 1].iter().cloned(),
+        );
+    }
+}
+
+/// Shortcut between random points along the segments of the path, not only
+/// between its waypoints, so the corners in narrow corridors are cut too.
+///
+/// Each try picks a point on one segment and a point on a later one, and
+/// replaces the part of the path between them by the straight edge if it is
+/// free (checked every `validity_resolution`) and shorter. The parts of the
+/// edges which are kept are not checked again, so inflate the obstacles of
+/// `is_free` by about the resolution if they must be free at any point.
+pub fn smooth_path_partial<FF, N>(
+    path: &mut Vec<Vec<N>>,
+    mut is_free: FF,
+    validity_resolution: N,
+    num_max_try: usize,
+    rng: &mut dyn RngCore,
+) where
+    FF: FnMut(&[N]) -> bool,
+    N: Float + Debug,
+{
+    let lerp = |a: &[N], b: &[N], t: N| {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| *a + (*b - *a) * t)
+            .collect::<Vec<_>>()
+    };
+    for _ in 0..num_max_try {
+        if path.len() < 3 {
+            return;
+        }
+        let lengths = path
+            .windows(2)
+            .map(|w| squared_euclidean(&w[0], &w[1]).sqrt())
+            .collect::<Vec<_>>();
+        let i = rng.gen_range(0..path.len() - 2);
+        let j = rng.gen_range(i + 1..path.len() - 1);
+        let t1 = N::from(rng.gen::<f64>()).unwrap();
+        let t2 = N::from(rng.gen::<f64>()).unwrap();
+        let a = lerp(&path[i], &path[i + 1], t1);
+        let b = lerp(&path[j], &path[j + 1], t2);
+        let replaced = lengths[i + 1..j]
+            .iter()
+            .fold((N::one() - t1) * lengths[i] + t2 * lengths[j], |sum, l| {
+                sum + *l
+            });
+        if squared_euclidean(&a, &b).sqrt() >= replaced
+            || !is_edge_free(&a, &b, validity_resolution, &mut is_free)
+        {
+            continue;
+        }
+        path.splice(i + 1..=j, [a, b]);
+    }
+}
+
+/// Refine the path by moving random waypoints a little (simulated annealing).
+///
+/// Each try moves one interior waypoint by up to `step` in each dimension.
+/// The move is accepted if both edges to its neighbours are free (checked
+/// every `validity_resolution`) and it reduces `cost`, or with the
+/// probability `exp(-increase / temperature)` otherwise, where the
+/// temperature decreases linearly from `initial_temperature` to zero. The
+/// best path found is kept. This needs no gradient of the cost or of the
+/// clearance, so it works with any cost and any `is_free`.
+#[allow(clippy::too_many_arguments)]
+pub fn perturb_path<FF, FC, N>(
+    path: &mut Vec<Vec<N>>,
+    mut is_free: FF,
+    mut cost: FC,
+    step: N,
+    validity_resolution: N,
+    initial_temperature: N,
+    num_max_try: usize,
+    rng: &mut dyn RngCore,
+) where
+    FF: FnMut(&[N]) -> bool,
+    FC: FnMut(&[Vec<N>]) -> N,
+    N: Float + Debug,
+{
+    if path.len() < 3 {
+        return;
+    }
+    let mut current = path.clone();
+    let mut current_cost = cost(&current);
+    let mut best_cost = current_cost;
+    for i in 0..num_max_try {
+        let temperature =
+            initial_temperature * N::from(num_max_try - i).unwrap() / N::from(num_max_try).unwrap();
+        let index = rng.gen_range(1..current.len() - 1);
+        let moved = current[index]
+            .iter()
+            .map(|v| *v + step * N::from(rng.gen_range(-1.0..=1.0)).unwrap())
+            .collect::<Vec<_>>();
+        if !is_edge_free(
+            &current[index - 1],
+            &moved,
+            validity_resolution,
+            &mut is_free,
+        ) || !is_edge_free(
+            &moved,
+            &current[index + 1],
+            validity_resolution,
+            &mut is_free,
+        ) {
+            continue;
+        }
+        let previous = mem::replace(&mut current[index], moved);
+        let new_cost = cost(&current);
+        let increase = new_cost - current_cost;
+        let accept = increase < N::zero()
+            || (temperature > N::zero()
+                && N::from(rng.gen::<f64>()).unwrap() < (-increase / temperature).exp());
+        if !accept {
+            current[index] = previous;
+            continue;
+        }
+        current_cost = new_cost;
+        if current_cost < best_cost {
+            best_cost = current_cost;
+            path.clone_from(&current);
+        }
+    }
+}
+
+#[test]
+fn it_works() {
     use rand::distributions::{Distribution, Uniform};
     let mut result = dual_rrt_connect(
         &[-1.2, 0.0],
@@ -263,3 +2205,1167 @@ fn it_works() {
     println!("{result:?}");
     assert!(result.len() >= 3);
 }
+
+#[test]
+fn connect_works() {
+    use rand::distributions::{Distribution, Uniform};
+    let result = dual_rrt_connect(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0),
+        || {
+            let between = Uniform::new(-2.0, 2.0);
+            let mut rng = rand::thread_rng();
+            vec![between.sample(&mut rng), between.sample(&mut rng)]
+        },
+        0.2,
+        1000,
+    )
+    .unwrap();
+    assert_eq!(result[0], [-1.2, 0.0]);
+    assert_eq!(result[result.len() - 1], [1.2, 0.0]);
+    for w in result.windows(2) {
+        assert!(squared_euclidean(&w[0], &w[1]).sqrt() <= 0.2 + 1e-9);
+    }
+}
+
+#[test]
+fn resolution_works() {
+    use rand::distributions::{Distribution, Uniform};
+    // thin wall which can be tunneled through with extend_length = 0.2
+    let mut is_free = |p: &[f64]| !(p[0].abs() < 0.01 && p[1].abs() < 1.0);
+    let result = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .validity_resolution(0.005)
+        .sampler(|| {
+            let between = Uniform::new(-2.0, 2.0);
+            let mut rng = rand::thread_rng();
+            vec![between.sample(&mut rng), between.sample(&mut rng)]
+        })
+        .plan(is_free)
+        .unwrap();
+    for w in result.windows(2) {
+        assert!(is_edge_free(&w[0], &w[1], 0.005, &mut is_free));
+    }
+}
+
+#[test]
+fn approximate_works() {
+    use rand::distributions::{Distribution, Uniform};
+    let result = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .max_nearest_checks(8)
+        .sampler(|| {
+            let between = Uniform::new(-2.0, 2.0);
+            let mut rng = rand::thread_rng();
+            vec![between.sample(&mut rng), between.sample(&mut rng)]
+        })
+        .plan(|p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0))
+        .unwrap();
+    assert!(result.len() >= 4);
+}
+
+#[test]
+fn steering_works() {
+    // vehicle which can move only along the axes
+    let steer = |from: &[f64], to: &[f64]| {
+        let corner = [to[0], from[1]];
+        let mut local = vec![from.to_vec()];
+        for (a, b) in [(from, &corner[..]), (&corner[..], to)] {
+            let num = (squared_euclidean(a, b).sqrt() / 0.1).ceil() as usize;
+            for i in 1..=num {
+                let t = i as f64 / num as f64;
+                local.push(vec![a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]);
+            }
+        }
+        Some(local)
+    };
+    let mut path = vec![
+        vec![0.0, 0.0],
+        vec![0.0, 1.0],
+        vec![1.0, 1.0],
+        vec![1.0, 0.0],
+        vec![2.0, 0.0],
+    ];
+    smooth_path_with_steering(
+        &mut path,
+        |p: &[f64]| p[1] > -0.5,
+        steer,
+        100,
+        &mut rand::thread_rng(),
+    );
+    assert!(crate::path::path_length(&path) < 4.0);
+    assert_eq!(path[0], [0.0, 0.0]);
+    assert_eq!(path[path.len() - 1], [2.0, 0.0]);
+    for w in path.windows(2) {
+        assert!(w[0][0] == w[1][0] || w[0][1] == w[1][1]);
+    }
+    // the shortcuts which miss the waypoints are rejected
+    let original = vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0]];
+    let mut path = original.clone();
+    let overshoot = |from: &[f64], to: &[f64]| Some(vec![from.to_vec(), vec![to[0] + 0.1, to[1]]]);
+    smooth_path_with_steering(
+        &mut path,
+        |_: &[f64]| true,
+        overshoot,
+        100,
+        &mut rand::thread_rng(),
+    );
+    assert_eq!(path, original);
+}
+
+#[test]
+fn time_budget_works() {
+    use rand::distributions::{Distribution, Uniform};
+    let sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let planner = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .time_budget(Duration::from_secs(5))
+        .sampler(sample);
+    assert!(planner.plan(is_free).is_ok());
+    // the goal is enclosed
+    let is_free = |p: &[f64]| p[0] < 1.0 || p[0] > 1.1;
+    let budget = Duration::from_millis(50);
+    let started = std::time::Instant::now();
+    let planner = planner
+        .goal(&[1.5, 0.0])
+        .extend_length(0.05)
+        .time_budget(budget);
+    assert!(planner.plan(is_free).is_err());
+    assert!(started.elapsed() < budget * 4);
+}
+
+#[test]
+fn straight_line_works() {
+    use rand::distributions::{Distribution, Uniform};
+    let sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    // trivial query, no tries are needed
+    let result = dual_rrt_connect(&[-1.2, 1.5], &[1.2, 1.5], is_free, sample, 0.2, 0).unwrap();
+    assert_eq!(result.len(), 13);
+    assert!((crate::path::path_length(&result) - 2.4).abs() < 1e-9);
+    assert!(result.iter().all(|q| q[1] == 1.5));
+}
+
+#[test]
+fn capacity_works() {
+    use rand::distributions::{Distribution, Uniform};
+    let sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let tree = Tree::<f64>::with_options("start", 2, Some(8), CoincidentSample::Reached, 5000);
+    assert!(tree.vertices().capacity() >= 5000);
+    let result = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .max_nearest_checks(8)
+        .capacity(5000)
+        .sampler(sample)
+        .plan(|p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0))
+        .unwrap();
+    assert_eq!(result[0], [-1.2, 0.0]);
+    assert_eq!(result[result.len() - 1], [1.2, 0.0]);
+}
+
+#[test]
+fn coincident_sample_works() {
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let mut always_free = |_: &[f64]| true;
+    let mut tree = Tree::with_options("start", 2, None, CoincidentSample::Reached, 0);
+    tree.add_vertex(&[0.0, 0.0]);
+    assert!(matches!(
+        tree.extend(&[0.0, 0.0], 0.2, 0.2, &mut always_free, &mut rng),
+        ExtendStatus::Reached(0)
+    ));
+    assert_eq!(tree.len(), 1);
+    tree.on_coincident = CoincidentSample::Skip;
+    assert!(matches!(
+        tree.extend(&[0.0, 0.0], 0.2, 0.2, &mut always_free, &mut rng),
+        ExtendStatus::Trapped
+    ));
+    assert_eq!(tree.len(), 1);
+    tree.on_coincident = CoincidentSample::Jitter(0.1);
+    assert!(matches!(
+        tree.extend(&[0.0, 0.0], 0.2, 0.2, &mut always_free, &mut rng),
+        ExtendStatus::Reached(1)
+    ));
+    assert_ne!(tree.vertex(1), [0.0, 0.0]);
+    assert!(tree.vertex(1).iter().all(|v| v.abs() <= 0.1));
+    // goal only sampler
+    for on_coincident in [CoincidentSample::Reached, CoincidentSample::Jitter(0.01)] {
+        let result = crate::RrtPlanner::new()
+            .start(&[-1.0, 0.0])
+            .goal(&[1.0, 0.0])
+            .extend_length(0.2)
+            .max_iterations(100)
+            .on_coincident(on_coincident)
+            .sampler(|| vec![1.0, 0.0])
+            .plan(always_free)
+            .unwrap();
+        assert_eq!(result[0], [-1.0, 0.0]);
+        assert_eq!(result[result.len() - 1], [1.0, 0.0]);
+        for w in result.windows(2) {
+            assert!(w[0] != w[1]);
+        }
+    }
+}
+
+#[test]
+fn bounds_work() {
+    use rand::distributions::{Distribution, Uniform};
+    let sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let mut always_free = |_: &[f64]| true;
+    let mut bounds = Bounds {
+        lower: vec![-1.0, -1.0],
+        upper: vec![1.0, 1.0],
+        policy: OutOfBounds::Clamp,
+    };
+    let mut tree = Tree::with_options("start", 2, None, CoincidentSample::Reached, 0);
+    tree.bounds = Some(bounds.clone());
+    tree.add_vertex(&[0.9, 0.0]);
+    assert!(matches!(
+        tree.extend(&[2.0, 0.0], 0.2, 0.2, &mut always_free, &mut rng),
+        ExtendStatus::Advanced(1)
+    ));
+    assert_eq!(tree.vertex(1), [1.0, 0.0]);
+    assert!(matches!(
+        tree.extend(&[2.0, 0.0], 0.2, 0.2, &mut always_free, &mut rng),
+        ExtendStatus::Trapped
+    ));
+    bounds.policy = OutOfBounds::Reject;
+    tree.bounds = Some(bounds.clone());
+    assert!(matches!(
+        tree.extend(&[0.0, 2.0], 0.2, 0.2, &mut always_free, &mut rng),
+        ExtendStatus::Advanced(2)
+    ));
+    assert!(matches!(
+        tree.extend(&[2.0, 0.1], 0.2, 0.2, &mut always_free, &mut rng),
+        ExtendStatus::Trapped
+    ));
+    for policy in [OutOfBounds::Reject, OutOfBounds::Clamp] {
+        let bounds = Bounds {
+            lower: vec![-1.5, -1.5],
+            upper: vec![1.5, 1.5],
+            policy,
+        };
+        let inside = bounds.clone();
+        let result = crate::RrtPlanner::new()
+            .start(&[-1.2, 0.0])
+            .goal(&[1.2, 0.0])
+            .extend_length(0.2)
+            .bounds(bounds.clone())
+            .sampler(sample)
+            .plan(|p: &[f64]| {
+                assert!(inside.contains(p), "{p:?}");
+                !(p[0].abs() < 1.0 && p[1].abs() < 1.0)
+            })
+            .unwrap();
+        assert!(result.iter().all(|q| bounds.contains(q)));
+    }
+}
+
+#[test]
+fn selection_works() {
+    use rand::distributions::{Distribution, Uniform};
+    use rand::SeedableRng;
+    let sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let mut tree = Tree::with_options("start", 2, None, CoincidentSample::Reached, 0);
+    tree.add_vertex(&[0.0, 0.0]);
+    tree.add_vertex(&[1.0, 0.0]);
+    tree.add_edge(0, 1);
+    tree.add_vertex(&[1.0, 1.0]);
+    tree.add_edge(1, 2);
+    let view = TreeView { tree: &tree };
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    assert_eq!(view.cost(2), 2.0);
+    assert_eq!(view.num_children(0), 1);
+    assert_eq!(NearestVertex.select(&view, &[1.1, 1.1], &mut rng), 2);
+    // the root is not the nearest, but the path through it is shorter
+    assert_eq!(BestCost(0.0).select(&view, &[0.1, 1.2], &mut rng), 2);
+    assert_eq!(BestCost(1.0).select(&view, &[0.1, 1.2], &mut rng), 0);
+    let leaves = (0..1000)
+        .filter(|_| ExplorationScore.select(&view, &[0.0, 0.0], &mut rng) == 2)
+        .count();
+    assert!((400..600).contains(&leaves), "{leaves}");
+    for selection in [
+        &mut NearestVertex as &mut dyn VertexSelection<f64>,
+        &mut ExplorationScore,
+        &mut BestCost(0.2),
+    ] {
+        let mut is_free = |p: &[f64]| !(p[0].abs() < 0.5 && p[1].abs() < 0.5);
+        let result = crate::RrtPlanner::new()
+            .start(&[-1.2, 0.0])
+            .goal(&[1.2, 0.0])
+            .extend_length(0.2)
+            .max_iterations(3000)
+            .selection(selection)
+            .sampler(sample)
+            .plan(is_free)
+            .unwrap();
+        assert_eq!(result[0], [-1.2, 0.0]);
+        assert_eq!(result[result.len() - 1], [1.2, 0.0]);
+        for w in result.windows(2) {
+            assert!(is_edge_free(&w[0], &w[1], 0.2, &mut is_free));
+        }
+    }
+    // the random choices are drawn from the rng of the planner
+    let plan = |seed| {
+        crate::RrtPlanner::new()
+            .start(&[-1.2, 0.0])
+            .goal(&[1.2, 0.0])
+            .extend_length(0.2)
+            .max_iterations(3000)
+            .selection(ExplorationScore)
+            .on_coincident(CoincidentSample::Jitter(0.05))
+            .seed(seed)
+            .sampler_with_rng(|rng| vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)])
+            .plan(|p: &[f64]| !(p[0].abs() < 0.5 && p[1].abs() < 0.5))
+            .unwrap()
+    };
+    assert_eq!(plan(1), plan(1));
+}
+
+#[test]
+fn perturb_works() {
+    let mut is_free = |p: &[f64]| !(p[0].abs() < 0.5 && p[1].abs() < 0.5);
+    let mut path = vec![
+        vec![-1.0, 0.0],
+        vec![-0.8, 1.5],
+        vec![0.0, 0.6],
+        vec![0.8, 1.5],
+        vec![1.0, 0.0],
+    ];
+    let initial_length = crate::path::path_length(&path);
+    perturb_path(
+        &mut path,
+        is_free,
+        crate::path::path_length,
+        0.05,
+        0.01,
+        0.01,
+        1000,
+        &mut rand::thread_rng(),
+    );
+    assert!(crate::path::path_length(&path) < initial_length);
+    assert_eq!(path.len(), 5);
+    for w in path.windows(2) {
+        assert!(is_edge_free(&w[0], &w[1], 0.01, &mut is_free));
+    }
+}
+
+#[test]
+fn partial_smoothing_works() {
+    let mut is_free = |p: &[f64]| !(p[0].abs() < 0.5 && p[1].abs() < 0.5);
+    // no shortcut between the waypoints is free, but the corners can be cut
+    let mut path = vec![
+        vec![-1.0, 0.0],
+        vec![-1.0, 1.0],
+        vec![1.0, 1.0],
+        vec![1.0, 0.0],
+    ];
+    let mut smoothed = path.clone();
+    smooth_path(&mut smoothed, is_free, 0.01, 100);
+    assert_eq!(smoothed, path);
+    // with a margin, since the parts of the checked edges are checked at
+    // the other points
+    let with_margin = |p: &[f64]| !(p[0].abs() < 0.52 && p[1].abs() < 0.52);
+    smooth_path_partial(&mut path, with_margin, 0.01, 1000, &mut rand::thread_rng());
+    assert!(crate::path::path_length(&path) < 3.2);
+    assert_eq!(path[0], [-1.0, 0.0]);
+    assert_eq!(path[path.len() - 1], [1.0, 0.0]);
+    for w in path.windows(2) {
+        assert!(is_edge_free(&w[0], &w[1], 0.01, &mut is_free));
+    }
+}
+
+#[test]
+fn tree_works() {
+    let mut tree = Tree::new(&[0.0, 0.0]);
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let mut always_free = |_: &[f64]| true;
+    let index = tree.add_vertex(&[0.0, 1.0]);
+    tree.add_edge(0, index);
+    assert_eq!(
+        tree.extend(&[0.0, 3.0], 0.5, 0.5, &mut always_free, &mut rng),
+        ExtendStatus::Advanced(2)
+    );
+    assert_eq!(
+        tree.connect(&[0.0, 3.0], 0.5, 0.5, &mut always_free, &mut rng),
+        ExtendStatus::Reached(5)
+    );
+    assert_eq!(tree.len(), 6);
+    assert_eq!(tree.nearest(&[0.1, 1.4]), 2);
+    assert_eq!(tree.node(2).parent_index, Some(1));
+    assert_eq!(tree.cost(5), 3.0);
+    assert_eq!(
+        tree.edges().collect::<Vec<_>>(),
+        [(0, 1), (1, 2), (2, 3), (3, 4), (4, 5)]
+    );
+    let path = tree.path(2);
+    assert_eq!(path, [[0.0, 0.0], [0.0, 1.0], [0.0, 1.5]]);
+}
+
+#[test]
+fn flat_vertices_work() {
+    let mut tree = Tree::new(&[0.0, 0.0]);
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let mut always_free = |_: &[f64]| true;
+    assert_eq!(
+        tree.connect(&[0.0, 3.0], 0.5, 0.5, &mut always_free, &mut rng),
+        ExtendStatus::Reached(6)
+    );
+    let path = tree.path(2);
+    assert_eq!(path, [[0.0, 0.0], [0.0, 0.5], [0.0, 1.0]]);
+    assert_eq!(tree.flat_path(2).to_vecs(), path);
+    assert_eq!(tree.flat_path(2).as_slice(), [0.0, 0.0, 0.0, 0.5, 0.0, 1.0]);
+    assert_eq!(tree.vertex(2), [0.0, 1.0]);
+    assert_eq!(tree.vertices().len(), 7);
+    assert_eq!(&tree.vertices()[6], [0.0, 3.0]);
+}
+
+#[test]
+fn motion_check_works() {
+    use rand::distributions::{Distribution, Uniform};
+    // a thin wall which can be tunneled through with the point checks
+    let wall_free = |p: &[f64]| !(p[0].abs() < 0.01 && p[1] < 1.0);
+    let is_motion_free = |from: &[f64], to: &[f64]| {
+        // the segment crosses x = 0 below y = 1
+        if (from[0] < 0.0) != (to[0] < 0.0) {
+            let t = from[0] / (from[0] - to[0]);
+            if from[1] + (to[1] - from[1]) * t < 1.0 {
+                return false;
+            }
+        }
+        wall_free(to)
+    };
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let path = crate::RrtPlanner::new()
+        .start(&[-1.0, 0.0])
+        .goal(&[1.0, 0.0])
+        .extend_length(0.3)
+        .sampler(random_sample)
+        .plan_with_motion_check(is_motion_free)
+        .unwrap();
+    assert!(path.windows(2).all(|w| is_motion_free(&w[0], &w[1])));
+    assert!(path.iter().any(|q| q[1] >= 1.0));
+}
+
+#[test]
+fn strategies_work() {
+    use rand::distributions::{Distribution, Uniform};
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    // a sampler which is stuck at the start is replaced by the uniform one
+    let stuck = || vec![-1.2, 0.0];
+    let result = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .sampler(stuck)
+        .strategy(random_sample)
+        .stall_iterations(20)
+        .plan_with_stats(|p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0))
+        .unwrap();
+    assert!(result.path.len() >= 4);
+    let switches = result.switches;
+    assert_eq!(switches[0].from, 0);
+    assert_eq!(switches[0].to, 1);
+    assert!(switches[0].iteration >= 20);
+}
+
+#[test]
+fn rng_works() {
+    // reproducible with the same seed
+    let plan = |seed| {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let mut path = crate::RrtPlanner::new()
+            .start(&[-1.2, 0.0])
+            .goal(&[1.2, 0.0])
+            .extend_length(0.2)
+            .sampler_with_rng(|rng| vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)])
+            .seed(seed)
+            .plan(|p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0))
+            .unwrap();
+        smooth_path_with_rng(
+            &mut path,
+            |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0),
+            0.2,
+            100,
+            &mut rng,
+        );
+        path
+    };
+    assert_eq!(plan(1), plan(1));
+    assert_ne!(plan(1), plan(2));
+}
+
+#[test]
+fn greedy_smoothing_works() {
+    let is_free = |p: &[f64]| !(p[0].abs() < 0.5 && p[1].abs() < 0.5);
+    // no shortcut between the waypoints is free
+    let path = vec![
+        vec![-1.0, 0.0],
+        vec![-1.0, 1.0],
+        vec![1.0, 1.0],
+        vec![1.0, 0.0],
+    ];
+    let mut greedy = path.clone();
+    smooth_path_greedy(&mut greedy, is_free, 0.01);
+    assert_eq!(greedy, path);
+    // the greedy shortcuts depend only on the path
+    let mut path = vec![
+        vec![-1.5, 0.0],
+        vec![-1.5, 0.8],
+        vec![-1.2, 1.5],
+        vec![0.0, 1.6],
+        vec![1.2, 1.5],
+        vec![1.5, 0.8],
+        vec![1.5, 0.0],
+    ];
+    smooth_path_greedy(
+        &mut path,
+        |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0),
+        0.01,
+    );
+    assert_eq!(
+        path,
+        [
+            vec![-1.5, 0.0],
+            vec![-1.2, 1.5],
+            vec![1.2, 1.5],
+            vec![1.5, 0.0]
+        ]
+    );
+}
+
+#[test]
+fn errors_work() {
+    use rand::distributions::{Distribution, Uniform};
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let plan = |start: &[f64], goal: &[f64], num_max_try| {
+        dual_rrt_connect(start, goal, is_free, random_sample, 0.2, num_max_try)
+    };
+    assert_eq!(
+        plan(&[0.0, 0.0], &[1.2, 0.0], 10),
+        Err(Error::StartInCollision)
+    );
+    assert_eq!(
+        plan(&[1.2, 0.0], &[0.0, 0.0], 10),
+        Err(Error::GoalInCollision)
+    );
+    assert_eq!(
+        plan(&[1.2, 0.0], &[1.2, 0.0, 0.0], 10),
+        Err(Error::DimensionMismatch { start: 2, goal: 3 })
+    );
+    let Err(Error::MaxIterationsReached {
+        best_partial: Some(partial),
+    }) = plan(&[-1.2, 0.0], &[1.2, 0.0], 0)
+    else {
+        panic!();
+    };
+    assert_eq!(partial, [[-1.2, 0.0]]);
+    assert_eq!(
+        Error::<f64>::MaxIterationsReached { best_partial: None }.to_string(),
+        "failed to find a path within the maximum number of iterations"
+    );
+}
+
+#[test]
+fn stats_work() {
+    use rand::distributions::{Distribution, Uniform};
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    // statistics
+    let planner = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .sampler(random_sample);
+    let result = planner.plan_with_stats(is_free).unwrap();
+    assert_eq!(result.path[0], [-1.2, 0.0]);
+    assert!((result.length - crate::path::path_length(&result.path)).abs() < 1e-9);
+    assert!(result.length > 2.4);
+    assert!(result.start_tree_size >= 1 && result.goal_tree_size >= 1);
+    assert!(result.num_iterations >= 1);
+    assert!(result.num_collision_checks > result.num_iterations);
+    let result = planner
+        .start(&[-1.2, 1.2])
+        .goal(&[1.2, 1.2])
+        .plan_with_stats(is_free)
+        .unwrap();
+    assert_eq!((result.start_tree_size, result.goal_tree_size), (0, 0));
+    assert_eq!(result.num_iterations, 0);
+    assert!((result.length - 2.4).abs() < 1e-9);
+}
+
+#[test]
+fn progress_works() {
+    use rand::distributions::{Distribution, Uniform};
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    // closest vertices of two trees
+    let mut tree_a = Tree::new(&[0.0, 0.0]);
+    let mut tree_b = Tree::new(&[3.0, 0.0]);
+    let index = tree_a.add_vertex(&[1.0, 0.0]);
+    tree_a.add_edge(0, index);
+    let index = tree_b.add_vertex(&[2.5, 0.5]);
+    tree_b.add_edge(0, index);
+    let index = tree_b.add_vertex(&[1.5, 0.0]);
+    tree_b.add_edge(1, index);
+    assert_eq!(tree_a.closest_pair(&tree_b), (1, 2, 0.5));
+    assert_eq!(tree_b.closest_pair(&tree_a), (2, 1, 0.5));
+
+    // the gap between the trees is reported until they are connected
+    let mut reports = Vec::new();
+    let result = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .sampler(random_sample)
+        .on_progress(1, |progress: &Progress<f64>| reports.push(progress.clone()))
+        .plan(is_free)
+        .unwrap();
+    assert!(result.len() >= 4);
+    for (i, progress) in reports.iter().enumerate() {
+        assert_eq!(progress.num_tries, i + 1);
+        let (q_start, q_goal) = &progress.closest_pair;
+        assert!((squared_euclidean(q_start, q_goal).sqrt() - progress.gap).abs() < 1e-9);
+        assert!(progress.start_tree_size + progress.goal_tree_size > 2);
+    }
+    assert!(reports.iter().any(|progress| progress.gap > 0.0));
+
+    // the trees are shown as they grow
+    let mut sizes = Vec::new();
+    let result = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .sampler(random_sample)
+        .on_trees(
+            |start_tree: TreeView<'_, f64>, goal_tree: TreeView<'_, f64>| {
+                assert!(start_tree.is_start_tree() && !goal_tree.is_start_tree());
+                assert_eq!(start_tree.vertex(0), [-1.2, 0.0]);
+                assert_eq!(goal_tree.parent(0), None);
+                sizes.push((start_tree.len(), goal_tree.len()));
+            },
+        )
+        .plan_with_stats(is_free)
+        .unwrap();
+    assert!(sizes
+        .windows(2)
+        .all(|w| w[0].0 <= w[1].0 && w[0].1 <= w[1].1));
+    assert_eq!(
+        sizes.last(),
+        Some(&(result.start_tree_size, result.goal_tree_size))
+    );
+}
+
+#[test]
+fn dual_rrt_connect_works() {
+    use rand::distributions::{Distribution, Uniform};
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    // step by step, with both trees accessible
+    let mut planner = DualRrtConnect::new(&[-1.2, 0.0], &[1.2, 0.0], 0.2);
+    let mut is_free = is_free;
+    let mut connected = false;
+    for _ in 0..1000 {
+        if planner.step(&random_sample(), 0.01, &mut is_free) {
+            connected = true;
+            break;
+        }
+        assert!(planner.path().is_none());
+        let (start_index, goal_index, gap) = planner.closest_pair();
+        let start_q = &planner.start_tree().node(start_index).data;
+        let goal_q = &planner.goal_tree().node(goal_index).data;
+        assert!((squared_euclidean(start_q, goal_q).sqrt() - gap).abs() < 1e-9);
+    }
+    assert!(connected);
+    assert_eq!(planner.start_tree().node(0).data, [-1.2, 0.0]);
+    assert_eq!(planner.goal_tree().node(0).data, [1.2, 0.0]);
+    let num_tries = planner.num_tries();
+    assert!(planner.step(&random_sample(), 0.01, &mut is_free));
+    assert_eq!(planner.num_tries(), num_tries);
+    let path = planner.path().unwrap();
+    assert_eq!(path[0], [-1.2, 0.0]);
+    assert_eq!(path[path.len() - 1], [1.2, 0.0]);
+    for w in path.windows(2) {
+        assert!(is_edge_free(&w[0], &w[1], 0.01, &mut is_free));
+    }
+    assert_eq!(planner.best_partial()[0], [-1.2, 0.0]);
+}
+
+#[test]
+fn revalidate_works() {
+    use rand::distributions::{Distribution, Uniform};
+    // a branch through a new obstacle is removed with its descendants
+    let mut tree = Tree::new(&[0.0, 0.0]);
+    for (parent, q) in [(0, [1.0, 0.0]), (1, [2.0, 0.0]), (0, [0.0, 1.0])] {
+        let index = tree.add_vertex(&q);
+        tree.add_edge(parent, index);
+    }
+    let mut is_free = |p: &[f64]| !((p[0] - 0.5).abs() < 0.1 && p[1].abs() < 0.1);
+    assert_eq!(
+        tree.prune(0.01, &mut is_free),
+        [Some(0), None, None, Some(1)]
+    );
+    assert_eq!(tree.len(), 2);
+    assert_eq!(tree.nearest(&[2.0, 0.0]), 0);
+    assert_eq!(tree.node(1).parent_index, Some(0));
+    assert_eq!(tree.num_children(0), 1);
+    // the parents may have higher indices than their children
+    let mut tree = Tree::new(&[0.0, 0.0]);
+    let (child, parent) = (tree.add_vertex(&[0.0, 2.0]), tree.add_vertex(&[0.0, 1.0]));
+    let blocked = tree.add_vertex(&[1.0, 0.0]);
+    tree.add_edge(parent, child);
+    tree.add_edge(0, parent);
+    tree.add_edge(0, blocked);
+    assert_eq!(
+        tree.prune(0.01, &mut is_free),
+        [Some(0), Some(2), Some(1), None]
+    );
+    assert_eq!(tree.node(2).parent_index, Some(1));
+    assert_eq!(tree.vertex(2), [0.0, 2.0]);
+
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let square = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let mut is_free = square;
+    let mut planner = DualRrtConnect::new(&[-1.2, 0.0], &[1.2, 0.0], 0.2);
+    while !planner.step(&random_sample(), 0.01, &mut is_free) {}
+    let path = planner.path().unwrap().to_vec();
+    let num_vertices = planner.start_tree().len() + planner.goal_tree().len();
+    assert_eq!(planner.revalidate(0.01, &mut is_free), 0);
+    assert_eq!(planner.path().unwrap(), path);
+    // a new obstacle on the path: only the blocked parts are removed
+    let q = path[path.len() / 2].clone();
+    let mut is_free =
+        |p: &[f64]| square(p) && !((p[0] - q[0]).abs() < 0.05 && (p[1] - q[1]).abs() < 0.05);
+    let num_removed = planner.revalidate(0.01, &mut is_free);
+    assert!(num_removed > 0);
+    assert_eq!(
+        planner.start_tree().len() + planner.goal_tree().len(),
+        num_vertices - num_removed
+    );
+    assert!(planner.path().is_none());
+    for tree in [planner.start_tree(), planner.goal_tree()] {
+        for (parent, child) in tree.edges() {
+            assert!(is_edge_free(
+                tree.vertex(parent),
+                tree.vertex(child),
+                0.01,
+                &mut is_free
+            ));
+        }
+    }
+    while !planner.step(&random_sample(), 0.01, &mut is_free) {}
+    let path = planner.path().unwrap();
+    for w in path.windows(2) {
+        assert!(is_edge_free(&w[0], &w[1], 0.01, &mut is_free));
+    }
+}
+
+#[test]
+fn provenance_works() {
+    use rand::distributions::{Distribution, Uniform};
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let mut is_free = is_free;
+    let mut planner = DualRrtConnect::new(&[-1.2, 0.0], &[1.2, 0.0], 0.2);
+    while !planner.step(&random_sample(), 0.01, &mut is_free) {}
+    let path = planner.path().unwrap();
+    let waypoints = planner.waypoints().unwrap();
+    assert_eq!(waypoints.len(), path.len());
+    for (waypoint, q) in waypoints.iter().zip(path) {
+        let tree = match waypoint.tree {
+            TreeRole::Start => planner.start_tree(),
+            TreeRole::Goal => planner.goal_tree(),
+        };
+        assert_eq!(&tree.node(waypoint.vertex).data, q);
+    }
+    assert_eq!(
+        (waypoints[0].tree, waypoints[0].vertex),
+        (TreeRole::Start, 0)
+    );
+    let last = waypoints.last().unwrap();
+    assert_eq!((last.tree, last.vertex), (TreeRole::Goal, 0));
+
+    let waypoints = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .sampler(random_sample)
+        .plan_waypoints(is_free)
+        .unwrap();
+    assert_eq!(waypoints[0].position, [-1.2, 0.0]);
+    assert_eq!(waypoints[0].tree, TreeRole::Start);
+    assert_eq!(waypoints.last().unwrap().tree, TreeRole::Goal);
+    // the waypoints of the start tree come first
+    let num_start = waypoints
+        .iter()
+        .take_while(|w| w.tree == TreeRole::Start)
+        .count();
+    assert!(waypoints[num_start..]
+        .iter()
+        .all(|w| w.tree == TreeRole::Goal));
+}
+
+#[test]
+fn is_reachable_works() {
+    use rand::distributions::{Distribution, Uniform};
+    use std::time::Instant;
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let planner = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .time_budget(Duration::from_millis(100))
+        .sampler(random_sample);
+    assert_eq!(planner.is_reachable(is_free), Ok(true));
+    let planner = planner.goal(&[-1.2, 0.5]);
+    assert_eq!(planner.is_reachable(is_free), Ok(true));
+    let planner = planner.goal(&[0.0, 0.0]);
+    assert_eq!(planner.is_reachable(is_free), Ok(false));
+    let started = Instant::now();
+    let wall = |p: &[f64]| p[0].abs() > 0.1;
+    let planner = planner.goal(&[1.2, 0.0]);
+    assert_eq!(planner.is_reachable(wall), Ok(false));
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn termination_works() {
+    use crate::budget::TerminationCondition;
+    use rand::distributions::{Distribution, Uniform};
+    use std::cell::Cell;
+    use std::time::Instant;
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let budget = Duration::from_millis(100);
+    let wall = |p: &[f64]| p[0].abs() > 0.1;
+    let planner = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .termination(TerminationCondition::new().timeout(budget))
+        .sampler(random_sample);
+    let path = planner.plan(is_free).unwrap();
+    assert_eq!(path[0], [-1.2, 0.0]);
+    assert_eq!(path[path.len() - 1], [1.2, 0.0]);
+    let started = Instant::now();
+    assert!(matches!(
+        planner.plan(wall),
+        Err(Error::MaxIterationsReached { .. })
+    ));
+    assert!(started.elapsed() >= budget);
+    let num_samples = Cell::new(0);
+    let counting_sample = || {
+        num_samples.set(num_samples.get() + 1);
+        random_sample()
+    };
+    let planner = planner
+        .termination(TerminationCondition::new().max_iterations(10))
+        .sampler(counting_sample);
+    assert!(planner.plan(wall).is_err());
+    assert_eq!(num_samples.get(), 10);
+    // a tree size alone may never stop a search which makes no progress
+    let planner = planner.termination(TerminationCondition::new().max_tree_size(20));
+    assert_eq!(
+        planner.plan(wall).unwrap_err(),
+        Error::InvalidConfiguration(
+            "the termination condition has no timeout, iteration limit or cancellation"
+        )
+    );
+    let planner = planner.termination(
+        TerminationCondition::new()
+            .max_tree_size(20)
+            .timeout(Duration::from_secs(10)),
+    );
+    assert!(matches!(
+        planner.plan(wall),
+        Err(Error::MaxIterationsReached { .. })
+    ));
+    let token = crate::budget::CancellationToken::new();
+    token.cancel();
+    let planner = planner.termination(
+        TerminationCondition::new()
+            .timeout(Duration::from_secs(10))
+            .cancelled_by(token),
+    );
+    let started = Instant::now();
+    assert!(planner.plan(wall).is_err());
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn region_works() {
+    use crate::goal::PointGoal;
+    use rand::distributions::{Distribution, Uniform};
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let region = PointGoal::new(&[1.2, 0.0], 0.3);
+    let planner = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .extend_length(0.2)
+        .sampler(random_sample);
+    let path = planner.plan_to_region(&region, is_free).unwrap();
+    assert_eq!(path[0], [-1.2, 0.0]);
+    assert!(region.contains(path.last().unwrap()));
+    assert!(path[..path.len() - 1].iter().all(|q| !region.contains(q)));
+    // any configuration above the obstacle
+    let above = |q: &[f64]| q[1] > 1.5;
+    let path = planner.plan_to_region(&above, is_free).unwrap();
+    assert!(above(path.last().unwrap()));
+    let planner = planner.max_iterations(0);
+    assert_eq!(
+        planner.plan_to_region(&above, is_free),
+        Err(Error::MaxIterationsReached { best_partial: None })
+    );
+    assert_eq!(
+        planner.plan_to_region(&region, is_free),
+        Err(Error::MaxIterationsReached {
+            best_partial: Some(vec![vec![-1.2, 0.0]])
+        })
+    );
+}
+
+#[test]
+fn goals_work() {
+    use rand::distributions::{Distribution, Uniform};
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    // many solutions of the inverse kinematics, one of them in collision
+    let goals = vec![vec![0.0, 0.0], vec![1.2, 0.0], vec![0.0, -1.5]];
+    let planner = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .extend_length(0.2)
+        .sampler(random_sample);
+    let path = planner.plan_to_goals(&goals, is_free).unwrap();
+    assert_eq!(path[0], [-1.2, 0.0]);
+    assert!(goals[1..].contains(path.last().unwrap()));
+    assert!(path.iter().all(|q| is_free(q)));
+    assert_eq!(
+        planner.plan_to_goals(&goals[..1], is_free),
+        Err(Error::GoalInCollision)
+    );
+    assert!(matches!(
+        planner.plan_to_goals(&[], is_free),
+        Err(Error::InvalidConfiguration(_))
+    ));
+    let mut rng = rand::thread_rng();
+    let path = planner
+        .plan_with_goal_sampler(
+            || {
+                rng.gen_bool(0.1)
+                    .then(|| goals[rng.gen_range(0..goals.len())].clone())
+            },
+            is_free,
+        )
+        .unwrap();
+    assert!(goals[1..].contains(path.last().unwrap()));
+}
+
+#[test]
+fn relaxation_works() {
+    use rand::distributions::{Distribution, Uniform};
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    // the goal in the obstacle is approached as close as the schedule allows
+    let tolerances = [0.1, 0.5, 1.2];
+    let planner = crate::RrtPlanner::new()
+        .start(&[-1.5, 0.0])
+        .goal(&[0.0, 0.0])
+        .extend_length(0.2)
+        .max_iterations(300)
+        .sampler(random_sample);
+    let relaxed = planner.plan_relaxed(&tolerances, is_free).unwrap();
+    assert_eq!(relaxed.tolerance, 1.2);
+    assert!(relaxed.distance <= 1.2 && relaxed.distance >= 1.0);
+    assert_eq!(relaxed.path[0], [-1.5, 0.0]);
+    assert_eq!(
+        squared_euclidean(relaxed.path.last().unwrap(), &[0.0, 0.0]).sqrt(),
+        relaxed.distance
+    );
+    // the free goal is reached exactly
+    let planner = planner.goal(&[1.5, 0.0]).max_iterations(1000);
+    let relaxed = planner.plan_relaxed(&tolerances, is_free).unwrap();
+    assert_eq!(relaxed.tolerance, 0.1);
+    assert!(relaxed.distance <= 0.1);
+    assert!(matches!(
+        planner.plan_relaxed(&[0.5, 0.1], is_free),
+        Err(Error::InvalidConfiguration(_))
+    ));
+}
+
+#[test]
+fn batch_works() {
+    use rand::distributions::{Distribution, Uniform};
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    // checked in batches
+    let mut batch_sizes = Vec::new();
+    let planner = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .sampler(random_sample);
+    let path = planner
+        .plan_batch(8, |batch: &[Vec<f64>]| {
+            batch_sizes.push(batch.len());
+            batch.iter().map(|q| is_free(q)).collect()
+        })
+        .unwrap();
+    assert_eq!(path[0], [-1.2, 0.0]);
+    assert_eq!(path[path.len() - 1], [1.2, 0.0]);
+    for w in path.windows(2) {
+        assert!(squared_euclidean(&w[0], &w[1]).sqrt() <= 0.2 + 1e-9);
+        assert!(is_free(&w[1]));
+    }
+    assert_eq!(batch_sizes[0], 2);
+    assert!(batch_sizes.iter().any(|n| *n > 1));
+    let check = |batch: &[Vec<f64>]| batch.iter().map(|q| is_free(q)).collect();
+    assert_eq!(
+        planner.start(&[0.0, 0.0]).plan_batch(8, check),
+        Err(Error::StartInCollision)
+    );
+    // a wall thinner than the steps is not jumped over
+    let is_free = |p: &[f64]| !(p[0].abs() < 0.01 && p[1] < 1.5);
+    let check = |batch: &[Vec<f64>]| batch.iter().map(|q| is_free(q)).collect();
+    let path = crate::RrtPlanner::new()
+        .start(&[-0.5, 0.0])
+        .goal(&[0.5, 0.0])
+        .extend_length(0.2)
+        .validity_resolution(0.005)
+        .sampler(random_sample)
+        .plan_batch(8, check)
+        .unwrap();
+    for w in path.windows(2) {
+        assert!(is_edge_free(&w[0], &w[1], 0.005, &mut |q: &[f64]| {
+            is_free(q)
+        }));
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_works() {
+    use rand::distributions::{Distribution, Uniform};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let num_checks = AtomicUsize::new(0);
+    let is_free = |p: &[f64]| {
+        num_checks.fetch_add(1, Ordering::Relaxed);
+        !(p[0].abs() < 1.0 && p[1].abs() < 1.0)
+    };
+    let random_sample = || {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    };
+    let planner = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .sampler(random_sample);
+    let path = planner.plan_parallel(8, is_free).unwrap();
+    assert_eq!(path[0], [-1.2, 0.0]);
+    assert_eq!(path[path.len() - 1], [1.2, 0.0]);
+    for w in path.windows(2) {
+        assert!(squared_euclidean(&w[0], &w[1]).sqrt() <= 0.2 + 1e-9);
+        assert!(is_free(&w[1]));
+    }
+    assert!(num_checks.load(Ordering::Relaxed) > 0);
+    assert!(matches!(
+        planner.max_iterations(0).plan_parallel(8, is_free),
+        Err(Error::MaxIterationsReached {
+            best_partial: Some(_)
+        })
+    ));
+}
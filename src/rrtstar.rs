@@ -17,14 +17,26 @@
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
 
+use crate::budget::{
+    ConditionMonitor, IterationController, SoftDeadline, StopReason, TerminationCondition,
+};
+use crate::collision::Scene;
+use crate::goal::GoalRegion;
+use crate::path::subdivide;
+use crate::roadmap::Roadmap;
+use crate::rrt::{is_edge_free, Bounds, Error, OutOfBounds};
+use crate::steer::steer;
 use kdtree::distance::squared_euclidean;
 use num_traits::float::Float;
 use num_traits::identities::Zero;
 use rand::{
     distributions::{Distribution, Uniform},
-    RngCore,
+    Rng, RngCore,
 };
+use std::cell::Cell;
 use std::fmt::Debug;
+use std::io::Write;
+use std::time::{Duration, Instant};
 
 // #[derive(Debug)]
 // enum ExtendStatus {
@@ -42,8 +54,11 @@ impl Weight for f32 {}
 /// Node that contains user data
 #[derive(Debug, Clone)]
 pub struct Node<T, W: Weight> {
+    /// Index of the parent node, `None` for the root
     pub parent_index: Option<usize>,
+    /// User data (the configuration)
     pub data: T,
+    /// Cost to come from the root
     pub weight: W,
 }
 
@@ -57,6 +72,52 @@ impl<T, W: Weight> Node<T, W> {
     }
 }
 
+/// Statistics of the rewiring step, to evaluate if rewiring pays off
+#[derive(Debug, Clone)]
+pub struct RewireStats<N, W> {
+    /// Number of neighbours considered for rewiring
+    pub num_candidates: usize,
+    /// Number of parent changes
+    pub num_rewires: usize,
+    /// Sum of the cost decrease of the rewired nodes
+    pub total_cost_improvement: W,
+    /// Neighbourhood radius used at each iteration which added a vertex
+    pub radius_history: Vec<N>,
+}
+
+impl<N, W: Weight> Default for RewireStats<N, W> {
+    fn default() -> Self {
+        RewireStats {
+            num_candidates: 0,
+            num_rewires: 0,
+            total_cost_improvement: W::zero(),
+            radius_history: Vec::new(),
+        }
+    }
+}
+
+impl<N, W: Weight> RewireStats<N, W> {
+    /// Average cost decrease per rewire, zero if nothing was rewired
+    pub fn average_cost_improvement(&self) -> W {
+        if self.num_rewires == 0 {
+            W::zero()
+        } else {
+            self.total_cost_improvement / W::from(self.num_rewires).unwrap()
+        }
+    }
+}
+
+/// Cost of the best solution at an improvement
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvergencePoint<W> {
+    /// Iteration of the improvement
+    pub iteration: usize,
+    /// Time from the start of planning
+    pub elapsed: Duration,
+    /// Length of the path from the start to the goal
+    pub cost: W,
+}
+
 /// RRT
 #[derive(Debug)]
 pub struct Tree<N, W>
@@ -71,6 +132,12 @@ where
     pub vertices: Vec<Node<Vec<N>, W>>,
     /// The goal index
     pub goal_index: Option<usize>,
+    /// Statistics of the rewiring
+    pub rewire_stats: RewireStats<N, W>,
+    /// Best solution cost at each improvement
+    pub convergence: Vec<ConvergencePoint<W>>,
+    // Indices of the children of each vertex
+    children: Vec<Vec<usize>>,
 }
 
 // impl default for Tree
@@ -84,6 +151,9 @@ where
             kdtree: kdtree::KdTree::new(2),
             vertices: Vec::new(),
             goal_index: None,
+            rewire_stats: RewireStats::default(),
+            convergence: Vec::new(),
+            children: Vec::new(),
         }
     }
 }
@@ -98,6 +168,9 @@ where
             kdtree: kdtree::KdTree::new(dim),
             vertices: Vec::new(),
             goal_index: None,
+            rewire_stats: RewireStats::default(),
+            convergence: Vec::new(),
+            children: Vec::new(),
         }
     }
 
@@ -106,16 +179,35 @@ where
         let index = self.vertices.len();
         self.kdtree.add(q.to_vec(), index).unwrap();
         self.vertices.push(Node::new(q.to_vec(), weight));
+        self.children.push(Vec::new());
         index
     }
 
-    //
+    // Add an edge from the parent to the child, which replaces the current
+    // parent of the child
     fn add_edge(&mut self, q1_index: usize, q2_index: usize) {
+        self.remove_edge(q2_index);
         self.vertices[q2_index].parent_index = Some(q1_index);
+        self.children[q1_index].push(q2_index);
     }
 
     fn remove_edge(&mut self, q_index: usize) {
-        self.vertices[q_index].parent_index = None;
+        if let Some(parent) = self.vertices[q_index].parent_index.take() {
+            self.children[parent].retain(|&child| child != q_index);
+        }
+    }
+
+    // Update the weights of the descendants of the nodes after their weights changed
+    fn update_descendant_weights(&mut self, indices: &[usize]) {
+        let mut stack = indices.to_vec();
+        while let Some(parent) = stack.pop() {
+            for &child in &self.children[parent] {
+                let d = squared_euclidean(&self.vertices[parent].data, &self.vertices[child].data)
+                    .sqrt();
+                self.vertices[child].weight = self.vertices[parent].weight + W::from(d).unwrap();
+                stack.push(child);
+            }
+        }
     }
 
     //
@@ -134,6 +226,46 @@ where
         nodes
     }
 
+    /// Path from the root to the node
+    pub fn path(&self, index: usize) -> Vec<Vec<N>> {
+        let mut path = self.get_until_root(index);
+        path.reverse();
+        path.push(self.vertices[index].data.clone());
+        path
+    }
+
+    /// Length of the path from the root to the node
+    pub fn path_cost(&self, index: usize) -> W {
+        let mut cost = W::zero();
+        let mut cur_index = index;
+        while let Some(parent_index) = self.vertices[cur_index].parent_index {
+            let d = squared_euclidean(
+                &self.vertices[cur_index].data,
+                &self.vertices[parent_index].data,
+            )
+            .sqrt();
+            cost = cost + W::from(d).unwrap();
+            cur_index = parent_index;
+        }
+        cost
+    }
+
+    /// Write the convergence curve as CSV with the columns `iteration,elapsed,cost`
+    /// (elapsed in seconds)
+    pub fn write_convergence_csv<T: Write>(&self, mut writer: T) -> std::io::Result<()> {
+        writeln!(writer, "iteration,elapsed,cost")?;
+        for point in &self.convergence {
+            writeln!(
+                writer,
+                "{},{},{}",
+                point.iteration,
+                point.elapsed.as_secs_f64(),
+                point.cost.to_f64().unwrap()
+            )?;
+        }
+        Ok(())
+    }
+
     // Get indices of nerest nodes within a radius
     fn get_nearest_neighbours(&self, q_new: &[N], extend_length: N) -> Vec<usize> {
         self.kdtree
@@ -145,12 +277,82 @@ where
     }
 }
 
+impl<N, W> Tree<N, W>
+where
+    N: Float + Zero + Debug,
+    W: Weight,
+{
+    /// Convert the tree into a roadmap which can be reused for other queries.
+    ///
+    /// In addition to the tree edges, each pair of vertices closer than
+    /// `connection_radius` is connected if the edge is free (checked every
+    /// `validity_resolution`).
+    pub fn into_roadmap<FF>(
+        self,
+        mut is_free: FF,
+        validity_resolution: N,
+        connection_radius: N,
+    ) -> Roadmap<N>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let mut roadmap = Roadmap::default();
+        for vertex in &self.vertices {
+            roadmap.add_vertex(&vertex.data);
+        }
+        for (index, vertex) in self.vertices.iter().enumerate() {
+            if let Some(parent) = vertex.parent_index {
+                roadmap.add_edge(parent, index);
+            }
+        }
+        for (index, vertex) in self.vertices.iter().enumerate() {
+            for neighbour in self.get_nearest_neighbours(&vertex.data, connection_radius) {
+                let other = &self.vertices[neighbour];
+                if neighbour <= index
+                    || vertex.parent_index == Some(neighbour)
+                    || other.parent_index == Some(index)
+                {
+                    continue;
+                }
+                if is_edge_free(&vertex.data, &other.data, validity_resolution, &mut is_free) {
+                    roadmap.add_edge(index, neighbour);
+                }
+            }
+        }
+        roadmap
+    }
+}
+
 /// RRT* error
 #[derive(Debug, derive_more::Error, derive_more::Display)]
 pub enum RRTStarError {
     /// Failed to find a path within the maximum number of iterations
     #[display(fmt = "Failed to find a path within the maximum number of iterations")]
     MaxItersReached,
+    /// Failed to find a path before the deadline
+    #[display(fmt = "Failed to find a path before the deadline")]
+    DeadlineReached,
+    /// The goal bias is not a probability in [0, 1]
+    #[display(fmt = "The goal bias is not a probability in [0, 1]")]
+    InvalidGoalBias,
+    /// The termination condition might never stop the planner
+    #[display(fmt = "The termination condition has no timeout, iteration limit or cancellation")]
+    UnboundedTermination,
+}
+
+impl<N> From<RRTStarError> for Error<N> {
+    fn from(error: RRTStarError) -> Self {
+        match error {
+            RRTStarError::MaxItersReached => Error::MaxIterationsReached { best_partial: None },
+            RRTStarError::DeadlineReached => Error::DeadlineReached,
+            RRTStarError::InvalidGoalBias => {
+                Error::InvalidConfiguration("the goal bias is not a probability in [0, 1]")
+            }
+            RRTStarError::UnboundedTermination => Error::InvalidConfiguration(
+                "the termination condition has no timeout, iteration limit or cancellation",
+            ),
+        }
+    }
 }
 
 // pub type RRTStarResult<N> = Result<Vec<Vec<N>>, RRTStarError>;
@@ -159,197 +361,1282 @@ pub type RRTStarResult<N, W> = Result<Tree<N, W>, RRTStarError>;
 
 /// search the path from start to goal which is free, using random_sample function
 /// https://erc-bpgc.github.io/handbook/automation/PathPlanners/Sampling_Based_Algorithms/RRT_Star/
+#[allow(clippy::too_many_arguments)]
 pub fn rrtstar<N>(
     start: &[N],
     goal: &[N],
-    mut is_collision_free: impl FnMut(&[N]) -> bool,
-    mut random_sample: impl FnMut() -> Vec<N>,
+    is_collision_free: impl FnMut(&[N]) -> bool,
+    random_sample: impl FnMut() -> Vec<N>,
     extend_length: N,
     max_iters: usize,
     neighbourhood_radius: N,
     stop_when_reach_goal: bool,
 ) -> RRTStarResult<N, f32>
-// ) -> Result<Vec<Vec<N>>, RRTStarError>
 where
-    // FF: FnMut(&[N]) -> bool,
-    // FR: Fn() -> Vec<N>,
     N: Float + Debug,
-    // W: Weight,
 {
-    assert_eq!(start.len(), goal.len());
-    let mut tree = Tree::<N, f32>::new(start.len());
-    tree.add_vertex(start, 0.0);
+    rrtstar_with_resolution(
+        start,
+        goal,
+        is_collision_free,
+        random_sample,
+        extend_length,
+        extend_length,
+        max_iters,
+        neighbourhood_radius,
+        stop_when_reach_goal,
+    )
+}
 
-    let mut goal_reached = false;
+/// Same as [`rrtstar`], but each edge is collision checked every
+/// `validity_resolution` instead of only at its end point.
+#[allow(clippy::too_many_arguments)]
+pub fn rrtstar_with_resolution<N>(
+    start: &[N],
+    goal: &[N],
+    is_collision_free: impl FnMut(&[N]) -> bool,
+    random_sample: impl FnMut() -> Vec<N>,
+    extend_length: N,
+    validity_resolution: N,
+    max_iters: usize,
+    neighbourhood_radius: N,
+    stop_when_reach_goal: bool,
+) -> RRTStarResult<N, f32>
+where
+    N: Float + Debug,
+{
+    rrtstar_with_neighbour_check(
+        start,
+        goal,
+        is_collision_free,
+        random_sample,
+        extend_length,
+        validity_resolution,
+        max_iters,
+        neighbourhood_radius,
+        stop_when_reach_goal,
+        check_edges(validity_resolution),
+        |_| false,
+    )
+}
 
-    // Path finding loop
-    for _ in 0..max_iters {
-        // 1. Random sample
-        let q_rand = random_sample();
-        // 2. Nearest neighbour
-        let nearest_index = tree.get_nearest_index(&q_rand);
-        let q_nearest = &tree.vertices[nearest_index].data;
-        // 3. Steer to get new point
-        let diff_dist = squared_euclidean(q_rand.as_slice(), q_nearest.as_slice()).sqrt();
-        let q_new = if diff_dist < extend_length {
-            q_rand.to_vec()
+/// Same as [`rrtstar`], but the goal itself is sampled with the probability
+/// returned by `goal_bias` for the current iteration.
+///
+/// A constant such as `|_| 0.1` gives the usual goal bias, while a schedule
+/// like `|i| 0.5 * 0.99f64.powi(i as i32)` decays it so that narrow passages
+/// are explored once the greedy attempts have failed. Planning stops with
+/// [`RRTStarError::InvalidGoalBias`] as soon as the schedule returns a bias
+/// which is not in [0, 1] (including NaN). Whether the goal is sampled is drawn from `rng`.
+#[allow(clippy::too_many_arguments)]
+pub fn rrtstar_with_goal_bias<N>(
+    start: &[N],
+    goal: &[N],
+    is_collision_free: impl FnMut(&[N]) -> bool,
+    mut random_sample: impl FnMut() -> Vec<N>,
+    mut goal_bias: impl FnMut(usize) -> f64,
+    extend_length: N,
+    validity_resolution: N,
+    max_iters: usize,
+    neighbourhood_radius: N,
+    stop_when_reach_goal: bool,
+    rng: &mut dyn RngCore,
+) -> RRTStarResult<N, f32>
+where
+    N: Float + Debug,
+{
+    let invalid_bias = Cell::new(false);
+    // one sample is drawn per iteration
+    let mut iteration = 0;
+    let biased_sample = || {
+        let bias = goal_bias(iteration);
+        iteration += 1;
+        if !(0.0..=1.0).contains(&bias) {
+            invalid_bias.set(true);
+            return random_sample();
+        }
+        if rng.gen_bool(bias) {
+            goal.to_vec()
         } else {
-            q_nearest
-                .iter()
-                .zip(q_rand)
-                .map(|(near, target)| *near + (target - *near) * extend_length / diff_dist)
-                .collect::<Vec<_>>()
-        };
-
-        // 4. Check if the new point is free
-        if !is_collision_free(&q_new) {
-            continue;
+            random_sample()
         }
+    };
+    let tree = rrtstar_with_neighbour_check(
+        start,
+        goal,
+        is_collision_free,
+        biased_sample,
+        extend_length,
+        validity_resolution,
+        max_iters,
+        neighbourhood_radius,
+        stop_when_reach_goal,
+        check_edges(validity_resolution),
+        |_| invalid_bias.get(),
+    );
+    if invalid_bias.get() {
+        return Err(RRTStarError::InvalidGoalBias);
+    }
+    tree
+}
 
-        // 5. Connect to the new point
-        // 5.1. Find nearest neighbours
-        let nearest = tree.get_nearest_neighbours(&q_new, neighbourhood_radius);
-        // 5.2. Insert the new point to the tree
-        let parent_weight = tree.vertices[nearest_index].weight;
-        let edge_weight = <f32 as num_traits::cast::NumCast>::from::<N>(extend_length)
-            .expect("N implements Float, same as W");
-        let cost_min = parent_weight + edge_weight;
-
-        let new_index = tree.add_vertex(&q_new, cost_min);
-        // 5.3. Connect to lowest cost path
-        let min_index = std::iter::once(&nearest_index)
-            .chain(nearest.iter())
-            .min_by(|&a, &b| {
-                let a_potential_weight = tree.vertices[*a].weight
-                    + <f32 as num_traits::cast::NumCast>::from(
-                        squared_euclidean(&q_new, &tree.vertices[*a].data).sqrt(),
-                    )
-                    .expect("N implements Float, same as W");
-
-                let b_potential_weight = tree.vertices[*b].weight
-                    + <f32 as num_traits::cast::NumCast>::from(
-                        squared_euclidean(&q_new, &tree.vertices[*b].data).sqrt(),
-                    )
-                    .expect("N implements Float, same as W");
-
-                a_potential_weight
-                    .partial_cmp(&b_potential_weight)
-                    .expect("Weight W of two nodes should be comparable")
-            })
-            .expect("iterator shouldn't be empty");
-
-        tree.add_edge(*min_index, new_index);
-
-        // 5.4. Rewire
-        for &near_index in nearest.iter() {
-            let near_weight = tree.vertices[near_index].weight;
-            let new_potential_cost = cost_min
-                + <f32 as num_traits::cast::NumCast>::from(
-                    squared_euclidean(&q_new, &tree.vertices[near_index].data).sqrt(),
-                )
-                .expect("N implements Float, same as W");
+/// Anytime RRT*, which improves the solution until it is good enough or
+/// the deadline has passed, instead of running a fixed number of iterations.
+///
+/// Returns the tree with the best solution found, or
+/// [`RRTStarError::DeadlineReached`] if no solution was found in time.
+#[allow(clippy::too_many_arguments)]
+pub fn rrtstar_with_deadline<N>(
+    start: &[N],
+    goal: &[N],
+    is_collision_free: impl FnMut(&[N]) -> bool,
+    random_sample: impl FnMut() -> Vec<N>,
+    extend_length: N,
+    validity_resolution: N,
+    neighbourhood_radius: N,
+    deadline: SoftDeadline,
+) -> RRTStarResult<N, f32>
+where
+    N: Float + Debug,
+{
+    let tree = rrtstar_with_deadline_impl(
+        start,
+        goal,
+        is_collision_free,
+        random_sample,
+        extend_length,
+        validity_resolution,
+        neighbourhood_radius,
+        deadline,
+    )?;
+    if tree.goal_index.is_none() {
+        return Err(RRTStarError::DeadlineReached);
+    }
+    Ok(tree)
+}
 
-            if new_potential_cost < near_weight {
-                tree.remove_edge(near_index);
-                tree.add_edge(new_index, near_index);
-                tree.vertices[near_index].weight = new_potential_cost;
+/// Same as [`rrtstar_with_deadline`], but the tree is returned even without
+/// solution
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rrtstar_with_deadline_impl<N>(
+    start: &[N],
+    goal: &[N],
+    is_collision_free: impl FnMut(&[N]) -> bool,
+    random_sample: impl FnMut() -> Vec<N>,
+    extend_length: N,
+    validity_resolution: N,
+    neighbourhood_radius: N,
+    deadline: SoftDeadline,
+) -> RRTStarResult<N, f32>
+where
+    N: Float + Debug,
+{
+    let mut controller = IterationController::new(deadline.budget);
+    let mut batch = 0;
+    rrtstar_with_neighbour_check(
+        start,
+        goal,
+        is_collision_free,
+        random_sample,
+        extend_length,
+        validity_resolution,
+        usize::MAX,
+        neighbourhood_radius,
+        false,
+        check_edges(validity_resolution),
+        |tree| {
+            let cost = tree.convergence.last().map(|point| point.cost);
+            if cost.is_some_and(|cost| deadline.is_good_enough(cost.into())) {
+                return true;
             }
-        }
-
-        // 6. Check if the goal is reached
-        if !goal_reached && squared_euclidean(&q_new, goal).sqrt() < extend_length {
-            let goal_weight = tree.vertices[new_index].weight
-                + <f32 as num_traits::cast::NumCast>::from(squared_euclidean(&q_new, goal).sqrt())
-                    .expect("N implements Float, same as W");
-            // println!("goal {:?} reached with weight {}", goal, goal_weight);
-            let goal_index = tree.add_vertex(goal, goal_weight);
-            tree.add_edge(new_index, goal_index);
-
-            tree.goal_index = Some(goal_index);
+            if batch == 0 {
+                batch = controller.next_batch();
+                if batch == 0 {
+                    return true;
+                }
+            }
+            batch -= 1;
+            false
+        },
+    )
+}
 
-            goal_reached = true;
+/// RRT*, which improves the solution until `condition` stops it.
+///
+/// Returns the tree with the best solution found, or
+/// [`RRTStarError::DeadlineReached`] if the timeout stopped it without
+/// solution, [`RRTStarError::MaxItersReached`] for the other criteria.
+/// A condition which is not [bounded](TerminationCondition::is_bounded) is
+/// rejected with [`RRTStarError::UnboundedTermination`].
+#[allow(clippy::too_many_arguments)]
+pub fn rrtstar_until<N>(
+    start: &[N],
+    goal: &[N],
+    is_collision_free: impl FnMut(&[N]) -> bool,
+    random_sample: impl FnMut() -> Vec<N>,
+    extend_length: N,
+    validity_resolution: N,
+    neighbourhood_radius: N,
+    condition: &TerminationCondition,
+) -> RRTStarResult<N, f32>
+where
+    N: Float + Debug,
+{
+    let (tree, unsolved) = rrtstar_until_impl(
+        start,
+        goal,
+        is_collision_free,
+        random_sample,
+        extend_length,
+        validity_resolution,
+        neighbourhood_radius,
+        condition,
+    )?;
+    if tree.goal_index.is_none() {
+        return Err(unsolved);
+    }
+    Ok(tree)
+}
 
-            if stop_when_reach_goal {
-                return Ok(tree);
-            }
-        }
+/// Same as [`rrtstar_until`], but the tree is returned even without
+/// solution, with the error of [`rrtstar_until`] in that case
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rrtstar_until_impl<N>(
+    start: &[N],
+    goal: &[N],
+    is_collision_free: impl FnMut(&[N]) -> bool,
+    random_sample: impl FnMut() -> Vec<N>,
+    extend_length: N,
+    validity_resolution: N,
+    neighbourhood_radius: N,
+    condition: &TerminationCondition,
+) -> Result<(Tree<N, f32>, RRTStarError), RRTStarError>
+where
+    N: Float + Debug,
+{
+    if !condition.is_bounded() {
+        return Err(RRTStarError::UnboundedTermination);
     }
+    let mut monitor = ConditionMonitor::new(condition);
+    let mut stop_reason = None;
+    let tree = rrtstar_with_neighbour_check(
+        start,
+        goal,
+        is_collision_free,
+        random_sample,
+        extend_length,
+        validity_resolution,
+        usize::MAX,
+        neighbourhood_radius,
+        false,
+        check_edges(validity_resolution),
+        |tree| {
+            let cost = tree.convergence.last().map(|point| point.cost.into());
+            stop_reason = monitor.check(tree.vertices.len(), cost);
+            stop_reason.is_some()
+        },
+    )?;
+    let unsolved = match stop_reason {
+        Some(StopReason::Timeout) => RRTStarError::DeadlineReached,
+        _ => RRTStarError::MaxItersReached,
+    };
+    Ok((tree, unsolved))
+}
 
-    if !stop_when_reach_goal {
-        return Ok(tree);
-    } else {
-        Err(RRTStarError::MaxItersReached)
+/// Informed RRT*, which improves the solution for `max_iters` iterations.
+///
+/// The samples are drawn by `random_sample` until the first solution is
+/// found. After that, only the prolate hyperspheroid of the points which can
+/// shorten the solution (defined by the start, the goal and the cost) is
+/// sampled, and it shrinks as the solution improves. The informed samples
+/// are drawn from `rng` and kept within `bounds`, if any, like the ones of
+/// `random_sample`.
+#[allow(clippy::too_many_arguments)]
+pub fn rrtstar_informed<N>(
+    start: &[N],
+    goal: &[N],
+    is_collision_free: impl FnMut(&[N]) -> bool,
+    mut random_sample: impl FnMut() -> Vec<N>,
+    extend_length: N,
+    validity_resolution: N,
+    max_iters: usize,
+    neighbourhood_radius: N,
+    bounds: Option<Bounds<N>>,
+    rng: &mut dyn RngCore,
+) -> RRTStarResult<N, f32>
+where
+    N: Float + Debug,
+{
+    let best_cost = Cell::new(None);
+    let tree = rrtstar_with_neighbour_check(
+        start,
+        goal,
+        is_collision_free,
+        || match best_cost.get() {
+            Some(cost) => {
+                let cost = N::from(cost).unwrap();
+                sample_informed(start, goal, cost, bounds.as_ref(), &mut *rng)
+            }
+            None => random_sample(),
+        },
+        extend_length,
+        validity_resolution,
+        max_iters,
+        neighbourhood_radius,
+        false,
+        check_edges(validity_resolution),
+        |tree| {
+            best_cost.set(tree.convergence.last().map(|point| point.cost));
+            false
+        },
+    )?;
+    if tree.goal_index.is_none() {
+        return Err(RRTStarError::MaxItersReached);
     }
+    Ok(tree)
 }
 
-/// select random two points, and try to connect.
-pub fn smooth_path<FF, N>(
-    path: &mut Vec<Vec<N>>,
-    mut is_free: FF,
+/// RRT* toward a goal region (e.g. a point with a tolerance or a predicate)
+/// for `max_iters` iterations.
+///
+/// Every vertex in the region is a goal candidate, and
+/// [`Tree::goal_index`] is the one with the lowest cost so far, which may
+/// change as the rewiring improves the candidates. If the region has a
+/// [`GoalRegion::sample`], the first new vertex within `extend_length` of it
+/// is also connected to it.
+#[allow(clippy::too_many_arguments)]
+pub fn rrtstar_to_region<N, G, FF>(
+    start: &[N],
+    goal: &G,
+    mut is_collision_free: FF,
+    mut random_sample: impl FnMut() -> Vec<N>,
     extend_length: N,
-    num_max_try: usize,
-    mut rng: &mut dyn RngCore,
-) where
+    validity_resolution: N,
+    max_iters: usize,
+    neighbourhood_radius: N,
+) -> RRTStarResult<N, f32>
+where
+    G: GoalRegion<N> + ?Sized,
     FF: FnMut(&[N]) -> bool,
     N: Float + Debug,
 {
-    if path.len() < 3 {
-        return;
+    let mut tree = Tree::<N, f32>::new(start.len());
+    tree.add_vertex(start, 0.0);
+    let mut q_goal = goal.sample();
+    let mut candidates = Vec::new();
+    if goal.contains(start) {
+        candidates.push(0);
+        tree.goal_index = Some(0);
     }
-    // let mut rng = rand::thread_rng();
-    for _ in 0..num_max_try {
-        let range1 = Uniform::new(0, path.len() - 2);
-        let ind1 = range1.sample(&mut rng);
-        let range2 = Uniform::new(ind1 + 2, path.len());
-        let ind2 = range2.sample(&mut rng);
-        let mut base_point = path[ind1].clone();
-        let point2 = path[ind2].clone();
-        let mut is_searching = true;
-        while is_searching {
-            let diff_dist = squared_euclidean(&base_point, &point2).sqrt();
-            if diff_dist < extend_length {
-                // reached!
-                // remove path[ind1+1] ... path[ind2-1]
-                let remove_index = ind1 + 1;
-                for _ in 0..(ind2 - ind1 - 1) {
-                    path.remove(remove_index);
-                }
-                if path.len() == 2 {
-                    return;
-                }
-                is_searching = false;
-            } else {
-                let check_point = base_point
-                    .iter()
-                    .zip(point2.iter())
-                    .map(|(near, target)| *near + (*target - *near) * extend_length / diff_dist)
-                    .collect::<Vec<_>>();
-                if !is_free(&check_point) {
-                    // trapped
-                    is_searching = false;
-                } else {
-                    // continue to extend
-                    base_point = check_point;
-                }
+    let mut check_neighbours = check_edges(validity_resolution);
+    let started = Instant::now();
+    for iteration in 0..max_iters {
+        let q_rand = random_sample();
+        let Some(new_index) = extend_rewire(
+            &mut tree,
+            &q_rand,
+            &mut is_collision_free,
+            extend_length,
+            validity_resolution,
+            neighbourhood_radius,
+            &mut check_neighbours,
+        ) else {
+            continue;
+        };
+        let q_new = tree.vertices[new_index].data.clone();
+        if goal.contains(&q_new) {
+            candidates.push(new_index);
+        } else if let Some(q) = q_goal.as_ref() {
+            let d = squared_euclidean(&q_new, q).sqrt();
+            if d < extend_length
+                && is_edge_free(&q_new, q, validity_resolution, &mut is_collision_free)
+            {
+                let weight = tree.vertices[new_index].weight
+                    + <f32 as num_traits::cast::NumCast>::from(d)
+                        .expect("N implements Float, same as W");
+                let goal_index = tree.add_vertex(q, weight);
+                tree.add_edge(new_index, goal_index);
+                candidates.push(goal_index);
+                q_goal = None;
             }
         }
+        // the best candidate, whose cost may also have been improved by the rewiring
+        let Some(&best) = candidates.iter().min_by(|a, b| {
+            tree.vertices[**a]
+                .weight
+                .total_cmp(&tree.vertices[**b].weight)
+        }) else {
+            continue;
+        };
+        tree.goal_index = Some(best);
+        let cost = tree.vertices[best].weight;
+        if tree.convergence.last().is_none_or(|last| cost < last.cost) {
+            tree.convergence.push(ConvergencePoint {
+                iteration,
+                elapsed: started.elapsed(),
+                cost,
+            });
+        }
+    }
+    if tree.goal_index.is_none() {
+        return Err(RRTStarError::MaxItersReached);
     }
+    Ok(tree)
 }
 
-#[test]
-fn it_works() {
-    use rand::distributions::{Distribution, Uniform};
-    let mut result = rrtstar(
-        &[-1.2, 0.0],
-        &[1.2, 0.0],
-        |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0),
-        || {
-            let between = Uniform::new(-2.0, 2.0);
-            let mut rng = rand::thread_rng();
-            vec![between.sample(&mut rng), between.sample(&mut rng)]
+/// Anytime RRT*, which keeps improving the solution for `max_iters`
+/// iterations after the first one is found.
+///
+/// `on_improvement` is called with the path and its cost every time a better
+/// path is found, so the execution of the first path can start while the
+/// planner refines it.
+#[allow(clippy::too_many_arguments)]
+pub fn rrtstar_anytime<N>(
+    start: &[N],
+    goal: &[N],
+    is_collision_free: impl FnMut(&[N]) -> bool,
+    random_sample: impl FnMut() -> Vec<N>,
+    extend_length: N,
+    validity_resolution: N,
+    max_iters: usize,
+    neighbourhood_radius: N,
+    mut on_improvement: impl FnMut(&[Vec<N>], f32),
+) -> RRTStarResult<N, f32>
+where
+    N: Float + Debug,
+{
+    let mut num_reported = 0;
+    let mut report = |tree: &Tree<N, f32>| {
+        if let (Some(goal_index), Some(point)) = (tree.goal_index, tree.convergence.last()) {
+            if tree.convergence.len() > num_reported {
+                num_reported = tree.convergence.len();
+                on_improvement(&tree.path(goal_index), point.cost);
+            }
+        }
+    };
+    let tree = rrtstar_with_neighbour_check(
+        start,
+        goal,
+        is_collision_free,
+        random_sample,
+        extend_length,
+        validity_resolution,
+        max_iters,
+        neighbourhood_radius,
+        false,
+        check_edges(validity_resolution),
+        |tree| {
+            report(tree);
+            false
+        },
+    )?;
+    // the improvement by the last iteration
+    report(&tree);
+    if tree.goal_index.is_none() {
+        return Err(RRTStarError::MaxItersReached);
+    }
+    Ok(tree)
+}
+
+/// Trees of [`rrtstar_bidirectional`], connected by the best path found
+#[derive(Debug)]
+pub struct BidirectionalTrees<N>
+where
+    N: Float + Zero + Debug,
+{
+    /// Tree rooted at the start
+    pub start_tree: Tree<N, f32>,
+    /// Tree rooted at the goal
+    pub goal_tree: Tree<N, f32>,
+    /// Vertices of the start tree and the goal tree joined by the best path
+    pub connection: (usize, usize),
+    /// Best solution cost at each improvement
+    pub convergence: Vec<ConvergencePoint<f32>>,
+}
+
+impl<N> BidirectionalTrees<N>
+where
+    N: Float + Zero + Debug,
+{
+    /// Best path from the start to the goal
+    pub fn path(&self) -> Vec<Vec<N>> {
+        let (start_index, goal_index) = self.connection;
+        let mut path = self.start_tree.path(start_index);
+        let mut to_goal = self.goal_tree.path(goal_index);
+        to_goal.reverse();
+        path.append(&mut to_goal);
+        path
+    }
+
+    /// Length of the best path
+    pub fn cost(&self) -> f32 {
+        connection_cost(&self.start_tree, &self.goal_tree, self.connection)
+    }
+}
+
+/// Cost of the path through the vertices of the start tree and the goal tree
+fn connection_cost<N>(
+    start_tree: &Tree<N, f32>,
+    goal_tree: &Tree<N, f32>,
+    (start_index, goal_index): (usize, usize),
+) -> f32
+where
+    N: Float + Zero + Debug,
+{
+    let q_start = &start_tree.vertices[start_index].data;
+    let q_goal = &goal_tree.vertices[goal_index].data;
+    start_tree.vertices[start_index].weight
+        + goal_tree.vertices[goal_index].weight
+        + squared_euclidean(q_start, q_goal).sqrt().to_f32().unwrap()
+}
+
+/// Bidirectional RRT* (B-RRT*), which grows rewiring trees from the start
+/// and the goal alternately.
+///
+/// Each new vertex is connected to the vertex of the other tree (in the
+/// neighbourhood, or the nearest one) which gives the cheapest path, if the
+/// edge is free. The trees keep improving for `max_iters` iterations, or
+/// until they are connected if `stop_when_connected` is true.
+#[allow(clippy::too_many_arguments)]
+pub fn rrtstar_bidirectional<N, FF>(
+    start: &[N],
+    goal: &[N],
+    mut is_collision_free: FF,
+    mut random_sample: impl FnMut() -> Vec<N>,
+    extend_length: N,
+    validity_resolution: N,
+    max_iters: usize,
+    neighbourhood_radius: N,
+    stop_when_connected: bool,
+) -> Result<BidirectionalTrees<N>, RRTStarError>
+where
+    FF: FnMut(&[N]) -> bool,
+    N: Float + Debug,
+{
+    assert_eq!(start.len(), goal.len());
+    let mut trees = [Tree::new(start.len()), Tree::new(goal.len())];
+    trees[0].add_vertex(start, 0.0);
+    trees[1].add_vertex(goal, 0.0);
+    let mut check_neighbours = check_edges(validity_resolution);
+    let mut connection: Option<(usize, usize)> = None;
+    let mut convergence: Vec<ConvergencePoint<f32>> = Vec::new();
+    let started = Instant::now();
+    // the tree which is extended in this iteration
+    let mut a = 0;
+    for iteration in 0..max_iters {
+        let q_rand = random_sample();
+        if let Some(new_index) = extend_rewire(
+            &mut trees[a],
+            &q_rand,
+            &mut is_collision_free,
+            extend_length,
+            validity_resolution,
+            neighbourhood_radius,
+            &mut check_neighbours,
+        ) {
+            // connect to the other tree through the cheapest free edge
+            let b = 1 - a;
+            let q_new = &trees[a].vertices[new_index].data;
+            let mut candidates = trees[b].get_nearest_neighbours(q_new, neighbourhood_radius);
+            let nearest_index = trees[b].get_nearest_index(q_new);
+            if !candidates.contains(&nearest_index) {
+                candidates.push(nearest_index);
+            }
+            let as_connection = |index: usize| {
+                if a == 0 {
+                    (new_index, index)
+                } else {
+                    (index, new_index)
+                }
+            };
+            let mut candidates = candidates
+                .into_iter()
+                .map(|index| {
+                    let pair = as_connection(index);
+                    (index, connection_cost(&trees[0], &trees[1], pair))
+                })
+                .collect::<Vec<_>>();
+            candidates.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+            let best_cost = connection.map(|pair| connection_cost(&trees[0], &trees[1], pair));
+            for (index, cost) in candidates {
+                if best_cost.is_some_and(|best_cost| cost >= best_cost) {
+                    break;
+                }
+                let q_other = &trees[b].vertices[index].data;
+                if is_edge_free(q_new, q_other, validity_resolution, &mut is_collision_free) {
+                    connection = Some(as_connection(index));
+                    break;
+                }
+            }
+        }
+        // record the improvement, also by the rewiring of the connected vertices
+        if let Some(pair) = connection {
+            let cost = connection_cost(&trees[0], &trees[1], pair);
+            if convergence.last().is_none_or(|last| cost < last.cost) {
+                convergence.push(ConvergencePoint {
+                    iteration,
+                    elapsed: started.elapsed(),
+                    cost,
+                });
+            }
+            if stop_when_connected {
+                break;
+            }
+        }
+        a = 1 - a;
+    }
+    let Some(connection) = connection else {
+        return Err(RRTStarError::MaxItersReached);
+    };
+    let [start_tree, goal_tree] = trees;
+    Ok(BidirectionalTrees {
+        start_tree,
+        goal_tree,
+        connection,
+        convergence,
+    })
+}
+
+/// Same as [`rrtstar`], but the distances and the edges to the neighbours
+/// of each new vertex are checked in parallel using rayon.
+///
+/// This pays off when `is_collision_free` is expensive and the
+/// neighbourhood is large.
+#[cfg(feature = "rayon")]
+#[allow(clippy::too_many_arguments)]
+pub fn rrtstar_parallel<N>(
+    start: &[N],
+    goal: &[N],
+    is_collision_free: impl Fn(&[N]) -> bool + Sync,
+    random_sample: impl FnMut() -> Vec<N>,
+    extend_length: N,
+    validity_resolution: N,
+    max_iters: usize,
+    neighbourhood_radius: N,
+    stop_when_reach_goal: bool,
+) -> RRTStarResult<N, f32>
+where
+    N: Float + Debug + Send + Sync,
+{
+    use rayon::prelude::*;
+    rrtstar_with_neighbour_check(
+        start,
+        goal,
+        is_collision_free,
+        random_sample,
+        extend_length,
+        validity_resolution,
+        max_iters,
+        neighbourhood_radius,
+        stop_when_reach_goal,
+        |is_collision_free, q_new, neighbours| {
+            let is_collision_free = &*is_collision_free;
+            neighbours
+                .par_iter()
+                .map(|q| {
+                    (
+                        squared_euclidean(q_new, q).sqrt(),
+                        is_edge_free(q, q_new, validity_resolution, &mut |x: &[N]| {
+                            is_collision_free(x)
+                        }),
+                    )
+                })
+                .collect()
+        },
+        |_| false,
+    )
+}
+
+/// Checks the edges from the neighbours to the new vertex one by one, every
+/// `validity_resolution`, and returns their lengths with the results.
+#[allow(clippy::type_complexity)]
+fn check_edges<N, FF>(
+    validity_resolution: N,
+) -> impl FnMut(&mut FF, &[N], &[&[N]]) -> Vec<(N, bool)>
+where
+    FF: FnMut(&[N]) -> bool,
+    N: Float + Debug,
+{
+    move |is_collision_free, q_new, neighbours| {
+        neighbours
+            .iter()
+            .map(|q| {
+                (
+                    squared_euclidean(q_new, q).sqrt(),
+                    is_edge_free(q, q_new, validity_resolution, is_collision_free),
+                )
+            })
+            .collect()
+    }
+}
+
+/// `check_neighbours` returns the distance from the new vertex and if the
+/// edge is free for each of the neighbours.
+#[allow(clippy::too_many_arguments)]
+fn rrtstar_with_neighbour_check<N, FF, FC>(
+    start: &[N],
+    goal: &[N],
+    mut is_collision_free: FF,
+    mut random_sample: impl FnMut() -> Vec<N>,
+    extend_length: N,
+    validity_resolution: N,
+    max_iters: usize,
+    neighbourhood_radius: N,
+    stop_when_reach_goal: bool,
+    mut check_neighbours: FC,
+    mut should_stop: impl FnMut(&Tree<N, f32>) -> bool,
+) -> RRTStarResult<N, f32>
+where
+    FF: FnMut(&[N]) -> bool,
+    FC: FnMut(&mut FF, &[N], &[&[N]]) -> Vec<(N, bool)>,
+    N: Float + Debug,
+{
+    assert_eq!(start.len(), goal.len());
+    let mut tree = Tree::<N, f32>::new(start.len());
+    tree.add_vertex(start, 0.0);
+
+    let mut goal_reached = false;
+    let started = Instant::now();
+
+    // Path finding loop
+    for iteration in 0..max_iters {
+        // 0. Stop early, e.g. given the cost of the current solution
+        if should_stop(&tree) {
+            break;
+        }
+        // 1. Random sample
+        let q_rand = random_sample();
+        // 2. - 5. Extend the tree toward the sample, and rewire
+        let Some(new_index) = extend_rewire(
+            &mut tree,
+            &q_rand,
+            &mut is_collision_free,
+            extend_length,
+            validity_resolution,
+            neighbourhood_radius,
+            &mut check_neighbours,
+        ) else {
+            continue;
+        };
+        let q_new = tree.vertices[new_index].data.clone();
+
+        // 6. Check if the goal is reached
+        if !goal_reached
+            && squared_euclidean(&q_new, goal).sqrt() < extend_length
+            && is_edge_free(&q_new, goal, validity_resolution, &mut is_collision_free)
+        {
+            let goal_weight = tree.vertices[new_index].weight
+                + <f32 as num_traits::cast::NumCast>::from(squared_euclidean(&q_new, goal).sqrt())
+                    .expect("N implements Float, same as W");
+            // println!("goal {:?} reached with weight {}", goal, goal_weight);
+            let goal_index = tree.add_vertex(goal, goal_weight);
+            tree.add_edge(new_index, goal_index);
+
+            tree.goal_index = Some(goal_index);
+            tree.convergence.push(ConvergencePoint {
+                iteration,
+                elapsed: started.elapsed(),
+                cost: tree.path_cost(goal_index),
+            });
+
+            goal_reached = true;
+
+            if stop_when_reach_goal {
+                return Ok(tree);
+            }
+        }
+
+        // 7. Record the improvement of the solution by the rewiring
+        if let (true, Some(goal_index)) = (goal_reached, tree.goal_index) {
+            let cost = tree.path_cost(goal_index);
+            if tree.convergence.last().is_some_and(|last| cost < last.cost) {
+                tree.convergence.push(ConvergencePoint {
+                    iteration,
+                    elapsed: started.elapsed(),
+                    cost,
+                });
+            }
+        }
+    }
+
+    if !stop_when_reach_goal {
+        Ok(tree)
+    } else {
+        Err(RRTStarError::MaxItersReached)
+    }
+}
+
+/// Steps 2. - 5. of RRT*: add a vertex from the nearest one toward `q_rand`
+/// with the cheapest parent in the neighbourhood, and rewire the neighbours
+/// through it. Returns the new vertex, `None` if the edge from the nearest
+/// vertex is not free.
+fn extend_rewire<N, W, FF, FC>(
+    tree: &mut Tree<N, W>,
+    q_rand: &[N],
+    is_collision_free: &mut FF,
+    extend_length: N,
+    validity_resolution: N,
+    neighbourhood_radius: N,
+    check_neighbours: &mut FC,
+) -> Option<usize>
+where
+    FF: FnMut(&[N]) -> bool,
+    FC: FnMut(&mut FF, &[N], &[&[N]]) -> Vec<(N, bool)>,
+    N: Float + Debug,
+    W: Weight,
+{
+    // 2. Nearest neighbour
+    let nearest_index = tree.get_nearest_index(q_rand);
+    let q_nearest = &tree.vertices[nearest_index].data;
+    // 3. Steer to get new point
+    let (q_new, _) = steer(q_nearest, q_rand, extend_length);
+
+    // 4. Check if the new point and the edge to it are free
+    if !is_edge_free(q_nearest, &q_new, validity_resolution, is_collision_free) {
+        return None;
+    }
+    let nearest_dist = squared_euclidean(&q_new, q_nearest).sqrt();
+
+    // 5. Connect to the new point
+    // 5.1. Find nearest neighbours and check the edges to them
+    let nearest = tree
+        .get_nearest_neighbours(&q_new, neighbourhood_radius)
+        .into_iter()
+        .filter(|&i| i != nearest_index)
+        .collect::<Vec<_>>();
+    let neighbours = nearest
+        .iter()
+        .map(|&i| tree.vertices[i].data.as_slice())
+        .collect::<Vec<_>>();
+    let checked = check_neighbours(is_collision_free, &q_new, &neighbours);
+    let to_weight = |d: N| W::from(d).expect("N implements Float, same as W");
+    // 5.2. Choose the parent which gives the lowest cost to come
+    let (parent_index, cost_new) = std::iter::once((nearest_index, nearest_dist))
+        .chain(
+            nearest
+                .iter()
+                .zip(checked.iter())
+                .filter(|(_, (_, is_free))| *is_free)
+                .map(|(&i, &(d, _))| (i, d)),
+        )
+        .map(|(i, d)| (i, tree.vertices[i].weight + to_weight(d)))
+        .min_by(|(_, a), (_, b)| {
+            a.partial_cmp(b)
+                .expect("Weight W of two nodes should be comparable")
+        })
+        .expect("iterator shouldn't be empty");
+
+    // 5.3. Insert the new point to the tree
+    let new_index = tree.add_vertex(&q_new, cost_new);
+    tree.add_edge(parent_index, new_index);
+
+    // 5.4. Rewire the neighbours through the new point if it is cheaper,
+    // and update the cost to come of their descendants
+    tree.rewire_stats.radius_history.push(neighbourhood_radius);
+    tree.rewire_stats.num_candidates += nearest.len();
+    let mut rewired = Vec::new();
+    for (&near_index, &(dist, is_free)) in nearest.iter().zip(checked.iter()) {
+        let near_weight = tree.vertices[near_index].weight;
+        let new_potential_cost = cost_new + to_weight(dist);
+
+        if is_free && new_potential_cost < near_weight {
+            tree.remove_edge(near_index);
+            tree.add_edge(new_index, near_index);
+            tree.vertices[near_index].weight = new_potential_cost;
+            rewired.push(near_index);
+            tree.rewire_stats.num_rewires += 1;
+            tree.rewire_stats.total_cost_improvement =
+                tree.rewire_stats.total_cost_improvement + near_weight - new_potential_cost;
+        }
+    }
+    tree.update_descendant_weights(&rewired);
+    Some(new_index)
+}
+
+/// Uniform sample in the prolate hyperspheroid of the points whose sum of
+/// the distances to `start` and `goal` is at most `cost` (informed sampling)
+///
+/// The samples outside of `bounds` are clamped or drawn again, following
+/// the policy of the bounds.
+fn sample_informed<N, R>(
+    start: &[N],
+    goal: &[N],
+    cost: N,
+    bounds: Option<&Bounds<N>>,
+    rng: &mut R,
+) -> Vec<N>
+where
+    N: Float,
+    R: Rng + ?Sized,
+{
+    // the hyperspheroid always contains the segment between start and goal,
+    // so the rejection ends unless they are out of the bounds too
+    const MAX_REJECTIONS: usize = 100;
+    let mut num_rejections = 0;
+    loop {
+        let q = sample_hyperspheroid(start, goal, cost, rng);
+        match bounds {
+            None => return q,
+            Some(bounds) if bounds.contains(&q) => return q,
+            Some(bounds)
+                if bounds.policy == OutOfBounds::Clamp || num_rejections == MAX_REJECTIONS =>
+            {
+                return bounds.clamp(&q)
+            }
+            Some(_) => num_rejections += 1,
+        }
+    }
+}
+
+fn sample_hyperspheroid<N, R>(start: &[N], goal: &[N], cost: N, rng: &mut R) -> Vec<N>
+where
+    N: Float,
+    R: Rng + ?Sized,
+{
+    let dim = start.len();
+    let c_min = squared_euclidean(start, goal).sqrt();
+    let two = N::one() + N::one();
+    // uniform in the unit ball: a direction from the isotropic Gaussian, and
+    // a radius whose volume below is uniform
+    let direction = loop {
+        let x = (0..dim)
+            .map(|_| {
+                // Box-Muller transform
+                let u1 = 1.0 - rng.gen::<f64>();
+                let u2 = rng.gen::<f64>();
+                (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+            })
+            .collect::<Vec<_>>();
+        let norm = x.iter().map(|v| v * v).sum::<f64>().sqrt();
+        if norm > f64::EPSILON {
+            break x.into_iter().map(move |v| v / norm);
+        }
+    };
+    let radius = rng.gen::<f64>().powf(1.0 / dim as f64);
+    let ball = direction.map(|v| N::from(v * radius).unwrap());
+    let major = cost / two;
+    let minor = (cost * cost - c_min * c_min).max(N::zero()).sqrt() / two;
+    let mut x = ball
+        .enumerate()
+        .map(|(i, v)| v * if i == 0 { major } else { minor })
+        .collect::<Vec<_>>();
+    // reflect the first axis onto the direction from start to goal
+    if c_min > N::zero() {
+        let v = (0..dim)
+            .map(|i| {
+                let e = if i == 0 { N::one() } else { N::zero() };
+                e - (goal[i] - start[i]) / c_min
+            })
+            .collect::<Vec<_>>();
+        let vv = v.iter().fold(N::zero(), |sum, a| sum + *a * *a);
+        if vv > N::epsilon() {
+            let vx = v
+                .iter()
+                .zip(&x)
+                .fold(N::zero(), |sum, (a, b)| sum + *a * *b);
+            for (x, v) in x.iter_mut().zip(&v) {
+                *x = *x - two * *v * vx / vv;
+            }
+        }
+    }
+    x.iter()
+        .enumerate()
+        .map(|(i, v)| *v + (start[i] + goal[i]) / two)
+        .collect()
+}
+
+/// Resumable RRT* optimization of a given path, e.g. from a grid planner or
+/// a teach pendant recording.
+///
+/// The tree is seeded with the path (split every `extend_length`), and
+/// grows with informed sampling around it, so only the samples which can
+/// shorten the path are drawn. [`PathOptimizer::optimize`] can be called
+/// again to continue from the current tree.
+#[derive(Debug)]
+pub struct PathOptimizer<N>
+where
+    N: Float + Zero + Debug,
+{
+    tree: Tree<N, f64>,
+    goal: Vec<N>,
+    extend_length: N,
+    validity_resolution: N,
+    neighbourhood_radius: N,
+    num_iterations: usize,
+    started: Instant,
+}
+
+impl<N> PathOptimizer<N>
+where
+    N: Float + Zero + Debug,
+{
+    /// Seed the tree with `path`, which is assumed to be free
+    pub fn new(
+        path: &[Vec<N>],
+        extend_length: N,
+        validity_resolution: N,
+        neighbourhood_radius: N,
+    ) -> Self {
+        assert!(!path.is_empty());
+        let mut tree = Tree::new(path[0].len());
+        let mut parent = None;
+        let mut weight = 0.0;
+        for (q, step) in subdivide(path, extend_length) {
+            weight += step.to_f64().unwrap();
+            let index = tree.add_vertex(&q, weight);
+            if let Some(parent) = parent {
+                tree.add_edge(parent, index);
+            }
+            parent = Some(index);
+        }
+        tree.goal_index = parent;
+        tree.convergence.push(ConvergencePoint {
+            iteration: 0,
+            elapsed: Duration::ZERO,
+            cost: weight,
+        });
+        Self {
+            tree,
+            goal: path[path.len() - 1].clone(),
+            extend_length,
+            validity_resolution,
+            neighbourhood_radius,
+            num_iterations: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Length of the current path
+    pub fn cost(&self) -> f64 {
+        self.tree.vertices[self.tree.goal_index.unwrap()].weight
+    }
+
+    /// Current path from the start to the goal
+    pub fn path(&self) -> Vec<Vec<N>> {
+        let mut path = self.tree.get_until_root(self.tree.goal_index.unwrap());
+        path.reverse();
+        path.push(self.goal.clone());
+        path
+    }
+
+    /// The tree, whose `convergence` has the cost at each improvement
+    pub fn tree(&self) -> &Tree<N, f64> {
+        &self.tree
+    }
+
+    /// Number of iterations of all the calls of [`PathOptimizer::optimize`]
+    pub fn num_iterations(&self) -> usize {
+        self.num_iterations
+    }
+
+    /// Grow and rewire the tree until `budget` has passed
+    pub fn optimize<FF>(&mut self, is_free: FF, budget: Duration, rng: &mut dyn RngCore)
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        self.optimize_until(is_free, SoftDeadline::new(budget), rng);
+    }
+
+    /// Grow and rewire the tree until the path is good enough or the
+    /// deadline has passed. Returns if the path is good enough.
+    pub fn optimize_until<FF>(
+        &mut self,
+        mut is_free: FF,
+        deadline: SoftDeadline,
+        rng: &mut dyn RngCore,
+    ) -> bool
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        let mut controller = IterationController::new(deadline.budget);
+        loop {
+            let batch = controller.next_batch();
+            if batch == 0 {
+                break;
+            }
+            for _ in 0..batch {
+                if deadline.is_good_enough(self.cost()) {
+                    return true;
+                }
+                self.step(&mut is_free, rng);
+            }
+        }
+        deadline.is_good_enough(self.cost())
+    }
+
+    /// Grow and rewire the tree until `condition` stops it, and return why.
+    ///
+    /// The tree size and the cost of the condition are the ones of the tree
+    /// of the optimizer, so e.g. a cost threshold stops as soon as the path
+    /// is short enough.
+    pub fn optimize_with_condition<FF>(
+        &mut self,
+        mut is_free: FF,
+        condition: &TerminationCondition,
+        rng: &mut dyn RngCore,
+    ) -> Result<StopReason, RRTStarError>
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        if !condition.is_bounded() {
+            return Err(RRTStarError::UnboundedTermination);
+        }
+        let mut monitor = ConditionMonitor::new(condition);
+        loop {
+            if let Some(reason) = monitor.check(self.tree.vertices.len(), Some(self.cost())) {
+                return Ok(reason);
+            }
+            self.step(&mut is_free, rng);
+        }
+    }
+
+    /// Grow and rewire the tree for `num_iterations`, independently of the
+    /// time it takes, e.g. to reproduce the result with a seeded `rng`
+    pub fn optimize_iterations<FF>(
+        &mut self,
+        mut is_free: FF,
+        num_iterations: usize,
+        rng: &mut dyn RngCore,
+    ) where
+        FF: FnMut(&[N]) -> bool,
+    {
+        for _ in 0..num_iterations {
+            self.step(&mut is_free, rng);
+        }
+    }
+
+    // One iteration of RRT* with a sample which may shorten the path
+    fn step<FF>(&mut self, is_free: &mut FF, rng: &mut dyn RngCore)
+    where
+        FF: FnMut(&[N]) -> bool,
+    {
+        self.num_iterations += 1;
+        let start = &self.tree.vertices[0].data;
+        let cost = N::from(self.cost()).unwrap();
+        let q_rand = sample_informed(start, &self.goal, cost, None, rng);
+        extend_rewire(
+            &mut self.tree,
+            &q_rand,
+            is_free,
+            self.extend_length,
+            self.validity_resolution,
+            self.neighbourhood_radius,
+            &mut check_edges(self.validity_resolution),
+        );
+        let cost = self.cost();
+        if self
+            .tree
+            .convergence
+            .last()
+            .is_some_and(|last| cost < last.cost)
+        {
+            self.tree.convergence.push(ConvergencePoint {
+                iteration: self.num_iterations,
+                elapsed: self.started.elapsed(),
+                cost,
+            });
+        }
+    }
+}
+
+/// Shorten `path` (from another planner) by RRT* with informed sampling
+/// in the free space of `scene` until `budget` has passed.
+///
+/// Use [`PathOptimizer`] to continue the optimization later.
+#[allow(clippy::too_many_arguments)]
+pub fn optimize_path<N>(
+    path: &[Vec<N>],
+    scene: &Scene<N>,
+    extend_length: N,
+    validity_resolution: N,
+    neighbourhood_radius: N,
+    budget: Duration,
+    rng: &mut dyn RngCore,
+) -> Vec<Vec<N>>
+where
+    N: Float + Debug,
+{
+    let mut optimizer = PathOptimizer::new(
+        path,
+        extend_length,
+        validity_resolution,
+        neighbourhood_radius,
+    );
+    optimizer.optimize(|q: &[N]| scene.is_free(q), budget, rng);
+    optimizer.path()
+}
+
+/// select random two points, and try to connect.
+pub fn smooth_path<FF, N>(
+    path: &mut Vec<Vec<N>>,
+    mut is_free: FF,
+    extend_length: N,
+    num_max_try: usize,
+    mut rng: &mut dyn RngCore,
+) where
+    FF: FnMut(&[N]) -> bool,
+    N: Float + Debug,
+{
+    if path.len() < 3 {
+        return;
+    }
+    // let mut rng = rand::thread_rng();
+    for _ in 0..num_max_try {
+        let range1 = Uniform::new(0, path.len() - 2);
+        let ind1 = range1.sample(&mut rng);
+        let range2 = Uniform::new(ind1 + 2, path.len());
+        let ind2 = range2.sample(&mut rng);
+        let mut base_point = path[ind1].clone();
+        let point2 = path[ind2].clone();
+        let mut is_searching = true;
+        while is_searching {
+            let diff_dist = squared_euclidean(&base_point, &point2).sqrt();
+            if diff_dist < extend_length {
+                // reached!
+                // remove path[ind1+1] ... path[ind2-1]
+                let remove_index = ind1 + 1;
+                for _ in 0..(ind2 - ind1 - 1) {
+                    path.remove(remove_index);
+                }
+                if path.len() == 2 {
+                    return;
+                }
+                is_searching = false;
+            } else {
+                let check_point = base_point
+                    .iter()
+                    .zip(point2.iter())
+                    .map(|(near, target)| *near + (*target - *near) * extend_length / diff_dist)
+                    .collect::<Vec<_>>();
+                if !is_free(&check_point) {
+                    // trapped
+                    is_searching = false;
+                } else {
+                    // continue to extend
+                    base_point = check_point;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn it_works() {
+    use rand::distributions::{Distribution, Uniform};
+    use rand::{rngs::StdRng, SeedableRng};
+    let mut rng = StdRng::seed_from_u64(0);
+    let result = rrtstar(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0),
+        || {
+            let between = Uniform::new(-2.0, 2.0);
+            vec![between.sample(&mut rng), between.sample(&mut rng)]
         },
         0.2,
         1000,
+        0.4,
+        true,
     )
     .unwrap();
     println!("{result:?}");
@@ -363,3 +1650,734 @@ fn it_works() {
     // println!("{result:?}");
     // assert!(result.len() >= 3);
 }
+
+#[test]
+fn rewire_stats_work() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let square = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let mut rng = StdRng::seed_from_u64(0);
+    let uniform = || vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)];
+    let result = rrtstar(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        uniform,
+        0.2,
+        1000,
+        0.4,
+        true,
+    )
+    .unwrap();
+    let stats = &result.rewire_stats;
+    assert!(!stats.radius_history.is_empty());
+    assert!(stats.num_rewires <= stats.num_candidates);
+    assert!(stats.average_cost_improvement() >= 0.0);
+}
+
+#[test]
+fn roadmap_works() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let square = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let uniform = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        move || vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    let result = rrtstar(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        uniform(0),
+        0.2,
+        1000,
+        0.4,
+        true,
+    )
+    .unwrap();
+    let goal_index = result.goal_index.unwrap();
+    let num_vertices = result.vertices.len();
+    let is_free = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let roadmap = result.into_roadmap(is_free, 0.05, 0.3);
+    assert_eq!(roadmap.vertices.len(), num_vertices);
+    assert!(roadmap.edges.len() >= num_vertices - 1);
+    let path = roadmap.shortest_path(0, goal_index).unwrap();
+    assert_eq!(path[0], 0);
+    assert_eq!(*path.last().unwrap(), goal_index);
+}
+
+#[test]
+fn convergence_works() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let square = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let uniform = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        move || vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    let result = rrtstar(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        uniform(0),
+        0.2,
+        1000,
+        0.4,
+        true,
+    )
+    .unwrap();
+    assert_eq!(result.convergence.len(), 1);
+    // anytime mode records each improvement
+    let result = rrtstar_with_resolution(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        uniform(1),
+        0.2,
+        0.05,
+        2000,
+        0.4,
+        false,
+    )
+    .unwrap();
+    assert!(!result.convergence.is_empty());
+    for w in result.convergence.windows(2) {
+        assert!(w[1].cost < w[0].cost && w[1].iteration > w[0].iteration);
+    }
+    let mut csv = Vec::new();
+    result.write_convergence_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("iteration,elapsed,cost\n"));
+    assert_eq!(csv.lines().count(), result.convergence.len() + 1);
+}
+
+#[test]
+fn optimizer_works() {
+    use rand::{rngs::StdRng, SeedableRng};
+    // detour around the obstacle from another planner
+    let scene = Scene {
+        obstacles: vec![crate::collision::Obstacle::Box(
+            crate::collision::AxisAlignedBox {
+                min: vec![-0.5, -0.5],
+                max: vec![0.5, 0.5],
+            },
+        )],
+    };
+    let detour = vec![
+        vec![-1.0, 0.0],
+        vec![-1.0, 1.5],
+        vec![1.0, 1.5],
+        vec![1.0, 0.0],
+    ];
+    let optimize = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut optimizer = PathOptimizer::new(&detour, 0.1, 0.01, 0.3);
+        assert!((optimizer.cost() - 5.0).abs() < 1e-9);
+        assert_eq!(optimizer.path().len(), 51);
+        optimizer.optimize_iterations(|q: &[f64]| scene.is_free(q), 2000, &mut rng);
+        assert_eq!(optimizer.num_iterations(), 2000);
+        optimizer
+    };
+    let mut optimizer = optimize(1);
+    // close to the shortest path over the corners of the box, 1 + sqrt(2)
+    let cost = optimizer.cost();
+    assert!(cost < 2.6, "{cost}");
+    assert_eq!(optimize(1).path(), optimizer.path());
+    // resumed from the current tree
+    let mut rng = rand::thread_rng();
+    optimizer.optimize(
+        |q: &[f64]| scene.is_free(q),
+        Duration::from_millis(100),
+        &mut rng,
+    );
+    assert!(optimizer.cost() <= cost);
+    let path = optimizer.path();
+    assert!((crate::path::path_length(&path) - optimizer.cost()).abs() < 1e-6);
+    assert_eq!(path[0], [-1.0, 0.0]);
+    assert_eq!(path[path.len() - 1], [1.0, 0.0]);
+    let mut is_free = |q: &[f64]| scene.is_free(q);
+    for w in path.windows(2) {
+        assert!(is_edge_free(&w[0], &w[1], 0.01, &mut is_free));
+    }
+    for w in optimizer.tree().convergence.windows(2) {
+        assert!(w[1].cost < w[0].cost);
+    }
+    let budget = Duration::from_millis(100);
+    let path = optimize_path(&detour, &scene, 0.1, 0.01, 0.3, budget, &mut rng);
+    assert!(crate::path::path_length(&path) < 5.0);
+}
+
+#[test]
+fn deadline_works() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let scene = Scene {
+        obstacles: vec![crate::collision::Obstacle::Box(
+            crate::collision::AxisAlignedBox {
+                min: vec![-0.5, -0.5],
+                max: vec![0.5, 0.5],
+            },
+        )],
+    };
+    let detour = vec![
+        vec![-1.0, 0.0],
+        vec![-1.0, 1.5],
+        vec![1.0, 1.5],
+        vec![1.0, 0.0],
+    ];
+    // stop as soon as the path is good enough
+    let mut optimizer = PathOptimizer::new(&detour, 0.1, 0.01, 0.3);
+    let deadline = SoftDeadline::new(Duration::from_secs(10)).with_target_cost(4.5);
+    let started = Instant::now();
+    let mut rng = StdRng::seed_from_u64(1);
+    assert!(optimizer.optimize_until(|q: &[f64]| scene.is_free(q), deadline, &mut rng));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(optimizer.cost() < 4.5);
+    // anytime planning with a target cost and a deadline
+    let square = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let uniform = || {
+        let mut rng = rand::thread_rng();
+        vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    // the shortest path around the corners of the square costs about 4.04
+    let mut rng = StdRng::seed_from_u64(1);
+    let seeded = || vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)];
+    let deadline = SoftDeadline::new(Duration::from_secs(10)).with_target_cost(4.6);
+    let started = Instant::now();
+    let result = rrtstar_with_deadline(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        seeded,
+        0.2,
+        0.05,
+        0.4,
+        deadline,
+    )
+    .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    let cost = result.convergence.last().unwrap().cost;
+    assert!(cost < 4.6, "{cost}");
+    // stopped at the first good enough solution
+    let num_improvements = result.convergence.len();
+    assert!(result.convergence[..num_improvements - 1]
+        .iter()
+        .all(|point| point.cost >= 4.6));
+    let deadline = SoftDeadline::new(Duration::from_millis(100));
+    let started = Instant::now();
+    let result = rrtstar_with_deadline(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        uniform,
+        0.2,
+        0.05,
+        0.4,
+        deadline,
+    )
+    .unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    assert!(result.goal_index.is_some());
+    let wall = |p: &[f64]| p[0].abs() > 0.1;
+    assert!(matches!(
+        rrtstar_with_deadline(
+            &[-1.2, 0.0],
+            &[1.2, 0.0],
+            wall,
+            uniform,
+            0.2,
+            0.05,
+            0.4,
+            deadline
+        ),
+        Err(RRTStarError::DeadlineReached)
+    ));
+}
+
+#[test]
+fn termination_works() {
+    let square = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let uniform = || {
+        let mut rng = rand::thread_rng();
+        vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    let wall = |p: &[f64]| p[0].abs() > 0.1;
+    // stop on any of the criteria of a termination condition
+    let condition = TerminationCondition::new()
+        .timeout(Duration::from_secs(10))
+        .cost_threshold(10.0);
+    let started = Instant::now();
+    let result = rrtstar_until(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        uniform,
+        0.2,
+        0.05,
+        0.4,
+        &condition,
+    )
+    .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(result.convergence.last().unwrap().cost < 10.0);
+    let condition = TerminationCondition::new()
+        .max_iterations(100_000)
+        .first_solution();
+    let result = rrtstar_until(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        uniform,
+        0.2,
+        0.05,
+        0.4,
+        &condition,
+    )
+    .unwrap();
+    assert_eq!(result.convergence.len(), 1);
+    let condition = TerminationCondition::new().max_tree_size(50);
+    assert!(matches!(
+        rrtstar_until(
+            &[-1.2, 0.0],
+            &[1.2, 0.0],
+            wall,
+            uniform,
+            0.2,
+            0.05,
+            0.4,
+            &condition
+        ),
+        Err(RRTStarError::UnboundedTermination)
+    ));
+    let condition = condition.timeout(Duration::from_secs(10));
+    assert!(matches!(
+        rrtstar_until(
+            &[-1.2, 0.0],
+            &[1.2, 0.0],
+            wall,
+            uniform,
+            0.2,
+            0.05,
+            0.4,
+            &condition
+        ),
+        Err(RRTStarError::MaxItersReached)
+    ));
+    let condition = TerminationCondition::new().timeout(Duration::from_millis(50));
+    assert!(matches!(
+        rrtstar_until(
+            &[-1.2, 0.0],
+            &[1.2, 0.0],
+            wall,
+            uniform,
+            0.2,
+            0.05,
+            0.4,
+            &condition
+        ),
+        Err(RRTStarError::DeadlineReached)
+    ));
+    // the optimizer stops as soon as the path is short enough
+    use rand::{rngs::StdRng, SeedableRng};
+    let detour = vec![
+        vec![-1.2, 0.0],
+        vec![-1.2, 1.5],
+        vec![1.2, 1.5],
+        vec![1.2, 0.0],
+    ];
+    let mut optimizer = PathOptimizer::new(&detour, 0.1, 0.01, 0.3);
+    let mut rng = StdRng::seed_from_u64(0);
+    let condition = TerminationCondition::new()
+        .max_iterations(100_000)
+        .cost_threshold(5.0);
+    assert!(matches!(
+        optimizer.optimize_with_condition(square, &condition, &mut rng),
+        Ok(StopReason::CostThreshold)
+    ));
+    assert!(optimizer.cost() < 5.0);
+    assert!(matches!(
+        optimizer.optimize_with_condition(square, &TerminationCondition::new(), &mut rng),
+        Err(RRTStarError::UnboundedTermination)
+    ));
+}
+
+#[test]
+fn goal_bias_works() {
+    use rand::{rngs::StdRng, SeedableRng};
+    // the samples never reach the goal, only the goal bias does
+    let left_half = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        move || vec![rng.gen_range(-2.0..0.0), rng.gen_range(-2.0..2.0)]
+    };
+    let is_free = |_: &[f64]| true;
+    let result = rrtstar_with_resolution(
+        &[-1.0, 0.0],
+        &[1.5, 0.0],
+        is_free,
+        left_half(0),
+        0.2,
+        0.05,
+        500,
+        0.4,
+        true,
+    );
+    assert!(result.is_err());
+    let mut schedule = Vec::new();
+    let result = rrtstar_with_goal_bias(
+        &[-1.0, 0.0],
+        &[1.5, 0.0],
+        is_free,
+        left_half(1),
+        |i| {
+            schedule.push(i);
+            0.5 * 0.99f64.powi(i as i32)
+        },
+        0.2,
+        0.05,
+        500,
+        0.4,
+        true,
+        &mut StdRng::seed_from_u64(0),
+    )
+    .unwrap();
+    assert!(result.goal_index.is_some());
+    assert!(schedule.iter().enumerate().all(|(n, i)| n == *i));
+    for bias in [f64::NAN, f64::INFINITY, 1.5, -0.2] {
+        assert!(matches!(
+            rrtstar_with_goal_bias(
+                &[-1.0, 0.0],
+                &[1.5, 0.0],
+                is_free,
+                left_half(2),
+                |i| if i < 10 { 0.1 } else { bias },
+                0.2,
+                0.05,
+                500,
+                0.4,
+                true,
+                &mut StdRng::seed_from_u64(1),
+            ),
+            Err(RRTStarError::InvalidGoalBias)
+        ));
+    }
+}
+
+#[test]
+fn informed_works() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let square = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let uniform = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        move || vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    let wall = |p: &[f64]| p[0].abs() > 0.1;
+    // informed sampling after the first solution
+    let mut num_samples = 0;
+    let mut sample = uniform(0);
+    let result = rrtstar_informed(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        || {
+            num_samples += 1;
+            sample()
+        },
+        0.2,
+        0.05,
+        2000,
+        0.4,
+        None,
+        &mut StdRng::seed_from_u64(0),
+    )
+    .unwrap();
+    let first = &result.convergence[0];
+    assert_eq!(num_samples, first.iteration + 1);
+    assert!(result.convergence.last().unwrap().cost <= first.cost);
+    assert!(matches!(
+        rrtstar_informed(
+            &[-1.2, 0.0],
+            &[1.2, 0.0],
+            wall,
+            uniform(1),
+            0.2,
+            0.05,
+            100,
+            0.4,
+            None,
+            &mut StdRng::seed_from_u64(1),
+        ),
+        Err(RRTStarError::MaxItersReached)
+    ));
+    // uniform in the ellipse: a quarter of the samples in the half sized one
+    let mut rng = StdRng::seed_from_u64(3);
+    let (start, goal) = ([-1.0, 0.0], [1.0, 0.0]);
+    let num_inner = (0..10000)
+        .map(|_| sample_informed(&start, &goal, 4.0, None, &mut rng))
+        .filter(|q| (q[0] / 2.0).powi(2) + (q[1] / 3f64.sqrt()).powi(2) <= 0.25)
+        .count();
+    assert!((2200..2800).contains(&num_inner), "{num_inner}");
+    // within the bounds of the samples
+    for policy in [OutOfBounds::Reject, OutOfBounds::Clamp] {
+        let bounds = Bounds {
+            lower: vec![-1.5, -0.5],
+            upper: vec![1.5, 0.5],
+            policy,
+        };
+        for _ in 0..1000 {
+            let q = sample_informed(&start, &goal, 4.0, Some(&bounds), &mut rng);
+            assert!(bounds.contains(&q));
+            if policy == OutOfBounds::Reject {
+                let d = squared_euclidean(&q, &start).sqrt() + squared_euclidean(&q, &goal).sqrt();
+                assert!(d <= 4.0 + 1e-9);
+            }
+        }
+    }
+    let bounds = Bounds {
+        lower: vec![-2.0, -0.5],
+        upper: vec![2.0, 2.0],
+        policy: OutOfBounds::Reject,
+    };
+    let result = rrtstar_informed(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        {
+            let mut rng = StdRng::seed_from_u64(4);
+            move || vec![rng.gen_range(-2.0..2.0), rng.gen_range(-0.5..2.0)]
+        },
+        0.2,
+        0.05,
+        2000,
+        0.4,
+        Some(bounds.clone()),
+        &mut StdRng::seed_from_u64(2),
+    )
+    .unwrap();
+    assert!(result.vertices.iter().all(|v| bounds.contains(&v.data)));
+}
+
+#[test]
+fn rewiring_works() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let square = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let uniform = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        move || vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    let result = rrtstar_with_resolution(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        uniform(0),
+        0.2,
+        0.05,
+        2000,
+        0.4,
+        false,
+    )
+    .unwrap();
+    // the cost to come is kept up to date by the rewiring
+    for (index, vertex) in result.vertices.iter().enumerate() {
+        assert!((vertex.weight - result.path_cost(index)).abs() < 1e-3);
+    }
+    // and so are the child lists
+    for (index, children) in result.children.iter().enumerate() {
+        assert!(children
+            .iter()
+            .all(|&child| result.vertices[child].parent_index == Some(index)));
+    }
+    let num_edges = result
+        .vertices
+        .iter()
+        .filter(|v| v.parent_index.is_some())
+        .count();
+    assert_eq!(
+        result.children.iter().map(Vec::len).sum::<usize>(),
+        num_edges
+    );
+    let result = rrtstar_informed(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        uniform(1),
+        0.2,
+        0.05,
+        2000,
+        0.4,
+        None,
+        &mut StdRng::seed_from_u64(0),
+    )
+    .unwrap();
+    // close to the shortest path around the corners of the square
+    let cost = result.convergence.last().unwrap().cost;
+    assert!(cost < 4.4, "{cost}");
+}
+
+#[test]
+fn anytime_works() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let square = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let uniform = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        move || vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    // every better path is reported while planning
+    let mut improvements = Vec::new();
+    let result = rrtstar_anytime(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        uniform(0),
+        0.2,
+        0.05,
+        2000,
+        0.4,
+        |path: &[Vec<f64>], cost| improvements.push((path.to_vec(), cost)),
+    )
+    .unwrap();
+    assert_eq!(improvements.len(), result.convergence.len());
+    for (path, cost) in &improvements {
+        assert_eq!(path[0], [-1.2, 0.0]);
+        assert_eq!(path[path.len() - 1], [1.2, 0.0]);
+        assert!((crate::path::path_length(path) as f32 - cost).abs() < 1e-3);
+    }
+    for w in improvements.windows(2) {
+        assert!(w[1].1 < w[0].1);
+    }
+}
+
+#[test]
+fn bidirectional_works() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let square = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let uniform = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        move || vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    let wall = |p: &[f64]| p[0].abs() > 0.1;
+    // bidirectional
+    let result = rrtstar_bidirectional(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        uniform(0),
+        0.2,
+        0.05,
+        2000,
+        0.4,
+        false,
+    )
+    .unwrap();
+    let path = result.path();
+    assert_eq!(path[0], [-1.2, 0.0]);
+    assert_eq!(path[path.len() - 1], [1.2, 0.0]);
+    let mut is_free = square;
+    for w in path.windows(2) {
+        assert!(is_edge_free(&w[0], &w[1], 0.05, &mut is_free));
+    }
+    let cost = result.cost();
+    assert!((crate::path::path_length(&path) as f32 - cost).abs() < 1e-3);
+    assert_eq!(result.convergence.last().unwrap().cost, cost);
+    for w in result.convergence.windows(2) {
+        assert!(w[1].cost < w[0].cost);
+    }
+    assert!(cost < 4.6, "{cost}");
+    let result = rrtstar_bidirectional(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        square,
+        uniform(1),
+        0.2,
+        0.05,
+        2000,
+        0.4,
+        true,
+    )
+    .unwrap();
+    assert_eq!(result.convergence.len(), 1);
+    assert!(matches!(
+        rrtstar_bidirectional(
+            &[-1.2, 0.0],
+            &[1.2, 0.0],
+            wall,
+            uniform(2),
+            0.2,
+            0.05,
+            100,
+            0.4,
+            true
+        ),
+        Err(RRTStarError::MaxItersReached)
+    ));
+}
+
+#[test]
+fn region_works() {
+    use rand::{rngs::StdRng, SeedableRng};
+    let square = |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let uniform = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        move || vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    let wall = |p: &[f64]| p[0].abs() > 0.1;
+    // any configuration on the right of the obstacle, or near a point
+    let right = |q: &[f64]| q[0] > 1.5;
+    let tree = rrtstar_to_region(
+        &[-1.2, 0.0],
+        &right,
+        square,
+        uniform(0),
+        0.2,
+        0.05,
+        2000,
+        0.4,
+    )
+    .unwrap();
+    let goal_index = tree.goal_index.unwrap();
+    let path = tree.path(goal_index);
+    assert_eq!(path[0], [-1.2, 0.0]);
+    assert!(right(path.last().unwrap()));
+    let best = (0..tree.vertices.len())
+        .filter(|i| right(&tree.vertices[*i].data))
+        .map(|i| tree.vertices[i].weight)
+        .fold(f32::INFINITY, f32::min);
+    assert_eq!(tree.vertices[goal_index].weight, best);
+    assert!(tree.convergence.windows(2).all(|w| w[1].cost < w[0].cost));
+    let region = crate::goal::PointGoal::new(&[1.2, 0.0], 0.3);
+    let tree = rrtstar_to_region(
+        &[-1.2, 0.0],
+        &region,
+        square,
+        uniform(1),
+        0.2,
+        0.05,
+        2000,
+        0.4,
+    )
+    .unwrap();
+    assert!(region.contains(&tree.path(tree.goal_index.unwrap()).pop().unwrap()));
+    assert!(matches!(
+        rrtstar_to_region(&[-1.2, 0.0], &right, wall, uniform(2), 0.2, 0.05, 100, 0.4),
+        Err(RRTStarError::MaxItersReached)
+    ));
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_works() {
+    use rand::distributions::{Distribution, Uniform};
+    use rand::{rngs::StdRng, SeedableRng};
+    let result = rrtstar_parallel(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0),
+        {
+            let between = Uniform::new(-2.0, 2.0);
+            let mut rng = StdRng::seed_from_u64(0);
+            move || vec![between.sample(&mut rng), between.sample(&mut rng)]
+        },
+        0.2,
+        0.05,
+        3000,
+        0.4,
+        true,
+    )
+    .unwrap();
+    assert!(result.goal_index.is_some());
+}
@@ -0,0 +1,685 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Combination of several samplers, masks on top of them, samplers backed
+//! by learned models, and a sampling funnel around the straight line.
+
+use crate::collision::Obstacle;
+use crate::rrt::Bounds;
+use num_traits::float::Float;
+use rand::{Rng, RngCore};
+use std::cell::{Cell, RefCell};
+use std::fmt::Debug;
+
+/// How the weights of [`MixtureSampler`] change during planning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Adaptation {
+    /// The weights are fixed
+    #[default]
+    Fixed,
+    /// Each weight is scaled by the (smoothed) rate of the successful
+    /// extensions of its samples
+    SuccessRate,
+    /// The components are arms of an adversarial bandit (Exp3) whose reward
+    /// is the success of the extension plus the cost improvement relative
+    /// to the largest improvement so far. The exploration rate decreases
+    /// with the number of samples, so there is nothing to tune.
+    Bandit,
+}
+
+/// Number of samples and successful extensions of a component of [`MixtureSampler`]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ComponentStats {
+    /// Number of drawn samples
+    pub num_samples: usize,
+    /// Number of samples reported as successful
+    pub num_successes: usize,
+    /// Sum of the reported cost improvements
+    pub total_improvement: f64,
+}
+
+type Sample<'a, N> = Box<dyn Fn(&mut dyn RngCore) -> Vec<N> + 'a>;
+
+struct Component<'a, N> {
+    weight: f64,
+    sample: Sample<'a, N>,
+}
+
+/// Sampler which draws from one of its components chosen by the weights
+/// (e.g. uniform, goal biased, bridge and path biased samplers).
+///
+/// `sample` takes `&self`, so `|rng| mixture.sample(rng)` can be used as
+/// the sampler of
+/// [`RrtPlanner::sampler_with_rng`](crate::RrtPlanner::sampler_with_rng),
+/// and the components draw from the same rng. The results of the extensions are
+/// reported by [`MixtureSampler::report`], e.g. from the
+/// [`RrtPlanner::on_extend`](crate::RrtPlanner::on_extend) callback.
+pub struct MixtureSampler<'a, N> {
+    components: Vec<Component<'a, N>>,
+    adaptation: Adaptation,
+    stats: RefCell<Vec<ComponentStats>>,
+    // importance weighted sum of the rewards of each component (Exp3)
+    scores: RefCell<Vec<f64>>,
+    max_improvement: RefCell<f64>,
+    // component and its probability of the last sample
+    last: RefCell<Option<(usize, f64)>>,
+}
+
+impl<'a, N> Default for MixtureSampler<'a, N> {
+    fn default() -> Self {
+        Self::new(Adaptation::Fixed)
+    }
+}
+
+impl<'a, N> MixtureSampler<'a, N> {
+    /// Create a sampler without components
+    pub fn new(adaptation: Adaptation) -> Self {
+        Self {
+            components: Vec::new(),
+            adaptation,
+            stats: RefCell::new(Vec::new()),
+            scores: RefCell::new(Vec::new()),
+            max_improvement: RefCell::new(0.0),
+            last: RefCell::new(None),
+        }
+    }
+
+    /// Add a component drawn with the probability proportional to `weight`,
+    /// which draws its samples from the given rng
+    pub fn with<F>(mut self, weight: f64, sample: F) -> Self
+    where
+        F: Fn(&mut dyn RngCore) -> Vec<N> + 'a,
+    {
+        assert!(weight >= 0.0);
+        self.components.push(Component {
+            weight,
+            sample: Box::new(sample),
+        });
+        self.stats.get_mut().push(ComponentStats::default());
+        self.scores.get_mut().push(0.0);
+        self
+    }
+
+    fn exploration_rate(&self) -> f64 {
+        let k = self.components.len() as f64;
+        let t = self
+            .stats
+            .borrow()
+            .iter()
+            .map(|s| s.num_samples)
+            .sum::<usize>()
+            .max(1) as f64;
+        (k * k.max(2.0).ln() / ((std::f64::consts::E - 1.0) * t))
+            .sqrt()
+            .min(1.0)
+    }
+
+    /// Current probabilities of the components
+    pub fn weights(&self) -> Vec<f64> {
+        let stats = self.stats.borrow();
+        let weights = self
+            .components
+            .iter()
+            .zip(stats.iter())
+            .map(|(c, s)| match self.adaptation {
+                Adaptation::Fixed | Adaptation::Bandit => c.weight,
+                Adaptation::SuccessRate => {
+                    c.weight * (s.num_successes as f64 + 1.0) / (s.num_samples as f64 + 2.0)
+                }
+            })
+            .collect::<Vec<_>>();
+        if self.adaptation != Adaptation::Bandit {
+            return normalize(weights);
+        }
+        // Exp3, with the given weights as the prior
+        let gamma = self.exploration_rate();
+        let k = self.components.len() as f64;
+        let scores = self.scores.borrow();
+        let max_score = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let exploitation = normalize(
+            weights
+                .iter()
+                .zip(scores.iter())
+                .map(|(w, score)| w * (gamma / k * (score - max_score)).exp())
+                .collect(),
+        );
+        exploitation
+            .into_iter()
+            .map(|p| (1.0 - gamma) * p + gamma / k)
+            .collect()
+    }
+
+    /// Statistics of each component
+    pub fn stats(&self) -> Vec<ComponentStats> {
+        self.stats.borrow().clone()
+    }
+
+    /// Draw a sample, choosing the component by `rng`
+    pub fn sample(&self, rng: &mut dyn RngCore) -> Vec<N> {
+        assert!(!self.components.is_empty());
+        let weights = self.weights();
+        let mut r = rng.gen::<f64>();
+        let index = weights
+            .iter()
+            .position(|w| {
+                r -= w;
+                r < 0.0
+            })
+            .unwrap_or(weights.len() - 1);
+        self.stats.borrow_mut()[index].num_samples += 1;
+        *self.last.borrow_mut() = Some((index, weights[index]));
+        (self.components[index].sample)(rng)
+    }
+
+    /// Report if the extension toward the last sample was successful
+    pub fn report(&self, success: bool) {
+        self.report_with_improvement(success, 0.0);
+    }
+
+    /// Report if the extension toward the last sample was successful, and
+    /// how much it decreased the cost of the solution (for optimizing planners)
+    pub fn report_with_improvement(&self, success: bool, improvement: f64) {
+        let Some((index, probability)) = self.last.borrow_mut().take() else {
+            return;
+        };
+        let mut stats = self.stats.borrow_mut();
+        if success {
+            stats[index].num_successes += 1;
+        }
+        let improvement = improvement.max(0.0);
+        stats[index].total_improvement += improvement;
+        let mut max_improvement = self.max_improvement.borrow_mut();
+        *max_improvement = max_improvement.max(improvement);
+        let relative_improvement = if *max_improvement > 0.0 {
+            improvement / *max_improvement
+        } else {
+            0.0
+        };
+        let reward = (if success { 0.5 } else { 0.0 }) + 0.5 * relative_improvement;
+        self.scores.borrow_mut()[index] += reward / probability;
+    }
+}
+
+// number of rejected samples after which the mask is considered wrong
+const MAX_REJECTIONS: usize = 100_000;
+
+type Predicate<'a, N> = Box<dyn Fn(&[N]) -> bool + 'a>;
+
+/// Sampler which rejects the samples of another sampler outside of the
+/// include regions (if any) or inside of the exclude regions.
+///
+/// Only the samples are masked, the trees can still be extended through
+/// the excluded regions toward samples beyond them. Use
+/// `|q| is_free(q) && mask.allows(q)` as `is_free` to forbid them.
+pub struct MaskedSampler<'a, N> {
+    sample: Box<dyn Fn() -> Vec<N> + 'a>,
+    includes: Vec<Predicate<'a, N>>,
+    excludes: Vec<Predicate<'a, N>>,
+}
+
+impl<'a, N> MaskedSampler<'a, N>
+where
+    N: Float + Debug + 'a,
+{
+    /// Mask `sample`, which allows everything until regions are added
+    pub fn new<F>(sample: F) -> Self
+    where
+        F: Fn() -> Vec<N> + 'a,
+    {
+        Self {
+            sample: Box::new(sample),
+            includes: Vec::new(),
+            excludes: Vec::new(),
+        }
+    }
+
+    /// Allow only the samples inside of `shape` or the other include regions
+    pub fn include(self, shape: Obstacle<N>) -> Self {
+        self.include_if(move |q: &[N]| shape.contains(q))
+    }
+
+    /// Allow only the samples for which `predicate` or the other include
+    /// regions are true
+    pub fn include_if<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&[N]) -> bool + 'a,
+    {
+        self.includes.push(Box::new(predicate));
+        self
+    }
+
+    /// Reject the samples inside of `shape`
+    pub fn exclude(self, shape: Obstacle<N>) -> Self {
+        self.exclude_if(move |q: &[N]| shape.contains(q))
+    }
+
+    /// Reject the samples for which `predicate` is true
+    pub fn exclude_if<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&[N]) -> bool + 'a,
+    {
+        self.excludes.push(Box::new(predicate));
+        self
+    }
+
+    /// Returns true if `q` passes the mask
+    pub fn allows(&self, q: &[N]) -> bool {
+        (self.includes.is_empty() || self.includes.iter().any(|include| include(q)))
+            && !self.excludes.iter().any(|exclude| exclude(q))
+    }
+
+    /// Draw a sample which passes the mask.
+    ///
+    /// # Panics
+    ///
+    /// Panics if too many samples in a row are rejected, e.g. when the
+    /// include regions are outside of the range of the sampler.
+    pub fn sample(&self) -> Vec<N> {
+        for _ in 0..MAX_REJECTIONS {
+            let q = (self.sample)();
+            if self.allows(&q) {
+                return q;
+            }
+        }
+        panic!("the sampling mask rejected {MAX_REJECTIONS} samples in a row");
+    }
+}
+
+type Model<'a, N> = Box<dyn FnMut(usize) -> Vec<Vec<N>> + 'a>;
+
+/// Number of samples generated, rejected and drawn from the fallback by
+/// [`LearnedSampler`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ModelStats {
+    /// Number of samples generated by the model
+    pub num_generated: usize,
+    /// Number of generated samples outside of the bounds or not valid
+    pub num_rejected: usize,
+    /// Number of samples drawn from the fallback sampler
+    pub num_fallbacks: usize,
+}
+
+/// Sampler backed by a generative model (e.g. a network trained on the
+/// solutions of similar problems), which is called with the batch size
+/// and returns a batch of samples.
+///
+/// The samples outside of the bounds or rejected by the validity check are
+/// dropped. When the model returns an empty batch, the fallback sampler is
+/// used if there is one.
+pub struct LearnedSampler<'a, N> {
+    model: RefCell<Model<'a, N>>,
+    batch_size: usize,
+    batch: RefCell<Vec<Vec<N>>>,
+    bounds: Option<Bounds<N>>,
+    is_valid: Option<Predicate<'a, N>>,
+    fallback: Option<Box<dyn Fn() -> Vec<N> + 'a>>,
+    stats: Cell<ModelStats>,
+}
+
+impl<'a, N> LearnedSampler<'a, N>
+where
+    N: Float + Debug + 'a,
+{
+    /// Draw the samples from `model`, which is asked for `batch_size` samples at once
+    pub fn new<F>(batch_size: usize, model: F) -> Self
+    where
+        F: FnMut(usize) -> Vec<Vec<N>> + 'a,
+    {
+        assert!(batch_size > 0);
+        Self {
+            model: RefCell::new(Box::new(model)),
+            batch_size,
+            batch: RefCell::new(Vec::new()),
+            bounds: None,
+            is_valid: None,
+            fallback: None,
+            stats: Cell::new(ModelStats::default()),
+        }
+    }
+
+    /// Reject the samples outside of `bounds`
+    pub fn within(mut self, bounds: Bounds<N>) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    /// Reject the samples for which `is_valid` is false (e.g. `is_free`)
+    pub fn valid_if<F>(mut self, is_valid: F) -> Self
+    where
+        F: Fn(&[N]) -> bool + 'a,
+    {
+        self.is_valid = Some(Box::new(is_valid));
+        self
+    }
+
+    /// Draw from `fallback` when the model returns no samples
+    pub fn or_else<F>(mut self, fallback: F) -> Self
+    where
+        F: Fn() -> Vec<N> + 'a,
+    {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+
+    /// Returns true if `q` is within the bounds and valid
+    pub fn accepts(&self, q: &[N]) -> bool {
+        self.bounds.as_ref().is_none_or(|bounds| bounds.contains(q))
+            && self.is_valid.as_ref().is_none_or(|is_valid| is_valid(q))
+    }
+
+    /// Statistics of the samples so far
+    pub fn stats(&self) -> ModelStats {
+        self.stats.get()
+    }
+
+    /// Draw an accepted sample from the model.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the model returns no samples and there is no fallback, or
+    /// if too many samples in a row are rejected.
+    pub fn sample(&self) -> Vec<N> {
+        let mut stats = self.stats.get();
+        let mut batch = self.batch.borrow_mut();
+        let mut num_rejected = 0;
+        let q = loop {
+            if batch.is_empty() {
+                *batch = (self.model.borrow_mut())(self.batch_size);
+                stats.num_generated += batch.len();
+                // the samples are drawn from the back, keep the order of the model
+                batch.reverse();
+            }
+            let Some(q) = batch.pop() else {
+                let fallback = self
+                    .fallback
+                    .as_ref()
+                    .expect("the model returned no samples");
+                stats.num_fallbacks += 1;
+                break fallback();
+            };
+            if self.accepts(&q) {
+                break q;
+            }
+            stats.num_rejected += 1;
+            num_rejected += 1;
+            assert!(
+                num_rejected < MAX_REJECTIONS,
+                "{MAX_REJECTIONS} samples of the model were rejected in a row"
+            );
+        };
+        self.stats.set(stats);
+        q
+    }
+}
+
+/// Sampler of a tube around the straight line from the start to the goal
+/// (a sampling funnel), which widens when the progress stalls.
+///
+/// In mostly open environments the path is close to the straight line, so
+/// sampling near it converges much faster than sampling the whole bounds.
+/// The radius of the tube grows by `growth` after `patience` extensions in
+/// a row failed (reported by [`CorridorSampler::report`], e.g. from the
+/// [`RrtPlanner::on_extend`](crate::RrtPlanner::on_extend) callback), up to
+/// `max_radius`.
+pub struct CorridorSampler<N> {
+    start: Vec<N>,
+    goal: Vec<N>,
+    radius: Cell<N>,
+    max_radius: N,
+    growth: N,
+    patience: usize,
+    num_failures: Cell<usize>,
+    bounds: Option<Bounds<N>>,
+}
+
+impl<N> CorridorSampler<N>
+where
+    N: Float + Debug,
+{
+    /// Tube of `initial_radius` from `start` to `goal`, which grows by the
+    /// factor 2 after 20 failures in a row, up to `max_radius`
+    pub fn new(start: &[N], goal: &[N], initial_radius: N, max_radius: N) -> Self {
+        assert_eq!(start.len(), goal.len());
+        assert!(initial_radius > N::zero() && initial_radius <= max_radius);
+        Self {
+            start: start.to_vec(),
+            goal: goal.to_vec(),
+            radius: Cell::new(initial_radius),
+            max_radius,
+            growth: N::from(2.0).unwrap(),
+            patience: 20,
+            num_failures: Cell::new(0),
+            bounds: None,
+        }
+    }
+
+    /// Grow the radius by `growth` after `patience` failures in a row
+    pub fn widen_after(mut self, patience: usize, growth: N) -> Self {
+        assert!(patience > 0 && growth > N::one());
+        self.patience = patience;
+        self.growth = growth;
+        self
+    }
+
+    /// Clamp the samples into `bounds`
+    pub fn within(mut self, bounds: Bounds<N>) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    /// Current radius of the tube
+    pub fn radius(&self) -> N {
+        self.radius.get()
+    }
+
+    /// Grow the radius now
+    pub fn widen(&self) {
+        self.radius
+            .set((self.radius.get() * self.growth).min(self.max_radius));
+        self.num_failures.set(0);
+    }
+
+    /// Report if the extension toward the last sample was successful
+    pub fn report(&self, success: bool) {
+        if success {
+            self.num_failures.set(0);
+            return;
+        }
+        self.num_failures.set(self.num_failures.get() + 1);
+        if self.num_failures.get() >= self.patience {
+            self.widen();
+        }
+    }
+
+    /// Draw a sample uniformly along the line, offset uniformly within the
+    /// ball of the radius
+    pub fn sample(&self, rng: &mut dyn RngCore) -> Vec<N> {
+        let dim = self.start.len();
+        let t = N::from(rng.gen::<f64>()).unwrap();
+        // uniform direction by the normal distribution (Box-Muller), and the
+        // distance for the uniform density in the ball
+        let direction = (0..dim)
+            .map(|_| {
+                let (u1, u2) = (1.0 - rng.gen::<f64>(), rng.gen::<f64>());
+                (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+            })
+            .collect::<Vec<_>>();
+        let norm = direction.iter().map(|d| d * d).sum::<f64>().sqrt();
+        let distance = rng.gen::<f64>().powf(1.0 / dim as f64) / norm.max(f64::MIN_POSITIVE);
+        let q = self
+            .start
+            .iter()
+            .zip(&self.goal)
+            .zip(&direction)
+            .map(|((a, b), d)| {
+                *a + (*b - *a) * t + self.radius.get() * N::from(d * distance).unwrap()
+            })
+            .collect::<Vec<_>>();
+        match &self.bounds {
+            Some(bounds) => bounds.clamp(&q),
+            None => q,
+        }
+    }
+}
+
+fn normalize(weights: Vec<f64>) -> Vec<f64> {
+    let sum = weights.iter().sum::<f64>();
+    weights.into_iter().map(|w| w / sum).collect()
+}
+
+#[test]
+fn it_works() {
+    use rand::distributions::{Distribution, Uniform};
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let mixture = MixtureSampler::new(Adaptation::Fixed)
+        .with(3.0, |_| vec![0.0])
+        .with(1.0, |_| vec![1.0]);
+    assert_eq!(mixture.weights(), [0.75, 0.25]);
+    let num_ones = (0..1000)
+        .filter(|_| mixture.sample(&mut rng)[0] == 1.0)
+        .count();
+    assert!((150..350).contains(&num_ones));
+    assert_eq!(mixture.stats()[1].num_samples, num_ones);
+
+    // the samples in the obstacle are never useful
+    let mixture = MixtureSampler::new(Adaptation::SuccessRate)
+        .with(1.0, |rng| {
+            let between = Uniform::new(-2.0, 2.0);
+            vec![between.sample(rng), between.sample(rng)]
+        })
+        .with(1.0, |_| vec![0.0, 0.0]);
+    let result = crate::RrtPlanner::new()
+        .start(&[-1.2, 0.0])
+        .goal(&[1.2, 0.0])
+        .extend_length(0.2)
+        .seed(0)
+        .sampler_with_rng(|rng| mixture.sample(rng))
+        .on_extend(|success| mixture.report(success))
+        .plan(|p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0));
+    assert!(result.is_ok());
+    let weights = mixture.weights();
+    assert!(weights[0] > weights[1]);
+
+    // a useless component is drawn only for exploration
+    let mixture = MixtureSampler::new(Adaptation::Bandit)
+        .with(1.0, |_| vec![0.0])
+        .with(1.0, |_| vec![1.0]);
+    for _ in 0..2000 {
+        let success = mixture.sample(&mut rng)[0] == 0.0;
+        mixture.report_with_improvement(success, if success { 0.1 } else { 0.0 });
+    }
+    let weights = mixture.weights();
+    assert!(weights[0] > 0.9, "{weights:?}");
+    assert!(weights[1] > 0.0);
+    let stats = mixture.stats();
+    assert!(stats[0].num_samples > stats[1].num_samples);
+    assert!((stats[0].total_improvement - 0.1 * stats[0].num_samples as f64).abs() < 1e-6);
+
+    // the masked sampler never draws from the excluded regions, and the
+    // path goes over the obstacle
+    use crate::collision::AxisAlignedBox;
+    let masked = MaskedSampler::new(|| {
+        let between = Uniform::new(-2.0, 2.0);
+        let mut rng = rand::thread_rng();
+        vec![between.sample(&mut rng), between.sample(&mut rng)]
+    })
+    .include(Obstacle::Box(AxisAlignedBox {
+        min: vec![-2.0, -2.0],
+        max: vec![1.5, 2.0],
+    }))
+    .exclude_if(|q: &[f64]| q[1] < -0.5);
+    assert!(masked.allows(&[0.0, 0.0]));
+    assert!(!masked.allows(&[1.8, 0.0]));
+    assert!(!masked.allows(&[0.0, -1.0]));
+    for _ in 0..100 {
+        let q = masked.sample();
+        assert!(q[0] <= 1.5 && q[1] >= -0.5);
+    }
+    let result = crate::rrt::dual_rrt_connect(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0) && masked.allows(p),
+        || masked.sample(),
+        0.2,
+        1000,
+    );
+    let path = result.unwrap();
+    assert!(path.iter().all(|q| masked.allows(q)));
+    assert!(path.iter().any(|q| q[1] >= 1.0));
+
+    // a "model" which proposes samples above the obstacle, partly out of
+    // bounds, and runs out after a few batches
+    let mut num_batches = 0;
+    let learned = LearnedSampler::new(4, |batch_size| {
+        num_batches += 1;
+        if num_batches > 100 {
+            return Vec::new();
+        }
+        let mut rng = rand::thread_rng();
+        (0..batch_size)
+            .map(|_| vec![rng.gen_range(-2.0..2.0), rng.gen_range(1.0..3.0)])
+            .collect()
+    })
+    .within(crate::rrt::Bounds {
+        lower: vec![-2.0, -2.0],
+        upper: vec![2.0, 2.0],
+        policy: Default::default(),
+    })
+    .valid_if(|q: &[f64]| !(q[0].abs() < 1.0 && q[1].abs() < 1.0))
+    .or_else(|| vec![0.0, 1.5]);
+    assert!(learned.accepts(&[0.0, 1.5]));
+    assert!(!learned.accepts(&[0.0, 2.5]));
+    assert!(!learned.accepts(&[0.0, 0.5]));
+    let path = crate::rrt::dual_rrt_connect(
+        &[-1.2, 0.0],
+        &[1.2, 0.0],
+        |p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0),
+        || learned.sample(),
+        0.2,
+        1000,
+    )
+    .unwrap();
+    assert!(path.iter().any(|q| q[1] >= 1.0));
+    for _ in 0..500 {
+        let q = learned.sample();
+        assert!(learned.accepts(&q));
+    }
+    let stats = learned.stats();
+    assert_eq!(stats.num_generated, 400);
+    assert!(stats.num_rejected > 0);
+    assert!(stats.num_fallbacks > 0);
+
+    let corridor = CorridorSampler::new(&[-1.5, 0.0], &[1.5, 0.0], 0.1, 2.0).widen_after(10, 1.5);
+    for _ in 0..100 {
+        let q = corridor.sample(&mut rng);
+        let segment = [vec![-1.5, 0.0], vec![1.5, 0.0]];
+        assert!(crate::path::distance_to_path(&q, &segment) <= 0.1 + 1e-9);
+    }
+    // the obstacle blocks the thin tube, so it widens
+    let path = crate::RrtPlanner::new()
+        .start(&[-1.5, 0.0])
+        .goal(&[1.5, 0.0])
+        .extend_length(0.2)
+        .max_iterations(10000)
+        .sampler_with_rng(|rng| corridor.sample(rng))
+        .on_extend(|success| corridor.report(success))
+        .plan(|p: &[f64]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0))
+        .unwrap();
+    assert_eq!(path[path.len() - 1], [1.5, 0.0]);
+    assert!(corridor.radius() > 1.0);
+    assert!(corridor.radius() <= 2.0);
+}
@@ -0,0 +1,915 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Car-like (SE(2)) planning with Dubins and Reeds-Shepp curves.
+//!
+//! Poses are `[x, y, theta]`. A car with a minimum turning radius can not
+//! follow straight lines in the pose space, so the steering between two poses
+//! is the shortest curve of arcs of the turning radius and straight segments:
+//! forward only for [`Dubins`] and forward and backward for [`ReedsShepp`].
+//! The curve length is the distance, which is at least the Euclidean distance
+//! of the positions, so the nearest neighbour search prunes the candidates of
+//! a kd-tree on the positions and still returns the exact nearest vertex.
+
+use crate::goal::angle_difference;
+use crate::rrt::Error;
+use kdtree::distance::squared_euclidean;
+use num_traits::float::Float;
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+use std::fmt::Debug;
+
+/// Planar pose `[x, y, theta]`
+pub type Pose<N> = [N; 3];
+
+/// Kind of a segment of a [`Curve`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Segment {
+    /// Arc turning to the left (counterclockwise when moving forward)
+    Left,
+    /// Straight line
+    Straight,
+    /// Arc turning to the right (clockwise when moving forward)
+    Right,
+}
+
+use Segment::{Left as L, Right as R, Straight as S};
+
+/// Direction in which the car moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Direction {
+    /// Moving forward
+    Forward,
+    /// Moving backward
+    Backward,
+}
+
+/// Part of a path which is driven with one steering primitive in one
+/// direction
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathSegment<N> {
+    /// Steering primitive
+    pub primitive: Segment,
+    /// Direction of the motion
+    pub direction: Direction,
+    /// Length of the motion (not negative)
+    pub length: N,
+    /// Pose at the end of the segment
+    pub end: Pose<N>,
+}
+
+/// Curve between two poses
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Curve<N> {
+    /// Segments and their lengths (negative when moving backward)
+    pub segments: Vec<(Segment, N)>,
+    /// Radius of the arcs
+    pub turning_radius: N,
+}
+
+impl<N: Float> Curve<N> {
+    /// Length of the curve
+    pub fn length(&self) -> N {
+        self.segments
+            .iter()
+            .fold(N::zero(), |sum, (_, length)| sum + length.abs())
+    }
+
+    /// Pose after moving `s` along the curve from `from` (clamped to the
+    /// ends of the curve)
+    pub fn pose_at(&self, from: &Pose<N>, s: N) -> Pose<N> {
+        let f = |v: N| v.to_f64().unwrap();
+        let r = f(self.turning_radius);
+        let mut remaining = f(s).max(0.0) / r;
+        // pose in the frame of `from`, for the unit turning radius
+        let (mut x, mut y, mut phi) = (0.0, 0.0, 0.0);
+        for &(segment, length) in &self.segments {
+            if remaining <= 0.0 {
+                break;
+            }
+            let length = f(length) / r;
+            let v = length.abs().min(remaining).copysign(length);
+            remaining -= v.abs();
+            match segment {
+                L => {
+                    x += (phi + v).sin() - phi.sin();
+                    y += -(phi + v).cos() + phi.cos();
+                    phi += v;
+                }
+                R => {
+                    x += -(phi - v).sin() + phi.sin();
+                    y += (phi - v).cos() - phi.cos();
+                    phi -= v;
+                }
+                S => {
+                    x += v * phi.cos();
+                    y += v * phi.sin();
+                }
+            }
+        }
+        let (sin, cos) = f(from[2]).sin_cos();
+        [
+            f(from[0]) + r * (cos * x - sin * y),
+            f(from[1]) + r * (sin * x + cos * y),
+            angle_difference(f(from[2]) + phi, 0.0),
+        ]
+        .map(|v| N::from(v).unwrap())
+    }
+
+    /// Segments of the curve from `from` with their directions and the
+    /// poses where they end (the segments of no length are skipped)
+    pub fn path_segments(&self, from: &Pose<N>) -> Vec<PathSegment<N>> {
+        let min_length = N::from(ZERO).unwrap() * self.turning_radius;
+        let mut s = N::zero();
+        self.segments
+            .iter()
+            .filter_map(|&(primitive, length)| {
+                s = s + length.abs();
+                (length.abs() > min_length).then(|| PathSegment {
+                    primitive,
+                    direction: if length < N::zero() {
+                        Direction::Backward
+                    } else {
+                        Direction::Forward
+                    },
+                    length: length.abs(),
+                    end: self.pose_at(from, s),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Steering of the poses
+pub trait Se2Space<N: Float> {
+    /// Shortest curve from `from` to `to`
+    fn curve(&self, from: &Pose<N>, to: &Pose<N>) -> Curve<N>;
+
+    /// Length of the shortest curve from `from` to `to`
+    fn distance(&self, from: &Pose<N>, to: &Pose<N>) -> N {
+        self.curve(from, to).length()
+    }
+
+    /// Pose at the fraction `t` of the shortest curve from `from` to `to`
+    fn interpolate(&self, from: &Pose<N>, to: &Pose<N>, t: N) -> Pose<N> {
+        let curve = self.curve(from, to);
+        curve.pose_at(from, curve.length() * t)
+    }
+}
+
+const ZERO: f64 = 10.0 * f64::EPSILON;
+
+fn mod2pi(x: f64) -> f64 {
+    let v = x.rem_euclid(TAU);
+    if TAU - v < 1e-10 {
+        0.0
+    } else {
+        v
+    }
+}
+
+fn polar(x: f64, y: f64) -> (f64, f64) {
+    (x.hypot(y), y.atan2(x))
+}
+
+/// `(x, y, phi)` of `to` in the frame of `from`, scaled by the radius
+fn local<N: Float>(from: &Pose<N>, to: &Pose<N>, turning_radius: N) -> (f64, f64, f64) {
+    let f = |v: N| v.to_f64().unwrap();
+    let (dx, dy) = (f(to[0] - from[0]), f(to[1] - from[1]));
+    let (sin, cos) = f(from[2]).sin_cos();
+    let r = f(turning_radius);
+    (
+        (cos * dx + sin * dy) / r,
+        (-sin * dx + cos * dy) / r,
+        f(to[2] - from[2]),
+    )
+}
+
+fn shortest<N: Float>(words: Vec<Vec<(Segment, f64)>>, turning_radius: N) -> Curve<N> {
+    let length = |word: &Vec<(Segment, f64)>| word.iter().map(|(_, v)| v.abs()).sum::<f64>();
+    let best = words
+        .into_iter()
+        .min_by(|a, b| length(a).total_cmp(&length(b)))
+        .unwrap();
+    Curve {
+        segments: best
+            .into_iter()
+            .map(|(segment, v)| (segment, N::from(v).unwrap() * turning_radius))
+            .collect(),
+        turning_radius,
+    }
+}
+
+/// Forward only car
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dubins<N> {
+    /// Minimum turning radius
+    pub turning_radius: N,
+}
+
+impl<N: Float> Dubins<N> {
+    /// Car with the minimum turning radius
+    pub fn new(turning_radius: N) -> Self {
+        assert!(turning_radius > N::zero());
+        Self { turning_radius }
+    }
+}
+
+impl<N: Float> Se2Space<N> for Dubins<N> {
+    fn curve(&self, from: &Pose<N>, to: &Pose<N>) -> Curve<N> {
+        let (x, y, phi) = local(from, to, self.turning_radius);
+        let d = x.hypot(y);
+        let th = y.atan2(x);
+        let (a, b) = (mod2pi(-th), mod2pi(phi - th));
+        if d < 1e-10 && mod2pi(a - b) < 1e-10 {
+            return shortest(vec![vec![(S, 0.0)]], self.turning_radius);
+        }
+        let (sa, sb, ca, cb) = (a.sin(), b.sin(), a.cos(), b.cos());
+        let cab = (a - b).cos();
+        let mut words = Vec::new();
+        // LSL
+        let tmp = 2.0 + d * d - 2.0 * cab + 2.0 * d * (sa - sb);
+        if tmp >= 0.0 {
+            let theta = (cb - ca).atan2(d + sa - sb);
+            words.push(vec![
+                (L, mod2pi(theta - a)),
+                (S, tmp.sqrt()),
+                (L, mod2pi(b - theta)),
+            ]);
+        }
+        // RSR
+        let tmp = 2.0 + d * d - 2.0 * cab + 2.0 * d * (sb - sa);
+        if tmp >= 0.0 {
+            let theta = (ca - cb).atan2(d - sa + sb);
+            words.push(vec![
+                (R, mod2pi(a - theta)),
+                (S, tmp.sqrt()),
+                (R, mod2pi(theta - b)),
+            ]);
+        }
+        // RSL
+        let tmp = d * d - 2.0 + 2.0 * cab - 2.0 * d * (sa + sb);
+        if tmp >= 0.0 {
+            let p = tmp.sqrt();
+            let theta = (ca + cb).atan2(d - sa - sb) - 2.0.atan2(p);
+            words.push(vec![(R, mod2pi(a - theta)), (S, p), (L, mod2pi(b - theta))]);
+        }
+        // LSR
+        let tmp = -2.0 + d * d + 2.0 * cab + 2.0 * d * (sa + sb);
+        if tmp >= 0.0 {
+            let p = tmp.sqrt();
+            let theta = (-ca - cb).atan2(d + sa + sb) - (-2.0).atan2(p);
+            words.push(vec![(L, mod2pi(theta - a)), (S, p), (R, mod2pi(theta - b))]);
+        }
+        // RLR
+        let tmp = (6.0 - d * d + 2.0 * cab + 2.0 * d * (sa - sb)) / 8.0;
+        if tmp.abs() <= 1.0 {
+            let p = TAU - tmp.acos();
+            let theta = (ca - cb).atan2(d - sa + sb);
+            let t = mod2pi(a - theta + 0.5 * p);
+            words.push(vec![(R, t), (L, p), (R, mod2pi(a - b - t + p))]);
+        }
+        // LRL
+        let tmp = (6.0 - d * d + 2.0 * cab + 2.0 * d * (sb - sa)) / 8.0;
+        if tmp.abs() <= 1.0 {
+            let p = TAU - tmp.acos();
+            let theta = (cb - ca).atan2(d + sa - sb);
+            let t = mod2pi(-a + theta + 0.5 * p);
+            words.push(vec![(L, t), (R, p), (L, mod2pi(b - a - t + p))]);
+        }
+        shortest(words, self.turning_radius)
+    }
+}
+
+/// Car which moves forward and backward
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReedsShepp<N> {
+    /// Minimum turning radius
+    pub turning_radius: N,
+}
+
+impl<N: Float> ReedsShepp<N> {
+    /// Car with the minimum turning radius
+    pub fn new(turning_radius: N) -> Self {
+        assert!(turning_radius > N::zero());
+        Self { turning_radius }
+    }
+}
+
+// The formulas of the curve families are the ones of Reeds and Shepp
+// (numbered as the sections 8.1 - 8.11 of the paper), for the unit radius.
+// The other curves of a family are found by the symmetries of the time flip
+// `(-x, y, -phi)`, the reflection `(x, -y, -phi)` and going backward.
+
+fn tau_omega(u: f64, v: f64, xi: f64, eta: f64, phi: f64) -> (f64, f64) {
+    let delta = mod2pi(u - v);
+    let a = u.sin() - delta.sin();
+    let b = u.cos() - delta.cos() - 1.0;
+    let t1 = (eta * a - xi * b).atan2(xi * a + eta * b);
+    let t2 = 2.0 * (delta.cos() - v.cos() - u.cos()) + 3.0;
+    let tau = if t2 < 0.0 {
+        mod2pi(t1 + PI)
+    } else {
+        mod2pi(t1)
+    };
+    (tau, mod2pi(tau - u + v - phi))
+}
+
+// 8.1
+fn lp_sp_lp(x: f64, y: f64, phi: f64) -> Option<[f64; 3]> {
+    let (u, t) = polar(x - phi.sin(), y - 1.0 + phi.cos());
+    let v = mod2pi(phi - t);
+    (t >= -ZERO && v >= -ZERO).then_some([t, u, v])
+}
+
+// 8.2
+fn lp_sp_rp(x: f64, y: f64, phi: f64) -> Option<[f64; 3]> {
+    let (u1, t1) = polar(x + phi.sin(), y - 1.0 - phi.cos());
+    let u1 = u1 * u1;
+    if u1 < 4.0 {
+        return None;
+    }
+    let u = (u1 - 4.0).sqrt();
+    let t = mod2pi(t1 + 2.0.atan2(u));
+    let v = mod2pi(t - phi);
+    (t >= -ZERO && v >= -ZERO).then_some([t, u, v])
+}
+
+// 8.3
+fn lp_rm_l(x: f64, y: f64, phi: f64) -> Option<[f64; 3]> {
+    let (u1, theta) = polar(x - phi.sin(), y - 1.0 + phi.cos());
+    if u1 > 4.0 {
+        return None;
+    }
+    let u = -2.0 * (0.25 * u1).asin();
+    let t = mod2pi(theta + 0.5 * u + PI);
+    let v = mod2pi(phi - t + u);
+    (t >= -ZERO && u <= ZERO).then_some([t, u, v])
+}
+
+// 8.7
+fn lp_rup_lum_rm(x: f64, y: f64, phi: f64) -> Option<[f64; 3]> {
+    let (xi, eta) = (x + phi.sin(), y - 1.0 - phi.cos());
+    let rho = 0.25 * (2.0 + xi.hypot(eta));
+    if rho > 1.0 {
+        return None;
+    }
+    let u = rho.acos();
+    let (t, v) = tau_omega(u, -u, xi, eta, phi);
+    (t >= -ZERO && v <= ZERO).then_some([t, u, v])
+}
+
+// 8.8
+fn lp_rum_lum_rp(x: f64, y: f64, phi: f64) -> Option<[f64; 3]> {
+    let (xi, eta) = (x + phi.sin(), y - 1.0 - phi.cos());
+    let rho = (20.0 - xi * xi - eta * eta) / 16.0;
+    if !(0.0..=1.0).contains(&rho) {
+        return None;
+    }
+    let u = -rho.acos();
+    if u < -FRAC_PI_2 {
+        return None;
+    }
+    let (t, v) = tau_omega(u, u, xi, eta, phi);
+    (t >= -ZERO && v >= -ZERO).then_some([t, u, v])
+}
+
+// 8.9
+fn lp_rm_sm_lm(x: f64, y: f64, phi: f64) -> Option<[f64; 3]> {
+    let (rho, theta) = polar(x - phi.sin(), y - 1.0 + phi.cos());
+    if rho < 2.0 {
+        return None;
+    }
+    let r = (rho * rho - 4.0).sqrt();
+    let u = 2.0 - r;
+    let t = mod2pi(theta + r.atan2(-2.0));
+    let v = mod2pi(phi - FRAC_PI_2 - t);
+    (t >= -ZERO && u <= ZERO && v <= ZERO).then_some([t, u, v])
+}
+
+// 8.10
+fn lp_rm_sm_rm(x: f64, y: f64, phi: f64) -> Option<[f64; 3]> {
+    let (xi, eta) = (x + phi.sin(), y - 1.0 - phi.cos());
+    let (rho, theta) = polar(-eta, xi);
+    if rho < 2.0 {
+        return None;
+    }
+    let t = theta;
+    let u = 2.0 - rho;
+    let v = mod2pi(t + FRAC_PI_2 - phi);
+    (t >= -ZERO && u <= ZERO && v <= ZERO).then_some([t, u, v])
+}
+
+// 8.11
+fn lp_rm_s_lm_rp(x: f64, y: f64, phi: f64) -> Option<[f64; 3]> {
+    let (xi, eta) = (x + phi.sin(), y - 1.0 - phi.cos());
+    let (rho, _) = polar(xi, eta);
+    if rho < 2.0 {
+        return None;
+    }
+    let u = 4.0 - (rho * rho - 4.0).sqrt();
+    if u > ZERO {
+        return None;
+    }
+    let t = mod2pi(((4.0 - u) * xi - 2.0 * eta).atan2(-2.0 * xi + (u - 4.0) * eta));
+    let v = mod2pi(t - phi);
+    (t >= -ZERO && v >= -ZERO).then_some([t, u, v])
+}
+
+type Family = fn(f64, f64, f64) -> Option<[f64; 3]>;
+
+/// Curves of `family` and its symmetries, where `word` gives the segments
+/// of the solution `[t, u, v]`
+fn symmetric(
+    words: &mut Vec<Vec<(Segment, f64)>>,
+    (x, y, phi): (f64, f64, f64),
+    family: Family,
+    word: impl Fn([f64; 3]) -> Vec<(Segment, f64)>,
+) {
+    let swap = |w: Vec<(Segment, f64)>| {
+        w.into_iter()
+            .map(|(s, v)| {
+                let s = match s {
+                    L => R,
+                    R => L,
+                    S => S,
+                };
+                (s, v)
+            })
+            .collect::<Vec<_>>()
+    };
+    let negate = |w: Vec<(Segment, f64)>| w.into_iter().map(|(s, v)| (s, -v)).collect();
+    if let Some(tuv) = family(x, y, phi) {
+        words.push(word(tuv));
+    }
+    if let Some(tuv) = family(-x, y, -phi) {
+        words.push(negate(word(tuv)));
+    }
+    if let Some(tuv) = family(x, -y, -phi) {
+        words.push(swap(word(tuv)));
+    }
+    if let Some(tuv) = family(-x, -y, phi) {
+        words.push(negate(swap(word(tuv))));
+    }
+}
+
+impl<N: Float> Se2Space<N> for ReedsShepp<N> {
+    fn curve(&self, from: &Pose<N>, to: &Pose<N>) -> Curve<N> {
+        let (x, y, phi) = local(from, to, self.turning_radius);
+        // the same curves driven from `to` to `from`
+        let backward = (
+            x * phi.cos() + y * phi.sin(),
+            x * phi.sin() - y * phi.cos(),
+            phi,
+        );
+        let h = FRAC_PI_2;
+        let mut words = Vec::new();
+        let forward = (x, y, phi);
+        // C S C
+        symmetric(&mut words, forward, lp_sp_lp, |[t, u, v]| {
+            vec![(L, t), (S, u), (L, v)]
+        });
+        symmetric(&mut words, forward, lp_sp_rp, |[t, u, v]| {
+            vec![(L, t), (S, u), (R, v)]
+        });
+        // C C C
+        symmetric(&mut words, forward, lp_rm_l, |[t, u, v]| {
+            vec![(L, t), (R, u), (L, v)]
+        });
+        symmetric(&mut words, backward, lp_rm_l, |[t, u, v]| {
+            vec![(L, v), (R, u), (L, t)]
+        });
+        // C C C C
+        symmetric(&mut words, forward, lp_rup_lum_rm, |[t, u, v]| {
+            vec![(L, t), (R, u), (L, -u), (R, v)]
+        });
+        symmetric(&mut words, forward, lp_rum_lum_rp, |[t, u, v]| {
+            vec![(L, t), (R, u), (L, u), (R, v)]
+        });
+        // C C S C
+        symmetric(&mut words, forward, lp_rm_sm_lm, |[t, u, v]| {
+            vec![(L, t), (R, -h), (S, u), (L, v)]
+        });
+        symmetric(&mut words, forward, lp_rm_sm_rm, |[t, u, v]| {
+            vec![(L, t), (R, -h), (S, u), (R, v)]
+        });
+        symmetric(&mut words, backward, lp_rm_sm_lm, |[t, u, v]| {
+            vec![(L, v), (S, u), (R, -h), (L, t)]
+        });
+        symmetric(&mut words, backward, lp_rm_sm_rm, |[t, u, v]| {
+            vec![(R, v), (S, u), (R, -h), (L, t)]
+        });
+        // C C S C C
+        symmetric(&mut words, forward, lp_rm_s_lm_rp, |[t, u, v]| {
+            vec![(L, t), (R, -h), (S, u), (L, -h), (R, v)]
+        });
+        shortest(words, self.turning_radius)
+    }
+}
+
+/// Poses searchable by the distance of a [`Se2Space`]
+pub struct Neighbours<N: Float> {
+    kdtree: kdtree::KdTree<N, usize, [N; 2]>,
+    poses: Vec<Pose<N>>,
+}
+
+impl<N: Float> Default for Neighbours<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Float> Neighbours<N> {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self {
+            kdtree: kdtree::KdTree::new(2),
+            poses: Vec::new(),
+        }
+    }
+
+    /// Add `pose` and return its index
+    pub fn add(&mut self, pose: &Pose<N>) -> usize {
+        let index = self.poses.len();
+        self.kdtree.add([pose[0], pose[1]], index).unwrap();
+        self.poses.push(*pose);
+        index
+    }
+
+    /// Pose at `index`
+    pub fn pose(&self, index: usize) -> &Pose<N> {
+        &self.poses[index]
+    }
+
+    /// Number of the poses
+    pub fn len(&self) -> usize {
+        self.poses.len()
+    }
+
+    /// True if there is no pose
+    pub fn is_empty(&self) -> bool {
+        self.poses.is_empty()
+    }
+
+    /// Index of the pose from which the curve to `to` is the shortest
+    pub fn nearest<S: Se2Space<N> + ?Sized>(&self, space: &S, to: &Pose<N>) -> Option<usize> {
+        let mut best = None;
+        let mut best_distance = N::infinity();
+        for (d, &index) in self
+            .kdtree
+            .iter_nearest(&to[..2], &squared_euclidean)
+            .ok()?
+        {
+            // the curves are no shorter than the straight line
+            if d.sqrt() >= best_distance {
+                break;
+            }
+            let d = space.distance(&self.poses[index], to);
+            if d < best_distance {
+                best = Some(index);
+                best_distance = d;
+            }
+        }
+        best
+    }
+}
+
+/// Poses along the curve from `from` to `to`, at most `resolution` apart
+/// (without `from`)
+pub fn discretize<N: Float, S: Se2Space<N> + ?Sized>(
+    space: &S,
+    from: &Pose<N>,
+    to: &Pose<N>,
+    resolution: N,
+) -> Vec<Pose<N>> {
+    let curve = space.curve(from, to);
+    let length = curve.length();
+    let num = (length / resolution).ceil().to_usize().unwrap().max(1);
+    (1..=num)
+        .map(|i| curve.pose_at(from, length * N::from(i).unwrap() / N::from(num).unwrap()))
+        .collect()
+}
+
+/// RRT in SE(2) with the curves of `space`.
+///
+/// Each step moves at most `extend_length` along the curve, and the curves are
+/// checked at `validity_resolution`. The goal is connected when it is within
+/// `extend_length` of a new vertex, so `random_sample` should also return the
+/// goal at times. The path is the poses along the curves at
+/// `validity_resolution`, because the straight lines between them are not
+/// feasible for the car.
+#[allow(clippy::too_many_arguments)]
+pub fn rrt<N, S, FF, FR>(
+    space: &S,
+    start: &Pose<N>,
+    goal: &Pose<N>,
+    is_free: FF,
+    random_sample: FR,
+    extend_length: N,
+    validity_resolution: N,
+    num_max_try: usize,
+) -> Result<Vec<Pose<N>>, Error<N>>
+where
+    N: Float + Debug,
+    S: Se2Space<N>,
+    FF: FnMut(&Pose<N>) -> bool,
+    FR: FnMut() -> Pose<N>,
+{
+    let vertices = search(
+        space,
+        start,
+        goal,
+        is_free,
+        random_sample,
+        extend_length,
+        validity_resolution,
+        num_max_try,
+    )?;
+    let mut path = vec![*start];
+    for w in vertices.windows(2) {
+        path.extend(discretize(space, &w[0], &w[1], validity_resolution));
+    }
+    *path.last_mut().unwrap() = *goal;
+    Ok(path)
+}
+
+/// [`rrt`] which returns the segments of the curves, so that the car can be
+/// commanded with the steering primitive and the direction of each of them.
+#[allow(clippy::too_many_arguments)]
+pub fn rrt_segments<N, S, FF, FR>(
+    space: &S,
+    start: &Pose<N>,
+    goal: &Pose<N>,
+    is_free: FF,
+    random_sample: FR,
+    extend_length: N,
+    validity_resolution: N,
+    num_max_try: usize,
+) -> Result<Vec<PathSegment<N>>, Error<N>>
+where
+    N: Float + Debug,
+    S: Se2Space<N>,
+    FF: FnMut(&Pose<N>) -> bool,
+    FR: FnMut() -> Pose<N>,
+{
+    let vertices = search(
+        space,
+        start,
+        goal,
+        is_free,
+        random_sample,
+        extend_length,
+        validity_resolution,
+        num_max_try,
+    )?;
+    let mut segments = Vec::new();
+    for w in vertices.windows(2) {
+        let num_segments = segments.len();
+        segments.extend(space.curve(&w[0], &w[1]).path_segments(&w[0]));
+        if segments.len() > num_segments {
+            segments.last_mut().unwrap().end = w[1];
+        }
+    }
+    Ok(segments)
+}
+
+/// Poses of the vertices from `start` to `goal` found by [`rrt`]
+#[allow(clippy::too_many_arguments)]
+fn search<N, S, FF, FR>(
+    space: &S,
+    start: &Pose<N>,
+    goal: &Pose<N>,
+    mut is_free: FF,
+    mut random_sample: FR,
+    extend_length: N,
+    validity_resolution: N,
+    num_max_try: usize,
+) -> Result<Vec<Pose<N>>, Error<N>>
+where
+    N: Float + Debug,
+    S: Se2Space<N>,
+    FF: FnMut(&Pose<N>) -> bool,
+    FR: FnMut() -> Pose<N>,
+{
+    assert!(extend_length > N::zero() && validity_resolution > N::zero());
+    if !is_free(start) {
+        return Err(Error::StartInCollision);
+    }
+    if !is_free(goal) {
+        return Err(Error::GoalInCollision);
+    }
+    let mut neighbours = Neighbours::new();
+    neighbours.add(start);
+    let mut parents = vec![None];
+    let mut try_goal = Some(0);
+    for _ in 0..num_max_try {
+        if let Some(index) = try_goal {
+            let pose = neighbours.pose(index);
+            if space.distance(pose, goal) <= extend_length
+                && discretize(space, pose, goal, validity_resolution)
+                    .iter()
+                    .all(&mut is_free)
+            {
+                let mut vertices = vec![*goal, *pose];
+                let mut index = index;
+                while let Some(parent) = parents[index] {
+                    vertices.push(*neighbours.pose(parent));
+                    index = parent;
+                }
+                vertices.reverse();
+                return Ok(vertices);
+            }
+        }
+        let q_rand = random_sample();
+        let nearest = neighbours.nearest(space, &q_rand).unwrap();
+        let q_near = *neighbours.pose(nearest);
+        let d = space.distance(&q_near, &q_rand);
+        let q_new = if d > extend_length {
+            space.interpolate(&q_near, &q_rand, extend_length / d)
+        } else {
+            q_rand
+        };
+        try_goal = None;
+        if discretize(space, &q_near, &q_new, validity_resolution)
+            .iter()
+            .all(&mut is_free)
+        {
+            try_goal = Some(neighbours.add(&q_new));
+            parents.push(Some(nearest));
+        }
+    }
+    Err(Error::MaxIterationsReached { best_partial: None })
+}
+
+#[test]
+fn it_works() {
+    use rand::Rng;
+    let close = |a: &Pose<f64>, b: &Pose<f64>| {
+        (a[0] - b[0]).hypot(a[1] - b[1]) < 1e-6 && angle_difference(a[2], b[2]).abs() < 1e-6
+    };
+    let random_pose = || {
+        let mut rng = rand::thread_rng();
+        [
+            rng.gen_range(-3.0..3.0),
+            rng.gen_range(-3.0..3.0),
+            rng.gen_range(-PI..PI),
+        ]
+    };
+    let dubins = Dubins::new(0.5);
+    let reeds_shepp = ReedsShepp::new(0.5);
+    for _ in 0..1000 {
+        let (a, b) = (random_pose(), random_pose());
+        let euclid = (a[0] - b[0]).hypot(a[1] - b[1]);
+        for curve in [dubins.curve(&a, &b), reeds_shepp.curve(&a, &b)] {
+            assert!(close(&curve.pose_at(&a, curve.length()), &b));
+            assert!(curve.length() >= euclid - 1e-9);
+        }
+        let (d, rs) = (dubins.distance(&a, &b), reeds_shepp.distance(&a, &b));
+        assert!(rs <= d + 1e-9);
+        assert!((rs - reeds_shepp.distance(&b, &a)).abs() < 1e-9);
+    }
+    // turning in place is impossible, backing up helps
+    let a = [0.0, 0.0, 0.0];
+    assert!(dubins.distance(&a, &a) < 1e-9);
+    assert!((dubins.distance(&a, &[1.0, 0.0, 0.0]) - 1.0).abs() < 1e-9);
+    assert!((reeds_shepp.distance(&a, &[-1.0, 0.0, 0.0]) - 1.0).abs() < 1e-9);
+    assert!(dubins.distance(&a, &[-1.0, 0.0, 0.0]) > 3.0);
+    let back_up = reeds_shepp.curve(&a, &[-1.0, 0.0, 0.0]).path_segments(&a);
+    assert_eq!(back_up.len(), 1);
+    assert_eq!(back_up[0].primitive, Segment::Straight);
+    assert_eq!(back_up[0].direction, Direction::Backward);
+    assert!((back_up[0].length - 1.0).abs() < 1e-9);
+    assert!(close(&back_up[0].end, &[-1.0, 0.0, 0.0]));
+    let half_circle = dubins.curve(&a, &[0.0, 1.0, PI]);
+    assert_eq!(
+        half_circle
+            .segments
+            .iter()
+            .filter(|(_, v)| *v > 1e-9)
+            .count(),
+        1
+    );
+    assert!((half_circle.length() - 0.5 * PI).abs() < 1e-9);
+
+    let mut neighbours = Neighbours::new();
+    assert_eq!(neighbours.nearest(&dubins, &a), None);
+    let poses = (0..200).map(|_| random_pose()).collect::<Vec<_>>();
+    for p in &poses {
+        neighbours.add(p);
+    }
+    for _ in 0..100 {
+        let q = random_pose();
+        for space in [&dubins as &dyn Se2Space<f64>, &reeds_shepp] {
+            let expected = poses
+                .iter()
+                .map(|p| space.distance(p, &q))
+                .fold(f64::INFINITY, f64::min);
+            let nearest = neighbours.nearest(space, &q).unwrap();
+            assert_eq!(space.distance(&poses[nearest], &q), expected);
+        }
+    }
+
+    // park backward into the slot between the walls
+    let is_free =
+        |p: &Pose<f64>| p[0].abs() < 3.0 && p[1].abs() < 3.0 && !(p[1] < 0.0 && p[0].abs() > 0.5);
+    let start = [-2.0, 1.0, 0.0];
+    let goal = [0.0, -1.5, FRAC_PI_2];
+    let random_sample = || {
+        if rand::thread_rng().gen_bool(0.1) {
+            goal
+        } else {
+            random_pose()
+        }
+    };
+    let path = rrt(
+        &reeds_shepp,
+        &start,
+        &goal,
+        is_free,
+        random_sample,
+        0.5,
+        0.05,
+        10000,
+    )
+    .unwrap();
+    assert_eq!(path[0], start);
+    assert_eq!(path.last().unwrap(), &goal);
+    assert!(path.iter().all(is_free));
+    for w in path.windows(2) {
+        assert!((w[0][0] - w[1][0]).hypot(w[0][1] - w[1][1]) <= 0.05 + 1e-9);
+    }
+    // driving each segment with its primitive and direction reaches the goal
+    let segments = rrt_segments(
+        &reeds_shepp,
+        &start,
+        &goal,
+        is_free,
+        random_sample,
+        0.5,
+        0.05,
+        10000,
+    )
+    .unwrap();
+    let mut pose = start;
+    for segment in &segments {
+        assert!(segment.length > 0.0);
+        let length = match segment.direction {
+            Direction::Forward => segment.length,
+            Direction::Backward => -segment.length,
+        };
+        let curve = Curve {
+            segments: vec![(segment.primitive, length)],
+            turning_radius: 0.5,
+        };
+        assert!(close(&curve.pose_at(&pose, segment.length), &segment.end));
+        assert!((0..10)
+            .map(|i| curve.pose_at(&pose, segment.length * i as f64 / 10.0))
+            .all(|p| is_free(&p)));
+        pose = segment.end;
+    }
+    assert_eq!(pose, goal);
+    // the slot is too narrow to turn around without backing up
+    let goal = [0.0, -1.5, -FRAC_PI_2];
+    let random_sample = || {
+        if rand::thread_rng().gen_bool(0.1) {
+            goal
+        } else {
+            random_pose()
+        }
+    };
+    let path = rrt(
+        &dubins,
+        &start,
+        &goal,
+        is_free,
+        random_sample,
+        0.5,
+        0.05,
+        50000,
+    )
+    .unwrap();
+    assert_eq!(path.last().unwrap(), &goal);
+    assert!(path.iter().all(is_free));
+    assert_eq!(
+        rrt(&dubins, &start, &goal, is_free, random_sample, 0.5, 0.05, 0).unwrap_err(),
+        Error::MaxIterationsReached { best_partial: None }
+    );
+}
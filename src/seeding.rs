@@ -0,0 +1,110 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Independent random streams derived from a master seed.
+//!
+//! Parallel planners must not share one generator, since the interleaving
+//! of the threads would then decide who gets which sample. Instead each task
+//! gets its own [`StdRng`] from [`SeedSequence::rng`], whose seed depends only
+//! on the master seed and the index of the task, so a multi-threaded run is
+//! replayed by the same master seed whatever the number of threads.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Advance the SplitMix64 generator at `state` and return its next output
+pub fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(GOLDEN_GAMMA);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Master seed from which the seeds of the tasks are split
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SeedSequence {
+    seed: u64,
+}
+
+impl SeedSequence {
+    /// Sequence of the master `seed`
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Master seed
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Seed of the task `index`
+    pub fn child_seed(&self, index: u64) -> u64 {
+        // mix the master seed first, so the streams of the neighbouring
+        // master seeds do not overlap
+        let mut state = self.seed;
+        let mut state = splitmix64(&mut state).wrapping_add(index.wrapping_mul(GOLDEN_GAMMA));
+        splitmix64(&mut state)
+    }
+
+    /// Sequence of the task `index`, to split it further (e.g. per query of
+    /// a batch and per thread of a query)
+    pub fn child(&self, index: u64) -> Self {
+        Self::new(self.child_seed(index))
+    }
+
+    /// Generator of the task `index`
+    pub fn rng(&self, index: u64) -> StdRng {
+        StdRng::seed_from_u64(self.child_seed(index))
+    }
+
+    /// Generators of the tasks `0..n`
+    pub fn rngs(&self, n: usize) -> Vec<StdRng> {
+        (0..n as u64).map(|i| self.rng(i)).collect()
+    }
+}
+
+#[test]
+fn it_works() {
+    use rand::Rng;
+    let mut state = 0;
+    // the reference outputs of SplitMix64 seeded by 0
+    assert_eq!(splitmix64(&mut state), 0xe220_a839_7b1d_cdaf);
+    assert_eq!(splitmix64(&mut state), 0x6e78_9e6a_a1b9_65f4);
+
+    let seeds = SeedSequence::new(42);
+    assert_eq!(seeds.child_seed(3), SeedSequence::new(42).child_seed(3));
+    let children = (0..1000).map(|i| seeds.child_seed(i)).collect::<Vec<_>>();
+    let mut unique = children.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(unique.len(), children.len());
+    assert_ne!(seeds.child_seed(0), SeedSequence::new(43).child_seed(0));
+    assert_ne!(seeds.child(1).child_seed(0), seeds.child_seed(1));
+
+    let draw = |rngs: &mut Vec<StdRng>| {
+        rngs.iter_mut()
+            .map(|rng| rng.gen::<u64>())
+            .collect::<Vec<_>>()
+    };
+    let first = draw(&mut seeds.rngs(4));
+    assert_eq!(first, draw(&mut seeds.rngs(4)));
+    assert_ne!(first[0], first[1]);
+    // the first streams do not depend on how many are split
+    assert_eq!(first[..2], draw(&mut seeds.rngs(2))[..]);
+}
@@ -0,0 +1,376 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Minimal HTTP planning service.
+//!
+//! `POST /plan` takes a JSON encoded [`PlanRequest`] and answers with a JSON
+//! encoded [`PlanResponse`].
+
+use crate::collision::{Obstacle, Scene};
+use kdtree::distance::squared_euclidean;
+use rand::distributions::{Distribution, Uniform};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use tracing::debug;
+
+/// Largest accepted request body in bytes
+pub const MAX_BODY_LENGTH: usize = 1 << 20;
+/// Largest accepted `num_max_try` and `num_smooth_try`
+pub const MAX_NUM_TRY: usize = 1_000_000;
+/// Largest accepted size of the sampling region in `extend_length`s
+pub const MAX_NUM_STEPS: f64 = 1e6;
+/// Largest accepted request line and headers in bytes
+pub const MAX_HEADER_LENGTH: usize = 8 << 10;
+/// Time after which a connection which sends nothing is dropped
+pub const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Planning request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanRequest {
+    /// Start configuration
+    pub start: Vec<f64>,
+    /// Goal configuration
+    pub goal: Vec<f64>,
+    /// Lower bounds of the sampling region
+    pub lower: Vec<f64>,
+    /// Upper bounds of the sampling region
+    pub upper: Vec<f64>,
+    /// Obstacles
+    #[serde(default)]
+    pub scene: Scene<f64>,
+    /// Unit length of extend
+    pub extend_length: f64,
+    /// Max repeat num
+    pub num_max_try: usize,
+    /// Number of smoothing trials, `0` disables smoothing
+    #[serde(default)]
+    pub num_smooth_try: usize,
+}
+
+/// Planning response
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlanResponse {
+    /// Found path, `None` if planning failed
+    pub path: Option<Vec<Vec<f64>>>,
+    /// Error message if planning failed
+    pub error: Option<String>,
+}
+
+/// Check that the request can be planned, returns the reason if not
+pub fn validate(request: &PlanRequest) -> Result<(), String> {
+    let dim = request.start.len();
+    if request.goal.len() != dim || request.lower.len() != dim || request.upper.len() != dim {
+        return Err("dimension mismatch".to_string());
+    }
+    if !(request.extend_length > 0.0 && request.extend_length.is_finite()) {
+        return Err("extend_length must be positive".to_string());
+    }
+    if request
+        .lower
+        .iter()
+        .zip(request.upper.iter())
+        .any(|(l, u)| !(l.is_finite() && u.is_finite() && l <= u))
+    {
+        return Err("lower and upper must be finite and lower <= upper".to_string());
+    }
+    let within = |q: &[f64]| {
+        q.iter()
+            .zip(request.lower.iter().zip(request.upper.iter()))
+            .all(|(x, (l, u))| l <= x && x <= u)
+    };
+    if !(within(&request.start) && within(&request.goal)) {
+        return Err("start and goal must be within lower and upper".to_string());
+    }
+    // bounds the length of every edge, and so the steps to extend along it
+    // (the diagonal may overflow to infinity, but is never NaN)
+    let diagonal = squared_euclidean(&request.lower, &request.upper).sqrt();
+    if diagonal / request.extend_length > MAX_NUM_STEPS {
+        return Err(format!(
+            "the sampling region must be at most {MAX_NUM_STEPS} extend_length across"
+        ));
+    }
+    if request.num_max_try > MAX_NUM_TRY || request.num_smooth_try > MAX_NUM_TRY {
+        return Err(format!(
+            "num_max_try and num_smooth_try must be <= {MAX_NUM_TRY}"
+        ));
+    }
+    for obstacle in &request.scene.obstacles {
+        let matches = match obstacle {
+            Obstacle::Sphere(s) => s.center.len() == dim,
+            Obstacle::Box(b) => b.min.len() == dim && b.max.len() == dim,
+        };
+        if !matches {
+            return Err("obstacle dimension mismatch".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Solve the request using `dual_rrt_connect`
+pub fn plan(request: &PlanRequest) -> PlanResponse {
+    if let Err(e) = validate(request) {
+        return PlanResponse {
+            path: None,
+            error: Some(e),
+        };
+    }
+    let ranges = request
+        .lower
+        .iter()
+        .zip(request.upper.iter())
+        .map(|(l, u)| Uniform::new_inclusive(*l, *u))
+        .collect::<Vec<_>>();
+    let is_free = |q: &[f64]| request.scene.is_free(q);
+    match crate::rrt::dual_rrt_connect(
+        &request.start,
+        &request.goal,
+        is_free,
+        || {
+            let mut rng = rand::thread_rng();
+            ranges.iter().map(|r| r.sample(&mut rng)).collect()
+        },
+        request.extend_length,
+        request.num_max_try,
+    ) {
+        Ok(mut path) => {
+            if request.num_smooth_try > 0 {
+                crate::rrt::smooth_path(
+                    &mut path,
+                    is_free,
+                    request.extend_length,
+                    request.num_smooth_try,
+                );
+            }
+            PlanResponse {
+                path: Some(path),
+                error: None,
+            }
+        }
+        Err(e) => PlanResponse {
+            path: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Serve `POST /plan` on `addr` forever
+pub fn serve<A: ToSocketAddrs>(addr: A) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    for stream in listener.incoming() {
+        if let Err(e) = handle_connection(stream?) {
+            debug!("connection error: {e}");
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    // the request line and the headers share `MAX_HEADER_LENGTH`
+    let mut header = reader.by_ref().take(MAX_HEADER_LENGTH as u64);
+    let mut request_line = String::new();
+    let mut complete = header.read_line(&mut request_line)? > 0 && request_line.ends_with('\n');
+    let mut content_length = 0;
+    while complete {
+        let mut line = String::new();
+        header.read_line(&mut line)?;
+        complete = line.ends_with('\n');
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    if !complete {
+        return write_response(
+            stream,
+            "431 Request Header Fields Too Large",
+            &PlanResponse {
+                path: None,
+                error: Some(format!("headers must be <= {MAX_HEADER_LENGTH} bytes")),
+            },
+        );
+    }
+    if content_length > MAX_BODY_LENGTH {
+        return write_response(
+            stream,
+            "413 Payload Too Large",
+            &PlanResponse {
+                path: None,
+                error: Some(format!("body must be <= {MAX_BODY_LENGTH} bytes")),
+            },
+        );
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let mut parts = request_line.split_whitespace();
+    let (status, response) = match (parts.next(), parts.next()) {
+        (Some("POST"), Some("/plan")) => {
+            match serde_json::from_slice::<PlanRequest>(&body)
+                .map_err(|e| e.to_string())
+                .and_then(|request| validate(&request).map(|_| request))
+            {
+                Ok(request) => ("200 OK", plan(&request)),
+                Err(e) => (
+                    "400 Bad Request",
+                    PlanResponse {
+                        path: None,
+                        error: Some(e),
+                    },
+                ),
+            }
+        }
+        _ => (
+            "404 Not Found",
+            PlanResponse {
+                path: None,
+                error: Some("not found".to_string()),
+            },
+        ),
+    };
+    write_response(stream, status, &response)
+}
+
+fn write_response(mut stream: TcpStream, status: &str, response: &PlanResponse) -> io::Result<()> {
+    let body = serde_json::to_vec(response)?;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+#[test]
+fn it_works() {
+    let request: PlanRequest = serde_json::from_str(
+        r#"{
+            "start": [-1.2, 0.0],
+            "goal": [1.2, 0.0],
+            "lower": [-2.0, -2.0],
+            "upper": [2.0, 2.0],
+            "scene": {"obstacles": [{"box": {"min": [-1.0, -1.0], "max": [1.0, 1.0]}}]},
+            "extend_length": 0.2,
+            "num_max_try": 1000,
+            "num_smooth_try": 100
+        }"#,
+    )
+    .unwrap();
+    let response = plan(&request);
+    assert!(response.error.is_none());
+    assert!(response.path.unwrap().len() >= 3);
+}
+
+#[test]
+fn rejects_invalid_requests() {
+    let valid: PlanRequest = serde_json::from_str(
+        r#"{
+            "start": [-1.2, 0.0],
+            "goal": [1.2, 0.0],
+            "lower": [-2.0, -2.0],
+            "upper": [2.0, 2.0],
+            "extend_length": 0.2,
+            "num_max_try": 1000
+        }"#,
+    )
+    .unwrap();
+    assert!(validate(&valid).is_ok());
+
+    let mut request = valid.clone();
+    request.lower[0] = 3.0;
+    assert!(plan(&request).error.is_some());
+    request.lower[0] = f64::NAN;
+    assert!(plan(&request).error.is_some());
+
+    let mut request = valid.clone();
+    request.goal[1] = 2.5;
+    assert!(plan(&request).error.is_some());
+
+    let mut request = valid.clone();
+    request.goal = vec![1e308, 0.0];
+    request.upper[0] = 1e308;
+    request.extend_length = 1e-300;
+    assert!(plan(&request).error.is_some());
+    request.goal = vec![1.2, 0.0];
+    request.upper[0] = 2.0;
+    assert!(plan(&request).error.is_some());
+
+    let mut request = valid.clone();
+    request.num_max_try = MAX_NUM_TRY + 1;
+    assert!(plan(&request).error.is_some());
+
+    let mut request = valid;
+    request
+        .scene
+        .obstacles
+        .push(Obstacle::Sphere(crate::collision::Sphere {
+            center: vec![0.0, 0.0, 0.0],
+            radius: 0.5,
+        }));
+    assert!(plan(&request).error.is_some());
+}
+
+#[test]
+fn rejects_large_body() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream).unwrap();
+    });
+    let mut client = TcpStream::connect(addr).unwrap();
+    write!(
+        client,
+        "POST /plan HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+        MAX_BODY_LENGTH + 1
+    )
+    .unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    server.join().unwrap();
+    assert!(response.starts_with("HTTP/1.1 413"));
+}
+
+#[test]
+fn rejects_long_headers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream).unwrap();
+    });
+    let mut client = TcpStream::connect(addr).unwrap();
+    // no line ends within the limit, and the server reads all of it
+    let prefix = "POST /plan HTTP/1.1\r\nX-Padding: ";
+    write!(
+        client,
+        "{prefix}{}",
+        "a".repeat(MAX_HEADER_LENGTH - prefix.len())
+    )
+    .unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    server.join().unwrap();
+    assert!(response.starts_with("HTTP/1.1 431"));
+}
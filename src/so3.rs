@@ -0,0 +1,249 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Orientation (SO(3)) planning.
+//!
+//! Orientations are unit quaternions `[w, x, y, z]`. `q` and `-q` are the
+//! same rotation, so the distance is the angle of the relative rotation, the
+//! interpolation is the shortest arc slerp, and the nearest neighbour search
+//! queries both `q` and `-q` (the chordal distance on the unit sphere is
+//! monotone in the angle, so the Euclidean kd-tree gives the right answer).
+
+use crate::rrt::Error;
+use kdtree::distance::squared_euclidean;
+use num_traits::float::Float;
+use rand::Rng;
+use std::fmt::Debug;
+use std::mem;
+
+/// Unit quaternion `[w, x, y, z]`
+pub type Quaternion<N> = [N; 4];
+
+fn dot<N: Float>(a: &Quaternion<N>, b: &Quaternion<N>) -> N {
+    a.iter().zip(b).fold(N::zero(), |sum, (a, b)| sum + *a * *b)
+}
+
+/// Scale `q` to unit length
+pub fn normalize<N: Float>(q: &Quaternion<N>) -> Quaternion<N> {
+    let norm = dot(q, q).sqrt();
+    q.map(|v| v / norm)
+}
+
+/// Angle of the rotation from `a` to `b`, in `[0, pi]`
+pub fn distance<N: Float>(a: &Quaternion<N>, b: &Quaternion<N>) -> N {
+    let two = N::one() + N::one();
+    two * dot(a, b).abs().min(N::one()).acos()
+}
+
+/// Interpolate along the shortest arc from `a` (`t = 0`) to `b` (`t = 1`)
+pub fn slerp<N: Float>(a: &Quaternion<N>, b: &Quaternion<N>, t: N) -> Quaternion<N> {
+    let mut d = dot(a, b);
+    let mut b = *b;
+    if d < N::zero() {
+        b = b.map(|v| -v);
+        d = -d;
+    }
+    let (wa, wb) = if d > N::from(0.9995).unwrap() {
+        (N::one() - t, t)
+    } else {
+        let theta = d.acos();
+        let sin = theta.sin();
+        (
+            ((N::one() - t) * theta).sin() / sin,
+            (t * theta).sin() / sin,
+        )
+    };
+    normalize(&[0, 1, 2, 3].map(|i| a[i] * wa + b[i] * wb))
+}
+
+/// Rotate the vector `v` by `q`
+pub fn rotate<N: Float>(q: &Quaternion<N>, v: &[N; 3]) -> [N; 3] {
+    let two = N::one() + N::one();
+    let u = [q[1], q[2], q[3]];
+    let cross = |a: &[N; 3], b: &[N; 3]| {
+        [
+            a[1] * b[2] - a[2] * b[1],
+            a[2] * b[0] - a[0] * b[2],
+            a[0] * b[1] - a[1] * b[0],
+        ]
+    };
+    let uv = cross(&u, v);
+    let uuv = cross(&u, &uv);
+    [0, 1, 2].map(|i| v[i] + two * (q[0] * uv[i] + uuv[i]))
+}
+
+/// Uniformly distributed random rotation
+pub fn random_rotation<N: Float, R: Rng + ?Sized>(rng: &mut R) -> Quaternion<N> {
+    let u1 = rng.gen::<f64>();
+    let u2 = rng.gen::<f64>() * std::f64::consts::TAU;
+    let u3 = rng.gen::<f64>() * std::f64::consts::TAU;
+    let a = (1.0 - u1).sqrt();
+    let b = u1.sqrt();
+    [a * u2.sin(), a * u2.cos(), b * u3.sin(), b * u3.cos()].map(|v| N::from(v).unwrap())
+}
+
+struct Tree<N: Float> {
+    kdtree: kdtree::KdTree<N, usize, Vec<N>>,
+    vertices: Vec<(Quaternion<N>, Option<usize>)>,
+    name: &'static str,
+}
+
+impl<N: Float + Debug> Tree<N> {
+    fn new(name: &'static str, root: &Quaternion<N>) -> Self {
+        let mut tree = Self {
+            kdtree: kdtree::KdTree::new(4),
+            vertices: Vec::new(),
+            name,
+        };
+        tree.add_vertex(root, None);
+        tree
+    }
+
+    fn add_vertex(&mut self, q: &Quaternion<N>, parent: Option<usize>) -> usize {
+        let index = self.vertices.len();
+        self.kdtree.add(q.to_vec(), index).unwrap();
+        self.vertices.push((*q, parent));
+        index
+    }
+
+    fn nearest(&self, q: &Quaternion<N>) -> usize {
+        let negated = q.map(|v| -v);
+        let (d1, i1) = self.kdtree.nearest(q, 1, &squared_euclidean).unwrap()[0];
+        let (d2, i2) = self
+            .kdtree
+            .nearest(&negated, 1, &squared_euclidean)
+            .unwrap()[0];
+        if d1 <= d2 {
+            *i1
+        } else {
+            *i2
+        }
+    }
+
+    /// Returns the index of the new vertex and true if it is the target
+    fn extend<FF>(
+        &mut self,
+        from: usize,
+        target: &Quaternion<N>,
+        extend_angle: N,
+        is_free: &mut FF,
+    ) -> Option<(usize, bool)>
+    where
+        FF: FnMut(&Quaternion<N>) -> bool,
+    {
+        let near = self.vertices[from].0;
+        let d = distance(&near, target);
+        let (q_new, reached) = if d < extend_angle {
+            (*target, true)
+        } else {
+            (slerp(&near, target, extend_angle / d), false)
+        };
+        if !is_free(&q_new) {
+            return None;
+        }
+        Some((self.add_vertex(&q_new, Some(from)), reached))
+    }
+
+    fn path_to_root(&self, mut index: usize) -> Vec<Quaternion<N>> {
+        let mut path = vec![self.vertices[index].0];
+        while let Some(parent) = self.vertices[index].1 {
+            index = parent;
+            path.push(self.vertices[index].0);
+        }
+        path
+    }
+}
+
+/// Dual RRT Connect in SO(3). Each step rotates by at most `extend_angle`.
+pub fn dual_rrt_connect<FF, FR, N>(
+    start: &Quaternion<N>,
+    goal: &Quaternion<N>,
+    mut is_free: FF,
+    random_sample: FR,
+    extend_angle: N,
+    num_max_try: usize,
+) -> Result<Vec<Quaternion<N>>, Error<N>>
+where
+    FF: FnMut(&Quaternion<N>) -> bool,
+    FR: Fn() -> Quaternion<N>,
+    N: Float + Debug,
+{
+    assert!(extend_angle > N::zero());
+    if !is_free(start) {
+        return Err(Error::StartInCollision);
+    }
+    if !is_free(goal) {
+        return Err(Error::GoalInCollision);
+    }
+    let mut tree_a = Tree::new("start", &normalize(start));
+    let mut tree_b = Tree::new("goal", &normalize(goal));
+    for _ in 0..num_max_try {
+        let q_rand = normalize(&random_sample());
+        let nearest = tree_a.nearest(&q_rand);
+        if let Some((new_index, _)) = tree_a.extend(nearest, &q_rand, extend_angle, &mut is_free) {
+            let q_new = tree_a.vertices[new_index].0;
+            let mut status = Some((tree_b.nearest(&q_new), false));
+            while let Some((index, false)) = status {
+                status = tree_b.extend(index, &q_new, extend_angle, &mut is_free);
+            }
+            if let Some((reach_index, true)) = status {
+                let mut path = tree_a.path_to_root(new_index);
+                path.reverse();
+                // the vertex at `reach_index` is the same as `q_new`
+                path.extend(tree_b.path_to_root(reach_index).into_iter().skip(1));
+                if tree_b.name == "start" {
+                    path.reverse();
+                }
+                return Ok(path);
+            }
+        }
+        mem::swap(&mut tree_a, &mut tree_b);
+    }
+    Err(Error::MaxIterationsReached { best_partial: None })
+}
+
+#[test]
+fn it_works() {
+    let identity = [1.0, 0.0, 0.0, 0.0];
+    let half = std::f64::consts::FRAC_1_SQRT_2;
+    // 90 degrees around z
+    let z90 = [half, 0.0, 0.0, half];
+    assert!((distance(&identity, &z90) - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+    assert!(distance(&z90, &z90.map(|v| -v)) < 1e-6);
+    let mid = slerp(&identity, &z90.map(|v| -v), 0.5);
+    assert!((distance(&identity, &mid) - std::f64::consts::FRAC_PI_4).abs() < 1e-9);
+    let v = rotate(&z90, &[1.0, 0.0, 0.0]);
+    assert!(v[0].abs() < 1e-9 && (v[1] - 1.0).abs() < 1e-9);
+
+    // camera which must not look near +y, turned from +x to -x
+    let mut is_free = |q: &Quaternion<f64>| rotate(q, &[1.0, 0.0, 0.0])[1] < 0.7;
+    let goal = [0.0, 0.0, 0.0, 1.0];
+    let path = dual_rrt_connect(
+        &identity,
+        &goal,
+        is_free,
+        || random_rotation(&mut rand::thread_rng()),
+        0.1,
+        1000,
+    )
+    .unwrap();
+    assert_eq!(path[0], identity);
+    assert_eq!(path[path.len() - 1], goal);
+    for w in path.windows(2) {
+        assert!(distance(&w[0], &w[1]) <= 0.1 + 1e-9);
+    }
+    assert!(path.iter().all(&mut is_free));
+}
@@ -0,0 +1,821 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Planning in state spaces other than the Euclidean `Vec<N>`.
+//!
+//! A [`StateSpace`] gives the metric, the interpolation and the uniform
+//! sampling of its states, so continuous joints (SO(2)), rotations (SO(3)),
+//! rigid body poses (SE(3)) and custom manifolds are planned by
+//! [`dual_rrt_connect`] of this module.
+//!
+//! Only [`dual_rrt_connect`] and [`Tree`] of this module take a
+//! [`StateSpace`]. [`crate::rrt::Tree`], [`crate::rrt::DualRrtConnect`],
+//! [`crate::RrtPlanner`], [`crate::rrtstar`] and the other planners stay on
+//! the Euclidean `&[N]` with their kd-trees, so their options (goal bias,
+//! bounds, time budget, callbacks, RRT*, ...) are not available here. The
+//! nearest vertex is searched by the distance of the space (by a linear
+//! scan, since a kd-tree needs the Euclidean metric), so prefer
+//! [`crate::rrt`] for the Euclidean configurations with many vertices.
+
+use crate::rrt::Error;
+use crate::so3::{self, Quaternion};
+use num_traits::float::Float;
+use rand::{Rng, RngCore};
+use std::fmt::Debug;
+use std::mem;
+use tracing::debug;
+
+/// Space of the states of a planning problem
+pub trait StateSpace<N: Float> {
+    /// State type
+    type State: Clone + Debug;
+
+    /// Number of the degrees of freedom
+    fn dimensions(&self) -> usize;
+
+    /// Distance between the states, a metric
+    fn distance(&self, a: &Self::State, b: &Self::State) -> N;
+
+    /// State at the fraction `t` of the way from `a` (`t = 0`) to `b` (`t = 1`)
+    fn interpolate(&self, a: &Self::State, b: &Self::State, t: N) -> Self::State;
+
+    /// Uniformly distributed random state
+    fn sample_uniform(&self, rng: &mut dyn RngCore) -> Self::State;
+}
+
+/// Euclidean space within the axis aligned bounds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Euclidean<N> {
+    /// Lower bounds
+    pub lower: Vec<N>,
+    /// Upper bounds
+    pub upper: Vec<N>,
+}
+
+impl<N: Float> Euclidean<N> {
+    /// Space between `lower` and `upper`
+    pub fn new(lower: &[N], upper: &[N]) -> Self {
+        assert_eq!(lower.len(), upper.len());
+        Self {
+            lower: lower.to_vec(),
+            upper: upper.to_vec(),
+        }
+    }
+}
+
+impl<N: Float + Debug> StateSpace<N> for Euclidean<N> {
+    type State = Vec<N>;
+
+    fn dimensions(&self) -> usize {
+        self.lower.len()
+    }
+
+    fn distance(&self, a: &Vec<N>, b: &Vec<N>) -> N {
+        a.iter()
+            .zip(b)
+            .fold(N::zero(), |sum, (a, b)| sum + (*a - *b).powi(2))
+            .sqrt()
+    }
+
+    fn interpolate(&self, a: &Vec<N>, b: &Vec<N>, t: N) -> Vec<N> {
+        a.iter().zip(b).map(|(a, b)| *a + (*b - *a) * t).collect()
+    }
+
+    fn sample_uniform(&self, rng: &mut dyn RngCore) -> Vec<N> {
+        self.lower
+            .iter()
+            .zip(&self.upper)
+            .map(|(lower, upper)| *lower + (*upper - *lower) * N::from(rng.gen::<f64>()).unwrap())
+            .collect()
+    }
+}
+
+/// Topology of a dimension of a [`JointSpace`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Topology {
+    /// Interval between the bounds
+    #[default]
+    Linear,
+    /// Circle, where the upper bound is the same as the lower one (a
+    /// continuous joint of `[-pi, pi)`)
+    Wrapping,
+}
+
+/// Difference from `b` to `a` of the dimension of `topology`, which is the
+/// shorter way around for [`Topology::Wrapping`] with the `period`
+pub fn difference<N: Float>(a: N, b: N, topology: Topology, period: N) -> N {
+    let d = a - b;
+    match topology {
+        Topology::Linear => d,
+        Topology::Wrapping => {
+            let half = period / (N::one() + N::one());
+            let d = d % period;
+            if d > half {
+                d - period
+            } else if d < -half {
+                d + period
+            } else {
+                d
+            }
+        }
+    }
+}
+
+/// Joints of a robot arm, each of them linear or wrapping within its bounds,
+/// so 179 and -179 degrees of a continuous joint are 2 degrees apart
+#[derive(Debug, Clone, PartialEq)]
+pub struct JointSpace<N> {
+    /// Lower bounds
+    pub lower: Vec<N>,
+    /// Upper bounds
+    pub upper: Vec<N>,
+    /// Topology of each dimension
+    pub topology: Vec<Topology>,
+}
+
+impl<N: Float> JointSpace<N> {
+    /// Space of linear joints between `lower` and `upper`
+    pub fn new(lower: &[N], upper: &[N]) -> Self {
+        assert_eq!(lower.len(), upper.len());
+        Self {
+            lower: lower.to_vec(),
+            upper: upper.to_vec(),
+            topology: vec![Topology::Linear; lower.len()],
+        }
+    }
+
+    /// Make the dimension `dim` wrap around at its bounds
+    pub fn wrapping(mut self, dim: usize) -> Self {
+        self.topology[dim] = Topology::Wrapping;
+        self
+    }
+
+    /// Map the wrapping dimensions of `q` into `[lower, upper)`
+    pub fn normalize(&self, q: &[N]) -> Vec<N> {
+        q.iter()
+            .enumerate()
+            .map(|(i, v)| match self.topology[i] {
+                Topology::Linear => *v,
+                Topology::Wrapping => {
+                    let period = self.upper[i] - self.lower[i];
+                    let v = (*v - self.lower[i]) % period;
+                    let v = if v < N::zero() { v + period } else { v };
+                    self.lower[i] + v
+                }
+            })
+            .collect()
+    }
+
+    fn differences<'a>(&'a self, a: &'a [N], b: &'a [N]) -> impl Iterator<Item = N> + 'a {
+        (0..a.len())
+            .map(move |i| difference(a[i], b[i], self.topology[i], self.upper[i] - self.lower[i]))
+    }
+}
+
+impl<N: Float + Debug> StateSpace<N> for JointSpace<N> {
+    type State = Vec<N>;
+
+    fn dimensions(&self) -> usize {
+        self.lower.len()
+    }
+
+    fn distance(&self, a: &Vec<N>, b: &Vec<N>) -> N {
+        self.differences(a, b)
+            .fold(N::zero(), |sum, d| sum + d.powi(2))
+            .sqrt()
+    }
+
+    fn interpolate(&self, a: &Vec<N>, b: &Vec<N>, t: N) -> Vec<N> {
+        let q = a
+            .iter()
+            .zip(self.differences(b, a))
+            .map(|(a, d)| *a + d * t)
+            .collect::<Vec<_>>();
+        self.normalize(&q)
+    }
+
+    fn sample_uniform(&self, rng: &mut dyn RngCore) -> Vec<N> {
+        Euclidean::new(&self.lower, &self.upper).sample_uniform(rng)
+    }
+}
+
+/// Configurations between the bounds with the custom metric `distance`, and
+/// the straight line interpolation
+pub struct Metric<N, F> {
+    /// Bounds of the sampling
+    pub bounds: Euclidean<N>,
+    distance: F,
+}
+
+impl<N: Float, F: Fn(&[N], &[N]) -> N> Metric<N, F> {
+    /// Space between `lower` and `upper` with `distance(a, b)`, which must
+    /// be a metric
+    pub fn new(lower: &[N], upper: &[N], distance: F) -> Self {
+        Self {
+            bounds: Euclidean::new(lower, upper),
+            distance,
+        }
+    }
+}
+
+impl<N: Float + Debug, F: Fn(&[N], &[N]) -> N> StateSpace<N> for Metric<N, F> {
+    type State = Vec<N>;
+
+    fn dimensions(&self) -> usize {
+        self.bounds.dimensions()
+    }
+
+    fn distance(&self, a: &Vec<N>, b: &Vec<N>) -> N {
+        (self.distance)(a, b)
+    }
+
+    fn interpolate(&self, a: &Vec<N>, b: &Vec<N>, t: N) -> Vec<N> {
+        self.bounds.interpolate(a, b, t)
+    }
+
+    fn sample_uniform(&self, rng: &mut dyn RngCore) -> Vec<N> {
+        self.bounds.sample_uniform(rng)
+    }
+}
+
+/// Define a struct of named floats and a [`StateSpace`] of it, each field
+/// being `linear(lower, upper)` or `revolute` (a continuous joint of
+/// `[-pi, pi)`). The space is a [`JointSpace`] in the order of the fields,
+/// and the float type must be `f32` or `f64`.
+///
+/// ```
+/// rrt::state_space! {
+///     #[derive(Debug, Clone, Copy, PartialEq)]
+///     pub struct Cart => CartSpace<f64> {
+///         pub x: linear(-1.0, 1.0),
+///         pub heading: revolute,
+///     }
+/// }
+/// use rrt::space::StateSpace;
+/// let a = Cart { x: 0.0, heading: 3.0 };
+/// let b = Cart { x: 0.0, heading: -3.0 };
+/// assert!(CartSpace::new().distance(&a, &b) < 0.3);
+/// ```
+#[cfg(feature = "derive")]
+#[macro_export]
+macro_rules! state_space {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident => $space:ident<$n:ty> {
+            $($fvis:vis $field:ident: $kind:ident $(($lower:expr, $upper:expr))?),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($fvis $field: $n,)+
+        }
+
+        impl $name {
+            /// Fields in the order of the declaration
+            #[allow(clippy::wrong_self_convention)]
+            pub fn to_vec(&self) -> Vec<$n> {
+                vec![$(self.$field),+]
+            }
+
+            /// State of the fields in the order of the declaration
+            pub fn from_slice(q: &[$n]) -> Self {
+                let mut q = q.iter().copied();
+                Self {
+                    $($field: q.next().expect("too few values"),)+
+                }
+            }
+        }
+
+        #[doc = concat!("Space of [`", stringify!($name), "`]")]
+        #[derive(Debug, Clone, PartialEq)]
+        $vis struct $space {
+            /// Bounds and topology of the fields
+            pub joints: $crate::space::JointSpace<$n>,
+        }
+
+        impl $space {
+            /// Space of the declared bounds
+            pub fn new() -> Self {
+                let mut lower = Vec::new();
+                let mut upper = Vec::new();
+                let mut topology = Vec::new();
+                $(
+                    let (l, u, t) = $crate::state_space!(@bounds $n, $kind $(($lower, $upper))?);
+                    lower.push(l);
+                    upper.push(u);
+                    topology.push(t);
+                )+
+                let mut joints = $crate::space::JointSpace::new(&lower, &upper);
+                joints.topology = topology;
+                Self { joints }
+            }
+        }
+
+        impl Default for $space {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl $crate::space::StateSpace<$n> for $space {
+            type State = $name;
+
+            fn dimensions(&self) -> usize {
+                self.joints.dimensions()
+            }
+
+            fn distance(&self, a: &$name, b: &$name) -> $n {
+                self.joints.distance(&a.to_vec(), &b.to_vec())
+            }
+
+            fn interpolate(&self, a: &$name, b: &$name, t: $n) -> $name {
+                $name::from_slice(&self.joints.interpolate(&a.to_vec(), &b.to_vec(), t))
+            }
+
+            fn sample_uniform(&self, rng: &mut dyn $crate::__rand::RngCore) -> $name {
+                $name::from_slice(&self.joints.sample_uniform(rng))
+            }
+        }
+    };
+    (@bounds $n:ty, linear($lower:expr, $upper:expr)) => {
+        ($lower as $n, $upper as $n, $crate::space::Topology::Linear)
+    };
+    (@bounds $n:ty, revolute) => {
+        (
+            -::std::f64::consts::PI as $n,
+            ::std::f64::consts::PI as $n,
+            $crate::space::Topology::Wrapping,
+        )
+    };
+}
+
+/// Rotations as unit quaternions (see [`crate::so3`])
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct So3;
+
+impl<N: Float + Debug> StateSpace<N> for So3 {
+    type State = Quaternion<N>;
+
+    fn dimensions(&self) -> usize {
+        3
+    }
+
+    fn distance(&self, a: &Quaternion<N>, b: &Quaternion<N>) -> N {
+        so3::distance(a, b)
+    }
+
+    fn interpolate(&self, a: &Quaternion<N>, b: &Quaternion<N>, t: N) -> Quaternion<N> {
+        so3::slerp(a, b, t)
+    }
+
+    fn sample_uniform(&self, rng: &mut dyn RngCore) -> Quaternion<N> {
+        so3::random_rotation(rng)
+    }
+}
+
+/// Rigid body poses `(position, orientation)`. The distance is the one of
+/// the positions plus the rotation angle times `rotation_weight`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Se3<N> {
+    /// Bounds of the position
+    pub position: Euclidean<N>,
+    /// Length which is equivalent to one radian of rotation
+    pub rotation_weight: N,
+}
+
+impl<N: Float> Se3<N> {
+    /// Poses with the position between `lower` and `upper`
+    pub fn new(lower: &[N; 3], upper: &[N; 3], rotation_weight: N) -> Self {
+        Self {
+            position: Euclidean::new(lower, upper),
+            rotation_weight,
+        }
+    }
+}
+
+impl<N: Float + Debug> StateSpace<N> for Se3<N> {
+    type State = ([N; 3], Quaternion<N>);
+
+    fn dimensions(&self) -> usize {
+        6
+    }
+
+    fn distance(&self, a: &Self::State, b: &Self::State) -> N {
+        self.position.distance(&a.0.to_vec(), &b.0.to_vec())
+            + self.rotation_weight * so3::distance(&a.1, &b.1)
+    }
+
+    fn interpolate(&self, a: &Self::State, b: &Self::State, t: N) -> Self::State {
+        (
+            [0, 1, 2].map(|i| a.0[i] + (b.0[i] - a.0[i]) * t),
+            so3::slerp(&a.1, &b.1, t),
+        )
+    }
+
+    fn sample_uniform(&self, rng: &mut dyn RngCore) -> Self::State {
+        let p = self.position.sample_uniform(rng);
+        ([p[0], p[1], p[2]], so3::random_rotation(rng))
+    }
+}
+
+/// Tree of the states of a space, for [`dual_rrt_connect`] of this module
+#[derive(Debug)]
+pub struct Tree<S: StateSpace<N>, N: Float> {
+    vertices: Vec<(S::State, Option<usize>)>,
+    name: &'static str,
+}
+
+impl<S: StateSpace<N>, N: Float> Tree<S, N> {
+    /// Create a tree which has only `root` (vertex 0)
+    pub fn new(root: &S::State) -> Self {
+        Self::with_name("start", root)
+    }
+    fn with_name(name: &'static str, root: &S::State) -> Self {
+        Self {
+            vertices: vec![(root.clone(), None)],
+            name,
+        }
+    }
+    /// Number of the vertices
+    pub fn len(&self) -> usize {
+        self.vertices.len()
+    }
+    /// Always false, the root is never removed
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+    /// State of the vertex
+    pub fn state(&self, index: usize) -> &S::State {
+        &self.vertices[index].0
+    }
+    /// Parent of the vertex, `None` for the root
+    pub fn parent(&self, index: usize) -> Option<usize> {
+        self.vertices[index].1
+    }
+    /// Vertex nearest to `q` by the distance of `space`
+    pub fn nearest(&self, space: &S, q: &S::State) -> usize {
+        self.vertices
+            .iter()
+            .map(|(v, _)| space.distance(v, q))
+            .enumerate()
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .unwrap()
+            .0
+    }
+    /// Add a vertex from the nearest one toward `target`, at most
+    /// `extend_length` away, if the motion is free (checked every
+    /// `validity_resolution`). Returns the new vertex and true if it is
+    /// `target`.
+    pub fn extend<FF>(
+        &mut self,
+        space: &S,
+        target: &S::State,
+        extend_length: N,
+        validity_resolution: N,
+        is_free: &mut FF,
+    ) -> Option<(usize, bool)>
+    where
+        FF: FnMut(&S::State) -> bool,
+    {
+        let nearest = self.nearest(space, target);
+        self.extend_from(
+            space,
+            nearest,
+            target,
+            extend_length,
+            validity_resolution,
+            is_free,
+        )
+    }
+    fn extend_from<FF>(
+        &mut self,
+        space: &S,
+        from: usize,
+        target: &S::State,
+        extend_length: N,
+        validity_resolution: N,
+        is_free: &mut FF,
+    ) -> Option<(usize, bool)>
+    where
+        FF: FnMut(&S::State) -> bool,
+    {
+        let q_near = &self.vertices[from].0;
+        let d = space.distance(q_near, target);
+        let (q_new, reached, step) = if d <= extend_length {
+            (target.clone(), true, d)
+        } else {
+            let q = space.interpolate(q_near, target, extend_length / d);
+            (q, false, extend_length)
+        };
+        let num = (step / validity_resolution)
+            .ceil()
+            .to_usize()
+            .unwrap()
+            .max(1);
+        let num_n = N::from(num).unwrap();
+        let is_motion_free = (1..=num)
+            .all(|i| is_free(&space.interpolate(q_near, &q_new, N::from(i).unwrap() / num_n)));
+        if !is_motion_free {
+            return None;
+        }
+        self.vertices.push((q_new, Some(from)));
+        Some((self.vertices.len() - 1, reached))
+    }
+    /// States from the root to the vertex
+    pub fn path(&self, mut index: usize) -> Vec<S::State> {
+        let mut path = vec![self.vertices[index].0.clone()];
+        while let Some(parent) = self.vertices[index].1 {
+            index = parent;
+            path.push(self.vertices[index].0.clone());
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Dual RRT Connect in `space`. The samples are drawn by
+/// [`StateSpace::sample_uniform`] from `rng`, each step moves at most
+/// `extend_length`, and the motions are checked every `validity_resolution`.
+///
+/// This is the only planner of the crate which takes a [`StateSpace`], and
+/// it has none of the options of [`crate::RrtPlanner`].
+#[allow(clippy::too_many_arguments)]
+pub fn dual_rrt_connect<S, FF, N>(
+    space: &S,
+    start: &S::State,
+    goal: &S::State,
+    mut is_free: FF,
+    extend_length: N,
+    validity_resolution: N,
+    num_max_try: usize,
+    rng: &mut dyn RngCore,
+) -> Result<Vec<S::State>, Error<N>>
+where
+    S: StateSpace<N>,
+    FF: FnMut(&S::State) -> bool,
+    N: Float + Debug,
+{
+    assert!(extend_length > N::zero() && validity_resolution > N::zero());
+    if !is_free(start) {
+        return Err(Error::StartInCollision);
+    }
+    if !is_free(goal) {
+        return Err(Error::GoalInCollision);
+    }
+    let mut tree_a = Tree::<S, N>::with_name("start", start);
+    let mut tree_b = Tree::<S, N>::with_name("goal", goal);
+    for _ in 0..num_max_try {
+        debug!("tree_a = {}, tree_b = {}", tree_a.len(), tree_b.len());
+        let q_rand = space.sample_uniform(rng);
+        if let Some((new_index, _)) = tree_a.extend(
+            space,
+            &q_rand,
+            extend_length,
+            validity_resolution,
+            &mut is_free,
+        ) {
+            let q_new = tree_a.state(new_index).clone();
+            let mut status = Some((tree_b.nearest(space, &q_new), false));
+            while let Some((index, false)) = status {
+                status = tree_b.extend_from(
+                    space,
+                    index,
+                    &q_new,
+                    extend_length,
+                    validity_resolution,
+                    &mut is_free,
+                );
+            }
+            if let Some((reach_index, true)) = status {
+                let mut path = tree_a.path(new_index);
+                let mut rest = tree_b.path(reach_index);
+                rest.reverse();
+                // the vertex at `reach_index` is the same as `q_new`
+                path.extend(rest.into_iter().skip(1));
+                if tree_b.name == "start" {
+                    path.reverse();
+                }
+                return Ok(path);
+            }
+        }
+        mem::swap(&mut tree_a, &mut tree_b);
+    }
+    Err(Error::MaxIterationsReached { best_partial: None })
+}
+
+#[test]
+fn it_works() {
+    let mut rng = rand::thread_rng();
+    let plane = Euclidean::new(&[-2.0, -2.0], &[2.0, 2.0]);
+    assert_eq!(StateSpace::<f64>::dimensions(&plane), 2);
+    let q = plane.sample_uniform(&mut rng);
+    assert!(q.iter().all(|v| (-2.0..=2.0).contains(v)));
+    let is_free = |q: &Vec<f64>| !(q[0].abs() < 1.0 && q[1].abs() < 1.0);
+    let (start, goal) = (vec![-1.2, 0.0], vec![1.2, 0.0]);
+    let path = dual_rrt_connect(
+        &plane,
+        &start,
+        &goal,
+        is_free,
+        0.2,
+        0.01,
+        1000,
+        &mut rand::thread_rng(),
+    )
+    .unwrap();
+    assert_eq!(path[0], start);
+    assert_eq!(path[path.len() - 1], goal);
+    for w in path.windows(2) {
+        assert!(plane.distance(&w[0], &w[1]) <= 0.2 + 1e-9);
+        assert!((0..=20).all(|i| is_free(&plane.interpolate(&w[0], &w[1], i as f64 / 20.0))));
+    }
+
+    // rigid body which must not point its x axis up while it moves
+    let space = Se3::new(&[-1.0, -1.0, -1.0], &[1.0, 1.0, 1.0], 0.5);
+    assert_eq!(StateSpace::<f64>::dimensions(&space), 6);
+    let half = std::f64::consts::FRAC_1_SQRT_2;
+    let start = ([-0.5, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0]);
+    let goal = ([0.5, 0.0, 0.0], [half, 0.0, 0.0, half]);
+    assert!(
+        (space.distance(&start, &goal) - (1.0 + 0.5 * std::f64::consts::FRAC_PI_2)).abs() < 1e-9
+    );
+    let mid = space.interpolate(&start, &goal, 0.5);
+    assert!((space.distance(&start, &mid) - space.distance(&mid, &goal)).abs() < 1e-9);
+    let is_free = |q: &([f64; 3], Quaternion<f64>)| so3::rotate(&q.1, &[1.0, 0.0, 0.0])[2] < 0.5;
+    let path = dual_rrt_connect(
+        &space,
+        &start,
+        &goal,
+        is_free,
+        0.3,
+        0.05,
+        1000,
+        &mut rand::thread_rng(),
+    )
+    .unwrap();
+    assert_eq!(path[0], start);
+    assert_eq!(path[path.len() - 1], goal);
+    assert!(path.iter().all(is_free));
+
+    let path = dual_rrt_connect(
+        &So3,
+        &start.1,
+        &goal.1,
+        |_: &Quaternion<f64>| true,
+        0.1,
+        0.1,
+        1000,
+        &mut rand::thread_rng(),
+    )
+    .unwrap();
+    for w in path.windows(2) {
+        assert!(So3.distance(&w[0], &w[1]) <= 0.1 + 1e-9);
+    }
+    assert_eq!(
+        dual_rrt_connect(
+            &So3,
+            &start.1,
+            &goal.1,
+            |_: &Quaternion<f64>| false,
+            0.1,
+            0.1,
+            10,
+            &mut rand::thread_rng(),
+        )
+        .unwrap_err(),
+        Error::StartInCollision
+    );
+
+    // continuous joint, the obstacle is only passed by wrapping around
+    let pi = std::f64::consts::PI;
+    let joints = JointSpace::new(&[-pi, -1.0], &[pi, 1.0]).wrapping(0);
+    let (a, b) = (
+        vec![179f64.to_radians(), 0.0],
+        vec![-179f64.to_radians(), 0.0],
+    );
+    assert!((joints.distance(&a, &b) - 2f64.to_radians()).abs() < 1e-9);
+    let mid = joints.interpolate(&a, &b, 0.5);
+    assert!((mid[0].abs() - pi).abs() < 1e-9);
+    assert_eq!(joints.normalize(&[pi + 0.5, 2.0])[1], 2.0);
+    assert!((joints.normalize(&[pi + 0.5, 0.0])[0] - (0.5 - pi)).abs() < 1e-9);
+    let is_free = |q: &Vec<f64>| q[0].abs() > 2.5;
+    let (start, goal) = (vec![2.8, 0.0], vec![-2.8, 0.0]);
+    let path = dual_rrt_connect(
+        &joints,
+        &start,
+        &goal,
+        is_free,
+        0.2,
+        0.01,
+        1000,
+        &mut rand::thread_rng(),
+    )
+    .unwrap();
+    assert_eq!(path[0], start);
+    assert_eq!(path[path.len() - 1], goal);
+    for w in path.windows(2) {
+        assert!(joints.distance(&w[0], &w[1]) <= 0.2 + 1e-9);
+    }
+    assert!(path.iter().all(is_free));
+    assert!(dual_rrt_connect(
+        &JointSpace::new(&[-pi, -1.0], &[pi, 1.0]),
+        &start,
+        &goal,
+        is_free,
+        0.2,
+        0.01,
+        100,
+        &mut rand::thread_rng(),
+    )
+    .is_err());
+
+    // Manhattan metric
+    let manhattan = Metric::new(&[-2.0, -2.0], &[2.0, 2.0], |a: &[f64], b: &[f64]| {
+        a.iter().zip(b).map(|(a, b)| (a - b).abs()).sum()
+    });
+    assert_eq!(manhattan.distance(&vec![0.0, 0.0], &vec![1.0, -1.0]), 2.0);
+    let is_free = |q: &Vec<f64>| !(q[0].abs() < 1.0 && q[1].abs() < 1.0);
+    let (start, goal) = (vec![-1.2, 0.0], vec![1.2, 0.0]);
+    let path = dual_rrt_connect(
+        &manhattan,
+        &start,
+        &goal,
+        is_free,
+        0.2,
+        0.01,
+        1000,
+        &mut rand::thread_rng(),
+    )
+    .unwrap();
+    for w in path.windows(2) {
+        assert!(manhattan.distance(&w[0], &w[1]) <= 0.2 + 1e-9);
+    }
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn state_space_macro_works() {
+    crate::state_space! {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Arm => ArmSpace<f64> {
+            shoulder: revolute,
+            elbow: linear(-1.5, 1.5),
+        }
+    }
+    let space = ArmSpace::new();
+    assert_eq!(space.dimensions(), 2);
+    assert_eq!(
+        space.joints.topology,
+        [Topology::Wrapping, Topology::Linear]
+    );
+    let a = Arm {
+        shoulder: 3.0,
+        elbow: 0.0,
+    };
+    let b = Arm {
+        shoulder: -3.0,
+        elbow: 0.0,
+    };
+    assert!((space.distance(&a, &b) - (2.0 * std::f64::consts::PI - 6.0)).abs() < 1e-9);
+    assert_eq!(Arm::from_slice(&a.to_vec()), a);
+    let mut rng = rand::thread_rng();
+    let q = space.sample_uniform(&mut rng);
+    assert!(q.elbow.abs() <= 1.5);
+    // the elbow must pass the zero while the shoulder goes the short way
+    let is_free = |q: &Arm| q.shoulder.abs() > 2.0 || q.elbow.abs() > 0.5;
+    let start = Arm {
+        shoulder: 2.8,
+        elbow: -1.0,
+    };
+    let goal = Arm {
+        shoulder: -2.8,
+        elbow: 1.0,
+    };
+    let path = dual_rrt_connect(
+        &space,
+        &start,
+        &goal,
+        is_free,
+        0.2,
+        0.01,
+        1000,
+        &mut rand::thread_rng(),
+    )
+    .unwrap();
+    assert_eq!(path[0], start);
+    assert_eq!(path[path.len() - 1], goal);
+    assert!(path.iter().all(is_free));
+}
@@ -0,0 +1,72 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Extension step used by the planners.
+
+use kdtree::distance::squared_euclidean;
+use num_traits::float::Float;
+
+/// Move from `from` toward `to` by at most `max_step`.
+///
+/// Returns `to` itself and `true` if it is closer than `max_step` (including
+/// when the points coincide), otherwise the point at `max_step` on the
+/// segment and `false`.
+pub fn steer<N: Float>(from: &[N], to: &[N], max_step: N) -> (Vec<N>, bool) {
+    assert!(max_step > N::zero());
+    assert_eq!(from.len(), to.len());
+    let dist = squared_euclidean(from, to).sqrt();
+    if dist < max_step {
+        return (to.to_vec(), true);
+    }
+    let q = from
+        .iter()
+        .zip(to)
+        .map(|(a, b)| *a + (*b - *a) * max_step / dist)
+        .collect();
+    (q, false)
+}
+
+#[test]
+fn it_works() {
+    // far target: exactly max_step toward it
+    let (q, reached) = steer(&[0.0, 0.0], &[3.0, 4.0], 1.0);
+    assert!(!reached);
+    assert!((q[0] - 0.6).abs() < 1e-12 && (q[1] - 0.8).abs() < 1e-12);
+    // near target
+    assert_eq!(steer(&[0.0, 0.0], &[0.3, 0.4], 1.0), (vec![0.3, 0.4], true));
+    // coincident points do not divide by zero
+    assert_eq!(steer(&[1.0, 2.0], &[1.0, 2.0], 0.1), (vec![1.0, 2.0], true));
+    // exactly at max_step is a full step, which lands on the target
+    let (q, reached) = steer(&[0.0], &[0.5], 0.5);
+    assert!(!reached);
+    assert_eq!(q, [0.5]);
+    // negative directions and f32
+    let (q, reached) = steer(&[1.0f32, 1.0, 1.0], &[-1.0, 1.0, 1.0], 0.5);
+    assert!(!reached);
+    assert_eq!(q, [0.5, 1.0, 1.0]);
+    // zero dimensional configurations
+    assert_eq!(steer::<f64>(&[], &[], 1.0), (vec![], true));
+    // the step never overshoots
+    for i in 1..100 {
+        let to = [i as f64 * 0.01, -(i as f64) * 0.02];
+        let (q, reached) = steer(&[0.0, 0.0], &to, 0.5);
+        let dist = squared_euclidean(&q, &[0.0, 0.0]).sqrt();
+        assert!(dist <= 0.5 + 1e-12);
+        assert!(squared_euclidean(&q, &to).sqrt() <= squared_euclidean(&[0.0, 0.0], &to).sqrt());
+        assert_eq!(reached, q == to && dist < 0.5);
+    }
+    assert!(std::panic::catch_unwind(|| steer(&[0.0], &[1.0], 0.0)).is_err());
+}
@@ -0,0 +1,251 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Planning while the world is updated by other threads.
+//!
+//! Sensor pipelines push cost or validity updates through a [`Publisher`]
+//! (a channel, so no lock is held around the planner), and the
+//! [`SubscribedPlanner`] applies the pending ones to its world model between
+//! its steps. After an update the trees are checked again in the new world,
+//! and only their blocked parts are removed (see
+//! [`DualRrtConnect::revalidate`]), so the search goes on from the rest of
+//! the trees instead of starting over.
+
+use crate::rrt::DualRrtConnect;
+use num_traits::float::Float;
+use std::fmt::Debug;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Sending side of the updates, which can be cloned for each source
+#[derive(Debug)]
+pub struct Publisher<U> {
+    sender: Sender<U>,
+}
+
+impl<U> Clone for Publisher<U> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<U> Publisher<U> {
+    /// Send `update` to the planner. Returns false if the planner is gone.
+    pub fn publish(&self, update: U) -> bool {
+        self.sender.send(update).is_ok()
+    }
+}
+
+/// Receiving side of the updates
+#[derive(Debug)]
+pub struct Subscription<U> {
+    receiver: Receiver<U>,
+}
+
+impl<U> Subscription<U> {
+    /// Give the pending updates to `f` in the order they were published,
+    /// without waiting, and return their number
+    pub fn drain<F: FnMut(U)>(&self, mut f: F) -> usize {
+        let mut num = 0;
+        while let Ok(update) = self.receiver.try_recv() {
+            f(update);
+            num += 1;
+        }
+        num
+    }
+}
+
+/// Create a connected publisher and subscription
+pub fn subscribe<U>() -> (Publisher<U>, Subscription<U>) {
+    let (sender, receiver) = mpsc::channel();
+    (Publisher { sender }, Subscription { receiver })
+}
+
+/// Persistent [`DualRrtConnect`] whose world model of type `W` is changed
+/// by the updates of type `U` from a [`Subscription`]
+#[derive(Debug)]
+pub struct SubscribedPlanner<N, W, U, FA>
+where
+    N: Float + Debug,
+{
+    planner: DualRrtConnect<N>,
+    validity_resolution: N,
+    world: W,
+    subscription: Subscription<U>,
+    apply: FA,
+    num_updates: usize,
+    num_blocked: usize,
+    num_removed: usize,
+}
+
+impl<N, W, U, FA> SubscribedPlanner<N, W, U, FA>
+where
+    N: Float + Debug,
+    FA: FnMut(&mut W, U),
+{
+    /// Plan from `start` to `goal` in `world`, which is changed by
+    /// `apply(world, update)` for each update of `subscription`. The edges are
+    /// checked every `validity_resolution`.
+    pub fn new(
+        start: &[N],
+        goal: &[N],
+        extend_length: N,
+        validity_resolution: N,
+        world: W,
+        subscription: Subscription<U>,
+        apply: FA,
+    ) -> Self {
+        Self {
+            planner: DualRrtConnect::new(start, goal, extend_length),
+            validity_resolution,
+            world,
+            subscription,
+            apply,
+            num_updates: 0,
+            num_blocked: 0,
+            num_removed: 0,
+        }
+    }
+
+    /// Apply the pending updates and remove the parts of the trees which
+    /// they block, then try once toward `q_rand` (see
+    /// [`DualRrtConnect::step`]) with `is_free(world, q)` unless there is a
+    /// path.
+    ///
+    /// Returns true while there is a path which is free in the current world.
+    pub fn step<FF>(&mut self, q_rand: &[N], mut is_free: FF) -> bool
+    where
+        FF: FnMut(&W, &[N]) -> bool,
+    {
+        let (world, apply) = (&mut self.world, &mut self.apply);
+        let num = self.subscription.drain(|update| apply(world, update));
+        self.num_updates += num;
+        let world = &self.world;
+        let mut is_free = |q: &[N]| is_free(world, q);
+        if num > 0 {
+            let had_path = self.planner.path().is_some();
+            self.num_removed += self
+                .planner
+                .revalidate(self.validity_resolution, &mut is_free);
+            if had_path && self.planner.path().is_none() {
+                self.num_blocked += 1;
+            }
+        }
+        self.planner.path().is_some()
+            || self
+                .planner
+                .step(q_rand, self.validity_resolution, &mut is_free)
+    }
+
+    /// Path which was free when it was last checked
+    pub fn path(&self) -> Option<&[Vec<N>]> {
+        self.planner.path()
+    }
+
+    /// Current world model
+    pub fn world(&self) -> &W {
+        &self.world
+    }
+
+    /// Number of the applied updates
+    pub fn num_updates(&self) -> usize {
+        self.num_updates
+    }
+
+    /// Number of times the path was blocked by the updates
+    pub fn num_blocked(&self) -> usize {
+        self.num_blocked
+    }
+
+    /// Number of the tree vertices removed because the updates blocked them
+    pub fn num_removed(&self) -> usize {
+        self.num_removed
+    }
+}
+
+#[test]
+fn it_works() {
+    use rand::Rng;
+    use std::thread;
+    // the world is a list of square obstacles `[x, y, half size]`
+    let is_free = |world: &Vec<[f64; 3]>, q: &[f64]| {
+        world
+            .iter()
+            .all(|o| (q[0] - o[0]).abs() > o[2] || (q[1] - o[1]).abs() > o[2])
+    };
+    let random_sample = || {
+        let mut rng = rand::thread_rng();
+        vec![rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)]
+    };
+    let (publisher, subscription) = subscribe();
+    let mut planner = SubscribedPlanner::new(
+        &[-1.5, 0.0],
+        &[1.5, 0.0],
+        0.2,
+        0.01,
+        Vec::new(),
+        subscription,
+        |world: &mut Vec<[f64; 3]>, obstacle| world.push(obstacle),
+    );
+    let mut num_steps = 0;
+    while !planner.step(&random_sample(), is_free) {
+        num_steps += 1;
+        assert!(num_steps < 1000);
+    }
+    // an update which does not block the path keeps it
+    let path = planner.path().unwrap().to_vec();
+    let far = [0.0, 10.0, 0.1];
+    assert!(publisher.publish(far));
+    assert!(planner.step(&random_sample(), is_free));
+    assert_eq!(planner.path().unwrap(), path);
+    assert_eq!(planner.num_blocked(), 0);
+    assert_eq!(planner.num_removed(), 0);
+
+    // a sensor thread sees obstacles over the whole path, away from the
+    // start and the goal
+    let sensor = publisher.clone();
+    let blocking = path
+        .iter()
+        .filter(|q| (q[0].abs() - 1.5).abs() > 0.1 || q[1].abs() > 0.1)
+        .map(|q| [q[0], q[1], 0.05])
+        .collect::<Vec<_>>();
+    let num_blocking = blocking.len();
+    thread::spawn(move || {
+        for obstacle in blocking {
+            sensor.publish(obstacle);
+        }
+    })
+    .join()
+    .unwrap();
+    // the blocked parts of the trees are removed, and the search goes on
+    // from the rest of them
+    let num_vertices = planner.planner.start_tree().len() + planner.planner.goal_tree().len();
+    planner.step(&random_sample(), is_free);
+    assert_eq!(planner.num_blocked(), 1);
+    assert_eq!(planner.num_updates(), 1 + num_blocking);
+    assert!(planner.num_removed() > 0);
+    assert!(planner.num_removed() < num_vertices);
+    let mut num_steps = 0;
+    while !planner.step(&random_sample(), is_free) {
+        num_steps += 1;
+        assert!(num_steps < 1000);
+    }
+    let path = planner.path().unwrap();
+    assert!(path.iter().all(|q| is_free(planner.world(), q)));
+    drop(planner);
+    assert!(!publisher.publish(far));
+}
@@ -0,0 +1,521 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Time parameterization of paths.
+//!
+//! The path is split into short segments, and the speed along it is the
+//! largest one which keeps each dimension within its velocity and
+//! acceleration limits, found by a forward and a backward integration pass
+//! (like TOPP, but on the sampled path). At the corners of the path the
+//! speed is limited so the change of the velocity between the neighbouring
+//! segments is also within the acceleration limits.
+
+use crate::path::subdivide;
+use num_traits::float::Float;
+
+/// Per dimension limits of the motion
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits<N> {
+    /// Maximum absolute velocity of each dimension
+    pub max_velocity: Vec<N>,
+    /// Maximum absolute acceleration of each dimension
+    pub max_acceleration: Vec<N>,
+}
+
+/// Timed point of a [`Trajectory`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrajectoryPoint<N> {
+    /// Time from the start
+    pub time: N,
+    /// Configuration
+    pub position: Vec<N>,
+    /// Velocity of each dimension
+    pub velocity: Vec<N>,
+}
+
+/// Path with timing, which starts and ends at rest
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory<N> {
+    /// Points in the order of time
+    pub points: Vec<TrajectoryPoint<N>>,
+}
+
+impl<N: Float> Trajectory<N> {
+    /// Time of the last point
+    pub fn duration(&self) -> N {
+        self.points.last().map_or(N::zero(), |p| p.time)
+    }
+
+    /// Configuration at `time`, interpolated linearly between the points
+    /// (and clamped to the start and the end)
+    pub fn position(&self, time: N) -> Vec<N> {
+        self.interpolate(time, |p| &p.position)
+    }
+
+    /// Velocity at `time`, interpolated linearly between the points (and
+    /// clamped to the start and the end)
+    pub fn velocity(&self, time: N) -> Vec<N> {
+        self.interpolate(time, |p| &p.velocity)
+    }
+
+    /// Points every `period` from the start, and the last point, e.g. for a
+    /// controller which runs at a fixed rate
+    pub fn sample(&self, period: N) -> Vec<TrajectoryPoint<N>> {
+        assert!(period > N::zero());
+        let duration = self.duration();
+        let mut samples = Vec::new();
+        let mut k = 0;
+        loop {
+            let time = period * N::from(k).unwrap();
+            if time >= duration {
+                break;
+            }
+            samples.push(TrajectoryPoint {
+                time,
+                position: self.position(time),
+                velocity: self.velocity(time),
+            });
+            k += 1;
+        }
+        samples.extend(self.points.last().cloned());
+        samples
+    }
+
+    fn interpolate<F>(&self, time: N, value: F) -> Vec<N>
+    where
+        F: Fn(&TrajectoryPoint<N>) -> &Vec<N>,
+    {
+        let next = self.points.partition_point(|p| p.time <= time);
+        if next == 0 {
+            return value(&self.points[0]).clone();
+        }
+        if next == self.points.len() {
+            return value(&self.points[next - 1]).clone();
+        }
+        let (a, b) = (&self.points[next - 1], &self.points[next]);
+        let t = (time - a.time) / (b.time - a.time);
+        value(a)
+            .iter()
+            .zip(value(b))
+            .map(|(a, b)| *a + (*b - *a) * t)
+            .collect()
+    }
+}
+
+// Largest speed along `direction` (a unit vector) within the per dimension `limits`
+fn speed_limit<N: Float>(direction: &[N], limits: &[N]) -> N {
+    direction
+        .iter()
+        .zip(limits)
+        .filter(|(u, _)| u.abs() > N::epsilon())
+        .map(|(u, limit)| *limit / u.abs())
+        .fold(N::infinity(), N::min)
+}
+
+// Time of a segment with constant acceleration between the speeds at its
+// ends, or accelerating and decelerating if both of them are zero
+fn segment_time<N: Float>(length: N, from: N, to: N, acceleration: N) -> N {
+    let two = N::one() + N::one();
+    if from + to > N::zero() {
+        two * length / (from + to)
+    } else {
+        two * (length / acceleration).sqrt()
+    }
+}
+
+const MAX_REFINEMENTS: usize = 1000;
+
+// Speed along the path split into short segments
+struct Profile<N> {
+    points: Vec<Vec<N>>,
+    lengths: Vec<N>,
+    directions: Vec<Vec<N>>,
+    // largest acceleration along each segment
+    accelerations: Vec<N>,
+    // speed at each point
+    speeds: Vec<N>,
+}
+
+impl<N: Float> Profile<N> {
+    fn new(path: &[Vec<N>], limits: &Limits<N>, resolution: N) -> Self {
+        assert!(!path.is_empty());
+        let two = N::one() + N::one();
+        let mut points = subdivide(path, resolution);
+        points.dedup_by(|b, a| b.0 == a.0);
+        let num = points.len();
+        let lengths = points
+            .windows(2)
+            .map(|w| {
+                w[0].0
+                    .iter()
+                    .zip(&w[1].0)
+                    .fold(N::zero(), |sum, (a, b)| sum + (*b - *a).powi(2))
+                    .sqrt()
+            })
+            .collect::<Vec<_>>();
+        let directions = points
+            .windows(2)
+            .zip(&lengths)
+            .map(|(w, length)| {
+                w[0].0
+                    .iter()
+                    .zip(&w[1].0)
+                    .map(|(a, b)| (*b - *a) / *length)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let accelerations = directions
+            .iter()
+            .map(|u| speed_limit(u, &limits.max_acceleration))
+            .collect::<Vec<_>>();
+
+        // speed limit at each point, zero at the ends
+        let mut max_speeds = vec![N::zero(); num];
+        for k in 1..num.saturating_sub(1) {
+            let (u0, u1) = (&directions[k - 1], &directions[k]);
+            let mut limit =
+                speed_limit(u0, &limits.max_velocity).min(speed_limit(u1, &limits.max_velocity));
+            // the velocity changes by speed * (u1 - u0) over about the shorter segment
+            let length = lengths[k - 1].min(lengths[k]);
+            for (i, max_acceleration) in limits.max_acceleration.iter().enumerate() {
+                let change = (u1[i] - u0[i]).abs();
+                if change > N::epsilon() {
+                    limit = limit.min((*max_acceleration * length / change).sqrt());
+                }
+            }
+            max_speeds[k] = limit;
+        }
+        let mut speeds = max_speeds.clone();
+        for _ in 0..MAX_REFINEMENTS {
+            speeds.clone_from(&max_speeds);
+            // forward and backward passes with the largest acceleration
+            for k in 0..num.saturating_sub(1) {
+                let reachable = (speeds[k].powi(2) + two * accelerations[k] * lengths[k]).sqrt();
+                speeds[k + 1] = speeds[k + 1].min(reachable);
+            }
+            for k in (0..num.saturating_sub(1)).rev() {
+                let reachable =
+                    (speeds[k + 1].powi(2) + two * accelerations[k] * lengths[k]).sqrt();
+                speeds[k] = speeds[k].min(reachable);
+            }
+            // The passes keep the acceleration along the path, but at the
+            // corners the change of the direction also needs acceleration. The
+            // points where the change of the segment velocities is too large are
+            // slowed down (the acceleration scales with the square of the speed).
+            let mut refined = false;
+            for k in 1..num.saturating_sub(1) {
+                let ratio = (0..limits.max_acceleration.len())
+                    .map(|i| {
+                        let v0 = (speeds[k - 1] + speeds[k]) / two * directions[k - 1][i];
+                        let v1 = (speeds[k] + speeds[k + 1]) / two * directions[k][i];
+                        let dt = (segment_time(
+                            lengths[k - 1],
+                            speeds[k - 1],
+                            speeds[k],
+                            accelerations[k - 1],
+                        ) + segment_time(
+                            lengths[k],
+                            speeds[k],
+                            speeds[k + 1],
+                            accelerations[k],
+                        )) / two;
+                        (v1 - v0).abs() / dt / limits.max_acceleration[i]
+                    })
+                    .fold(N::zero(), N::max);
+                if ratio > N::one() + N::from(1e-9).unwrap() {
+                    max_speeds[k] = speeds[k] / ratio.sqrt() * N::from(0.99).unwrap();
+                    refined = true;
+                }
+            }
+            if !refined {
+                break;
+            }
+        }
+        Self {
+            points: points.into_iter().map(|(q, _)| q).collect(),
+            lengths,
+            directions,
+            accelerations,
+            speeds,
+        }
+    }
+
+    fn segment_time(&self, k: usize) -> N {
+        segment_time(
+            self.lengths[k],
+            self.speeds[k],
+            self.speeds[k + 1],
+            self.accelerations[k],
+        )
+    }
+
+    // Velocity at the point `k` moving at `speed`, in the average of the
+    // directions of the neighbouring segments
+    fn velocity(&self, k: usize, speed: N) -> Vec<N> {
+        let two = N::one() + N::one();
+        let num = self.points.len();
+        (0..self.points[k].len())
+            .map(|i| {
+                let before = if k > 0 {
+                    self.directions[k - 1][i]
+                } else {
+                    N::zero()
+                };
+                let after = if k + 1 < num {
+                    self.directions[k][i]
+                } else {
+                    N::zero()
+                };
+                speed * (before + after) / two
+            })
+            .collect()
+    }
+
+    // Distance along the segment `k` at `time` from its start, and the
+    // integral of the distance over the time
+    fn advance(&self, k: usize, time: N) -> (N, N) {
+        let two = N::one() + N::one();
+        let six = two + two + two;
+        let (length, from, to) = (self.lengths[k], self.speeds[k], self.speeds[k + 1]);
+        if from + to > N::zero() {
+            let a = (to * to - from * from) / (two * length);
+            (
+                from * time + a * time * time / two,
+                from * time * time / two + a * time.powi(3) / six,
+            )
+        } else {
+            // accelerating to the middle, then decelerating
+            let a = self.accelerations[k];
+            let duration = self.segment_time(k);
+            let half = duration / two;
+            if time <= half {
+                (a * time * time / two, a * time.powi(3) / six)
+            } else {
+                let rest = duration - time;
+                (
+                    length - a * rest * rest / two,
+                    a * half.powi(3) / six + length * (time - half)
+                        - a * ((duration - half).powi(3) - rest.powi(3)) / six,
+                )
+            }
+        }
+    }
+}
+
+/// Time parameterize `path` within `limits`. The path is split every
+/// `resolution`, and each of the split points is a point of the trajectory.
+pub fn time_parameterize<N: Float>(
+    path: &[Vec<N>],
+    limits: &Limits<N>,
+    resolution: N,
+) -> Trajectory<N> {
+    let profile = Profile::new(path, limits, resolution);
+    let mut time = N::zero();
+    let mut trajectory = Trajectory {
+        points: Vec::with_capacity(profile.points.len()),
+    };
+    for (k, position) in profile.points.iter().enumerate() {
+        if k > 0 {
+            time = time + profile.segment_time(k - 1);
+        }
+        trajectory.points.push(TrajectoryPoint {
+            time,
+            position: position.clone(),
+            velocity: profile.velocity(k, profile.speeds[k]),
+        });
+    }
+    trajectory
+}
+
+/// Same as [`time_parameterize`], but the jerk of each dimension is also
+/// limited by `max_jerk` (S-curve), so the acceleration is continuous,
+/// including at the start and the end.
+///
+/// The distance along the path is smoothed by a moving average over the
+/// time `2 * max_acceleration / max_jerk` (the largest of the dimensions),
+/// like the FIR filters which make S-curves from trapezoidal profiles. The
+/// path is unchanged, and the trajectory is longer by the window. On the
+/// straight parts the limits are kept, at the corners they hold only
+/// approximately, since the smoothed speed is an average over the corner.
+pub fn time_parameterize_with_jerk<N: Float>(
+    path: &[Vec<N>],
+    limits: &Limits<N>,
+    max_jerk: &[N],
+    resolution: N,
+) -> Trajectory<N> {
+    let two = N::one() + N::one();
+    let profile = Profile::new(path, limits, resolution);
+    let num = profile.points.len();
+    let window = limits
+        .max_acceleration
+        .iter()
+        .zip(max_jerk)
+        .map(|(a, j)| two * *a / *j)
+        .fold(N::zero(), N::max);
+    if num < 2 || window <= N::zero() {
+        return time_parameterize(path, limits, resolution);
+    }
+    // time, distance and integral of the distance at each point
+    let mut starts = vec![(N::zero(), N::zero(), N::zero())];
+    for k in 0..num - 1 {
+        let (time, distance, integral) = starts[k];
+        let duration = profile.segment_time(k);
+        let (_, segment_integral) = profile.advance(k, duration);
+        starts.push((
+            time + duration,
+            distance + profile.lengths[k],
+            integral + distance * duration + segment_integral,
+        ));
+    }
+    let (end_time, length, end_integral) = starts[num - 1];
+    // distance and its integral at `time`, at rest before the start and after the end
+    let state = |time: N| {
+        if time <= N::zero() {
+            (N::zero(), N::zero())
+        } else if time >= end_time {
+            (length, end_integral + length * (time - end_time))
+        } else {
+            let k = starts.partition_point(|s| s.0 <= time) - 1;
+            let (start, distance, integral) = starts[k];
+            let (advanced, advanced_integral) = profile.advance(k, time - start);
+            (
+                distance + advanced,
+                integral + distance * (time - start) + advanced_integral,
+            )
+        }
+    };
+    let smoothed = |time: N| (state(time).1 - state(time - window).1) / window;
+
+    let mut trajectory = Trajectory {
+        points: Vec::with_capacity(num),
+    };
+    let mut time = N::zero();
+    for (k, position) in profile.points.iter().enumerate() {
+        let distance = starts[k].1;
+        if k == num - 1 {
+            time = end_time + window;
+        } else if k > 0 {
+            // the smoothed distance increases monotonically
+            let (mut low, mut high) = (time, end_time + window);
+            for _ in 0..100 {
+                let middle = (low + high) / two;
+                if smoothed(middle) < distance {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            time = high;
+        }
+        let speed = (state(time).0 - state(time - window).0) / window;
+        trajectory.points.push(TrajectoryPoint {
+            time,
+            position: position.clone(),
+            velocity: profile.velocity(k, speed),
+        });
+    }
+    trajectory
+}
+
+#[test]
+fn it_works() {
+    let limits = Limits {
+        max_velocity: vec![1.0, 0.5],
+        max_acceleration: vec![2.0, 1.0],
+    };
+    let path = vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![1.0, 1.0]];
+    let trajectory = time_parameterize(&path, &limits, 0.05);
+    let points = &trajectory.points;
+    assert_eq!(points.len(), 41);
+    assert_eq!(points[0].time, 0.0);
+    assert!(points[0].velocity.iter().all(|v| *v == 0.0));
+    assert!(points[40].velocity.iter().all(|v| *v == 0.0));
+    // 1 m at 1 m/s, and 1 m at 0.5 m/s, with accelerations
+    assert!(trajectory.duration() > 3.0 && trajectory.duration() < 5.0);
+    assert_eq!(trajectory.position(-1.0), [0.0, 0.0]);
+    assert_eq!(trajectory.position(10.0), [1.0, 1.0]);
+    let t = points[20].time;
+    assert_eq!(trajectory.position(t), [1.0, 0.0]);
+    assert_eq!(trajectory.velocity(t), points[20].velocity);
+    // at the rate of a controller
+    let samples = trajectory.sample(0.01);
+    assert_eq!(
+        samples.len(),
+        (trajectory.duration() / 0.01).ceil() as usize + 1
+    );
+    assert_eq!(samples[1].time, 0.01);
+    assert_eq!(samples.last(), points.last());
+    for sample in &samples {
+        assert!(sample.velocity[0].abs() <= 1.0 + 1e-9 && sample.velocity[1].abs() <= 0.5 + 1e-9);
+    }
+    let velocities = points
+        .windows(2)
+        .map(|w| {
+            let dt = w[1].time - w[0].time;
+            assert!(dt > 0.0);
+            [0, 1].map(|i| (w[1].position[i] - w[0].position[i]) / dt)
+        })
+        .collect::<Vec<_>>();
+    for v in &velocities {
+        assert!(v[0].abs() <= 1.0 + 1e-9 && v[1].abs() <= 0.5 + 1e-9);
+    }
+    // the corner is taken slowly enough for the accelerations
+    for (w, v) in points.windows(3).zip(velocities.windows(2)) {
+        let dt = (w[2].time - w[0].time) / 2.0;
+        for (i, max_acceleration) in limits.max_acceleration.iter().enumerate() {
+            let a = (v[1][i] - v[0][i]) / dt;
+            assert!(a.abs() <= max_acceleration + 1e-6, "{a}");
+        }
+    }
+
+    // single point
+    let trajectory = time_parameterize(&[vec![1.0]], &limits, 0.1);
+    assert_eq!(trajectory.points.len(), 1);
+    assert_eq!(trajectory.duration(), 0.0);
+    // short segment with both ends at rest
+    let trajectory = time_parameterize(&[vec![0.0], vec![0.01]], &limits, 0.1);
+    assert!((trajectory.duration() - 2.0 * (0.01f64 / 2.0).sqrt()).abs() < 1e-9);
+
+    // S-curve on a straight line
+    let limits = Limits {
+        max_velocity: vec![1.0],
+        max_acceleration: vec![2.0],
+    };
+    let path = vec![vec![0.0], vec![1.0]];
+    let unlimited = time_parameterize(&path, &limits, 0.01);
+    let trajectory = time_parameterize_with_jerk(&path, &limits, &[20.0], 0.01);
+    // longer by the window of 2 * 2.0 / 20.0
+    assert!((trajectory.duration() - unlimited.duration() - 0.2).abs() < 1e-6);
+    let points = &trajectory.points;
+    assert_eq!(points.len(), unlimited.points.len());
+    assert_eq!(points[0].velocity, [0.0]);
+    assert_eq!(points[points.len() - 1].velocity, [0.0]);
+    let velocities = points
+        .windows(2)
+        .map(|w| (w[1].position[0] - w[0].position[0]) / (w[1].time - w[0].time))
+        .collect::<Vec<_>>();
+    let mut accelerations = vec![0.0];
+    for (w, v) in points.windows(3).zip(velocities.windows(2)) {
+        let a = (v[1] - v[0]) / ((w[2].time - w[0].time) / 2.0);
+        assert!(a.abs() <= 2.0 + 1e-3, "{a}");
+        accelerations.push(a);
+    }
+    accelerations.push(0.0);
+    for (w, a) in points.windows(2).zip(accelerations.windows(2)) {
+        let jerk = (a[1] - a[0]) / (w[1].time - w[0].time);
+        assert!(jerk.abs() <= 20.0, "{jerk}");
+    }
+}
@@ -0,0 +1,103 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Fixed dimension facade of the planners.
+//!
+//! The configurations are `[N; D]`, so dimension mismatches between start,
+//! goal and samples are caught at compile time.
+
+use crate::rrt::Error;
+use num_traits::float::Float;
+use std::fmt::Debug;
+
+/// Dual RRT Connect planner for `D` dimensional configurations
+#[derive(Debug, Clone)]
+pub struct Rrt<N, const D: usize> {
+    start: [N; D],
+    goal: [N; D],
+    extend_length: N,
+    num_max_try: usize,
+}
+
+impl<N, const D: usize> Rrt<N, D>
+where
+    N: Float + Debug,
+{
+    /// Create a planner from `start` to `goal`
+    pub fn new(start: [N; D], goal: [N; D], extend_length: N, num_max_try: usize) -> Self {
+        Self {
+            start,
+            goal,
+            extend_length,
+            num_max_try,
+        }
+    }
+
+    /// search the path from start to goal which is free, using random_sample function
+    pub fn plan<FF, FR>(&self, mut is_free: FF, random_sample: FR) -> Result<Vec<[N; D]>, Error<N>>
+    where
+        FF: FnMut(&[N; D]) -> bool,
+        FR: Fn() -> [N; D],
+    {
+        let path = crate::rrt::dual_rrt_connect(
+            &self.start,
+            &self.goal,
+            |q: &[N]| is_free(to_array(q)),
+            || random_sample().to_vec(),
+            self.extend_length,
+            self.num_max_try,
+        )?;
+        Ok(path.iter().map(|q| *to_array(q)).collect())
+    }
+
+    /// select random two points, and try to connect.
+    pub fn smooth_path<FF>(&self, path: &mut Vec<[N; D]>, mut is_free: FF, num_max_try: usize)
+    where
+        FF: FnMut(&[N; D]) -> bool,
+    {
+        let mut vec_path = path.iter().map(|q| q.to_vec()).collect();
+        crate::rrt::smooth_path(
+            &mut vec_path,
+            |q: &[N]| is_free(to_array(q)),
+            self.extend_length,
+            num_max_try,
+        );
+        *path = vec_path.iter().map(|q| *to_array(q)).collect();
+    }
+}
+
+fn to_array<N, const D: usize>(q: &[N]) -> &[N; D] {
+    q.try_into()
+        .expect("planner only creates configurations of the same dimension")
+}
+
+#[test]
+fn it_works() {
+    use rand::distributions::{Distribution, Uniform};
+    let planner = Rrt::new([-1.2, 0.0], [1.2, 0.0], 0.2, 1000);
+    let is_free = |p: &[f64; 2]| !(p[0].abs() < 1.0 && p[1].abs() < 1.0);
+    let mut result = planner
+        .plan(is_free, || {
+            let between = Uniform::new(-2.0, 2.0);
+            let mut rng = rand::thread_rng();
+            [between.sample(&mut rng), between.sample(&mut rng)]
+        })
+        .unwrap();
+    assert!(result.len() >= 4);
+    planner.smooth_path(&mut result, is_free, 100);
+    assert!(result.len() >= 3);
+    assert_eq!(result[0], [-1.2, 0.0]);
+}
@@ -0,0 +1,153 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Validation of the planned paths by an independent checker.
+//!
+//! Safety architectures often plan with a fast checker and accept the path
+//! only if a second one (e.g. a more conservative model of the robot, or a
+//! separately certified implementation) agrees. [`validate`] runs such a
+//! checker along the whole path and reports where it disagrees, instead of
+//! a single boolean.
+
+use kdtree::distance::squared_euclidean;
+use num_traits::float::Float;
+
+/// Part of a segment of the path which the checker rejected
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation<N> {
+    /// Index of the segment, from `path[segment]` to `path[segment + 1]`
+    pub segment: usize,
+    /// Fractions along the segment of the first and the last rejected points
+    pub range: (N, N),
+    /// First rejected configuration
+    pub configuration: Vec<N>,
+    /// Number of the rejected points of the segment
+    pub num_rejected: usize,
+}
+
+/// Result of [`validate`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationReport<N> {
+    /// Rejected segments in the order of the path
+    pub violations: Vec<Violation<N>>,
+    /// Number of the configurations checked
+    pub num_checks: usize,
+}
+
+impl<N> ValidationReport<N> {
+    /// Returns true if the checker accepted the whole path
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Check the configurations every `resolution` along `path` (including all
+/// of its waypoints) with `checker`. A path of a single waypoint is its
+/// segment 0, and each waypoint belongs to the segment which ends there.
+pub fn validate<FC, N>(path: &[Vec<N>], mut checker: FC, resolution: N) -> ValidationReport<N>
+where
+    FC: FnMut(&[N]) -> bool,
+    N: Float,
+{
+    assert!(resolution > N::zero());
+    let mut report = ValidationReport {
+        violations: Vec::new(),
+        num_checks: 0,
+    };
+    let Some(first) = path.first() else {
+        return report;
+    };
+    report.num_checks += 1;
+    let mut first_violation = (!checker(first)).then(|| Violation {
+        segment: 0,
+        range: (N::zero(), N::zero()),
+        configuration: first.clone(),
+        num_rejected: 1,
+    });
+    if path.len() == 1 {
+        report.violations.extend(first_violation);
+        return report;
+    }
+    for (segment, w) in path.windows(2).enumerate() {
+        let mut violation = first_violation.take();
+        let length = squared_euclidean(&w[0], &w[1]).sqrt();
+        let num = (length / resolution).ceil().to_usize().unwrap_or(1).max(1);
+        for i in 1..=num {
+            let t = N::from(i).unwrap() / N::from(num).unwrap();
+            let q = if i == num {
+                w[1].clone()
+            } else {
+                w[0].iter()
+                    .zip(&w[1])
+                    .map(|(a, b)| *a + (*b - *a) * t)
+                    .collect()
+            };
+            report.num_checks += 1;
+            if checker(&q) {
+                continue;
+            }
+            match &mut violation {
+                Some(violation) => {
+                    violation.range.1 = t;
+                    violation.num_rejected += 1;
+                }
+                None => {
+                    violation = Some(Violation {
+                        segment,
+                        range: (t, t),
+                        configuration: q,
+                        num_rejected: 1,
+                    })
+                }
+            }
+        }
+        report.violations.extend(violation);
+    }
+    report
+}
+
+#[test]
+fn it_works() {
+    let planning = |q: &[f64]| !(q[0].abs() < 1.0 && q[1].abs() < 1.0);
+    // with a margin of 0.1
+    let conservative = |q: &[f64]| !(q[0].abs() < 1.1 && q[1].abs() < 1.1);
+    let path = vec![
+        vec![-1.5, 0.0],
+        vec![-1.05, 0.5],
+        vec![-1.05, 1.5],
+        vec![1.5, 1.5],
+    ];
+    let report = validate(&path, planning, 0.01);
+    assert!(report.is_valid());
+    assert!(report.num_checks > 300);
+    let report = validate(&path, conservative, 0.01);
+    assert!(!report.is_valid());
+    let segments = report
+        .violations
+        .iter()
+        .map(|v| v.segment)
+        .collect::<Vec<_>>();
+    assert_eq!(segments, [0, 1]);
+    let violation = &report.violations[0];
+    assert!(!conservative(&violation.configuration));
+    assert!(violation.range.0 > 0.5 && violation.range.1 == 1.0);
+    assert!(violation.num_rejected > 1);
+
+    let report = validate(&path[..1], |_: &[f64]| false, 0.01);
+    assert_eq!(report.violations[0].segment, 0);
+    assert_eq!(report.num_checks, 1);
+    assert!(validate::<_, f64>(&[], |_: &[f64]| false, 0.01).is_valid());
+}
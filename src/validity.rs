@@ -0,0 +1,199 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Time-limited validity results for slowly changing worlds.
+//!
+//! A validity check can tell how long its answer stays correct, e.g. until
+//! the next predicted update of a moving obstacle. [`RevalidatingChecker`]
+//! caches the answers for vertices and edges and checks them again only when
+//! they have become stale.
+
+use num_traits::float::Float;
+use std::collections::HashMap;
+
+/// Result of a validity check which may expire
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Validity<T> {
+    /// True if the configuration is free
+    pub is_free: bool,
+    /// The result must be checked again after this time, `None` if it never expires
+    pub valid_until: Option<T>,
+}
+
+impl<T: PartialOrd + Copy> Validity<T> {
+    /// Result which never expires
+    pub fn forever(is_free: bool) -> Self {
+        Self {
+            is_free,
+            valid_until: None,
+        }
+    }
+
+    /// Returns true if the result can still be used at `now`
+    pub fn is_valid_at(&self, now: T) -> bool {
+        self.valid_until.is_none_or(|until| now < until)
+    }
+
+    fn earliest(a: Option<T>, b: Option<T>) -> Option<T> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(if b < a { b } else { a }),
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+}
+
+/// When the cached results are checked again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RevalidationPolicy {
+    /// Check again only when the cached result has expired
+    #[default]
+    Lazy,
+    /// Never use the cache
+    Always,
+    /// Use the cached result even if it has expired (static world)
+    Never,
+}
+
+/// Validity checker which caches time-limited results of vertices and edges
+pub struct RevalidatingChecker<N, T, F> {
+    check: F,
+    policy: RevalidationPolicy,
+    vertices: HashMap<Vec<u64>, Validity<T>>,
+    edges: HashMap<(Vec<u64>, Vec<u64>), Validity<T>>,
+    num_checks: usize,
+    _phantom: std::marker::PhantomData<N>,
+}
+
+fn key<N: Float>(q: &[N]) -> Vec<u64> {
+    q.iter().map(|v| v.to_f64().unwrap().to_bits()).collect()
+}
+
+impl<N, T, F> RevalidatingChecker<N, T, F>
+where
+    N: Float,
+    T: PartialOrd + Copy,
+    F: FnMut(&[N], T) -> Validity<T>,
+{
+    /// Wrap `check`, which returns the validity of a configuration at the given time
+    pub fn new(check: F, policy: RevalidationPolicy) -> Self {
+        Self {
+            check,
+            policy,
+            vertices: HashMap::new(),
+            edges: HashMap::new(),
+            num_checks: 0,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Number of calls of the wrapped check
+    pub fn num_checks(&self) -> usize {
+        self.num_checks
+    }
+
+    /// Forget all of the cached results
+    pub fn invalidate_all(&mut self) {
+        self.vertices.clear();
+        self.edges.clear();
+    }
+
+    fn is_usable(&self, validity: &Validity<T>, now: T) -> bool {
+        match self.policy {
+            RevalidationPolicy::Lazy => validity.is_valid_at(now),
+            RevalidationPolicy::Always => false,
+            RevalidationPolicy::Never => true,
+        }
+    }
+
+    /// Validity of `q` at `now`, checked again only if the cached result is stale
+    pub fn validity(&mut self, q: &[N], now: T) -> Validity<T> {
+        let k = key(q);
+        if let Some(cached) = self.vertices.get(&k) {
+            if self.is_usable(cached, now) {
+                return *cached;
+            }
+        }
+        self.num_checks += 1;
+        let validity = (self.check)(q, now);
+        self.vertices.insert(k, validity);
+        validity
+    }
+
+    /// Returns true if `q` is free at `now`
+    pub fn is_free(&mut self, q: &[N], now: T) -> bool {
+        self.validity(q, now).is_free
+    }
+
+    /// Returns true if the edge is free at `now`, checked every `resolution`.
+    ///
+    /// The edge expires when the first of its checked points expires.
+    pub fn is_edge_free(&mut self, from: &[N], to: &[N], resolution: N, now: T) -> bool {
+        let k = (key(from), key(to));
+        if let Some(cached) = self.edges.get(&k) {
+            if self.is_usable(cached, now) {
+                return cached.is_free;
+            }
+        }
+        let mut valid_until = None;
+        let is_free = crate::rrt::is_edge_free(from, to, resolution, &mut |q: &[N]| {
+            let validity = self.validity(q, now);
+            valid_until = Validity::earliest(valid_until, validity.valid_until);
+            validity.is_free
+        });
+        self.edges.insert(
+            k,
+            Validity {
+                is_free,
+                valid_until,
+            },
+        );
+        is_free
+    }
+}
+
+#[test]
+fn it_works() {
+    // an obstacle at x = 0.5 which leaves at t = 10
+    let check = |q: &[f64], now: f64| {
+        if now < 10.0 {
+            Validity {
+                is_free: (q[0] - 0.5).abs() > 0.1,
+                valid_until: Some(10.0),
+            }
+        } else {
+            Validity::forever(true)
+        }
+    };
+    let mut checker = RevalidatingChecker::new(check, RevalidationPolicy::Lazy);
+    assert!(!checker.is_edge_free(&[0.0], &[1.0], 0.1, 0.0));
+    let num_checks = checker.num_checks();
+    assert!(!checker.is_edge_free(&[0.0], &[1.0], 0.1, 5.0));
+    assert_eq!(checker.num_checks(), num_checks);
+    assert!(checker.is_edge_free(&[0.0], &[1.0], 0.1, 10.0));
+    assert!(checker.num_checks() > num_checks);
+
+    let mut checker = RevalidatingChecker::new(check, RevalidationPolicy::Never);
+    assert!(!checker.is_free(&[0.5], 0.0));
+    assert!(!checker.is_free(&[0.5], 20.0));
+    checker.invalidate_all();
+    assert!(checker.is_free(&[0.5], 20.0));
+
+    let mut checker = RevalidatingChecker::new(check, RevalidationPolicy::Always);
+    checker.is_free(&[0.0], 0.0);
+    checker.is_free(&[0.0], 0.0);
+    assert_eq!(checker.num_checks(), 2);
+}